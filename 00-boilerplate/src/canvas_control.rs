@@ -36,7 +36,7 @@ impl Component for CanvasControl {
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
         CanvasControl{
-            callback: callback,
+            callback,
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
@@ -133,8 +133,8 @@ impl CanvasControl {
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -39,7 +39,7 @@ impl Component for CanvasControl {
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
         CanvasControl{
-            callback: callback,
+            callback,
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
@@ -148,15 +148,15 @@ impl CanvasControl {
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
         // Set up the shaders - and compile them
-        let vert_code = include_str!("./simple.vert");
-        let frag_code = include_str!("./simple.frag");
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/simple.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/simple.frag"));
 
         let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
+        gl.shader_source(&vert_shader, vert_code);
         gl.compile_shader(&vert_shader);
 
         let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
+        gl.shader_source(&frag_shader, frag_code);
         gl.compile_shader(&frag_shader);
 
         // Create the shader program and attach our now compiled shaders
@@ -193,8 +193,8 @@ impl CanvasControl {
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -207,7 +207,7 @@ impl CanvasControl {
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
 
         gl.draw_arrays(GL::TRIANGLES, 0, 3);
 
@@ -1,11 +1,13 @@
-use std::rc::Rc;
-
-use web_sys::{window, HtmlCanvasElement,HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+use web_sys::{window, HtmlCanvasElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
 use yew::prelude::*;
 
 use wasm_bindgen::{prelude::*, JsCast};
 use gloo_console::log;
 
+use shader_common::noise::SeededNoise;
+use shader_common::param_panel::ParamPanel;
+use shader_common::params::ParamValue;
+
 pub struct CanvasControl {
     callback: Closure<dyn FnMut()>,
     canvas: Option<HtmlCanvasElement>,
@@ -18,9 +20,13 @@ pub struct CanvasControl {
     u_time: f32,
     height: i32,
     width: i32,
+    // The generated noise texture, the seed is on the panel
+    noise: SeededNoise,
+    on_param: Callback<(String, ParamValue)>,
 }
 
 pub enum CanvasControlMsg {
+    SetParam((String, ParamValue)),
     Render
 }
 
@@ -28,8 +34,6 @@ pub enum CanvasControlMsg {
 #[derive(Clone, Debug, PartialEq, Eq, Properties)]
 pub struct CanvasControlProps;
 
-const TEXTURE_1: &str = "assets/noise.png";
-
 impl Component for CanvasControl {
     type Message = CanvasControlMsg;
     type Properties = CanvasControlProps;
@@ -44,7 +48,7 @@ impl Component for CanvasControl {
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
         CanvasControl{
-            callback: callback,
+            callback,
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
@@ -55,11 +59,17 @@ impl Component for CanvasControl {
             u_time: 0.0,
             height: height as i32,
             width: width as i32,
+            noise: SeededNoise::new(),
+            on_param: ctx.link().callback(CanvasControlMsg::SetParam),
         }
     }
 
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool{
         match msg {
+            CanvasControlMsg::SetParam((_, value)) => {
+                self.noise.set(value);
+                true
+            },
             CanvasControlMsg::Render => {
                 self.render();
                 true
@@ -70,6 +80,11 @@ impl Component for CanvasControl {
     fn view(&self, _ctx: &Context<Self>) -> Html {
         html! {
             <div class="game_canvas">
+                <ParamPanel
+                    params={vec![SeededNoise::param()]}
+                    values={vec![self.noise.value()]}
+                    on_change={self.on_param.clone()}
+                />
                 <canvas id="canvas"
                     style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px;"}
                     ref={self.node_ref.clone()}
@@ -159,15 +174,15 @@ impl CanvasControl {
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
         // Set up the shaders - and compile them
-        let vert_code = include_str!("./texture.vert");
-        let frag_code = include_str!("./texture.frag");
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/texture.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/texture.frag"));
 
         let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
+        gl.shader_source(&vert_shader, vert_code);
         gl.compile_shader(&vert_shader);
 
         let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
+        gl.shader_source(&frag_shader, frag_code);
         gl.compile_shader(&frag_shader);
 
         // Create the shader program and attach our now compiled shaders
@@ -192,44 +207,9 @@ impl CanvasControl {
         self.time_location = gl.get_uniform_location(&shader_program, "u_time");
         gl.uniform1f(self.time_location.as_ref() , 1.0); //self.last_update as f32
 
-        // Setup the texture 
-        // based on https://snoozetime.github.io/2019/12/19/webgl-texture.html
-        let texture = gl.create_texture().unwrap();
-        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-
-        let image: HtmlImageElement = HtmlImageElement::new().unwrap();
-        let imgrc = Rc::new(image.clone());
-
-        {
-            let image = imgrc.clone();
-            let texture = texture.clone();
-            let gl = Rc::new(gl.clone());
-
-            let a = Closure::wrap(Box::new(move || {
-                gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-    
-                let _ = gl.tex_image_2d_with_u32_and_u32_and_image(
-                    GL::TEXTURE_2D,
-                    0,
-                    GL::RGBA.try_into().unwrap(),
-                    GL::RGBA.try_into().unwrap(),
-                    GL::UNSIGNED_BYTE,
-                    &image,
-                );
-    
-                // different from webgl1 where we need the pic to be power of 2
-                gl.generate_mipmap(GL::TEXTURE_2D);
-            }) as Box<dyn FnMut()>);
-
-            imgrc.set_onload(Some(a.as_ref().unchecked_ref()));
-    
-            // Normally we'd store the handle to later get dropped at an appropriate
-            // time but for now we want it to be a global handler so we use the
-            // forget method to drop it without invalidating the closure. Note that
-            // this is leaking memory in Rust, so this should be done judiciously!
-            a.forget();
-        }
-        image.set_src(TEXTURE_1);
+        // The noise texture is generated rather than loaded, on the next frame and again
+        // whenever the seed changes, see shader_common::noise
+        self.noise.forget();
 
         self.shader_program = Some(shader_program);
     }
@@ -243,8 +223,8 @@ impl CanvasControl {
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -257,7 +237,8 @@ impl CanvasControl {
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
+        self.noise.bind(gl);
 
         gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
 
@@ -266,4 +247,4 @@ impl CanvasControl {
             .request_animation_frame(self.callback.as_ref().unchecked_ref())
             .unwrap();
     }
-}
\ No newline at end of file
+}
@@ -1,23 +1,31 @@
-use std::rc::Rc;
-
-use web_sys::{window, HtmlCanvasElement,HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+use web_sys::{window, HtmlCanvasElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
 use yew::prelude::*;
 
 use wasm_bindgen::{prelude::*, JsCast};
 use gloo_console::log;
 
+use shader_common::program::{Defines, ProgramCache};
+use shader_common::noise::SeededNoise;
+use shader_common::param_panel::ParamPanel;
+use shader_common::params::ParamValue;
+
 pub struct CanvasControl {
     callback: Closure<dyn FnMut()>,
     canvas: Option<HtmlCanvasElement>,
     gl: Option<GL>,
     node_ref: NodeRef,
     last_update: f64,
+    program_cache: ProgramCache,
+    variant: usize,
     shader_program: Option<WebGlProgram>,
     time_location: Option<WebGlUniformLocation>,
     tri_count: i32,
     u_time: f32,
     height: i32,
     width: i32,
+    // The generated noise texture, the seed is on the panel
+    noise: SeededNoise,
+    on_param: Callback<(String, ParamValue)>,
 }
 
 pub enum CanvasControlMsg {
@@ -27,6 +35,8 @@ pub enum CanvasControlMsg {
     TouchStart((f64, f64)),
     TouchEnd((f64, f64)),
     TouchMove((f64, f64)),
+    SetVariant(usize),
+    SetParam((String, ParamValue)),
     Render,
     Null
 }
@@ -35,7 +45,15 @@ pub enum CanvasControlMsg {
 #[derive(Clone, Debug, PartialEq, Eq, Properties)]
 pub struct CanvasControlProps;
 
-const TEXTURE_1: &str = "/assets/noise.png";
+// Variants of the tunnel - (name, TUNNEL_SIZE, TUNNEL_SPEED). Each one is compiled into its own
+// program with the values baked in as #defines.
+const VARIANTS: &[(&str, f32, f32)] = &[
+    ("Narrow", 0.1, 0.5),
+    ("Default", 0.25, 0.5),
+    ("Wide", 0.5, 0.5),
+    ("Reverse", 0.25, -0.5),
+];
+const DEFAULT_VARIANT: usize = 1;
 
 impl Component for CanvasControl {
     type Message = CanvasControlMsg;
@@ -50,17 +68,21 @@ impl Component for CanvasControl {
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
         CanvasControl{
-            callback: callback,
+            callback,
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
             last_update: instant::now(),
+            program_cache: ProgramCache::new(),
+            variant: DEFAULT_VARIANT,
             shader_program: None,
             time_location: None,
             tri_count: 0,
             u_time: 0.0,
             height: height as i32,
             width: width as i32,
+            noise: SeededNoise::new(),
+            on_param: ctx.link().callback(CanvasControlMsg::SetParam),
         }
     }
 
@@ -88,6 +110,15 @@ impl Component for CanvasControl {
                 // log!("Event here TouchMove => ", evt.0, evt.1);
                 true
             },
+            CanvasControlMsg::SetVariant(variant) => {
+                self.variant = variant;
+                self.use_program();
+                true
+            },
+            CanvasControlMsg::SetParam((_, value)) => {
+                self.noise.set(value);
+                true
+            },
             CanvasControlMsg::Render => {
                 // log!("Render");
                 self.render();
@@ -128,8 +159,22 @@ impl Component for CanvasControl {
             }
         });
 
+        let variant_buttons = VARIANTS.iter().enumerate().map(|(variant, (name, _, _))| {
+            let onclick = ctx.link().callback(move |_| CanvasControlMsg::SetVariant(variant));
+            let class = if variant == self.variant { "selected" } else { "" };
+            html! { <button class={class} onclick={onclick}>{ *name }</button> }
+        });
+
         html! {
             <div class="game_canvas">
+                <ParamPanel
+                    params={vec![SeededNoise::param()]}
+                    values={vec![self.noise.value()]}
+                    on_change={self.on_param.clone()}
+                />
+                <div class="quality_select">
+                    { for variant_buttons }
+                </div>
                 <canvas id="canvas"
                     style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px;"}
                     onmousedown={onmousedown}
@@ -187,7 +232,7 @@ impl CanvasControl {
     }
 
     fn reload(&mut self) {
-        // Set up shaders and 
+        // Set up the vertex buffer and texture, the shader is set up by use_program
         let gl = match &self.gl {
             Some(gl)=> gl,
             None => {
@@ -196,9 +241,6 @@ impl CanvasControl {
             }
         };
 
-        let vert_code = include_str!("./basic.vert");
-        let frag_code = include_str!("./basic.frag");
-
         let _: &HtmlCanvasElement = match &self.canvas {
             Some(canv) => canv,
             None => return,
@@ -222,18 +264,40 @@ impl CanvasControl {
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&vertex_buffer));
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
-        let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
-        gl.compile_shader(&vert_shader);
+        // The noise texture is generated rather than loaded, on the next frame and again
+        // whenever the seed changes, see shader_common::noise
+        self.noise.forget();
+
+        self.use_program();
+    }
+
+    // Switch to the program for the current variant, compiling it if this is the first time it
+    // has been used. Uniform and attribute locations belong to a program so have to be looked
+    // up again every time we switch.
+    fn use_program(&mut self) {
+        let gl = match &self.gl {
+            Some(gl)=> gl.clone(),
+            None => {
+                log!("ERROR Setting up scene without a proper gl context");
+                return;
+            }
+        };
 
-        let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
-        gl.compile_shader(&frag_shader);
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/basic.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/basic.frag"));
 
-        let shader_program: WebGlProgram = gl.create_program().unwrap();
-        gl.attach_shader(&shader_program, &vert_shader);
-        gl.attach_shader(&shader_program, &frag_shader);
-        gl.link_program(&shader_program);
+        let (_, size, speed) = VARIANTS[self.variant];
+        let defines = Defines::new()
+            .set_float("TUNNEL_SIZE", size)
+            .set_float("TUNNEL_SPEED", speed);
+
+        let shader_program = match self.program_cache.get_or_compile(&gl, vert_code, frag_code, &defines) {
+            Ok(program) => program,
+            Err(err) => {
+                log!("ERROR compiling shader:", err);
+                return;
+            }
+        };
 
         gl.use_program(Some(&shader_program));
 
@@ -246,46 +310,7 @@ impl CanvasControl {
         gl.uniform2f(canvassize.as_ref(), self.width as f32, self.height as f32);
 
         self.time_location = gl.get_uniform_location(&shader_program, "u_time");
-        gl.uniform1f(self.time_location.as_ref() , 1.0); //self.last_update as f32
-
-        // Setup the texture 
-        // based on https://snoozetime.github.io/2019/12/19/webgl-texture.html
-        let texture = gl.create_texture().unwrap();
-        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-
-        let image: HtmlImageElement = HtmlImageElement::new().unwrap();
-        let imgrc = Rc::new(image.clone());
-
-        {
-            let image = imgrc.clone();
-            let texture = texture.clone();
-            let gl = Rc::new(gl.clone());
-
-            let a = Closure::wrap(Box::new(move || {
-                gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-    
-                let _ = gl.tex_image_2d_with_u32_and_u32_and_image(
-                    GL::TEXTURE_2D,
-                    0,
-                    GL::RGBA.try_into().unwrap(),
-                    GL::RGBA.try_into().unwrap(),
-                    GL::UNSIGNED_BYTE,
-                    &image,
-                );
-    
-                // different from webgl1 where we need the pic to be power of 2
-                gl.generate_mipmap(GL::TEXTURE_2D);
-            }) as Box<dyn FnMut()>);
-
-            imgrc.set_onload(Some(a.as_ref().unchecked_ref()));
-    
-            // Normally we'd store the handle to later get dropped at an appropriate
-            // time but for now we want it to be a global handler so we use the
-            // forget method to drop it without invalidating the closure. Note that
-            // this is leaking memory in Rust, so this should be done judiciously!
-            a.forget();
-        }
-        image.set_src(TEXTURE_1);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
 
         self.shader_program = Some(shader_program);
     }
@@ -298,8 +323,8 @@ impl CanvasControl {
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -312,7 +337,8 @@ impl CanvasControl {
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
+        self.noise.bind(gl);
 
         gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
 
@@ -321,4 +347,4 @@ impl CanvasControl {
             .request_animation_frame(self.callback.as_ref().unchecked_ref())
             .unwrap();
     }
-}
\ No newline at end of file
+}
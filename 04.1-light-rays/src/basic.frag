@@ -1,23 +1,15 @@
 // Taken from here: https://www.shadertoy.com/view/4djBRm
 precision mediump float;
 
-const float TUNNEL_SIZE  = 0.25;	// smaller values for smaller/thinner tunnel
-const float TUNNEL_SPEED = 0.025;		// speed of tunnel effect, negative values ok
+uniform float TUNNEL_SIZE; // @range 0.05..1 @default 0.25 @label Tunnel size
+uniform float TUNNEL_SPEED; // @range -0.1..0.1 @default 0.025 @step 0.005 @label Tunnel speed
 
-const float PI = 3.141592;
+#include "lib/tunnel.glsl"
 
 uniform float u_time;
 uniform vec2 canvasSize;
 uniform sampler2D texNoise;
 
-vec2 tunnel(vec2 uv, float size, float time)
-{
-    vec2 p  = -1.0 + (2.0 * uv);
-    float a = atan(p.y, p.x);
-    float r = sqrt(dot(p, p));
-    return vec2(a / PI, time + (size / r));
-}
-
 void main()
 {
 	vec2 uv = vec2(canvasSize.y / canvasSize.x, 1.);
@@ -1,11 +1,14 @@
-use std::rc::Rc;
-
-use web_sys::{window, HtmlCanvasElement,HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+use web_sys::{window, HtmlCanvasElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
 use yew::prelude::*;
 
 use wasm_bindgen::{prelude::*, JsCast};
 use gloo_console::log;
 
+use shader_common::param_panel::ParamPanel;
+use shader_common::preset_panel::PresetPanel;
+use shader_common::params::{Param, ParamValue, ShaderParams};
+use shader_common::noise::SeededNoise;
+
 pub struct CanvasControl {
     callback: Closure<dyn FnMut()>,
     canvas: Option<HtmlCanvasElement>,
@@ -18,6 +21,12 @@ pub struct CanvasControl {
     u_time: f32,
     height: i32,
     width: i32,
+    params: ShaderParams,
+    on_param: Callback<(String, ParamValue)>,
+    on_param_reset: Callback<()>,
+    on_preset: Callback<Vec<(String, ParamValue)>>,
+    // The generated noise texture, its seed is on the panel with the shader's parameters
+    noise: SeededNoise,
 }
 
 pub enum CanvasControlMsg {
@@ -27,6 +36,9 @@ pub enum CanvasControlMsg {
     TouchStart((f64, f64)),
     TouchEnd((f64, f64)),
     TouchMove((f64, f64)),
+    SetParam((String, ParamValue)),
+    ResetParams,
+    LoadPreset(Vec<(String, ParamValue)>),
     Render,
     Null
 }
@@ -35,8 +47,6 @@ pub enum CanvasControlMsg {
 #[derive(Clone, Debug, PartialEq, Eq, Properties)]
 pub struct CanvasControlProps;
 
-const TEXTURE_1: &str = "/assets/noise.png";
-
 impl Component for CanvasControl {
     type Message = CanvasControlMsg;
     type Properties = CanvasControlProps;
@@ -49,8 +59,13 @@ impl Component for CanvasControl {
         let width = window().unwrap().inner_width().unwrap().as_f64().unwrap();
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
+        // Made once up front so the panel's props don't change every frame
+        let on_param = ctx.link().callback(CanvasControlMsg::SetParam);
+        let on_param_reset = ctx.link().callback(|_| CanvasControlMsg::ResetParams);
+        let on_preset = ctx.link().callback(CanvasControlMsg::LoadPreset);
+
         CanvasControl{
-            callback: callback,
+            callback,
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
@@ -61,6 +76,11 @@ impl Component for CanvasControl {
             u_time: 0.0,
             height: height as i32,
             width: width as i32,
+            params: ShaderParams::from_source(include_str!(concat!(env!("OUT_DIR"), "/basic.frag"))),
+            on_param,
+            on_param_reset,
+            on_preset,
+            noise: SeededNoise::new(),
         }
     }
 
@@ -88,6 +108,21 @@ impl Component for CanvasControl {
                 // log!("Event here TouchMove => ", evt.0, evt.1);
                 true
             },
+            CanvasControlMsg::SetParam((name, value)) => {
+                self.set_param(&name, value);
+                true
+            },
+            CanvasControlMsg::ResetParams => {
+                self.params.reset();
+                self.noise.reset();
+                true
+            },
+            CanvasControlMsg::LoadPreset(values) => {
+                for (name, value) in values {
+                    self.set_param(&name, value);
+                }
+                true
+            },
             CanvasControlMsg::Render => {
                 // log!("Render");
                 self.render();
@@ -128,8 +163,23 @@ impl Component for CanvasControl {
             }
         });
 
+        let (params, values) = self.panel_params();
+
         html! {
             <div class="game_canvas">
+                <ParamPanel
+                    params={params.clone()}
+                    values={values.clone()}
+                    on_change={self.on_param.clone()}
+                    on_reset={self.on_param_reset.clone()}
+                >
+                    <PresetPanel
+                        tutorial={env!("CARGO_PKG_NAME")}
+                        params={params}
+                        values={values}
+                        on_load={self.on_preset.clone()}
+                    />
+                </ParamPanel>
                 <canvas id="canvas"
                     style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px;"}
                     onmousedown={onmousedown}
@@ -196,8 +246,8 @@ impl CanvasControl {
             }
         };
 
-        let vert_code = include_str!("./basic.vert");
-        let frag_code = include_str!("./basic.frag");
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/basic.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/basic.frag"));
 
         let _: &HtmlCanvasElement = match &self.canvas {
             Some(canv) => canv,
@@ -223,11 +273,11 @@ impl CanvasControl {
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
         let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
+        gl.shader_source(&vert_shader, vert_code);
         gl.compile_shader(&vert_shader);
 
         let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
+        gl.shader_source(&frag_shader, frag_code);
         gl.compile_shader(&frag_shader);
 
         let shader_program: WebGlProgram = gl.create_program().unwrap();
@@ -248,48 +298,33 @@ impl CanvasControl {
         self.time_location = gl.get_uniform_location(&shader_program, "u_time");
         gl.uniform1f(self.time_location.as_ref() , 1.0); //self.last_update as f32
 
-        // Setup the texture 
-        // based on https://snoozetime.github.io/2019/12/19/webgl-texture.html
-        let texture = gl.create_texture().unwrap();
-        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-
-        let image: HtmlImageElement = HtmlImageElement::new().unwrap();
-        let imgrc = Rc::new(image.clone());
-
-        {
-            let image = imgrc.clone();
-            let texture = texture.clone();
-            let gl = Rc::new(gl.clone());
-
-            let a = Closure::wrap(Box::new(move || {
-                gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-    
-                let _ = gl.tex_image_2d_with_u32_and_u32_and_image(
-                    GL::TEXTURE_2D,
-                    0,
-                    GL::RGBA.try_into().unwrap(),
-                    GL::RGBA.try_into().unwrap(),
-                    GL::UNSIGNED_BYTE,
-                    &image,
-                );
-    
-                // different from webgl1 where we need the pic to be power of 2
-                gl.generate_mipmap(GL::TEXTURE_2D);
-            }) as Box<dyn FnMut()>);
-
-            imgrc.set_onload(Some(a.as_ref().unchecked_ref()));
-    
-            // Normally we'd store the handle to later get dropped at an appropriate
-            // time but for now we want it to be a global handler so we use the
-            // forget method to drop it without invalidating the closure. Note that
-            // this is leaking memory in Rust, so this should be done judiciously!
-            a.forget();
-        }
-        image.set_src(TEXTURE_1);
+        // Uniforms annotated in the shader, driven by the parameter panel
+        self.params.locate(gl, &shader_program);
+
+        // The noise texture is generated rather than loaded, on the next frame and again
+        // whenever the seed changes
+        self.noise.forget();
 
         self.shader_program = Some(shader_program);
     }
 
+    // The shader's parameters with the noise seed after them, for the panel and presets
+    fn panel_params(&self) -> (Vec<Param>, Vec<ParamValue>) {
+        let mut params = self.params.params().to_vec();
+        let mut values = self.params.values().to_vec();
+        params.push(SeededNoise::param());
+        values.push(self.noise.value());
+        (params, values)
+    }
+
+    fn set_param(&mut self, name: &str, value: ParamValue) {
+        if name == SeededNoise::PARAM {
+            self.noise.set(value);
+        } else {
+            self.params.set(name, value);
+        }
+    }
+
     fn render(&mut self) {
         self.canvas_update();
         
@@ -298,8 +333,8 @@ impl CanvasControl {
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -312,7 +347,9 @@ impl CanvasControl {
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
+        self.params.upload(gl);
+        self.noise.bind(gl);
 
         gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
 
@@ -321,4 +358,4 @@ impl CanvasControl {
             .request_animation_frame(self.callback.as_ref().unchecked_ref())
             .unwrap();
     }
-}
\ No newline at end of file
+}
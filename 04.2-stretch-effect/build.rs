@@ -0,0 +1,8 @@
+use shader_common::build::ShaderBuild;
+
+fn main() {
+    // Every quad vertex is x, y, z - see the vertices in canvas_control.rs
+    ShaderBuild::new("src")
+        .attribute("a_position", 3)
+        .run();
+}
@@ -4,20 +4,12 @@ precision mediump float;
 const float TUNNEL_SIZE  = 0.25;	// smaller values for smaller/thinner tunnel
 const float TUNNEL_SPEED = 0.025;		// speed of tunnel effect, negative values ok
 
-const float PI = 3.141592;
+#include "lib/tunnel.glsl"
 
 uniform float u_time;
 uniform vec2 canvasSize;
 uniform sampler2D texNoise;
 
-vec2 tunnel(vec2 uv, float size, float time)
-{
-    vec2 p  = -1.0 + (2.0 * uv);
-    float a = atan(p.y, p.x);
-    float r = sqrt(dot(p, p));
-    return vec2(a / PI, time + (size / r));
-}
-
 void main()
 {
   float scale = min(canvasSize.x, canvasSize.y);
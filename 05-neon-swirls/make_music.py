@@ -0,0 +1,62 @@
+#!/usr/bin/env python3
+# Writes assets/music.wav, the loop the swirls react to: 8 seconds at 120bpm with a kick on every
+# beat, off-beat hi-hats, a bass line and an arpeggio. Standard library only.
+
+import math
+import random
+import struct
+import wave
+from pathlib import Path
+
+RATE = 22050
+BPM = 120
+BEAT = 60 / BPM
+BARS = 4
+
+random.seed(1)
+
+# A minor, F, C, G - one chord a bar, as MIDI notes
+CHORDS = [(57, 60, 64), (53, 57, 60), (48, 52, 55), (55, 59, 62)]
+
+
+def freq(note):
+    return 440 * 2 ** ((note - 69) / 12)
+
+
+def sample(t):
+    beat, in_beat = divmod(t, BEAT)
+    bar = int(beat) // 4
+    chord = CHORDS[bar % len(CHORDS)]
+
+    kick = math.sin(2 * math.pi * (45 + 120 * math.exp(-in_beat * 35)) * in_beat) * math.exp(-in_beat * 9)
+
+    off_beat = (in_beat - BEAT / 2) % BEAT
+    hat = (random.random() * 2 - 1) * math.exp(-off_beat * 60) if in_beat >= BEAT / 2 else 0.0
+
+    bass_note = freq(chord[0] - 24)
+    bass_phase = (t * bass_note) % 1.0
+    bass = (2 * bass_phase - 1) * 0.6 * math.exp(-(in_beat % (BEAT / 2)) * 4)
+
+    step = BEAT / 4
+    arp_index, in_step = divmod(t, step)
+    arp_note = freq(chord[int(arp_index) % 3] + 12)
+    arp = math.sin(2 * math.pi * arp_note * t) * math.exp(-in_step * 10)
+
+    return 0.45 * kick + 0.12 * hat + 0.25 * bass + 0.2 * arp
+
+
+def main():
+    frames = int(RATE * BEAT * 4 * BARS)
+    data = bytearray()
+    for n in range(frames):
+        value = max(-1.0, min(1.0, sample(n / RATE)))
+        data += struct.pack("<h", int(value * 32767))
+
+    with wave.open(str(Path(__file__).parent / "assets" / "music.wav"), "wb") as out:
+        out.setnchannels(1)
+        out.setsampwidth(2)
+        out.setframerate(RATE)
+        out.writeframes(bytes(data))
+
+
+main()
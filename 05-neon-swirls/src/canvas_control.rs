@@ -1,10 +1,35 @@
-use std::rc::Rc;
+use std::collections::BTreeMap;
 
-use web_sys::{window, HtmlCanvasElement,HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+use web_sys::{window, HtmlAudioElement, HtmlCanvasElement, Response, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
 use yew::prelude::*;
+use yew_router::prelude::*;
 
 use wasm_bindgen::{prelude::*, JsCast};
 use gloo_console::log;
+use wasm_bindgen_futures::JsFuture;
+
+use shader_common::audio::{AudioAnalyzer, AudioClip, AudioFrame, AudioUniforms};
+use shader_common::download;
+use shader_common::editor::ShaderEditor;
+use shader_common::gesture::{self, Gesture, GestureRecognizer, GestureUniforms};
+use shader_common::key_binding_panel::KeyBindingPanel;
+use shader_common::keyboard::{AppAction, KeyBindings, KeyInput, KeyboardState};
+use shader_common::library;
+use shader_common::palette::{Palette, PaletteFade, PaletteUniforms};
+use shader_common::palette_editor::PaletteEditor;
+use shader_common::perf::{FrameStats, GpuTimer};
+use shader_common::perf_hud::PerfHud;
+use shader_common::param_panel::ParamPanel;
+use shader_common::preset_panel::PresetPanel;
+use shader_common::params::{Param, ParamKind, ParamValue, ShaderParams};
+use shader_common::pointer::{self, PointerAction, PointerState, PointerUpdate};
+use shader_common::preprocess::preprocess;
+use shader_common::program::{compile_program, Defines};
+use shader_common::url_state::UrlState;
+use shader_common::video::VideoTexture;
+#[cfg(feature = "hot-reload")]
+use shader_common::hot_reload::{self, HotReload};
+use shader_common::noise::SeededNoise;
 
 pub struct CanvasControl {
     callback: Closure<dyn FnMut()>,
@@ -13,35 +38,164 @@ pub struct CanvasControl {
     node_ref: NodeRef,
     last_update: f64,
     shader_program: Option<WebGlProgram>,
+    canvas_size_location: Option<WebGlUniformLocation>,
     time_location: Option<WebGlUniformLocation>,
     tri_count: i32,
     u_time: f32,
+    speed: f32,
+    paused: bool,
+    keyboard: KeyboardState,
+    gestures: GestureRecognizer,
+    gesture_uniforms: GestureUniforms,
+    keyboard_location: Option<WebGlUniformLocation>,
+    key_bindings: KeyBindings,
+    on_bind_key: Callback<(AppAction, String)>,
+    screenshot_pending: bool,
+    // The music, once it's loaded, and whether it's playing
+    music: Option<AudioClip>,
+    music_on: bool,
+    audio_ref: NodeRef,
+    analyzer: AudioAnalyzer,
+    audio_uniforms: AudioUniforms,
+    // Drawn behind the swirls, None if it couldn't be played
+    video: Option<VideoTexture>,
     height: i32,
     width: i32,
-    red: f32,
+    window_width: i32,
+    // Canvas pixels per CSS pixel, width and height are in CSS pixels
+    pixel_ratio: f64,
+    pointer: PointerState,
+    // Frame times for the performance overlay, kept whether or not it's showing
+    frame_stats: FrameStats,
+    show_hud: bool,
+    // None if the browser can't time the GPU
+    gpu_timer: Option<GpuTimer>,
+    gpu_time: Option<f64>,
+    show_editor: bool,
+    editor_error: Option<String>,
+    on_edit: Callback<String>,
+    params: ShaderParams,
+    on_param: Callback<(String, ParamValue)>,
+    on_param_reset: Callback<()>,
+    on_preset: Callback<Vec<(String, ParamValue)>>,
+    // The lines' colours, on the wall clock so they still fade while paused
+    palette: PaletteFade,
+    palette_uniforms: PaletteUniforms,
+    on_palette: Callback<Palette>,
+    on_palette_preset: Callback<Palette>,
+    // The generated noise texture, its seed is on the panel with the shader's parameters
+    noise: SeededNoise,
+    colour: PointerColour,
     red_location: Option<WebGlUniformLocation>,
-    green: f32,
-    green_location: Option<WebGlUniformLocation>,
+    blue_location: Option<WebGlUniformLocation>,
+    #[cfg(feature = "hot-reload")]
+    hot_reload: Option<HotReload>,
+}
+
+// The colour picked with the pointer. There's no slider for it, but it goes in the link with the
+// shader's params so a shared link shows the same colours.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PointerColour {
+    red: f32,
     blue: f32,
-    blue_location: Option<WebGlUniformLocation>
+}
+
+impl Default for PointerColour {
+    fn default() -> Self {
+        PointerColour { red: 0.0, blue: 0.0 }
+    }
+}
+
+const COLOUR_NAMES: [&str; 2] = ["red", "blue"];
+
+impl PointerColour {
+    fn named_values(&self) -> Vec<(String, ParamValue)> {
+        COLOUR_NAMES.iter().zip([self.red, self.blue]).map(|(name, value)| (name.to_string(), ParamValue::Float(value))).collect()
+    }
+
+    // False if `name` isn't one of ours
+    fn set(&mut self, name: &str, value: ParamValue) -> bool {
+        let ParamValue::Float(value) = value else {
+            return false;
+        };
+        let value = value.clamp(0.0, 1.0);
+        match name {
+            "red" => self.red = value,
+            "blue" => self.blue = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+// What the link can hold: the shader's params, the noise seed and the pointer colour
+fn link_params(params: &[Param]) -> Vec<Param> {
+    let colour = COLOUR_NAMES.iter().map(|name| Param {
+        name: name.to_string(),
+        label: name.to_string(),
+        kind: ParamKind::Slider { min: 0.0, max: 1.0, step: 0.001 },
+        default: ParamValue::Float(0.0),
+        previous_names: Vec::new(),
+    });
+    params.iter().cloned().chain([SeededNoise::param()]).chain(colour).collect()
 }
 
 pub enum CanvasControlMsg {
-    MouseDown((f64, f64)),
-    MouseUp((f64,f64)),
-    MouseMove((f64,f64)),
-    TouchStart((f64, f64)),
-    TouchEnd((f64, f64)),
-    TouchMove((f64, f64)),
+    Pointer(PointerUpdate),
+    Touches(Vec<gesture::TouchPoint>),
+    Gesture(Gesture),
+    KeyDown(KeyInput),
+    KeyUp(KeyInput),
+    Blur,
+    BindKey((AppAction, String)),
+    #[cfg(feature = "hot-reload")]
+    ShaderChanged(String),
+    #[cfg(feature = "hot-reload")]
+    ShaderSources((String, String)),
+    ToggleEditor,
+    EditorSource(String),
+    SetParam((String, ParamValue)),
+    ResetParams,
+    LoadPreset(Vec<(String, ParamValue)>),
+    SetPalette(Palette),
+    FadePalette(Palette),
+    ShareLink,
+    MusicLoaded(Result<AudioClip, String>),
+    ToggleMusic,
+    ToggleHud,
     Render,
-    Null
 }
 
 
 #[derive(Clone, Debug, PartialEq, Eq, Properties)]
 pub struct CanvasControlProps;
 
-const TEXTURE_1: &str = "/assets/noise.png";
+// The keyboard state texture goes on the unit after the noise texture
+const KEYBOARD_UNIT: u32 = 1;
+// And the audio texture after that
+const AUDIO_UNIT: u32 = 2;
+const MUSIC: &str = "/assets/music.wav";
+// How far the <audio> element can wander from u_time before it's moved back, in seconds
+const MAX_MUSIC_DRIFT: f64 = 0.1;
+// The video goes on the last unit, the noise texture is shown until it has a frame
+const VIDEO_UNIT: u32 = 3;
+// AV1, or H.264 for browsers without an AV1 decoder (see the README for making it)
+const VIDEO: &[(&str, &str)] = &[
+    ("/assets/clip.webm", "video/webm; codecs=\"av01.0.00M.08\""),
+    ("/assets/clip.mp4", "video/mp4; codecs=\"avc1.42C01E\""),
+];
+// Seconds to fade to a palette picked from the presets
+const PALETTE_FADE: f64 = 1.5;
+// Where the "next tutorial" key goes
+const NEXT_TUTORIAL: &str = "/06-fractal-pattern/";
+
+// Names to fetch from shader-watch when hot reloading
+#[cfg(feature = "hot-reload")]
+const VERT_NAME: &str = "swirl.vert";
+const FRAG_NAME: &str = "swirl.frag";
+
+// The fragment shader as written, before includes are expanded, for the editor to start from
+const FRAG_SOURCE: &str = include_str!("./swirl.frag");
 
 impl Component for CanvasControl {
     type Message = CanvasControlMsg;
@@ -55,108 +209,337 @@ impl Component for CanvasControl {
         let width = window().unwrap().inner_width().unwrap().as_f64().unwrap();
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
-        CanvasControl{
-            callback: callback,
+        // Made once up front so the panels' props don't change every frame
+        let on_edit = ctx.link().callback(CanvasControlMsg::EditorSource);
+        let on_param = ctx.link().callback(CanvasControlMsg::SetParam);
+        let on_param_reset = ctx.link().callback(|_| CanvasControlMsg::ResetParams);
+        let on_preset = ctx.link().callback(CanvasControlMsg::LoadPreset);
+        let on_palette = ctx.link().callback(CanvasControlMsg::SetPalette);
+        let on_palette_preset = ctx.link().callback(CanvasControlMsg::FadePalette);
+        let on_bind_key = ctx.link().callback(CanvasControlMsg::BindKey);
+
+        let mut control = CanvasControl{
+            callback,
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
             last_update: instant::now(),
             shader_program: None,
+            canvas_size_location: None,
             time_location: None,
             tri_count: 0,
             u_time: 0.0,
+            speed: 1.0,
+            paused: false,
+            keyboard: KeyboardState::default(),
+            // One finger is left for picking the colour
+            gestures: GestureRecognizer::new().pan_touches(2),
+            gesture_uniforms: GestureUniforms::default(),
+            keyboard_location: None,
+            key_bindings: KeyBindings::default(),
+            on_bind_key,
+            screenshot_pending: false,
+            music: None,
+            music_on: false,
+            audio_ref: NodeRef::default(),
+            analyzer: AudioAnalyzer::new(),
+            audio_uniforms: AudioUniforms::default(),
+            video: VideoTexture::new(VIDEO).map_err(|err| log!("ERROR loading video:", err)).ok(),
             height: height as i32,
             width: width as i32,
-            red: 0.0,
+            window_width: width as i32,
+            pixel_ratio: pointer::device_pixel_ratio(),
+            pointer: PointerState::default(),
+            frame_stats: FrameStats::new(),
+            show_hud: false,
+            gpu_timer: None,
+            gpu_time: None,
+            show_editor: false,
+            editor_error: None,
+            on_edit,
+            params: ShaderParams::from_source(include_str!(concat!(env!("OUT_DIR"), "/swirl.frag"))),
+            on_param,
+            on_param_reset,
+            on_preset,
+            palette: PaletteFade::new(Palette::default()),
+            palette_uniforms: PaletteUniforms::default(),
+            on_palette,
+            on_palette_preset,
+            noise: SeededNoise::new(),
+            colour: PointerColour::default(),
             red_location: None,
-            green: 1.0,
-            green_location: None,
-            blue: 0.0,
-            blue_location: None
+            blue_location: None,
+            #[cfg(feature = "hot-reload")]
+            hot_reload: None,
+        };
+
+        // Pick up anything shared in the link
+        if let Some(query) = ctx.link().location().and_then(|location| location.query::<BTreeMap<String, String>>().ok()) {
+            control.apply_url_state(UrlState::from_query(&query, &link_params(control.params.params())));
         }
+
+        // Decoded here rather than by the <audio> element so it can be analysed
+        ctx.link().send_future(async {
+            let clip = fetch_bytes(MUSIC).await.and_then(|bytes| AudioClip::decode(&bytes));
+            CanvasControlMsg::MusicLoaded(clip)
+        });
+        control
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool{
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool{
         match msg {
-            CanvasControlMsg::MouseDown(evt) => {
-                self.red = (evt.0 / self.width as f64) as f32;
-                self.green = 1.-self.red;
-                self.blue = (evt.1 / self.height as f64) as f32;
+            CanvasControlMsg::Pointer(update) => {
+                self.pointer.update(update);
+
+                // The mouse, or the first finger, picks the colour
+                if matches!(update.action, PointerAction::Down | PointerAction::Move) {
+                    let (width, height) = self.canvas_size();
+                    let (u, v) = self.pointer.uv((width as f32, height as f32));
+                    self.colour.red = u;
+                    // Top to bottom, as it's always been
+                    self.colour.blue = 1. - v;
+                }
+                true
+            },
+            CanvasControlMsg::Touches(touches) => {
+                // More than one finger pans, pinches and rotates the swirl
+                let gestures = self.gestures.update(&touches);
+                ctx.link().send_message_batch(gestures.into_iter().map(CanvasControlMsg::Gesture).collect());
+                true
+            },
+            CanvasControlMsg::Gesture(gesture) => {
+                // The totals go to the shader every frame. If it's been pinched down to
+                // nothing there's no way to get it back, so start again.
+                if gesture == Gesture::End && self.gestures.state().zoom < 0.05 {
+                    self.gestures.reset();
+                }
+                false
+            },
+            CanvasControlMsg::KeyDown(key) => {
+                match self.key_bindings.action(&key.code) {
+                    Some(action) => {
+                        if !key.repeat {
+                            self.run_action(action);
+                        }
+                    },
+                    None => self.keyboard.key_down(key.key_code),
+                }
+                false
+            },
+            CanvasControlMsg::KeyUp(key) => {
+                self.keyboard.key_up(key.key_code);
+                false
+            },
+            CanvasControlMsg::Blur => {
+                self.keyboard.release_all();
+                false
+            },
+            CanvasControlMsg::BindKey((action, code)) => {
+                self.key_bindings.rebind(action, &code);
+                true
+            },
+            #[cfg(feature = "hot-reload")]
+            CanvasControlMsg::ShaderChanged(name) => {
+                if hot_reload::affects(&name, VERT_NAME) || hot_reload::affects(&name, FRAG_NAME) {
+                    ctx.link().send_future_batch(async {
+                        let vert = hot_reload::fetch_source(hot_reload::DEFAULT_HTTP_URL, VERT_NAME).await;
+                        let frag = hot_reload::fetch_source(hot_reload::DEFAULT_HTTP_URL, FRAG_NAME).await;
+                        match (vert, frag) {
+                            (Ok(vert), Ok(frag)) => vec![CanvasControlMsg::ShaderSources((vert, frag))],
+                            (Err(err), _) | (_, Err(err)) => {
+                                log!("ERROR fetching shaders:", err);
+                                Vec::new()
+                            }
+                        }
+                    });
+                }
+                false
+            },
+            #[cfg(feature = "hot-reload")]
+            CanvasControlMsg::ShaderSources((vert_code, frag_code)) => {
+                let gl = self.gl.clone().expect("GL Context not initialized!");
+                match compile_program(&gl, &vert_code, &frag_code, &Defines::new()) {
+                    Ok(program) => {
+                        log!("Reloaded", FRAG_NAME);
+                        if let Some(old) = self.shader_program.take() {
+                            gl.delete_program(Some(&old));
+                        }
+                        self.params.update_source(&frag_code);
+                        self.use_program(program);
+                    },
+                    // Carry on with the last program that worked until the shader is fixed
+                    Err(err) => log!("ERROR reloading shader, keeping the previous one:", err),
+                }
+                false
+            },
+            CanvasControlMsg::ToggleEditor => {
+                // The editor takes the right half of the screen, so draw at half the width
+                self.show_editor = !self.show_editor;
+                self.width = if self.show_editor { self.window_width / 2 } else { self.window_width };
+
+                let (width, height) = self.canvas_size();
+                if let Some(gl) = &self.gl {
+                    gl.uniform2f(self.canvas_size_location.as_ref(), width as f32, height as f32);
+                }
+                true
+            },
+            CanvasControlMsg::EditorSource(source) => {
+                self.compile_edit(&source);
+                true
+            },
+            CanvasControlMsg::SetParam((name, value)) => {
+                self.set_param(&name, value);
+                self.write_url_state(ctx);
+                true
+            },
+            CanvasControlMsg::ResetParams => {
+                self.params.reset();
+                self.noise.reset();
+                self.write_url_state(ctx);
                 true
             },
-            CanvasControlMsg::MouseUp(_evt) => {
+            CanvasControlMsg::SetPalette(palette) => {
+                self.palette.set(palette);
+                self.write_url_state(ctx);
                 true
             },
-            CanvasControlMsg::MouseMove(evt) => {
-                self.red = (evt.0 / self.width as f64) as f32;
-                self.green = 1.-self.red;
-                self.blue = (evt.1 / self.height as f64) as f32;
+            CanvasControlMsg::FadePalette(palette) => {
+                self.palette.fade_to(palette, instant::now() / 1000.0, PALETTE_FADE);
+                self.write_url_state(ctx);
                 true
             },
-            CanvasControlMsg::TouchStart(evt) => {
-                self.red = (evt.0 / self.width as f64) as f32;
-                self.green = 1.-self.red;
-                self.blue = (evt.1 / self.height as f64) as f32;
+            CanvasControlMsg::LoadPreset(values) => {
+                for (name, value) in values {
+                    self.set_param(&name, value);
+                }
+                self.write_url_state(ctx);
                 true
             },
-            CanvasControlMsg::TouchEnd(_evt) => {
+            CanvasControlMsg::ShareLink => {
+                self.write_url_state(ctx);
+                false
+            },
+            CanvasControlMsg::MusicLoaded(clip) => {
+                match clip {
+                    Ok(clip) => self.music = Some(clip),
+                    Err(err) => log!("ERROR loading music:", err),
+                }
                 true
             },
-            CanvasControlMsg::TouchMove(evt) => {
-                self.red = (evt.0 / self.width as f64) as f32;
-                self.green = 1.-self.red;
-                self.blue = (evt.1 / self.height as f64) as f32;
+            CanvasControlMsg::ToggleMusic => {
+                self.music_on = !self.music_on;
+                self.analyzer.reset();
+                // Straight away, browsers only allow playing in answer to a click
+                self.sync_music();
+                true
+            },
+            CanvasControlMsg::ToggleHud => {
+                self.run_action(AppAction::ToggleHud);
                 true
             },
             CanvasControlMsg::Render => {
                 self.render();
                 true
             },
-            CanvasControlMsg::Null => {
-                true
-            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let onmousedown = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseDown((evt.page_x() as f64, evt.page_y() as f64))
-        });
-        let onmousemove = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseMove((evt.page_x() as f64, evt.page_y() as f64))
-        });
-        let onmouseup = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseUp((evt.page_x() as f64, evt.page_y() as f64))
-        });
-        let ontouchstart = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchStart((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
-        });
-        let ontouchend = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchEnd((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
-        });
-        let ontouchmove = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchMove((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
+        // Mouse, pen and the first finger all come through pointer events
+        let pointer_callback = |action| {
+            ctx.link().batch_callback(move |evt: PointerEvent| {
+                PointerUpdate::from_event(&evt, action).map(CanvasControlMsg::Pointer)
+            })
+        };
+        // Every touch event just passes on all the fingers that are down, the recognizer
+        // works out what changed. For touchend that's the ones left.
+        let ontouch = ctx.link().batch_callback(move |evt: TouchEvent| {
+            evt.prevent_default();
+            let canvas: HtmlCanvasElement = evt.current_target()?.dyn_into().ok()?;
+            Some(CanvasControlMsg::Touches(gesture::touch_points(&evt.touches(), &canvas)))
         });
 
+        let onkeydown = ctx.link().callback(|evt: KeyboardEvent| CanvasControlMsg::KeyDown(KeyInput::from(&evt)));
+        let onkeyup = ctx.link().callback(|evt: KeyboardEvent| CanvasControlMsg::KeyUp(KeyInput::from(&evt)));
+        let onblur = ctx.link().callback(|_: FocusEvent| CanvasControlMsg::Blur);
+
+        let ontoggleeditor = ctx.link().callback(|_| CanvasControlMsg::ToggleEditor);
+        let onsharelink = ctx.link().callback(|_| CanvasControlMsg::ShareLink);
+        let ontogglemusic = ctx.link().callback(|_| CanvasControlMsg::ToggleMusic);
+        let ontogglehud = ctx.link().callback(|_| CanvasControlMsg::ToggleHud);
+        let (canvas_width_px, canvas_height_px) = self.canvas_size();
+        let canvas_width = if self.show_editor { "50vw" } else { "100vw" };
+        let (panel_params, panel_values) = self.panel_params();
+
         html! {
             <div class="game_canvas">
+                <button class="editor_toggle" onclick={ontoggleeditor}>
+                    { if self.show_editor { "Close editor" } else { "Edit shader" } }
+                </button>
+                <button class="share_link" onclick={onsharelink} title="Put the current time and parameters in the address bar">
+                    { "Link to this view" }
+                </button>
+                <button class="music_toggle" onclick={ontogglemusic} disabled={self.music.is_none()}>
+                    { if self.music_on { "Music off" } else { "Music on" } }
+                </button>
+                <button class="hud_toggle" onclick={ontogglehud}>
+                    { if self.show_hud { "Hide stats" } else { "Frame stats" } }
+                </button>
+                if self.show_hud {
+                    <PerfHud
+                        stats={self.frame_stats.clone()}
+                        gpu_time={self.gpu_time}
+                        gpu_timer={self.gpu_timer.is_some()}
+                        width={canvas_width_px}
+                        height={canvas_height_px}
+                        pixel_ratio={self.pixel_ratio}
+                    />
+                }
+                <audio ref={self.audio_ref.clone()} src={MUSIC} loop=true preload="auto"></audio>
+                <ParamPanel
+                    params={panel_params.clone()}
+                    values={panel_values.clone()}
+                    on_change={self.on_param.clone()}
+                    on_reset={self.on_param_reset.clone()}
+                >
+                    <PaletteEditor
+                        palette={self.palette.target()}
+                        on_change={self.on_palette.clone()}
+                        on_preset={self.on_palette_preset.clone()}
+                    />
+                    <PresetPanel
+                        tutorial={env!("CARGO_PKG_NAME")}
+                        params={panel_params}
+                        values={panel_values}
+                        on_load={self.on_preset.clone()}
+                    />
+                    <KeyBindingPanel
+                        bindings={self.key_bindings.clone()}
+                        on_bind={self.on_bind_key.clone()}
+                    />
+                </ParamPanel>
+                if self.show_editor {
+                    <ShaderEditor
+                        name={FRAG_NAME}
+                        original={FRAG_SOURCE}
+                        error={self.editor_error.clone().map(AttrValue::from)}
+                        on_change={self.on_edit.clone()}
+                    />
+                }
                 <canvas id="canvas"
-                    style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px;"}
-                    onmousedown={onmousedown}
-                    onmousemove={onmousemove}
-                    onmouseup={onmouseup}
-                    ontouchstart={ontouchstart}
-                    ontouchend={ontouchend}
-                    ontouchmove={ontouchmove}
+                    style={format!("margin: 0px; width: {}; height: 100vh; left:0px; top:0px; touch-action: none;", canvas_width)}
+                    onpointerdown={pointer_callback(PointerAction::Down)}
+                    onpointermove={pointer_callback(PointerAction::Move)}
+                    onpointerup={pointer_callback(PointerAction::Up)}
+                    onpointerleave={pointer_callback(PointerAction::Leave)}
+                    onpointercancel={pointer_callback(PointerAction::Leave)}
+                    ontouchstart={ontouch.clone()}
+                    ontouchend={ontouch.clone()}
+                    ontouchcancel={ontouch.clone()}
+                    ontouchmove={ontouch}
+                    onkeydown={onkeydown}
+                    onkeyup={onkeyup}
+                    onblur={onblur}
                     ref={self.node_ref.clone()}
                     tabindex = "1"
                 ></canvas>
@@ -174,14 +557,31 @@ impl Component for CanvasControl {
             .dyn_into()
             .unwrap();
 
-        c.set_width(self.width as u32);
-        c.set_height(self.height as u32);
+        let (width, height) = self.canvas_size();
+        c.set_width(width as u32);
+        c.set_height(height as u32);
 
         self.canvas = Some(c);
         self.gl = Some(gl);
 
         if first_render {
             self.reload();
+            self.gpu_timer = self.gl.as_ref().and_then(GpuTimer::new);
+
+            // So keys work without having to click on the canvas first
+            if let Some(canvas) = &self.canvas {
+                let _ = canvas.focus();
+            }
+
+            #[cfg(feature = "hot-reload")]
+            {
+                let link = ctx.link().clone();
+                let on_change = move |name| link.send_message(CanvasControlMsg::ShaderChanged(name));
+                match HotReload::connect(hot_reload::DEFAULT_WS_URL, on_change) {
+                    Ok(hot_reload) => self.hot_reload = Some(hot_reload),
+                    Err(err) => log!("ERROR starting shader hot reload:", err),
+                }
+            }
 
             ctx.link().send_message(CanvasControlMsg::Render);
         }
@@ -197,16 +597,135 @@ impl CanvasControl {
             return;
         }
         let diff = now - self.last_update;
+        self.frame_stats.record(diff);
 
         let delta = diff as f64 / 1000.0; // Frac of seconds
-        self.u_time += delta as f32;
+        if !self.paused {
+            self.u_time += delta as f32 * self.speed;
+        }
 
         // Do updates using delta
         self.last_update = now;
     }
 
+    // Keep the <audio> element playing in step with u_time, as that's what the analysis follows
+    fn sync_music(&self) {
+        let (Some(audio), Some(music)) = (self.audio_ref.cast::<HtmlAudioElement>(), &self.music) else {
+            return;
+        };
+        if !self.music_on || self.paused || self.speed <= 0.0 {
+            if !audio.paused() {
+                let _ = audio.pause();
+            }
+            return;
+        }
+
+        if audio.playback_rate() != self.speed as f64 {
+            audio.set_playback_rate(self.speed as f64);
+        }
+        let time = (self.u_time as f64).rem_euclid(music.duration());
+        if (audio.current_time() - time).abs() > MAX_MUSIC_DRIFT {
+            audio.set_current_time(time);
+        }
+        if audio.paused() {
+            if let Err(err) = audio.play() {
+                log!("ERROR playing music:", err);
+            }
+        }
+    }
+
+    // The video runs off the same clock as everything else, so it pauses, speeds up and loops with it
+    fn sync_video(&mut self) {
+        let Some(video) = &self.video else {
+            return;
+        };
+        // Say so once and carry on with the noise, e.g. for a browser that can't play either clip
+        if let Some(err) = video.error() {
+            log!("ERROR playing video:", err);
+            self.video = None;
+            return;
+        }
+        video.sync(self.u_time as f64, !self.paused, self.speed as f64);
+    }
+
+    // Size of the canvas in its own pixels, which is what the shader sees
+    fn canvas_size(&self) -> (i32, i32) {
+        ((self.width as f64 * self.pixel_ratio) as i32, (self.height as f64 * self.pixel_ratio) as i32)
+    }
+
+    fn run_action(&mut self, action: AppAction) {
+        match action {
+            AppAction::Pause => self.paused = !self.paused,
+            // Has to happen straight after drawing, so leave it for the next frame
+            AppAction::Screenshot => self.screenshot_pending = true,
+            AppAction::NextTutorial => {
+                if let Err(err) = window().unwrap().location().set_href(NEXT_TUTORIAL) {
+                    log!("ERROR going to the next tutorial:", err);
+                }
+            },
+            // Start counting afresh each time it's opened
+            AppAction::ToggleHud => {
+                self.show_hud = !self.show_hud;
+                self.frame_stats.reset();
+                self.gpu_time = None;
+            },
+        }
+    }
+
+    fn apply_url_state(&mut self, state: UrlState) {
+        if let Some(time) = state.time {
+            self.u_time = time;
+        }
+        if let Some(speed) = state.speed {
+            self.speed = speed;
+        }
+        if let Some(palette) = state.palette {
+            self.palette.set(palette);
+        }
+        for (name, value) in state.values {
+            self.set_param(&name, value);
+        }
+    }
+
+    // The pointer colour, the noise seed or one of the shader's params
+    fn set_param(&mut self, name: &str, value: ParamValue) {
+        if self.colour.set(name, value) {
+            return;
+        }
+        if name == SeededNoise::PARAM {
+            self.noise.set(value);
+        } else {
+            self.params.set(name, value);
+        }
+    }
+
+    // The shader's parameters with the noise seed after them, for the panel and presets
+    fn panel_params(&self) -> (Vec<Param>, Vec<ParamValue>) {
+        let mut params = self.params.params().to_vec();
+        let mut values = self.params.values().to_vec();
+        params.push(SeededNoise::param());
+        values.push(self.noise.value());
+        (params, values)
+    }
+
+    // Replace rather than push, so dragging a slider doesn't fill up the history
+    fn write_url_state(&self, ctx: &Context<Self>) {
+        let state = UrlState {
+            time: Some(self.u_time),
+            speed: Some(self.speed),
+            palette: Some(self.palette.target()),
+            values: self.params.named_values().into_iter().chain([(SeededNoise::PARAM.to_string(), self.noise.value())]).chain(self.colour.named_values()).collect(),
+        };
+        let (Some(navigator), Some(route)) = (ctx.link().navigator(), ctx.link().route::<crate::Route>()) else {
+            return;
+        };
+        if let Err(err) = navigator.replace_with_query(&route, &state.to_query()) {
+            log!("ERROR updating the link:", err.to_string());
+        }
+    }
+
     fn reload(&mut self) {
-        // Set up shaders and 
+        // Set up the vertex buffer and texture, the shader is set up by use_program
         let gl = match &self.gl {
             Some(gl)=> gl,
             None => {
@@ -215,9 +734,6 @@ impl CanvasControl {
             }
         };
 
-        let vert_code = include_str!("./swirl.vert");
-        let frag_code = include_str!("./swirl.frag");
-
         let _: &HtmlCanvasElement = match &self.canvas {
             Some(canv) => canv,
             None => return,
@@ -241,18 +757,23 @@ impl CanvasControl {
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&vertex_buffer));
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
-        let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
-        gl.compile_shader(&vert_shader);
+        // The noise texture is generated rather than loaded, on the next frame and again
+        // whenever the seed changes
+        self.noise.forget();
 
-        let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
-        gl.compile_shader(&frag_shader);
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/swirl.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/swirl.frag"));
 
-        let shader_program: WebGlProgram = gl.create_program().unwrap();
-        gl.attach_shader(&shader_program, &vert_shader);
-        gl.attach_shader(&shader_program, &frag_shader);
-        gl.link_program(&shader_program);
+        match compile_program(gl, vert_code, frag_code, &Defines::new()) {
+            Ok(program) => self.use_program(program),
+            Err(err) => log!("ERROR compiling shader:", err),
+        }
+    }
+
+    // Start drawing with `shader_program`. Uniform and attribute locations belong to a
+    // program so have to be looked up again whenever it changes.
+    fn use_program(&mut self, shader_program: WebGlProgram) {
+        let gl = self.gl.as_ref().expect("GL Context not initialized!");
 
         gl.use_program(Some(&shader_program));
 
@@ -261,72 +782,80 @@ impl CanvasControl {
         gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
         gl.enable_vertex_attrib_array(position);
 
-        let canvassize = gl.get_uniform_location(&shader_program, "canvasSize");
-        gl.uniform2f(canvassize.as_ref(), self.width as f32, self.height as f32);
+        self.canvas_size_location = gl.get_uniform_location(&shader_program, "canvasSize");
+        let (width, height) = self.canvas_size();
+        gl.uniform2f(self.canvas_size_location.as_ref(), width as f32, height as f32);
 
         self.time_location = gl.get_uniform_location(&shader_program, "u_time");
-        gl.uniform1f(self.time_location.as_ref() , 1.0);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
 
         // Get color uniform locations
         self.red_location = gl.get_uniform_location(&shader_program, "red");
-        gl.uniform1f(self.red_location.as_ref() , 1.0);
-        self.green_location = gl.get_uniform_location(&shader_program, "green");
-        gl.uniform1f(self.green_location.as_ref() , 1.0);
         self.blue_location = gl.get_uniform_location(&shader_program, "blue");
-        gl.uniform1f(self.blue_location.as_ref() , 1.0); 
-
-        // Setup the texture 
-        // based on https://snoozetime.github.io/2019/12/19/webgl-texture.html
-        let texture = gl.create_texture().unwrap();
-        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-
-        let image: HtmlImageElement = HtmlImageElement::new().unwrap();
-        let imgrc = Rc::new(image.clone());
-
-        {
-            let image = imgrc.clone();
-            let texture = texture.clone();
-            let gl = Rc::new(gl.clone());
-
-            let a = Closure::wrap(Box::new(move || {
-                gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-    
-                let _ = gl.tex_image_2d_with_u32_and_u32_and_image(
-                    GL::TEXTURE_2D,
-                    0,
-                    GL::RGBA.try_into().unwrap(),
-                    GL::RGBA.try_into().unwrap(),
-                    GL::UNSIGNED_BYTE,
-                    &image,
-                );
-    
-                // different from webgl1 where we need the pic to be power of 2
-                gl.generate_mipmap(GL::TEXTURE_2D);
-            }) as Box<dyn FnMut()>);
-
-            imgrc.set_onload(Some(a.as_ref().unchecked_ref()));
-    
-            // Normally we'd store the handle to later get dropped at an appropriate
-            // time but for now we want it to be a global handler so we use the
-            // forget method to drop it without invalidating the closure. Note that
-            // this is leaking memory in Rust, so this should be done judiciously!
-            a.forget();
+
+        self.params.locate(gl, &shader_program);
+
+        self.gesture_uniforms.locate(gl, &shader_program);
+        self.pointer.locate(gl, &shader_program);
+
+        self.keyboard_location = gl.get_uniform_location(&shader_program, "iKeyboard");
+        gl.uniform1i(self.keyboard_location.as_ref(), KEYBOARD_UNIT as i32);
+
+        self.audio_uniforms.locate(gl, &shader_program);
+        self.palette_uniforms.locate(gl, &shader_program);
+        if let Some(video) = &mut self.video {
+            video.locate(gl, &shader_program);
         }
-        image.set_src(TEXTURE_1);
 
         self.shader_program = Some(shader_program);
     }
 
+    // Compile a fragment shader from the editor. Errors are handed back to the editor and we
+    // keep drawing with whatever compiled last.
+    fn compile_edit(&mut self, frag_source: &str) {
+        let gl = self.gl.clone().expect("GL Context not initialized!");
+
+        let processed = match preprocess(FRAG_NAME, frag_source, |path| library::resolve(path).map(str::to_string)) {
+            Ok(processed) => processed,
+            Err(err) => {
+                self.editor_error = Some(format!("ERROR: {}", err));
+                return;
+            }
+        };
+
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/swirl.vert"));
+        match compile_program(&gl, vert_code, &processed.source, &Defines::new()) {
+            Ok(program) => {
+                if let Some(old) = self.shader_program.take() {
+                    gl.delete_program(Some(&old));
+                }
+                // The edit may have added or removed parameters
+                self.params.update_source(&processed.source);
+                self.use_program(program);
+                self.editor_error = None;
+            },
+            Err(log) => self.editor_error = Some(processed.source_map.remap_log(&log)),
+        }
+    }
+
     fn render(&mut self) {
         self.canvas_update();
-        
+        self.sync_music();
+        self.sync_video();
+
+        let audio = match &self.music {
+            Some(music) if self.music_on => self.analyzer.analyze(music, self.u_time as f64),
+            _ => AudioFrame::silent(),
+        };
+
         let gl = self.gl.as_ref().expect("GL Context not initialized!");
 
+        let (width, height) = self.canvas_size();
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            width,
+            height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -335,22 +864,117 @@ impl CanvasControl {
         // Enable the depth test
         gl.enable(GL::DEPTH_TEST);
 
+        // Only timed while the stats are showing
+        if let Some(timer) = self.gpu_timer.as_mut().filter(|_| self.show_hud) {
+            timer.begin();
+        }
+
         // Clear the color buffer bit
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
         
         // Pass color uniforms
-        gl.uniform1f(self.red_location.as_ref() , self.red);
-        gl.uniform1f(self.green_location.as_ref() , self.green);
-        gl.uniform1f(self.blue_location.as_ref() , self.blue);
+        gl.uniform1f(self.red_location.as_ref() , self.colour.red);
+        gl.uniform1f(self.blue_location.as_ref() , self.colour.blue);
+
+        // And anything set from the parameter panel
+        self.params.upload(gl);
+        self.noise.bind(gl);
+
+        self.keyboard.upload(gl, KEYBOARD_UNIT);
+        self.gesture_uniforms.upload(gl, &self.gestures.state());
+        self.pointer.upload(gl);
+        self.audio_uniforms.upload(gl, &audio, AUDIO_UNIT);
+        self.palette_uniforms.upload(gl, &self.palette.at(instant::now() / 1000.0));
+        if let Some(video) = &mut self.video {
+            video.upload(gl, VIDEO_UNIT);
+        }
 
         gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
+        if let Some(timer) = self.gpu_timer.as_mut().filter(|_| self.show_hud) {
+            timer.end();
+            self.gpu_time = timer.poll(gl);
+        }
+        self.keyboard.end_frame();
+        self.pointer.end_frame();
+
+        if self.screenshot_pending {
+            self.screenshot_pending = false;
+            if let Some(canvas) = &self.canvas {
+                if let Err(err) = download::save_canvas(canvas, "neon-swirls.png") {
+                    log!("ERROR saving screenshot:", err);
+                }
+            }
+        }
 
         window()
             .unwrap()
             .request_animation_frame(self.callback.as_ref().unchecked_ref())
             .unwrap();
     }
-}
\ No newline at end of file
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>, String> {
+    let window = window().ok_or("No window to fetch from")?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|err| format!("Unable to fetch {}: {:?}", url, err))?;
+    let response: Response = response.dyn_into().map_err(|err| format!("{:?}", err))?;
+    if !response.ok() {
+        return Err(format!("Unable to fetch {}: {}", url, response.status()));
+    }
+
+    let buffer = response.array_buffer().map_err(|err| format!("{:?}", err))?;
+    let buffer = JsFuture::from(buffer).await.map_err(|err| format!("{:?}", err))?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swirl_params() -> ShaderParams {
+        ShaderParams::from_source(include_str!(concat!(env!("OUT_DIR"), "/swirl.frag")))
+    }
+
+    #[test]
+    fn reads_the_colour_from_a_link() {
+        let query = BTreeMap::from([("red".to_string(), "0.2".to_string())]);
+        let state = UrlState::from_query(&query, &link_params(swirl_params().params()));
+        assert_eq!(state.values, vec![("red".to_string(), ParamValue::Float(0.2))]);
+
+        let mut colour = PointerColour::default();
+        for (name, value) in state.values {
+            assert!(colour.set(&name, value));
+        }
+        assert_eq!(colour, PointerColour { red: 0.2, blue: 0.0 });
+    }
+
+    #[test]
+    fn reads_the_noise_seed_from_a_link() {
+        let query = BTreeMap::from([(SeededNoise::PARAM.to_string(), "7".to_string())]);
+        let state = UrlState::from_query(&query, &link_params(swirl_params().params()));
+        assert_eq!(state.values, vec![(SeededNoise::PARAM.to_string(), ParamValue::Float(7.0))]);
+    }
+
+    #[test]
+    fn colour_and_palette_round_trip() {
+        let colour = PointerColour { red: 0.25, blue: 0.5 };
+        let params = swirl_params();
+        let state = UrlState {
+            palette: shader_common::palette::preset("Ember"),
+            values: params.named_values().into_iter().chain(colour.named_values()).collect(),
+            ..UrlState::default()
+        };
+        let read = UrlState::from_query(&state.to_query(), &link_params(params.params()));
+        assert_eq!(read.palette, state.palette);
+
+        let mut read_colour = PointerColour::default();
+        for (name, value) in read.values {
+            read_colour.set(&name, value);
+        }
+        assert_eq!(read_colour, colour);
+    }
+}
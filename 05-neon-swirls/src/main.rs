@@ -7,11 +7,13 @@ mod canvas_control;
 enum Route {
     #[at("/")]
     Home,
+    #[at("/neon-swirls")]
+    NeonSwirls,
 }
 
 fn switch(routes: Route) -> Html {    
     match routes {
-        Route::Home => html!{
+        Route::Home | Route::NeonSwirls => html!{
             <canvas_control::CanvasControl />
         }
     }
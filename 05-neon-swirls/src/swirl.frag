@@ -1,42 +1,61 @@
 // Taken from here: https://www.shadertoy.com/view/4djBRm
 precision highp float;
 
-const float TUNNEL_SIZE  = 0.25;	// smaller values for smaller/thinner tunnel
-const float TUNNEL_SPEED = 0.5;		// speed of tunnel effect, negative values ok
-
-const float PI = 3.141592;
+#include "lib/constants.glsl"
+#include "lib/palette.glsl"
+#include "lib/keyboard.glsl"
+#include "lib/rotation.glsl"
+#include "lib/audio.glsl"
 
 uniform float u_time;
 uniform vec2 canvasSize;
 uniform sampler2D texNoise;
+
+// assets/clip.webm, once it has a frame to show - until then the noise is used instead
+uniform sampler2D u_video;
+uniform float u_video_ready;
+uniform vec2 u_video_size;
 uniform float red;
-uniform float green;
 uniform float blue;
 
-vec2 tunnel(vec2 uv, float size, float time)
-{
-    vec2 p  = -1.0 + (2.0 * uv);
-    float a = atan(p.y, p.x);
-    float r = sqrt(dot(p, p));
-    return vec2(a / PI, time + (size / r));
-}
+// From two finger gestures on a touch screen
+uniform vec2 u_pan;
+uniform float u_zoom;
+uniform float u_rotation;
 
-vec3 palette(float t, vec3 a, vec3 b, vec3 c, vec3 d) {
-  return a + b*cos(6.28318*(c*t+d));
-}
+// Shadertoy style mouse: xy is the pointer while a button is down, z is positive while it's held
+uniform vec4 iMouse;
 
 float circle_dist(vec2 start, vec2 end) {
   vec2 calc = vec2(start.x - end.x, start.y - end.y);
   return length(start - end);
 }
 
-float dis=.5;
-float width=.1;
-float blur=.3;
+// These show up as sliders on the page - see the @ annotations
+uniform float dis; // @range 0.1..2 @default 0.5 @label Spacing
+uniform float width; // @range 0..0.5 @default 0.1 @label Line width
+uniform float blur; // @range 0..1 @default 0.3 @label Blur
+uniform float background; // @range 0..1 @default 0.35 @label Video
+
+// Fill the screen with the video, cropping whichever way it's too long
+vec2 coverUv(vec2 fragCoord) {
+  vec2 uv = fragCoord / canvasSize - 0.5;
+  float screenAspect = canvasSize.x / canvasSize.y;
+  float videoAspect = u_video_size.x / max(u_video_size.y, 1.0);
+  if (screenAspect > videoAspect) {
+    uv.y *= videoAspect / screenAspect;
+  } else {
+    uv.x *= screenAspect / videoAspect;
+  }
+  // Video rows start at the top
+  return vec2(uv.x, -uv.y) + 0.5;
+}
 
 void main()
 {
-	vec2 uv = ( gl_FragCoord.xy * 2.0 - canvasSize.xy ) / canvasSize.y;
+	vec2 uv = ( ( gl_FragCoord.xy - u_pan ) * 2.0 - canvasSize.xy ) / canvasSize.y;
+  // Turn the other way to the fingers, so the swirl follows them
+  uv = rotate2d( -u_rotation ) * uv / u_zoom;
   
   //vec2 o = uv;
   
@@ -47,9 +66,29 @@ void main()
   float offset = ( log(l) + ( angle / ( 2.*PI ) ) * dis );
   float circles = mod( offset + u_time, dis );
   
-  vec3 lineColor = vec3(red, green, blue);
+  // The pointer picks where along the palette to start, and it shifts further out
+  vec3 lineColor = palette(red + 0.5 * blue + 0.15 * log(l));
+  // Treble pushes the colour round towards the next one along
+  lineColor = mix(lineColor, lineColor.gbr, 0.5 * u_treble);
+
+  // The lines thicken on each beat, and with the part of the spectrum at this distance out
+  float lineWidth = width * (1.0 + 0.8 * u_beat + 0.5 * audioSpectrum(clamp(l * 0.3, 0.0, 1.0)));
+
+  vec3 col = ( smoothstep( circles-blur, circles, lineWidth ) - smoothstep( circles, circles+blur, lineWidth ) ) * lineColor;
+  col *= 1.0 + 0.5 * u_beat;
+
+  // Behind the lines, dimmed so they still stand out
+  vec3 noise = texture2D(texNoise, gl_FragCoord.xy / canvasSize).rgb;
+  vec3 video = texture2D(u_video, coverUv(gl_FragCoord.xy)).rgb;
+  col += mix(noise, video, u_video_ready) * background * (1.0 - col);
+
+  // Hold the up arrow to brighten, press I to invert
+  col *= 1.0 + keyDown(KEY_UP);
+  col = mix(col, lineColor - col, keyToggled(73.0));
 
-  vec3 col = ( smoothstep( circles-blur, circles, width ) - smoothstep( circles, circles+blur, width ) ) * lineColor;
+  // A glow under the pointer while the button is held
+  float held = iMouse.z > 0.0 ? 1.0 : 0.0;
+  col += held * lineColor * 0.3 * smoothstep(0.15 * canvasSize.y, 0.0, length(gl_FragCoord.xy - iMouse.xy));
 
   gl_FragColor = vec4(col, 1.0);
 }
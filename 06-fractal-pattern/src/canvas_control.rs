@@ -1,41 +1,70 @@
 use std::rc::Rc;
 
-use web_sys::{window, HtmlCanvasElement,HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+use web_sys::{window, HtmlCanvasElement, PointerEvent, WheelEvent, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
 use yew::prelude::*;
 
 use wasm_bindgen::{prelude::*, JsCast};
 use gloo_console::log;
 
+use shader_common::camera_controls::CameraControls;
+use shader_common::gesture::{self, TouchPoint};
+use shader_common::lesson::{Lesson, UniformOverrides};
+use shader_common::lesson_panel::LessonPanel;
+use shader_common::pointer::{self, PointerAction, PointerUpdate};
+use shader_common::program::{Defines, ProgramCache};
+use shader_common::noise::SeededNoise;
+use shader_common::param_panel::ParamPanel;
+use shader_common::params::ParamValue;
+
 pub struct CanvasControl {
     callback: Closure<dyn FnMut()>,
+    camera: CameraControls,
     canvas: Option<HtmlCanvasElement>,
     gl: Option<GL>,
     node_ref: NodeRef,
     last_update: f64,
+    // None if lesson.md didn't parse
+    lesson: Option<Rc<Lesson>>,
+    lesson_open: bool,
+    lesson_step: usize,
+    // The current step's defines, applied over the quality level's while the lesson is open
+    lesson_defines: Defines,
+    lesson_uniforms: UniformOverrides,
+    program_cache: ProgramCache,
+    quality: usize,
     shader_program: Option<WebGlProgram>,
     time_location: Option<WebGlUniformLocation>,
     tri_count: i32,
     u_time: f32,
     height: i32,
     width: i32,
+    // The generated noise texture, the seed is on the panel
+    noise: SeededNoise,
+    on_param: Callback<(String, ParamValue)>,
 }
 
 pub enum CanvasControlMsg {
-    MouseDown((f64, f64)),
-    MouseUp((f64,f64)),
-    MouseMove((f64,f64)),
-    TouchStart((f64, f64)),
-    TouchEnd((f64, f64)),
-    TouchMove((f64, f64)),
+    Pointer(PointerUpdate),
+    Touches(Vec<TouchPoint>),
+    // Wheel movement in pixels, and where the cursor is
+    Wheel((f64, (f32, f32))),
+    ResetCamera,
+    SetQuality(usize),
+    ToggleLesson,
+    LessonStep(usize),
+    LessonDefine((String, i32)),
+    SetParam((String, ParamValue)),
     Render,
-    Null
 }
 
 
 #[derive(Clone, Debug, PartialEq, Eq, Properties)]
 pub struct CanvasControlProps;
 
-const TEXTURE_1: &str = "/assets/noise.png";
+// Each quality level recompiles the shader with a different DEPTH_COUNT - the maximum number of
+// times the fractal is folded. Lower levels are much cheaper on slow GPUs.
+const QUALITY_LEVELS: &[(&str, i32)] = &[("Low", 4), ("Medium", 10), ("High", 20)];
+const DEFAULT_QUALITY: usize = 2;
 
 impl Component for CanvasControl {
     type Message = CanvasControlMsg;
@@ -49,90 +78,156 @@ impl Component for CanvasControl {
         let width = window().unwrap().inner_width().unwrap().as_f64().unwrap();
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
+        let lesson = match Lesson::parse(include_str!("lesson.md")) {
+            Ok(lesson) => Some(Rc::new(lesson)),
+            Err(err) => {
+                log!("ERROR in lesson.md:", err);
+                None
+            }
+        };
+
         CanvasControl{
-            callback: callback,
+            callback,
+            camera: CameraControls::default(),
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
             last_update: instant::now(),
+            lesson,
+            lesson_open: false,
+            lesson_step: 0,
+            lesson_defines: Defines::new(),
+            lesson_uniforms: UniformOverrides::default(),
+            program_cache: ProgramCache::new(),
+            quality: DEFAULT_QUALITY,
             shader_program: None,
             time_location: None,
             tri_count: 0,
             u_time: 0.0,
             height: height as i32,
             width: width as i32,
+            noise: SeededNoise::new(),
+            on_param: ctx.link().callback(CanvasControlMsg::SetParam),
         }
     }
 
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool{
         match msg {
-            CanvasControlMsg::MouseDown(_evt) => {
-                true
+            // The camera is drawn with the next frame, no need to redraw the page
+            CanvasControlMsg::Pointer(update) => {
+                self.camera.pointer(update, instant::now());
+                false
+            },
+            CanvasControlMsg::Touches(touches) => {
+                self.camera.touches(&touches);
+                false
             },
-            CanvasControlMsg::MouseUp(_evt) => {
+            CanvasControlMsg::Wheel((delta, position)) => {
+                self.camera.wheel(delta, position);
+                false
+            },
+            CanvasControlMsg::ResetCamera => {
+                self.camera.camera.reset();
+                false
+            },
+            CanvasControlMsg::SetQuality(level) => {
+                self.quality = level;
+                self.use_program();
                 true
             },
-            CanvasControlMsg::MouseMove(_evt) => {
+            CanvasControlMsg::ToggleLesson => {
+                self.lesson_open = !self.lesson_open;
+                self.set_lesson_step(self.lesson_step);
                 true
             },
-            CanvasControlMsg::TouchStart(_evt) => {
+            CanvasControlMsg::LessonStep(step) => {
+                self.set_lesson_step(step);
                 true
             },
-            CanvasControlMsg::TouchEnd(_evt) => {
+            CanvasControlMsg::LessonDefine((name, value)) => {
+                self.lesson_defines = self.lesson_defines.clone().set_int(&name, value);
+                self.use_program();
                 true
             },
-            CanvasControlMsg::TouchMove(_evt) => {
+            CanvasControlMsg::SetParam((_, value)) => {
+                self.noise.set(value);
                 true
             },
             CanvasControlMsg::Render => {
                 self.render();
                 true
             },
-            CanvasControlMsg::Null => {
-                true
-            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let onmousedown = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseDown((evt.page_x() as f64, evt.page_y() as f64))
-        });
-        let onmousemove = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseMove((evt.page_x() as f64, evt.page_y() as f64))
+        // Drag to move, wheel or pinch to zoom, twist two fingers to turn, double click to reset
+        let pointer_callback = |action| {
+            ctx.link().batch_callback(move |evt: PointerEvent| {
+                PointerUpdate::from_event(&evt, action).map(CanvasControlMsg::Pointer)
+            })
+        };
+        let ontouch = ctx.link().batch_callback(move |evt: TouchEvent| {
+            evt.prevent_default();
+            let canvas: HtmlCanvasElement = evt.current_target()?.dyn_into().ok()?;
+            Some(CanvasControlMsg::Touches(gesture::touch_points(&evt.touches(), &canvas)))
         });
-        let onmouseup = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseUp((evt.page_x() as f64, evt.page_y() as f64))
+        let onwheel = ctx.link().batch_callback(move |evt: WheelEvent| {
+            evt.prevent_default();
+            let position = pointer::event_position(&evt)?;
+            Some(CanvasControlMsg::Wheel((pointer::wheel_pixels(&evt), position)))
         });
-        let ontouchstart = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchStart((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
+        let ondblclick = ctx.link().callback(|_: MouseEvent| CanvasControlMsg::ResetCamera);
+
+        let quality_buttons = QUALITY_LEVELS.iter().enumerate().map(|(level, (name, _))| {
+            let onclick = ctx.link().callback(move |_| CanvasControlMsg::SetQuality(level));
+            let class = if level == self.quality { "selected" } else { "" };
+            html! { <button class={class} onclick={onclick}>{ *name }</button> }
         });
-        let ontouchend = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchEnd((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
+        let lesson_button = self.lesson.as_ref().map(|_| {
+            let onclick = ctx.link().callback(|_| CanvasControlMsg::ToggleLesson);
+            let class = if self.lesson_open { "selected" } else { "" };
+            html! { <button class={class} onclick={onclick}>{ "Lesson" }</button> }
         });
-        let ontouchmove = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchMove((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
+        let lesson_panel = self.lesson.clone().filter(|_| self.lesson_open).map(|lesson| {
+            html! {
+                <LessonPanel
+                    lesson={lesson}
+                    step={self.lesson_step}
+                    defines={self.lesson_defines.clone()}
+                    source={include_str!("fractal.frag")}
+                    on_step={ctx.link().callback(CanvasControlMsg::LessonStep)}
+                    on_define={ctx.link().callback(CanvasControlMsg::LessonDefine)}
+                    on_close={ctx.link().callback(|_| CanvasControlMsg::ToggleLesson)}
+                />
             }
         });
 
         html! {
             <div class="game_canvas">
+                <ParamPanel
+                    params={vec![SeededNoise::param()]}
+                    values={vec![self.noise.value()]}
+                    on_change={self.on_param.clone()}
+                />
+                <div class="quality_select">
+                    { for quality_buttons }
+                    { for lesson_button }
+                </div>
+                { for lesson_panel }
                 <canvas id="canvas"
-                    style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px;"}
-                    onmousedown={onmousedown}
-                    onmousemove={onmousemove}
-                    onmouseup={onmouseup}
-                    ontouchstart={ontouchstart}
-                    ontouchend={ontouchend}
-                    ontouchmove={ontouchmove}
+                    style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px; touch-action: none;"}
+                    onpointerdown={pointer_callback(PointerAction::Down)}
+                    onpointermove={pointer_callback(PointerAction::Move)}
+                    onpointerup={pointer_callback(PointerAction::Up)}
+                    onpointerleave={pointer_callback(PointerAction::Leave)}
+                    onpointercancel={pointer_callback(PointerAction::Leave)}
+                    ontouchstart={ontouch.clone()}
+                    ontouchmove={ontouch.clone()}
+                    ontouchend={ontouch.clone()}
+                    ontouchcancel={ontouch}
+                    onwheel={onwheel}
+                    ondblclick={ondblclick}
                     ref={self.node_ref.clone()}
                     tabindex = "1"
                 ></canvas>
@@ -152,6 +247,7 @@ impl Component for CanvasControl {
 
         c.set_width(self.width as u32);
         c.set_height(self.height as u32);
+        self.camera.camera.set_viewport(self.width as f64, self.height as f64);
 
         self.canvas = Some(c);
         self.gl = Some(gl);
@@ -176,13 +272,31 @@ impl CanvasControl {
 
         let delta = diff as f64 / 1000.0; // Frac of seconds
         self.u_time += delta as f32;
+        self.camera.update(delta);
 
         // Do updates using delta
         self.last_update = now;
     }
 
+    // Set the shader up for a step of the lesson, or back to normal if it's been closed
+    fn set_lesson_step(&mut self, step: usize) {
+        self.lesson_step = step;
+        let step = self.lesson.as_ref().and_then(|lesson| lesson.steps.get(step)).filter(|_| self.lesson_open);
+        match step {
+            Some(step) => {
+                self.lesson_defines = step.defines();
+                self.lesson_uniforms.set(&step.uniforms);
+            },
+            None => {
+                self.lesson_defines = Defines::new();
+                self.lesson_uniforms.set(&[]);
+            },
+        }
+        self.use_program();
+    }
+
     fn reload(&mut self) {
-        // Set up shaders and 
+        // Set up the vertex buffer and texture, the shader is set up by use_program
         let gl = match &self.gl {
             Some(gl)=> gl,
             None => {
@@ -191,9 +305,6 @@ impl CanvasControl {
             }
         };
 
-        let vert_code = include_str!("./fractal.vert");
-        let frag_code = include_str!("./fractal.frag");
-
         let _: &HtmlCanvasElement = match &self.canvas {
             Some(canv) => canv,
             None => return,
@@ -217,18 +328,41 @@ impl CanvasControl {
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&vertex_buffer));
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
-        let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
-        gl.compile_shader(&vert_shader);
+        // The noise texture is generated rather than loaded, on the next frame and again
+        // whenever the seed changes, see shader_common::noise
+        self.noise.forget();
 
-        let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
-        gl.compile_shader(&frag_shader);
+        self.use_program();
+    }
+
+    // Switch to the program for the current quality level, compiling it if this is the first
+    // time it has been used. Uniform and attribute locations belong to a program so have to
+    // be looked up again every time we switch.
+    fn use_program(&mut self) {
+        let gl = match &self.gl {
+            Some(gl)=> gl.clone(),
+            None => {
+                log!("ERROR Setting up scene without a proper gl context");
+                return;
+            }
+        };
+
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/fractal.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/fractal.frag"));
+
+        let (_, depth_count) = QUALITY_LEVELS[self.quality];
+        let mut defines = Defines::new().set_int("DEPTH_COUNT", depth_count);
+        if self.lesson_open {
+            defines = defines.merge(&self.lesson_defines);
+        }
 
-        let shader_program: WebGlProgram = gl.create_program().unwrap();
-        gl.attach_shader(&shader_program, &vert_shader);
-        gl.attach_shader(&shader_program, &frag_shader);
-        gl.link_program(&shader_program);
+        let shader_program = match self.program_cache.get_or_compile(&gl, vert_code, frag_code, &defines) {
+            Ok(program) => program,
+            Err(err) => {
+                log!("ERROR compiling shader:", err);
+                return;
+            }
+        };
 
         gl.use_program(Some(&shader_program));
 
@@ -241,46 +375,10 @@ impl CanvasControl {
         gl.uniform2f(canvassize.as_ref(), self.width as f32, self.height as f32);
 
         self.time_location = gl.get_uniform_location(&shader_program, "u_time");
-        gl.uniform1f(self.time_location.as_ref() , 1.0);
-
-        // Setup the texture 
-        // based on https://snoozetime.github.io/2019/12/19/webgl-texture.html
-        let texture = gl.create_texture().unwrap();
-        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-
-        let image: HtmlImageElement = HtmlImageElement::new().unwrap();
-        let imgrc = Rc::new(image.clone());
-
-        {
-            let image = imgrc.clone();
-            let texture = texture.clone();
-            let gl = Rc::new(gl.clone());
-
-            let a = Closure::wrap(Box::new(move || {
-                gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
-    
-                let _ = gl.tex_image_2d_with_u32_and_u32_and_image(
-                    GL::TEXTURE_2D,
-                    0,
-                    GL::RGBA.try_into().unwrap(),
-                    GL::RGBA.try_into().unwrap(),
-                    GL::UNSIGNED_BYTE,
-                    &image,
-                );
-    
-                // different from webgl1 where we need the pic to be power of 2
-                gl.generate_mipmap(GL::TEXTURE_2D);
-            }) as Box<dyn FnMut()>);
-
-            imgrc.set_onload(Some(a.as_ref().unchecked_ref()));
-    
-            // Normally we'd store the handle to later get dropped at an appropriate
-            // time but for now we want it to be a global handler so we use the
-            // forget method to drop it without invalidating the closure. Note that
-            // this is leaking memory in Rust, so this should be done judiciously!
-            a.forget();
-        }
-        image.set_src(TEXTURE_1);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
+
+        self.camera.camera.locate(&gl, &shader_program);
+        self.lesson_uniforms.locate(&gl, &shader_program);
 
         self.shader_program = Some(shader_program);
     }
@@ -293,8 +391,8 @@ impl CanvasControl {
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -307,13 +405,39 @@ impl CanvasControl {
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
+        self.camera.camera.upload(gl);
+        self.lesson_uniforms.upload(gl);
+        self.noise.bind(gl);
 
         gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
+        self.camera.end_frame();
 
         window()
             .unwrap()
             .request_animation_frame(self.callback.as_ref().unchecked_ref())
             .unwrap();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lesson.md points at fractal.frag by line number, so editing the shader can leave a step
+    // highlighting the wrong lines. Ranges past the end, or starting or ending on a blank line,
+    // have drifted.
+    #[test]
+    fn lesson_lines_are_in_the_shader() {
+        let lesson = Lesson::parse(include_str!("lesson.md")).unwrap();
+        let shader: Vec<&str> = include_str!("fractal.frag").lines().collect();
+        for step in &lesson.steps {
+            for range in &step.lines {
+                assert!(*range.start() >= 1 && *range.end() <= shader.len(), "{:?} in \"{}\" isn't in the shader", range, step.title);
+                for line in [*range.start(), *range.end()] {
+                    assert!(!shader[line - 1].trim().is_empty(), "line {} in \"{}\" is blank", line, step.title);
+                }
+            }
+        }
+    }
+}
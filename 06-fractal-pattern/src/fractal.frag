@@ -4,18 +4,20 @@ uniform float u_time;
 uniform vec2 canvasSize;
 uniform sampler2D texNoise;
 
-#define PI 3.1412
-#define TWO_PI 6.2824
+#include "lib/constants.glsl"
+#include "lib/camera.glsl"
 
-// Details for the fractal max
+// Details for the fractal max - the Rust side overrides this for each quality level
+#ifndef DEPTH_COUNT
 #define DEPTH_COUNT 20
+#endif
 
 const int MAX_FRACTAL_DEPTH = DEPTH_COUNT;
-const float max_num = float(DEPTH_COUNT);
+const float max_num = max(float(DEPTH_COUNT), 1.0); // the lesson uses 0, and mod(x, 0.) is undefined
 
 void main(void)
 {
-  vec2 uv = ( gl_FragCoord.xy * 2.0 - canvasSize.xy ) / canvasSize.y;
+  vec2 uv = 2.0 * cameraUv();
   vec3 col = vec3(0.);
 
   float angle = (2./3.)*PI;
@@ -0,0 +1,55 @@
+#!/usr/bin/env python3
+# Writes the grades bundled with the kaleidoscope as 17 point .cube LUTs. Any other .cube file
+# can be imported on the page.
+
+from pathlib import Path
+
+HERE = Path(__file__).parent
+SIZE = 17
+
+
+def clamp(x):
+    return max(0.0, min(1.0, x))
+
+
+def luma(r, g, b):
+    return 0.2126 * r + 0.7152 * g + 0.0722 * b
+
+
+def mix(a, b, t):
+    return a + (b - a) * t
+
+
+def teal_and_orange(r, g, b):
+    # Gentle S curve for contrast, then shadows pushed towards teal and highlights towards orange
+    r, g, b = (mix(c, c * c * (3 - 2 * c), 0.5) for c in (r, g, b))
+    l = luma(r, g, b)
+    shadow, highlight = 0.12 * (1 - l), 0.12 * l
+    r, g, b = r - shadow + highlight, g + 0.3 * shadow + 0.2 * highlight, b + shadow - highlight
+    # A little more saturation
+    l = luma(r, g, b)
+    return tuple(clamp(mix(l, c, 1.15)) for c in (r, g, b))
+
+
+def bleach_bypass(r, g, b):
+    # Overlay the black and white image on the colour one, as if the silver was left in
+    l = luma(r, g, b)
+    overlay = lambda c: 2 * c * l if c < 0.5 else 1 - 2 * (1 - c) * (1 - l)
+    r, g, b = (mix(c, overlay(c), 0.8) for c in (r, g, b))
+    l = luma(r, g, b)
+    return tuple(clamp(mix(l, c, 0.55)) for c in (r, g, b))
+
+
+def write(name, title, grade):
+    step = lambda i: i / (SIZE - 1)
+    lines = ['TITLE "%s"' % title, "LUT_3D_SIZE %d" % SIZE, ""]
+    # Red changes fastest, then green, then blue
+    for b in range(SIZE):
+        for g in range(SIZE):
+            for r in range(SIZE):
+                lines.append("%.4f %.4f %.4f" % grade(step(r), step(g), step(b)))
+    (HERE / name).write_text("\n".join(lines) + "\n")
+
+
+write("teal_orange.cube", "Teal and orange", teal_and_orange)
+write("bleach_bypass.cube", "Bleach bypass", bleach_bypass)
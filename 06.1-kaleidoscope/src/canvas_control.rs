@@ -1,17 +1,32 @@
 use std::rc::Rc;
 
-use web_sys::{window, HtmlCanvasElement,HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+use web_sys::{window, File, HtmlCanvasElement, HtmlImageElement, HtmlInputElement, HtmlSelectElement, PointerEvent, WheelEvent, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
 use yew::prelude::*;
 
 use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
 use gloo_console::log;
 
+use shader_common::camera_controls::CameraControls;
+use shader_common::gesture::{self, TouchPoint};
+use shader_common::lut::CubeLut;
+use shader_common::param_panel::ParamPanel;
+use shader_common::preset_panel::PresetPanel;
+use shader_common::params::{ParamValue, ShaderParams};
+use shader_common::pointer::{self, PointerAction, PointerUpdate};
+use shader_common::program::{Defines, ProgramCache};
+use shader_common::steps::ShaderSteps;
+
+use crate::grading::GradingPass;
+
 pub struct CanvasControl {
     callback: Closure<dyn FnMut()>,
+    camera: CameraControls,
     canvas: Option<HtmlCanvasElement>,
     gl: Option<GL>,
     node_ref: NodeRef,
     last_update: f64,
+    program_cache: ProgramCache,
     shader_program: Option<WebGlProgram>,
     time_location: Option<WebGlUniformLocation>,
     tri_count: i32,
@@ -22,17 +37,37 @@ pub struct CanvasControl {
     mouse_x_loc: Option<WebGlUniformLocation>,
     mouse_y: f32,
     mouse_y_loc: Option<WebGlUniformLocation>,
+    params: ShaderParams,
+    on_param: Callback<(String, ParamValue)>,
+    on_param_reset: Callback<()>,
+    on_preset: Callback<Vec<(String, ParamValue)>>,
+    grading: Option<GradingPass>,
+    // Title of the LUT in use, None draws straight to the screen
+    grade: Option<String>,
+    grade_amount: f32,
+    grade_error: Option<String>,
+    // The `// @step` stages marked in fractal.frag
+    steps: ShaderSteps,
+    // Index into the steps while building up, None shows the whole shader
+    build_step: Option<usize>,
 }
 
 pub enum CanvasControlMsg {
-    MouseDown((f64, f64)),
-    MouseUp((f64,f64)),
-    MouseMove((f64,f64)),
-    TouchStart((f64, f64)),
-    TouchEnd((f64, f64)),
-    TouchMove((f64, f64)),
+    Pointer(PointerUpdate),
+    Touches(Vec<TouchPoint>),
+    // Wheel movement in pixels, and where the cursor is
+    Wheel((f64, (f32, f32))),
+    ResetCamera,
+    SetParam((String, ParamValue)),
+    ResetParams,
+    LoadPreset(Vec<(String, ParamValue)>),
+    // One of LUTS by name, anything else turns grading off
+    SetLut(String),
+    ImportLut(File),
+    LutImported(Result<String, String>),
+    SetGradeAmount(f32),
+    SetBuildStep(Option<usize>),
     Render,
-    Null
 }
 
 
@@ -41,6 +76,12 @@ pub struct CanvasControlProps;
 
 const TEXTURE_1: &str = "/assets/forest_scene.png";
 
+// Made by luts/make_luts.py, other .cube files can be imported
+const LUTS: &[(&str, &str)] = &[
+    ("Teal and orange", include_str!("../luts/teal_orange.cube")),
+    ("Bleach bypass", include_str!("../luts/bleach_bypass.cube")),
+];
+
 impl Component for CanvasControl {
     type Message = CanvasControlMsg;
     type Properties = CanvasControlProps;
@@ -53,12 +94,24 @@ impl Component for CanvasControl {
         let width = window().unwrap().inner_width().unwrap().as_f64().unwrap();
         let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
 
+        // Made once up front so the panel's props don't change every frame
+        let on_param = ctx.link().callback(CanvasControlMsg::SetParam);
+        let on_param_reset = ctx.link().callback(|_| CanvasControlMsg::ResetParams);
+        let on_preset = ctx.link().callback(CanvasControlMsg::LoadPreset);
+
+        let steps = ShaderSteps::parse(include_str!(concat!(env!("OUT_DIR"), "/fractal.frag"))).unwrap_or_else(|err| {
+            log!("ERROR reading the build-up steps:", err);
+            ShaderSteps::default()
+        });
+
         CanvasControl{
-            callback: callback,
+            callback,
+            camera: CameraControls::default(),
             canvas: None,
             gl: None,
             node_ref: NodeRef::default(),
             last_update: instant::now(),
+            program_cache: ProgramCache::new(),
             shader_program: None,
             time_location: None,
             tri_count: 0,
@@ -68,85 +121,217 @@ impl Component for CanvasControl {
             mouse_x: 0.85,
             mouse_x_loc: None,
             mouse_y: 0.85,
-            mouse_y_loc: None
+            mouse_y_loc: None,
+            params: ShaderParams::from_source(include_str!(concat!(env!("OUT_DIR"), "/fractal.frag"))),
+            on_param,
+            on_param_reset,
+            on_preset,
+            grading: None,
+            grade: None,
+            grade_amount: 1.0,
+            grade_error: None,
+            steps,
+            build_step: None,
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool{
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool{
         match msg {
-            CanvasControlMsg::MouseDown(evt) => {
-                self.mouse_x = evt.0 as f32 / self.width as f32;
-                self.mouse_y = evt.1 as f32 / self.height as f32;
+            // Dragging moves the camera, a click still picks the colour and speed
+            CanvasControlMsg::Pointer(update) => {
+                self.camera.pointer(update, instant::now());
+                if let Some((x, y)) = self.camera.pointer.click() {
+                    self.mouse_x = x / self.width as f32;
+                    // mouse_y has always been measured down from the top
+                    self.mouse_y = 1.0 - y / self.height as f32;
+                }
+                false
+            },
+            CanvasControlMsg::Touches(touches) => {
+                self.camera.touches(&touches);
+                false
+            },
+            CanvasControlMsg::Wheel((delta, position)) => {
+                self.camera.wheel(delta, position);
+                false
+            },
+            CanvasControlMsg::ResetCamera => {
+                self.camera.camera.reset();
+                false
+            },
+            CanvasControlMsg::SetParam((name, value)) => {
+                self.params.set(&name, value);
+                true
+            },
+            CanvasControlMsg::ResetParams => {
+                self.params.reset();
                 true
             },
-            CanvasControlMsg::MouseUp(_evt) => {
+            CanvasControlMsg::LoadPreset(values) => {
+                for (name, value) in values {
+                    self.params.set(&name, value);
+                }
                 true
             },
-            CanvasControlMsg::MouseMove(_evt) => {
+            CanvasControlMsg::SetLut(name) => {
+                let lut = LUTS.iter().find(|(title, _)| *title == name).map(|(_, text)| CubeLut::parse(text));
+                match lut {
+                    Some(Ok(lut)) => self.set_lut(Some(lut)),
+                    Some(Err(err)) => log!("ERROR in a bundled LUT:", err),
+                    None => self.set_lut(None),
+                }
                 true
             },
-            CanvasControlMsg::TouchStart(evt) => {
-                self.mouse_x = evt.0 as f32 / self.width as f32;
-                self.mouse_y = evt.1 as f32 / self.height as f32;
+            CanvasControlMsg::ImportLut(file) => {
+                ctx.link().send_future(async move {
+                    let text = JsFuture::from(file.text()).await.map_err(|err| format!("Unable to read {}: {:?}", file.name(), err));
+                    CanvasControlMsg::LutImported(text.and_then(|text| text.as_string().ok_or_else(|| "File isn't text".to_string())))
+                });
+                false
+            },
+            CanvasControlMsg::LutImported(text) => {
+                match text.and_then(|text| CubeLut::parse(&text)) {
+                    Ok(mut lut) => {
+                        if lut.title.is_empty() {
+                            lut.title = "Imported".to_string();
+                        }
+                        self.set_lut(Some(lut));
+                    },
+                    Err(err) => self.grade_error = Some(err),
+                }
                 true
             },
-            CanvasControlMsg::TouchEnd(_evt) => {
+            CanvasControlMsg::SetGradeAmount(amount) => {
+                self.grade_amount = amount;
                 true
             },
-            CanvasControlMsg::TouchMove(evt) => {
-                self.mouse_x = evt.0 as f32 / self.width as f32;
-                self.mouse_y = evt.1 as f32 / self.height as f32;
+            CanvasControlMsg::SetBuildStep(step) => {
+                self.build_step = step;
+                self.use_program();
                 true
             },
             CanvasControlMsg::Render => {
                 self.render();
                 true
             },
-            CanvasControlMsg::Null => {
-                true
-            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let onmousedown = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseDown((evt.page_x() as f64, evt.page_y() as f64))
+        // Drag to move, wheel or pinch to zoom, twist two fingers to turn, double click to reset
+        let pointer_callback = |action| {
+            ctx.link().batch_callback(move |evt: PointerEvent| {
+                PointerUpdate::from_event(&evt, action).map(CanvasControlMsg::Pointer)
+            })
+        };
+        let ontouch = ctx.link().batch_callback(move |evt: TouchEvent| {
+            evt.prevent_default();
+            let canvas: HtmlCanvasElement = evt.current_target()?.dyn_into().ok()?;
+            Some(CanvasControlMsg::Touches(gesture::touch_points(&evt.touches(), &canvas)))
         });
-        let onmousemove = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseMove((evt.page_x() as f64, evt.page_y() as f64))
+        let onwheel = ctx.link().batch_callback(move |evt: WheelEvent| {
+            evt.prevent_default();
+            let position = pointer::event_position(&evt)?;
+            Some(CanvasControlMsg::Wheel((pointer::wheel_pixels(&evt), position)))
         });
-        let onmouseup = ctx.link().callback(move |evt: MouseEvent| {
-            CanvasControlMsg::MouseUp((evt.page_x() as f64, evt.page_y() as f64))
+        let ondblclick = ctx.link().callback(|_: MouseEvent| CanvasControlMsg::ResetCamera);
+
+        let onlut = ctx.link().callback(|evt: Event| {
+            let select: HtmlSelectElement = evt.target_unchecked_into();
+            CanvasControlMsg::SetLut(select.value())
         });
-        let ontouchstart = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchStart((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
+        let onamount = ctx.link().batch_callback(|evt: InputEvent| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            input.value().parse().ok().map(CanvasControlMsg::SetGradeAmount)
         });
-        let ontouchend = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchEnd((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
-            }
+        let onimport = ctx.link().batch_callback(|evt: Event| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            let file = input.files().and_then(|files| files.get(0));
+            // Clear it so choosing the same file again still fires a change
+            input.set_value("");
+            file.map(CanvasControlMsg::ImportLut)
         });
-        let ontouchmove = ctx.link().callback(move |evt: TouchEvent | {
-            match evt.touches().get(0) {
-                Some(touch) => CanvasControlMsg::TouchMove((touch.page_x() as f64, touch.page_y() as f64)),
-                None => CanvasControlMsg::Null,
+        // Walk forwards and backwards through the shader's @steps, or back to all of it
+        let build_up = (!self.steps.is_empty()).then(|| {
+            let count = self.steps.steps().len();
+            let set_step = |step: Option<usize>| ctx.link().callback(move |_: MouseEvent| CanvasControlMsg::SetBuildStep(step));
+            match self.build_step {
+                Some(index) => {
+                    let step = &self.steps.steps()[index];
+                    let label = if step.label.is_empty() { String::new() } else { format!(": {}", step.label) };
+                    html! {
+                        <div class="build_up">
+                            <button class="selected" onclick={set_step(None)}>{ "Build up" }</button>
+                            <button disabled={index == 0} onclick={set_step(Some(index.saturating_sub(1)))}>{ "Back" }</button>
+                            <span>{ format!("Step {} of {}{}", index + 1, count, label) }</span>
+                            <button disabled={index + 1 >= count} onclick={set_step(Some(index + 1))}>{ "Forward" }</button>
+                        </div>
+                    }
+                },
+                None => html! {
+                    <div class="build_up">
+                        <button onclick={set_step(Some(0))}>{ "Build up" }</button>
+                    </div>
+                },
             }
         });
+        // An imported LUT gets an entry of its own while it's in use
+        let imported = self.grade.as_ref().filter(|grade| LUTS.iter().all(|(title, _)| title != grade));
 
         html! {
             <div class="game_canvas">
+                <ParamPanel
+                    params={self.params.params().to_vec()}
+                    values={self.params.values().to_vec()}
+                    on_change={self.on_param.clone()}
+                    on_reset={self.on_param_reset.clone()}
+                >
+                    <PresetPanel
+                        tutorial={env!("CARGO_PKG_NAME")}
+                        params={self.params.params().to_vec()}
+                        values={self.params.values().to_vec()}
+                        on_load={self.on_preset.clone()}
+                    />
+                    <div class="grade_panel">
+                        <div class="param_row">
+                            <label>{ "Grade" }</label>
+                            <select onchange={onlut}>
+                                <option value="" selected={self.grade.is_none()}>{ "None" }</option>
+                                { for LUTS.iter().map(|(title, _)| html! {
+                                    <option value={*title} selected={self.grade.as_deref() == Some(*title)}>{ *title }</option>
+                                }) }
+                                if let Some(title) = imported {
+                                    <option value={title.clone()} selected=true disabled=true>{ title }</option>
+                                }
+                            </select>
+                        </div>
+                        <div class="param_row">
+                            <label>{ "Amount" }</label>
+                            <input type="range" min="0" max="1" step="0.01" value={self.grade_amount.to_string()} oninput={onamount} />
+                        </div>
+                        <label class="preset_import">
+                            { "Import .cube" }
+                            <input type="file" accept=".cube" onchange={onimport} />
+                        </label>
+                        if let Some(error) = &self.grade_error {
+                            <div class="preset_error">{ error }</div>
+                        }
+                    </div>
+                </ParamPanel>
+                { for build_up }
                 <canvas id="canvas"
-                    style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px;"}
-                    onmousedown={onmousedown}
-                    onmousemove={onmousemove}
-                    onmouseup={onmouseup}
-                    ontouchstart={ontouchstart}
-                    ontouchend={ontouchend}
-                    ontouchmove={ontouchmove}
+                    style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px; touch-action: none;"}
+                    onpointerdown={pointer_callback(PointerAction::Down)}
+                    onpointermove={pointer_callback(PointerAction::Move)}
+                    onpointerup={pointer_callback(PointerAction::Up)}
+                    onpointerleave={pointer_callback(PointerAction::Leave)}
+                    onpointercancel={pointer_callback(PointerAction::Leave)}
+                    ontouchstart={ontouch.clone()}
+                    ontouchmove={ontouch.clone()}
+                    ontouchend={ontouch.clone()}
+                    ontouchcancel={ontouch}
+                    onwheel={onwheel}
+                    ondblclick={ondblclick}
                     ref={self.node_ref.clone()}
                     tabindex = "1"
                 ></canvas>
@@ -166,6 +351,7 @@ impl Component for CanvasControl {
 
         c.set_width(self.width as u32);
         c.set_height(self.height as u32);
+        self.camera.camera.set_viewport(self.width as f64, self.height as f64);
 
         self.canvas = Some(c);
         self.gl = Some(gl);
@@ -190,13 +376,14 @@ impl CanvasControl {
 
         let delta = diff as f64 / 1000.0; // Frac of seconds
         self.u_time += delta as f32;
+        self.camera.update(delta);
 
         // Do updates using delta
         self.last_update = now;
     }
 
     fn reload(&mut self) {
-        // Set up shaders and 
+        // Set up the vertex buffer, texture and grading pass, the shader is set up by use_program
         let gl = match &self.gl {
             Some(gl)=> gl,
             None => {
@@ -205,9 +392,6 @@ impl CanvasControl {
             }
         };
 
-        let vert_code = include_str!("./fractal.vert");
-        let frag_code = include_str!("./fractal.frag");
-
         let _: &HtmlCanvasElement = match &self.canvas {
             Some(canv) => canv,
             None => return,
@@ -231,38 +415,6 @@ impl CanvasControl {
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&vertex_buffer));
         gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
 
-        let vert_shader = gl.create_shader(GL::VERTEX_SHADER).unwrap();
-        gl.shader_source(&vert_shader, &vert_code);
-        gl.compile_shader(&vert_shader);
-
-        let frag_shader = gl.create_shader(GL::FRAGMENT_SHADER).unwrap();
-        gl.shader_source(&frag_shader, &frag_code);
-        gl.compile_shader(&frag_shader);
-
-        let shader_program: WebGlProgram = gl.create_program().unwrap();
-        gl.attach_shader(&shader_program, &vert_shader);
-        gl.attach_shader(&shader_program, &frag_shader);
-        gl.link_program(&shader_program);
-
-        gl.use_program(Some(&shader_program));
-
-        // Attach the position vector as an attribute for the GL context.
-        let position = gl.get_attrib_location(&shader_program, "a_position") as u32;
-        gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(position);
-
-        let canvassize = gl.get_uniform_location(&shader_program, "canvasSize");
-        gl.uniform2f(canvassize.as_ref(), self.width as f32, self.height as f32);
-
-        self.time_location = gl.get_uniform_location(&shader_program, "u_time");
-        gl.uniform1f(self.time_location.as_ref() , 1.0);
-
-        // Store mouse location
-        self.mouse_x_loc = gl.get_uniform_location(&shader_program, "mouse_x");
-        gl.uniform1f(self.mouse_x_loc.as_ref() , 1.0);
-        self.mouse_y_loc = gl.get_uniform_location(&shader_program, "mouse_y");
-        gl.uniform1f(self.mouse_y_loc.as_ref() , 1.0);
-
         // Setup the texture 
         // based on https://snoozetime.github.io/2019/12/19/webgl-texture.html
         let texture = gl.create_texture().unwrap();
@@ -283,7 +435,7 @@ impl CanvasControl {
                     GL::TEXTURE_2D,
                     0,
                     GL::RGBA.try_into().unwrap(),
-                    GL::RGBA.try_into().unwrap(),
+                    GL::RGBA,
                     GL::UNSIGNED_BYTE,
                     &image,
                 );
@@ -302,19 +454,102 @@ impl CanvasControl {
         }
         image.set_src(TEXTURE_1);
 
+        match GradingPass::new(gl) {
+            Ok(grading) => self.grading = Some(grading),
+            Err(err) => log!("ERROR compiling the grading pass, LUTs won't apply:", err),
+        }
+
+        self.use_program();
+    }
+
+    // Switch to the whole shader, or just the steps up to the one being built up to. Each
+    // version is only compiled the first time, and the uniforms have to be found again in it.
+    fn use_program(&mut self) {
+        let gl = match &self.gl {
+            Some(gl)=> gl.clone(),
+            None => {
+                log!("ERROR Setting up scene without a proper gl context");
+                return;
+            }
+        };
+
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/fractal.vert"));
+        let frag_code = match self.build_step.and_then(|index| self.steps.steps().get(index)) {
+            Some(step) => self.steps.up_to(step.number),
+            None => include_str!(concat!(env!("OUT_DIR"), "/fractal.frag")).to_string(),
+        };
+
+        let shader_program = match self.program_cache.get_or_compile(&gl, vert_code, &frag_code, &Defines::new()) {
+            Ok(program) => program,
+            Err(err) => {
+                log!("ERROR compiling shader:", err);
+                return;
+            }
+        };
+
+        gl.use_program(Some(&shader_program));
+
+        // Attach the position vector as an attribute for the GL context.
+        let position = gl.get_attrib_location(&shader_program, "a_position") as u32;
+        gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(position);
+
+        let canvassize = gl.get_uniform_location(&shader_program, "canvasSize");
+        gl.uniform2f(canvassize.as_ref(), self.width as f32, self.height as f32);
+
+        self.time_location = gl.get_uniform_location(&shader_program, "u_time");
+        self.mouse_x_loc = gl.get_uniform_location(&shader_program, "mouse_x");
+        self.mouse_y_loc = gl.get_uniform_location(&shader_program, "mouse_y");
+
+        // Uniforms annotated in the shader, driven by the parameter panel
+        self.params.locate(&gl, &shader_program);
+        self.camera.camera.locate(&gl, &shader_program);
+
         self.shader_program = Some(shader_program);
     }
 
+    // Grade with `lut` from now on, None goes back to drawing straight to the screen
+    fn set_lut(&mut self, lut: Option<CubeLut>) {
+        self.grade_error = None;
+        let lut = match lut {
+            Some(lut) => lut,
+            None => {
+                self.grade = None;
+                return;
+            }
+        };
+        let (gl, grading) = match (&self.gl, &mut self.grading) {
+            (Some(gl), Some(grading)) => (gl, grading),
+            _ => {
+                self.grade_error = Some("Grading isn't available".to_string());
+                return;
+            }
+        };
+        match grading.set_lut(gl, &lut) {
+            Ok(()) => self.grade = Some(lut.title),
+            Err(err) => self.grade_error = Some(err),
+        }
+    }
+
     fn render(&mut self) {
         self.canvas_update();
         
         let gl = self.gl.as_ref().expect("GL Context not initialized!");
 
+        // Only worth the extra pass while a LUT is doing something
+        let mut grading = match &mut self.grading {
+            Some(grading) if self.grade.is_some() && self.grade_amount > 0.0 => Some(grading),
+            _ => None,
+        };
+        if let Some(grading) = &mut grading {
+            grading.begin(gl, self.width, self.height);
+        }
+
         gl.viewport(
             0,
             0,
-            self.width as i32,
-            self.height as i32,
+            self.width,
+            self.height,
         );
 
         gl.clear_color(0., 0.7, 0., 1.0);
@@ -327,13 +562,19 @@ impl CanvasControl {
         gl.clear(GL::COLOR_BUFFER_BIT);
        
         // Update uniforms in the shaders - for now just the u_time (time since start in secs)
-        gl.uniform1f(self.time_location.as_ref() , self.u_time as f32);
+        gl.uniform1f(self.time_location.as_ref() , self.u_time);
 
         // Update the current mouse locations
         gl.uniform1f(self.mouse_x_loc.as_ref() , self.mouse_x);
         gl.uniform1f(self.mouse_y_loc.as_ref() , self.mouse_y);
+        self.params.upload(gl);
+        self.camera.camera.upload(gl);
 
         gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
+        if let (Some(grading), Some(program)) = (grading, &self.shader_program) {
+            grading.finish(gl, program, self.grade_amount, self.tri_count);
+        }
+        self.camera.end_frame();
 
         window()
             .unwrap()
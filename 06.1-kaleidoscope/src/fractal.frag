@@ -2,12 +2,13 @@ precision highp float;
 
 uniform float u_time;
 uniform vec2 canvasSize;
-uniform sampler2D texture;
+uniform sampler2D texScene;
 uniform float mouse_x;
 uniform float mouse_y;
+uniform float recenter; // @range -1..1 @default 0.25 @label Recenter
 
-#define PI 3.1415
-#define TWO_PI 6.2824
+#include "lib/constants.glsl"
+#include "lib/camera.glsl"
 
 // Details for the fractal max
 #define DEPTH_COUNT 10
@@ -21,14 +22,15 @@ vec2 N(float angle) {
 
 void main(void)
 {
-  vec2 uv = (gl_FragCoord.xy-(.5*canvasSize.xy)) / canvasSize.y;
+  vec2 uv = cameraUv();
   uv *= 2.;
   
   float angle = (5./6.) * PI;
-  uv.y -= 0.25; // Fiddle factor to recenter the image
+  uv.y -= recenter; // Fiddle factor to recenter the image
   
   vec3 col = vec3(0.);
 
+  // @step 1 Reflection
   uv.x = abs(uv.x); // Reflect around center line
   
   vec2 n = N(angle);
@@ -38,9 +40,11 @@ void main(void)
 
   // DEBUG - uncomment to show the lines of where reflection is ocurring
   // col += smoothstep(.01, .0, abs(d));
+  // @step end
 
   float scale = 2.;
   
+  // @step 2 Fractal
   // Calculate depth of max number of iterations
   // We cycle between low depth and high depth
   // - more depth means more recursions of the fractal pattern
@@ -73,6 +77,7 @@ void main(void)
       break;
     }
   }
+  // @step end
       
   d = length(uv - vec2(clamp(uv.x, -1.,1.), 0.));
 
@@ -84,8 +89,10 @@ void main(void)
   // col.g += (1.0-uv.y) * 0.1;
 
   col.b = 0.6;
+  // @step 3 Texture
   uv /= scale;
-  col += texture2D(texture, uv*2.+(u_time*.1)).rgb;
+  col += texture2D(texScene, uv*2.+(u_time*.1)).rgb;
   col *= (mouse_x * 0.8) + 0.1;
+  // @step end
   gl_FragColor = vec4(col, 1.);
 }
\ No newline at end of file
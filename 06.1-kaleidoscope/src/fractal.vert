@@ -1,7 +1,7 @@
 precision mediump float;
 
-attribute vec2 a_position;
+attribute vec3 a_position;
 
 void main() {
-    gl_Position = vec4(a_position, 0.0, 1.0);
+    gl_Position = vec4(a_position, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+precision mediump float;
+
+// The final pass: the kaleidoscope, already drawn into u_scene, graded through a 3D LUT
+uniform vec2 canvasSize;
+uniform sampler2D u_scene;
+uniform float u_grade_amount;
+
+#include "lib/lut.glsl"
+
+void main(void)
+{
+    vec3 color = texture2D(u_scene, gl_FragCoord.xy / canvasSize).rgb;
+    gl_FragColor = vec4(gradeColor(color, u_grade_amount), 1.0);
+}
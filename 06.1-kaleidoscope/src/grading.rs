@@ -0,0 +1,105 @@
+// The grading pass. While a LUT is picked the kaleidoscope is drawn into a texture the size of
+// the canvas instead of the screen, then grade.frag draws that texture to the screen through
+// the LUT (see shader_common::lut).
+
+use web_sys::{WebGlFramebuffer, WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+
+use shader_common::lut::{CubeLut, LutTexture};
+use shader_common::program::{compile_program, Defines};
+
+// The kaleidoscope's own image stays on unit 0
+const SCENE_UNIT: u32 = 1;
+const LUT_UNIT: u32 = 2;
+
+pub struct GradingPass {
+    program: WebGlProgram,
+    position: u32,
+    framebuffer: Option<WebGlFramebuffer>,
+    scene: Option<WebGlTexture>,
+    // Size of the scene texture, it's made again if the canvas changes
+    size: (i32, i32),
+    canvas_size_location: Option<WebGlUniformLocation>,
+    scene_location: Option<WebGlUniformLocation>,
+    amount_location: Option<WebGlUniformLocation>,
+    lut: LutTexture,
+}
+
+impl GradingPass {
+    pub fn new(gl: &GL) -> Result<GradingPass, String> {
+        // The vertex shader is the same full screen quad
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/fractal.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/grade.frag"));
+        let program = compile_program(gl, vert_code, frag_code, &Defines::new())?;
+
+        let mut lut = LutTexture::default();
+        lut.locate(gl, &program);
+        Ok(GradingPass {
+            position: gl.get_attrib_location(&program, "a_position") as u32,
+            framebuffer: gl.create_framebuffer(),
+            scene: gl.create_texture(),
+            size: (0, 0),
+            canvas_size_location: gl.get_uniform_location(&program, "canvasSize"),
+            scene_location: gl.get_uniform_location(&program, "u_scene"),
+            amount_location: gl.get_uniform_location(&program, "u_grade_amount"),
+            program,
+            lut,
+        })
+    }
+
+    pub fn set_lut(&mut self, gl: &GL, lut: &CubeLut) -> Result<(), String> {
+        self.lut.upload(gl, lut, LUT_UNIT)
+    }
+
+    // Everything drawn from here until finish() goes into the scene texture
+    pub fn begin(&mut self, gl: &GL, width: i32, height: i32) {
+        if self.size != (width, height) {
+            gl.active_texture(GL::TEXTURE0 + SCENE_UNIT);
+            gl.bind_texture(GL::TEXTURE_2D, self.scene.as_ref());
+            let _ = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                width,
+                height,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                None,
+            );
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::NEAREST as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            gl.active_texture(GL::TEXTURE0);
+
+            gl.bind_framebuffer(GL::FRAMEBUFFER, self.framebuffer.as_ref());
+            gl.framebuffer_texture_2d(GL::FRAMEBUFFER, GL::COLOR_ATTACHMENT0, GL::TEXTURE_2D, self.scene.as_ref(), 0);
+            self.size = (width, height);
+        }
+        gl.bind_framebuffer(GL::FRAMEBUFFER, self.framebuffer.as_ref());
+    }
+
+    // Draw the graded scene to the screen, then put `scene_program` back for the next frame
+    pub fn finish(&self, gl: &GL, scene_program: &WebGlProgram, amount: f32, tri_count: i32) {
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+        gl.use_program(Some(&self.program));
+        gl.vertex_attrib_pointer_with_i32(self.position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(self.position);
+
+        gl.active_texture(GL::TEXTURE0 + SCENE_UNIT);
+        gl.bind_texture(GL::TEXTURE_2D, self.scene.as_ref());
+        gl.active_texture(GL::TEXTURE0);
+        gl.uniform1i(self.scene_location.as_ref(), SCENE_UNIT as i32);
+        gl.uniform2f(self.canvas_size_location.as_ref(), self.size.0 as f32, self.size.1 as f32);
+        gl.uniform1f(self.amount_location.as_ref(), amount);
+        self.lut.bind(gl, LUT_UNIT);
+
+        gl.draw_arrays(GL::TRIANGLES, 0, tri_count);
+
+        gl.use_program(Some(scene_program));
+        let position = gl.get_attrib_location(scene_program, "a_position") as u32;
+        gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(position);
+    }
+}
@@ -2,6 +2,7 @@ use yew::prelude::*;
 use yew_router::prelude::*;
 
 mod canvas_control;
+mod grading;
 
 #[derive(Clone, Routable, PartialEq)]
 enum Route {
@@ -0,0 +1,580 @@
+use web_sys::{window, HtmlCanvasElement, PointerEvent, WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation, WheelEvent};
+use yew::prelude::*;
+
+use wasm_bindgen::{prelude::*, JsCast};
+use gloo_console::log;
+
+use shader_common::camera::Camera2d;
+use shader_common::camera_controls::CameraControls;
+use shader_common::gesture::{self, TouchPoint};
+use shader_common::gradient::{Gradient, GradientStop, GradientTexture, Interpolation};
+use shader_common::gradient_editor::GradientEditor;
+use shader_common::perf::{FrameStats, GpuTimer};
+use shader_common::perf_hud::PerfHud;
+use shader_common::pointer::{self, PointerAction, PointerUpdate};
+use shader_common::program::{Defines, ProgramCache};
+
+use crate::ds::Ds;
+use crate::orbit::{self, Fractal, ReferenceOrbit};
+
+pub struct CanvasControl {
+    callback: Closure<dyn FnMut()>,
+    camera: CameraControls,
+    canvas: Option<HtmlCanvasElement>,
+    gl: Option<GL>,
+    node_ref: NodeRef,
+    last_update: f64,
+    program_cache: ProgramCache,
+    fractal: Fractal,
+    // Where the Mandelbrot view was before switching to a Julia set, to go back to
+    mandelbrot_view: Option<((f64, f64), f64, f64)>,
+    // None picks one from the zoom
+    precision: Option<Precision>,
+    // Perturbation needs OES_texture_float for the reference orbit
+    float_textures: bool,
+    orbit_texture: Option<WebGlTexture>,
+    // What the orbit in the texture was worked out for, so it's only redone when that changes
+    orbit_key: Option<(Fractal, (f64, f64), usize)>,
+    orbit_length: usize,
+    // Colours for the escape count, looked up in a baked 256x1 texture
+    gradient: Gradient,
+    gradient_texture: GradientTexture,
+    on_gradient: Callback<Gradient>,
+    uniforms: Uniforms,
+    shader_program: Option<WebGlProgram>,
+    // The performance overlay, worth watching as the zoom goes deeper
+    frame_stats: FrameStats,
+    show_hud: bool,
+    gpu_timer: Option<GpuTimer>,
+    gpu_time: Option<f64>,
+    tri_count: i32,
+    height: i32,
+    width: i32,
+}
+
+#[derive(Default)]
+struct Uniforms {
+    center: Option<WebGlUniformLocation>,
+    scale: Option<WebGlUniformLocation>,
+    rotation: Option<WebGlUniformLocation>,
+    iterations: Option<WebGlUniformLocation>,
+    precision: Option<WebGlUniformLocation>,
+    fractal: Option<WebGlUniformLocation>,
+    julia: Option<WebGlUniformLocation>,
+    orbit_length: Option<WebGlUniformLocation>,
+}
+
+pub enum CanvasControlMsg {
+    Pointer(PointerUpdate),
+    Touches(Vec<TouchPoint>),
+    // Wheel movement in pixels, and where the cursor is
+    Wheel((f64, (f32, f32))),
+    ResetCamera,
+    ShowMandelbrot,
+    // The Julia set for the point in the middle of the Mandelbrot view
+    ShowJulia,
+    SetPrecision(Option<Precision>),
+    SetGradient(Gradient),
+    ToggleHud,
+    Render,
+}
+
+// How the shader iterates, the numbers are u_precision in mandelbrot.frag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    Float = 0,
+    DoubleSingle = 1,
+    Perturbation = 2,
+}
+
+impl Precision {
+    fn name(self) -> &'static str {
+        match self {
+            Precision::Float => "Float",
+            Precision::DoubleSingle => "Double-single",
+            Precision::Perturbation => "Perturbation",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Properties)]
+pub struct CanvasControlProps;
+
+// Beyond this floats can't tell neighbouring pixels apart. Pixels stay distinct in double-single
+// to about 10^11 and in perturbation to about 10^13, where the f64 camera centre runs out.
+const FLOAT_ZOOM: f64 = 1e4;
+const MAX_ZOOM: f64 = 1e13;
+
+const PRECISIONS: &[(&str, Option<Precision>)] = &[
+    ("Auto", None),
+    ("Float", Some(Precision::Float)),
+    ("Double-single", Some(Precision::DoubleSingle)),
+    ("Perturbation", Some(Precision::Perturbation)),
+];
+
+const ORBIT_UNIT: u32 = 0;
+const GRADIENT_UNIT: u32 = 1;
+
+// Ultra Fractal's default colouring, going round back to where it started
+fn default_gradient() -> Gradient {
+    let stops = [
+        (0.0, [0, 7, 100]),
+        (0.16, [32, 107, 203]),
+        (0.42, [237, 255, 255]),
+        (0.6425, [255, 170, 0]),
+        (0.8575, [0, 2, 0]),
+        (1.0, [0, 7, 100]),
+    ]
+    .iter()
+    .map(|(position, color)| GradientStop { position: *position, color: color.map(|c| c as f32 / 255.0) })
+    .collect();
+    Gradient::new("Ultra", stops, Interpolation::Oklab).expect("The default gradient has stops")
+}
+
+impl Component for CanvasControl {
+    type Message = CanvasControlMsg;
+    type Properties = CanvasControlProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let comp_ctx = ctx.link().clone();
+        let callback =
+            Closure::wrap(Box::new(move || comp_ctx.send_message(CanvasControlMsg::Render)) as Box<dyn FnMut()>);
+
+        let width = window().unwrap().inner_width().unwrap().as_f64().unwrap();
+        let height = window().unwrap().inner_height().unwrap().as_f64().unwrap();
+
+        let mut camera = CameraControls::default();
+        camera.camera = Camera2d::new().max_zoom(MAX_ZOOM);
+
+        let mut control = CanvasControl{
+            callback,
+            camera,
+            canvas: None,
+            gl: None,
+            node_ref: NodeRef::default(),
+            last_update: instant::now(),
+            program_cache: ProgramCache::new(),
+            fractal: Fractal::Mandelbrot,
+            mandelbrot_view: None,
+            precision: None,
+            float_textures: false,
+            orbit_texture: None,
+            orbit_key: None,
+            orbit_length: 0,
+            gradient: default_gradient(),
+            gradient_texture: GradientTexture::default(),
+            on_gradient: ctx.link().callback(CanvasControlMsg::SetGradient),
+            uniforms: Uniforms::default(),
+            shader_program: None,
+            frame_stats: FrameStats::new(),
+            show_hud: false,
+            gpu_timer: None,
+            gpu_time: None,
+            tri_count: 0,
+            height: height as i32,
+            width: width as i32,
+        };
+        control.home();
+        control
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool{
+        match msg {
+            // The camera is drawn with the next frame, no need to redraw the page
+            CanvasControlMsg::Pointer(update) => {
+                self.camera.pointer(update, instant::now());
+                false
+            },
+            CanvasControlMsg::Touches(touches) => {
+                self.camera.touches(&touches);
+                false
+            },
+            CanvasControlMsg::Wheel((delta, position)) => {
+                self.camera.wheel(delta, position);
+                false
+            },
+            CanvasControlMsg::ResetCamera => {
+                self.home();
+                false
+            },
+            CanvasControlMsg::ShowMandelbrot => {
+                if let Some((center, zoom, rotation)) = self.mandelbrot_view.take() {
+                    self.fractal = Fractal::Mandelbrot;
+                    let camera = &mut self.camera.camera;
+                    camera.reset();
+                    camera.center = center;
+                    camera.zoom = zoom;
+                    camera.rotation = rotation;
+                }
+                true
+            },
+            CanvasControlMsg::ShowJulia => {
+                if self.fractal == Fractal::Mandelbrot {
+                    let camera = &self.camera.camera;
+                    self.mandelbrot_view = Some((camera.center, camera.zoom, camera.rotation));
+                    self.fractal = Fractal::Julia(camera.center);
+                    self.home();
+                }
+                true
+            },
+            CanvasControlMsg::SetPrecision(precision) => {
+                self.precision = precision;
+                true
+            },
+            CanvasControlMsg::SetGradient(gradient) => {
+                self.gradient = gradient;
+                true
+            },
+            CanvasControlMsg::ToggleHud => {
+                self.show_hud = !self.show_hud;
+                self.frame_stats.reset();
+                self.gpu_time = None;
+                true
+            },
+            CanvasControlMsg::Render => {
+                self.render();
+                true
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        // Drag to move, wheel or pinch to zoom, twist two fingers to turn, double click to reset
+        let pointer_callback = |action| {
+            ctx.link().batch_callback(move |evt: PointerEvent| {
+                PointerUpdate::from_event(&evt, action).map(CanvasControlMsg::Pointer)
+            })
+        };
+        let ontouch = ctx.link().batch_callback(move |evt: TouchEvent| {
+            evt.prevent_default();
+            let canvas: HtmlCanvasElement = evt.current_target()?.dyn_into().ok()?;
+            Some(CanvasControlMsg::Touches(gesture::touch_points(&evt.touches(), &canvas)))
+        });
+        let onwheel = ctx.link().batch_callback(move |evt: WheelEvent| {
+            evt.prevent_default();
+            let position = pointer::event_position(&evt)?;
+            Some(CanvasControlMsg::Wheel((pointer::wheel_pixels(&evt), position)))
+        });
+        let ondblclick = ctx.link().callback(|_: MouseEvent| CanvasControlMsg::ResetCamera);
+
+        let precision_buttons = PRECISIONS.iter().map(|(name, precision)| {
+            let precision = *precision;
+            let onclick = ctx.link().callback(move |_| CanvasControlMsg::SetPrecision(precision));
+            let class = if precision == self.precision { "selected" } else { "" };
+            let disabled = precision == Some(Precision::Perturbation) && !self.float_textures;
+            html! { <button class={class} onclick={onclick} disabled={disabled}>{ *name }</button> }
+        });
+        let is_julia = matches!(self.fractal, Fractal::Julia(_));
+        let camera = &self.camera.camera;
+
+        html! {
+            <div class="game_canvas">
+                <div class="quality_select">
+                    <div>
+                        <button class={if is_julia { "" } else { "selected" }} onclick={ctx.link().callback(|_| CanvasControlMsg::ShowMandelbrot)}>{ "Mandelbrot" }</button>
+                        <button class={if is_julia { "selected" } else { "" }} onclick={ctx.link().callback(|_| CanvasControlMsg::ShowJulia)} disabled={is_julia}>{ "Julia here" }</button>
+                        <button class={if self.show_hud { "selected" } else { "" }} onclick={ctx.link().callback(|_| CanvasControlMsg::ToggleHud)}>{ "Frame stats" }</button>
+                    </div>
+                    <div>
+                        { for precision_buttons }
+                    </div>
+                    <div class="fractal_info">
+                        { format!("Zoom {:.2e}, {} iterations, {}", camera.zoom, orbit::iterations_for_zoom(camera.zoom), self.current_precision().name()) }
+                    </div>
+                </div>
+                if self.show_hud {
+                    <PerfHud
+                        stats={self.frame_stats.clone()}
+                        gpu_time={self.gpu_time}
+                        gpu_timer={self.gpu_timer.is_some()}
+                        width={self.width}
+                        height={self.height}
+                        pixel_ratio={1.0}
+                    />
+                }
+                <details class="gradient_panel">
+                    <summary>{ "Colours" }</summary>
+                    <GradientEditor tutorial="mandelbrot" gradient={self.gradient.clone()} on_change={self.on_gradient.clone()} />
+                </details>
+                <canvas id="canvas"
+                    style={"margin: 0px; width: 100vw; height: 100vh; left:0px; top:0px; touch-action: none;"}
+                    onpointerdown={pointer_callback(PointerAction::Down)}
+                    onpointermove={pointer_callback(PointerAction::Move)}
+                    onpointerup={pointer_callback(PointerAction::Up)}
+                    onpointerleave={pointer_callback(PointerAction::Leave)}
+                    onpointercancel={pointer_callback(PointerAction::Leave)}
+                    ontouchstart={ontouch.clone()}
+                    ontouchmove={ontouch.clone()}
+                    ontouchend={ontouch.clone()}
+                    ontouchcancel={ontouch}
+                    onwheel={onwheel}
+                    ondblclick={ondblclick}
+                    ref={self.node_ref.clone()}
+                    tabindex = "1"
+                ></canvas>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        // Grab context and other setup
+        let c = self.node_ref.cast::<HtmlCanvasElement>().unwrap();
+        let gl: GL = c
+            .get_context("webgl")
+            .unwrap()
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+
+        c.set_width(self.width as u32);
+        c.set_height(self.height as u32);
+        self.camera.camera.set_viewport(self.width as f64, self.height as f64);
+
+        self.canvas = Some(c);
+        self.gl = Some(gl);
+
+        if first_render {
+            self.reload();
+            self.gpu_timer = self.gl.as_ref().and_then(GpuTimer::new);
+
+            ctx.link().send_message(CanvasControlMsg::Render);
+        }
+    }
+}
+
+impl CanvasControl {
+
+    // The whole set in view
+    fn home(&mut self) {
+        let camera = &mut self.camera.camera;
+        camera.reset();
+        match self.fractal {
+            Fractal::Mandelbrot => {
+                camera.center = (-0.5, 0.0);
+                camera.zoom = 0.4;
+            },
+            Fractal::Julia(_) => {
+                camera.zoom = 0.35;
+            },
+        }
+    }
+
+    fn current_precision(&self) -> Precision {
+        match self.precision {
+            Some(Precision::Perturbation) if !self.float_textures => Precision::DoubleSingle,
+            Some(precision) => precision,
+            None if self.camera.camera.zoom < FLOAT_ZOOM => Precision::Float,
+            None if self.float_textures => Precision::Perturbation,
+            None => Precision::DoubleSingle,
+        }
+    }
+
+    fn canvas_update(&mut self) {
+        let now = instant::now();
+
+        if self.last_update >= now {
+            return;
+        }
+        let diff = now - self.last_update;
+        self.frame_stats.record(diff);
+
+        let delta = diff as f64 / 1000.0; // Frac of seconds
+        self.camera.update(delta);
+
+        // Do updates using delta
+        self.last_update = now;
+    }
+
+    fn reload(&mut self) {
+        // Set up the vertex buffer and orbit texture, then the shader
+        let gl = match &self.gl {
+            Some(gl)=> gl,
+            None => {
+                log!("ERROR Setting up scene without a proper gl context");
+                return;
+            }
+        };
+
+        let vertices: Vec<f32> = vec![
+            -1.0, -1.0, 0.,
+            1.0, -1.0, 0.,
+            1.0, 1.0, 0.,
+            -1.0, -1.0, 0.,
+            -1.0, 1.0, 0.,
+            1.0, 1.0, 0.
+        ];
+
+        // Store count of triangle points (each point is 3 coords)
+        self.tri_count = vertices.len() as i32 / 3;
+
+        let vertex_buffer = gl.create_buffer().unwrap();
+        let verts = js_sys::Float32Array::from(vertices.as_slice());
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&vertex_buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
+
+        // The reference orbit goes in a one pixel high float texture, read back exactly
+        self.float_textures = matches!(gl.get_extension("OES_texture_float"), Ok(Some(_)));
+        if self.float_textures {
+            let texture = gl.create_texture().unwrap();
+            gl.active_texture(GL::TEXTURE0 + ORBIT_UNIT);
+            gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::NEAREST as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            self.orbit_texture = Some(texture);
+        } else {
+            log!("OES_texture_float isn't available, deep zooms will use double-single instead of perturbation");
+        }
+
+        self.use_program();
+    }
+
+    fn use_program(&mut self) {
+        let gl = match &self.gl {
+            Some(gl)=> gl.clone(),
+            None => {
+                log!("ERROR Setting up scene without a proper gl context");
+                return;
+            }
+        };
+
+        let vert_code = include_str!(concat!(env!("OUT_DIR"), "/mandelbrot.vert"));
+        let frag_code = include_str!(concat!(env!("OUT_DIR"), "/mandelbrot.frag"));
+
+        let defines = Defines::new()
+            .set_int("MAX_ITERATIONS", orbit::MAX_ITERATIONS as i32)
+            .set_int("ORBIT_SIZE", orbit::ORBIT_TEXTURE_WIDTH as i32);
+
+        let shader_program = match self.program_cache.get_or_compile(&gl, vert_code, frag_code, &defines) {
+            Ok(program) => program,
+            Err(err) => {
+                log!("ERROR compiling shader:", err);
+                return;
+            }
+        };
+
+        gl.use_program(Some(&shader_program));
+
+        // Attach the position vector as an attribute for the GL context.
+        let position = gl.get_attrib_location(&shader_program, "a_position") as u32;
+        gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(position);
+
+        let canvassize = gl.get_uniform_location(&shader_program, "canvasSize");
+        gl.uniform2f(canvassize.as_ref(), self.width as f32, self.height as f32);
+
+        let orbit = gl.get_uniform_location(&shader_program, "u_orbit");
+        gl.uniform1i(orbit.as_ref(), ORBIT_UNIT as i32);
+        self.gradient_texture.locate(&gl, &shader_program);
+
+        let location = |name| gl.get_uniform_location(&shader_program, name);
+        self.uniforms = Uniforms {
+            center: location("u_center"),
+            scale: location("u_scale"),
+            rotation: location("u_rotation"),
+            iterations: location("u_iterations"),
+            precision: location("u_precision"),
+            fractal: location("u_fractal"),
+            julia: location("u_julia"),
+            orbit_length: location("u_orbit_length"),
+        };
+
+        self.shader_program = Some(shader_program);
+    }
+
+    // Work the reference orbit out again for the middle of the screen if anything's changed
+    fn update_orbit(&mut self, iterations: usize) {
+        let gl = match &self.gl {
+            Some(gl) => gl,
+            None => return,
+        };
+        let key = (self.fractal, self.camera.camera.center, iterations);
+        if self.orbit_key == Some(key) {
+            return;
+        }
+
+        let orbit = ReferenceOrbit::compute(self.fractal, key.1, iterations);
+        let data = js_sys::Float32Array::from(orbit.texture_data().as_slice());
+
+        gl.active_texture(GL::TEXTURE0 + ORBIT_UNIT);
+        gl.bind_texture(GL::TEXTURE_2D, self.orbit_texture.as_ref());
+        let result = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+            GL::TEXTURE_2D,
+            0,
+            GL::RGBA as i32,
+            orbit::ORBIT_TEXTURE_WIDTH as i32,
+            1,
+            0,
+            GL::RGBA,
+            GL::FLOAT,
+            Some(&data),
+        );
+        if let Err(err) = result {
+            log!("ERROR uploading the reference orbit:", err);
+            return;
+        }
+
+        self.orbit_length = orbit.points.len();
+        self.orbit_key = Some(key);
+    }
+
+    fn render(&mut self) {
+        self.canvas_update();
+
+        let precision = self.current_precision();
+        let iterations = orbit::iterations_for_zoom(self.camera.camera.zoom);
+        if precision == Precision::Perturbation {
+            self.update_orbit(iterations);
+        }
+
+        let gl = self.gl.as_ref().expect("GL Context not initialized!");
+
+        gl.viewport(
+            0,
+            0,
+            self.width,
+            self.height,
+        );
+
+        // Only timed while the stats are showing
+        if let Some(timer) = self.gpu_timer.as_mut().filter(|_| self.show_hud) {
+            timer.begin();
+        }
+
+        gl.clear_color(0., 0.7, 0., 1.0);
+        gl.clear(GL::COLOR_BUFFER_BIT);
+
+        // The centre needs more precision than a float, everything else is relative to it
+        let camera = &self.camera.camera;
+        let (x, y) = (Ds::from_f64(camera.center.0), Ds::from_f64(camera.center.1));
+        gl.uniform4f(self.uniforms.center.as_ref(), x.hi, x.lo, y.hi, y.lo);
+        gl.uniform1f(self.uniforms.scale.as_ref(), (1.0 / camera.zoom) as f32);
+        gl.uniform2f(self.uniforms.rotation.as_ref(), camera.rotation.cos() as f32, camera.rotation.sin() as f32);
+        gl.uniform1i(self.uniforms.iterations.as_ref(), iterations as i32);
+        gl.uniform1i(self.uniforms.precision.as_ref(), precision as i32);
+        gl.uniform1i(self.uniforms.orbit_length.as_ref(), self.orbit_length as i32);
+
+        let (fractal, julia) = match self.fractal {
+            Fractal::Mandelbrot => (0, (0.0, 0.0)),
+            Fractal::Julia(c) => (1, c),
+        };
+        let (jx, jy) = (Ds::from_f64(julia.0), Ds::from_f64(julia.1));
+        gl.uniform1i(self.uniforms.fractal.as_ref(), fractal);
+        gl.uniform4f(self.uniforms.julia.as_ref(), jx.hi, jx.lo, jy.hi, jy.lo);
+        self.gradient_texture.upload(gl, &self.gradient, GRADIENT_UNIT);
+
+        gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
+        if let Some(timer) = self.gpu_timer.as_mut().filter(|_| self.show_hud) {
+            timer.end();
+            self.gpu_time = timer.poll(gl);
+        }
+        self.camera.end_frame();
+
+        window()
+            .unwrap()
+            .request_animation_frame(self.callback.as_ref().unchecked_ref())
+            .unwrap();
+    }
+}
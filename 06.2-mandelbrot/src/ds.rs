@@ -0,0 +1,141 @@
+// Double-single numbers on the CPU, doing exactly what lib/double_single.glsl does in the
+// shader. The page only needs from_f64 to send the camera centre, the arithmetic is here so the
+// shader's version can be checked against f64 in the tests.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ds {
+    pub hi: f32,
+    pub lo: f32,
+}
+
+impl Ds {
+    pub fn from_f64(value: f64) -> Ds {
+        let hi = value as f32;
+        Ds { hi, lo: (value - hi as f64) as f32 }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.hi as f64 + self.lo as f64
+    }
+}
+
+// Split into two halves of 12 bits so their products are exact
+fn split(a: f32) -> (f32, f32) {
+    let t = 4097.0 * a;
+    let hi = t - (t - a);
+    (hi, a - hi)
+}
+
+impl Add for Ds {
+    type Output = Ds;
+
+    fn add(self, other: Ds) -> Ds {
+        // Add the high parts keeping the rounding error, then fold in the low parts
+        let s = self.hi + other.hi;
+        let v = s - self.hi;
+        let mut e = (self.hi - (s - v)) + (other.hi - v);
+        e += self.lo + other.lo;
+        let hi = s + e;
+        Ds { hi, lo: e - (hi - s) }
+    }
+}
+
+impl Neg for Ds {
+    type Output = Ds;
+
+    fn neg(self) -> Ds {
+        Ds { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+impl Sub for Ds {
+    type Output = Ds;
+
+    fn sub(self, other: Ds) -> Ds {
+        self + -other
+    }
+}
+
+impl Mul for Ds {
+    type Output = Ds;
+
+    fn mul(self, other: Ds) -> Ds {
+        let p = self.hi * other.hi;
+        let (ah, al) = split(self.hi);
+        let (bh, bl) = split(other.hi);
+        let mut e = ((ah * bh - p) + ah * bl + al * bh) + al * bl;
+        e += self.hi * other.lo + self.lo * other.hi;
+        let hi = p + e;
+        Ds { hi, lo: e - (hi - p) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 48 bits of mantissa, with a little room for rounding
+    const EPSILON: f64 = 1e-13;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= EPSILON * b.abs().max(1.0)
+    }
+
+    const VALUES: &[f64] = &[0.0, 1.0, -0.75, 0.1, 1.0 / 3.0, -1.7499999999, 0.3000000000001234, 123.456789012345];
+
+    #[test]
+    fn from_f64_keeps_more_than_a_float() {
+        for value in VALUES {
+            assert!(close(Ds::from_f64(*value).to_f64(), *value), "{}", value);
+        }
+        // An f32 alone can't tell these apart
+        let a = Ds::from_f64(1.0 + 1e-10);
+        assert_eq!(a.hi, 1.0);
+        assert!(a.lo > 0.0);
+    }
+
+    #[test]
+    fn add_sub_and_mul_match_f64() {
+        for a in VALUES {
+            for b in VALUES {
+                let (da, db) = (Ds::from_f64(*a), Ds::from_f64(*b));
+                assert!(close((da + db).to_f64(), a + b), "{} + {}", a, b);
+                assert!(close((da - db).to_f64(), a - b), "{} - {}", a, b);
+                assert!(close((da * db).to_f64(), a * b), "{} * {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_offsets_survive() {
+        // The reason for all this: moving a pixel's width at a zoom of 10^12
+        let centre = Ds::from_f64(-0.743643887037158);
+        let offset = Ds::from_f64(3.5e-13);
+        assert!(close((centre + offset).to_f64(), -0.743643887037158 + 3.5e-13));
+        assert_ne!((centre + offset).to_f64(), centre.to_f64());
+        assert_eq!(centre.hi + offset.hi, centre.hi);
+    }
+
+    #[test]
+    fn mandelbrot_iterations_follow_f64() {
+        // z = z^2 + c in double-single stays with f64 far longer than f32 would
+        let c = (-0.743643887037158, 0.131825904205311);
+        let (cr, ci) = (Ds::from_f64(c.0), Ds::from_f64(c.1));
+        let (mut zr, mut zi) = (Ds::from_f64(0.0), Ds::from_f64(0.0));
+        let (mut fr, mut fi) = (0.0f64, 0.0f64);
+        let two = Ds::from_f64(2.0);
+
+        for _ in 0..30 {
+            let re = zr * zr - zi * zi + cr;
+            zi = zr * zi * two + ci;
+            zr = re;
+            let re = fr * fr - fi * fi + c.0;
+            fi = 2.0 * fr * fi + c.1;
+            fr = re;
+        }
+        assert!((zr.to_f64() - fr).abs() < 1e-10);
+        assert!((zi.to_f64() - fi).abs() < 1e-10);
+    }
+}
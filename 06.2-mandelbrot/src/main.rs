@@ -0,0 +1,36 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+mod canvas_control;
+// Both have CPU copies of what the shader does, which only the tests use
+#[cfg_attr(not(test), allow(dead_code))]
+mod ds;
+#[cfg_attr(not(test), allow(dead_code))]
+mod orbit;
+
+#[derive(Clone, Routable, PartialEq)]
+enum Route {
+    #[at("/")]
+    Home,
+}
+
+fn switch(routes: Route) -> Html {    
+    match routes {
+        Route::Home => html!{
+            <canvas_control::CanvasControl />
+        }
+    }
+}
+
+#[function_component(App)]
+fn app_body() -> Html {
+    html! {
+        <BrowserRouter>
+            <Switch<Route> render={switch} />
+        </BrowserRouter>
+    }
+}
+
+fn main() {
+    yew::Renderer::<App>::new().render();
+}
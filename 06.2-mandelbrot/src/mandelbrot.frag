@@ -0,0 +1,128 @@
+precision highp float;
+
+uniform vec2 canvasSize;
+// Camera centre in double-single, (x.hi, x.lo, y.hi, y.lo)
+uniform vec4 u_center;
+// Pattern units per canvas height, and the camera's turn as (cos, sin)
+uniform float u_scale;
+uniform vec2 u_rotation;
+uniform int u_iterations;
+// 0 float, 1 double-single, 2 perturbation - see Precision in canvas_control.rs
+uniform int u_precision;
+// 0 Mandelbrot, 1 Julia with the constant u_julia, in double-single like u_center
+uniform int u_fractal;
+uniform vec4 u_julia;
+// Reference orbit from orbit.rs for perturbation, Z(n) in the red and green of texel n
+uniform sampler2D u_orbit;
+uniform int u_orbit_length;
+// Colours for the smooth escape count, from the gradient editor. It repeats, so the count can
+// just keep going.
+uniform sampler2D u_gradient;
+
+#include "lib/double_single.glsl"
+
+// The Rust side sets these from orbit.rs
+#ifndef MAX_ITERATIONS
+#define MAX_ITERATIONS 2000
+#endif
+#ifndef ORBIT_SIZE
+#define ORBIT_SIZE 2048
+#endif
+
+// Escape radius of 256, squared
+const float BAILOUT = 65536.0;
+
+vec2 complexMul(vec2 a, vec2 b)
+{
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// Each iterate function returns where z escaped and after how many steps, or a negative count
+// if it never did
+
+// Plain floats, fine until neighbouring pixels are closer than a float can tell apart
+vec3 iterateFloat(vec2 offset)
+{
+    vec2 point = u_center.xz + offset;
+    vec2 z = u_fractal == 1 ? point : vec2(0.0);
+    vec2 c = u_fractal == 1 ? u_julia.xz : point;
+
+    for (int i = 0; i < MAX_ITERATIONS; i++) {
+        if (i >= u_iterations) break;
+        if (dot(z, z) > BAILOUT) return vec3(z, float(i));
+        z = complexMul(z, z) + c;
+    }
+    return vec3(0.0, 0.0, -1.0);
+}
+
+// The same in double-single, about twice as deep but several times slower
+vec3 iterateDoubleSingle(vec2 offset)
+{
+    vec4 point = vec4(dsAdd(u_center.xy, dsSet(offset.x)), dsAdd(u_center.zw, dsSet(offset.y)));
+    vec4 z = u_fractal == 1 ? point : vec4(0.0);
+    vec4 c = u_fractal == 1 ? u_julia : point;
+
+    for (int i = 0; i < MAX_ITERATIONS; i++) {
+        if (i >= u_iterations) break;
+        if (dot(z.xz, z.xz) > BAILOUT) return vec3(z.xz, float(i));
+        z = dsComplexAdd(dsComplexSquare(z), c);
+    }
+    return vec3(0.0, 0.0, -1.0);
+}
+
+vec2 orbitPoint(int n)
+{
+    return texture2D(u_orbit, vec2((float(n) + 0.5) / float(ORBIT_SIZE), 0.5)).xy;
+}
+
+// Follow the difference from the reference orbit, see orbit.rs. Only `offset` needs to be
+// precise and it's small, so this goes as deep as the f64 orbit at float speed.
+vec3 iteratePerturbation(vec2 offset)
+{
+    vec2 dz = u_fractal == 1 ? offset : vec2(0.0);
+    vec2 dc = u_fractal == 1 ? vec2(0.0) : offset;
+    int m = 0;
+
+    for (int i = 0; i < MAX_ITERATIONS; i++) {
+        if (i >= u_iterations) break;
+        vec2 reference = orbitPoint(m);
+        vec2 z = reference + dz;
+        if (dot(z, z) > BAILOUT) return vec3(z, float(i));
+
+        // Rebase onto the start of the orbit before the difference loses its precision
+        if (dot(z, z) < dot(dz, dz) || m + 1 >= u_orbit_length) {
+            reference = orbitPoint(0);
+            dz = z - reference;
+            m = 0;
+        }
+        dz = 2.0 * complexMul(reference, dz) + complexMul(dz, dz) + dc;
+        m++;
+    }
+    return vec3(0.0, 0.0, -1.0);
+}
+
+void main(void)
+{
+    // How far this pixel is from the centre of the screen in pattern units. It's small, so a
+    // float is plenty even when the centre needs more.
+    vec2 p = (gl_FragCoord.xy - 0.5 * canvasSize.xy) / canvasSize.y;
+    vec2 offset = mat2(u_rotation.x, u_rotation.y, -u_rotation.y, u_rotation.x) * p * u_scale;
+
+    vec3 escape;
+    if (u_precision == 2) {
+        escape = iteratePerturbation(offset);
+    } else if (u_precision == 1) {
+        escape = iterateDoubleSingle(offset);
+    } else {
+        escape = iterateFloat(offset);
+    }
+
+    vec3 col = vec3(0.0);
+    if (escape.z >= 0.0) {
+        // Smooth iteration count, n + 1 - log2(log|z|), so the colours don't band
+        float n = escape.z + 1.0 - log2(0.5 * log(dot(escape.xy, escape.xy)));
+        col = texture2D(u_gradient, vec2(0.02 * n, 0.5)).rgb;
+    }
+
+    gl_FragColor = vec4(col, 1.0);
+}
@@ -0,0 +1,251 @@
+// The reference orbit for perturbation rendering.
+//
+// Deep in a zoom, neighbouring pixels differ by far less than a float can resolve. Instead of
+// iterating every pixel from scratch, one point (the middle of the screen) is iterated here in
+// f64, Z(n+1) = Z(n)^2 + C, and each pixel only follows its small difference from it:
+//
+//   z(n) = Z(n) + d(n)    d(n+1) = 2 Z(n) d(n) + d(n)^2 + dc
+//
+// d stays small enough for a float in the shader. When a pixel gets closer to 0 than to the
+// reference, or the reference escapes before the pixel does, d loses precision ("glitches"), so
+// the pixel is rebased onto the start of the orbit again (Zhuoran's rebasing).
+//
+// perturbed() is the shader's loop written out in f64 so it can be tested against direct().
+
+// Has to match MAX_ITERATIONS and ORBIT_SIZE in mandelbrot.frag, which are set from these
+pub const MAX_ITERATIONS: usize = 2000;
+pub const ORBIT_TEXTURE_WIDTH: usize = 2048;
+
+// Escape radius, large so the smooth colouring is smooth
+pub const BAILOUT: f64 = 256.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fractal {
+    // z starts at 0 and c is the point on screen
+    Mandelbrot,
+    // z starts at the point on screen and c is fixed
+    Julia((f64, f64)),
+}
+
+pub struct ReferenceOrbit {
+    // Z(0), Z(1), ... until the orbit escapes or runs out of iterations
+    pub points: Vec<(f64, f64)>,
+}
+
+impl ReferenceOrbit {
+    pub fn compute(fractal: Fractal, reference: (f64, f64), max_iterations: usize) -> ReferenceOrbit {
+        let (mut z, c) = start(fractal, reference);
+        let mut points = vec![z];
+        for _ in 0..max_iterations.min(ORBIT_TEXTURE_WIDTH - 1) {
+            if norm(z) > BAILOUT * BAILOUT {
+                break;
+            }
+            z = add(mul(z, z), c);
+            points.push(z);
+        }
+        ReferenceOrbit { points }
+    }
+
+    // One RGBA float texel per point, Z in red and green, padded out to ORBIT_TEXTURE_WIDTH
+    pub fn texture_data(&self) -> Vec<f32> {
+        let mut data = vec![0.0; ORBIT_TEXTURE_WIDTH * 4];
+        for (texel, point) in data.chunks_exact_mut(4).zip(&self.points) {
+            texel[0] = point.0 as f32;
+            texel[1] = point.1 as f32;
+        }
+        data
+    }
+
+    // Smooth iteration count for the point `delta` away from the reference, None if it never
+    // escapes. The same steps as iteratePerturbation in mandelbrot.frag.
+    pub fn perturbed(&self, fractal: Fractal, delta: (f64, f64), max_iterations: usize) -> Option<f64> {
+        let (mut dz, dc) = match fractal {
+            Fractal::Mandelbrot => ((0.0, 0.0), delta),
+            Fractal::Julia(_) => (delta, (0.0, 0.0)),
+        };
+        // Where along the reference orbit this pixel is, which drops back to 0 on a rebase
+        let mut m = 0;
+
+        for n in 0..max_iterations {
+            let mut reference = self.points[m];
+            let z = add(reference, dz);
+            if norm(z) > BAILOUT * BAILOUT {
+                return Some(smooth_iterations(n, z));
+            }
+            if norm(z) < norm(dz) || m + 1 >= self.points.len() {
+                reference = self.points[0];
+                dz = sub(z, reference);
+                m = 0;
+            }
+            dz = add(add(scale(mul(reference, dz), 2.0), mul(dz, dz)), dc);
+            m += 1;
+        }
+        None
+    }
+}
+
+// Iterate `point` on its own, the way the float and double-single modes do
+pub fn direct(fractal: Fractal, point: (f64, f64), max_iterations: usize) -> Option<f64> {
+    let (mut z, c) = start(fractal, point);
+    for n in 0..max_iterations {
+        if norm(z) > BAILOUT * BAILOUT {
+            return Some(smooth_iterations(n, z));
+        }
+        z = add(mul(z, z), c);
+    }
+    None
+}
+
+// Escaped after `n` iterations at `z`. Counts up continuously rather than in whole steps so the
+// colours don't band: n + 1 - log2(log|z|)
+pub fn smooth_iterations(n: usize, z: (f64, f64)) -> f64 {
+    n as f64 + 1.0 - (0.5 * norm(z).ln()).log2()
+}
+
+// More detail needs more iterations, roughly 100 more for every ten times further in
+pub fn iterations_for_zoom(zoom: f64) -> usize {
+    let extra = 100.0 * zoom.max(1.0).log10();
+    (200 + extra as usize).min(MAX_ITERATIONS)
+}
+
+fn start(fractal: Fractal, point: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+    match fractal {
+        Fractal::Mandelbrot => ((0.0, 0.0), point),
+        Fractal::Julia(c) => (point, c),
+    }
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn scale(a: (f64, f64), factor: f64) -> (f64, f64) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn norm(a: (f64, f64)) -> f64 {
+    a.0 * a.0 + a.1 * a.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JULIA: Fractal = Fractal::Julia((-0.8, 0.156));
+
+    // Offsets of a small grid of pixels, `spacing` apart
+    fn grid(spacing: f64) -> Vec<(f64, f64)> {
+        let mut deltas = Vec::new();
+        for y in -4..=4 {
+            for x in -4..=4 {
+                deltas.push((x as f64 * spacing, y as f64 * spacing));
+            }
+        }
+        deltas
+    }
+
+    fn assert_matches_direct(fractal: Fractal, reference: (f64, f64), spacing: f64, max_iterations: usize) {
+        let orbit = ReferenceOrbit::compute(fractal, reference, max_iterations);
+        for delta in grid(spacing) {
+            let point = add(reference, delta);
+            let expected = direct(fractal, point, max_iterations);
+            let perturbed = orbit.perturbed(fractal, delta, max_iterations);
+            match (expected, perturbed) {
+                // Near the edge of the set rounding grows with every step, whichever way it's done
+                (Some(expected), Some(perturbed)) => {
+                    assert!((expected - perturbed).abs() < 1e-4 * expected.max(10.0), "{:?}: {} vs {}", point, expected, perturbed)
+                }
+                (None, None) => (),
+                _ => panic!("{:?}: {:?} vs {:?}", point, expected, perturbed),
+            }
+        }
+    }
+
+    #[test]
+    fn orbit_of_zero_stays_at_zero() {
+        let orbit = ReferenceOrbit::compute(Fractal::Mandelbrot, (0.0, 0.0), 100);
+        assert_eq!(orbit.points.len(), 101);
+        assert!(orbit.points.iter().all(|point| *point == (0.0, 0.0)));
+    }
+
+    #[test]
+    fn orbit_of_minus_one_cycles() {
+        let orbit = ReferenceOrbit::compute(Fractal::Mandelbrot, (-1.0, 0.0), 6);
+        assert_eq!(orbit.points, vec![(0.0, 0.0), (-1.0, 0.0), (0.0, 0.0), (-1.0, 0.0), (0.0, 0.0), (-1.0, 0.0), (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn orbit_stops_once_it_escapes() {
+        // 0, 1, 2, 5, 26, 677 - the last is past the bailout
+        let orbit = ReferenceOrbit::compute(Fractal::Mandelbrot, (1.0, 0.0), 100);
+        assert_eq!(orbit.points.len(), 6);
+        assert_eq!(orbit.points[5], (677.0, 0.0));
+        assert_eq!(direct(Fractal::Mandelbrot, (1.0, 0.0), 100), Some(smooth_iterations(5, (677.0, 0.0))));
+    }
+
+    #[test]
+    fn julia_orbit_starts_at_the_point() {
+        let orbit = ReferenceOrbit::compute(JULIA, (0.25, 0.5), 10);
+        assert_eq!(orbit.points[0], (0.25, 0.5));
+        assert_eq!(orbit.points[1], add(mul((0.25, 0.5), (0.25, 0.5)), (-0.8, 0.156)));
+    }
+
+    #[test]
+    fn orbit_fits_the_texture() {
+        let orbit = ReferenceOrbit::compute(Fractal::Mandelbrot, (0.0, 0.0), MAX_ITERATIONS);
+        assert!(orbit.points.len() <= ORBIT_TEXTURE_WIDTH);
+        let data = orbit.texture_data();
+        assert_eq!(data.len(), ORBIT_TEXTURE_WIDTH * 4);
+
+        let orbit = ReferenceOrbit::compute(Fractal::Mandelbrot, (-1.0, 0.0), 3);
+        assert_eq!(&orbit.texture_data()[..16], &[0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn smooth_iterations_are_continuous() {
+        // Just escaping after n steps gives about the same count as only just not after n - 1
+        let r = BAILOUT * 1.0001;
+        let just_out = smooth_iterations(10, (r, 0.0));
+        let squared = smooth_iterations(9, (r.sqrt(), 0.0));
+        assert!((just_out - squared).abs() < 1e-3, "{} vs {}", just_out, squared);
+        assert!(smooth_iterations(10, (1e6, 0.0)) < smooth_iterations(10, (300.0, 0.0)));
+    }
+
+    #[test]
+    fn perturbation_matches_direct_iteration() {
+        assert_matches_direct(Fractal::Mandelbrot, (-0.75, 0.1), 1e-3, 500);
+        assert_matches_direct(Fractal::Mandelbrot, (-0.743643887037158, 0.131825904205311), 1e-7, 1000);
+        assert_matches_direct(JULIA, (0.1, 0.2), 1e-3, 500);
+    }
+
+    #[test]
+    fn rebasing_handles_a_reference_inside_the_set() {
+        // The reference never escapes but most of the grid does
+        assert_matches_direct(Fractal::Mandelbrot, (0.0, 0.0), 0.1, 200);
+    }
+
+    #[test]
+    fn rebasing_handles_a_reference_that_escapes_early() {
+        // The reference escapes after a few steps, -0.1 is inside the set
+        let orbit = ReferenceOrbit::compute(Fractal::Mandelbrot, (0.5, 0.0), 200);
+        assert!(orbit.points.len() < 10);
+        assert_eq!(orbit.perturbed(Fractal::Mandelbrot, (-0.6, 0.0), 200), None);
+        assert_matches_direct(Fractal::Mandelbrot, (0.5, 0.0), 0.05, 200);
+    }
+
+    #[test]
+    fn iterations_grow_with_zoom() {
+        assert_eq!(iterations_for_zoom(0.5), 200);
+        assert_eq!(iterations_for_zoom(1000.0), 500);
+        assert!(iterations_for_zoom(1e12) > iterations_for_zoom(1e6));
+        assert_eq!(iterations_for_zoom(1e30), MAX_ITERATIONS);
+    }
+}
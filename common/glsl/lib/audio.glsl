@@ -0,0 +1,21 @@
+// Audio from shader_common::audio. u_audio is 512x2 in Shadertoy's layout: row 0 is the
+// spectrum (0 to 1, low to high frequencies), row 1 the waveform with silence at 0.5.
+uniform sampler2D u_audio;
+// How loud the bass, middle and treble are, 0 to 1
+uniform float u_bass;
+uniform float u_mid;
+uniform float u_treble;
+// 1.0 on a beat, dying away to 0
+uniform float u_beat;
+
+// Spectrum at x, 0 for the lowest frequency to 1 for the highest
+float audioSpectrum(float x)
+{
+    return texture2D(u_audio, vec2(x, 0.25)).x;
+}
+
+// Waveform at x across the last 512 samples, -1 to 1
+float audioWave(float x)
+{
+    return texture2D(u_audio, vec2(x, 0.75)).x * 2.0 - 1.0;
+}
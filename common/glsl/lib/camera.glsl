@@ -0,0 +1,9 @@
+// The pan/zoom/rotate camera from shader_common::camera. Needs canvasSize declared first.
+uniform mat3 u_camera;
+
+// The centred screen position (canvas height is 1.0) moved by the camera
+vec2 cameraUv()
+{
+    vec2 p = (gl_FragCoord.xy - 0.5 * canvasSize.xy) / canvasSize.y;
+    return (u_camera * vec3(p, 1.0)).xy;
+}
@@ -0,0 +1,3 @@
+// Shared constants - include this rather than defining PI in each shader
+#define PI 3.14159265359
+#define TWO_PI 6.28318530718
@@ -0,0 +1,58 @@
+// Double-single arithmetic: a number kept as the unevaluated sum of two floats, hi + lo, for
+// about twice the precision of a float (48 bits of mantissa rather than 24). Based on Dekker's
+// and Knuth's error free additions and products, as in the DSFUN90 library.
+//
+// A vec2 is one number (hi, lo), a vec4 a complex number (re.hi, re.lo, im.hi, im.lo).
+// The Rust side can split an f64 into the two parts with `hi = x as f32; lo = (x - hi) as f32`.
+
+vec2 dsSet(float a)
+{
+    return vec2(a, 0.0);
+}
+
+vec2 dsAdd(vec2 a, vec2 b)
+{
+    // Add the high parts keeping the rounding error, then fold in the low parts
+    float s = a.x + b.x;
+    float v = s - a.x;
+    float e = (a.x - (s - v)) + (b.x - v);
+    e += a.y + b.y;
+    float hi = s + e;
+    return vec2(hi, e - (hi - s));
+}
+
+vec2 dsSub(vec2 a, vec2 b)
+{
+    return dsAdd(a, -b);
+}
+
+// Split a float into two halves of 12 bits so their products are exact
+vec2 dsSplit(float a)
+{
+    float t = 4097.0 * a;
+    float hi = t - (t - a);
+    return vec2(hi, a - hi);
+}
+
+vec2 dsMul(vec2 a, vec2 b)
+{
+    float p = a.x * b.x;
+    vec2 sa = dsSplit(a.x);
+    vec2 sb = dsSplit(b.x);
+    float e = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+    e += a.x * b.y + a.y * b.x;
+    float hi = p + e;
+    return vec2(hi, e - (hi - p));
+}
+
+vec4 dsComplexAdd(vec4 a, vec4 b)
+{
+    return vec4(dsAdd(a.xy, b.xy), dsAdd(a.zw, b.zw));
+}
+
+vec4 dsComplexSquare(vec4 z)
+{
+    vec2 re = dsSub(dsMul(z.xy, z.xy), dsMul(z.zw, z.zw));
+    vec2 im = dsMul(dsMul(z.xy, z.zw), dsSet(2.0));
+    return vec4(re, im);
+}
@@ -0,0 +1,31 @@
+// Keyboard state, Shadertoy style. The page uploads a 256x3 texture indexed by JavaScript key
+// code: row 0 is 1.0 while the key is held, row 1 only on the frame it went down and row 2 flips
+// each time it's pressed. See shader_common::keyboard.
+uniform sampler2D iKeyboard;
+
+#define KEY_SPACE 32.0
+#define KEY_LEFT 37.0
+#define KEY_UP 38.0
+#define KEY_RIGHT 39.0
+#define KEY_DOWN 40.0
+// Letters are their upper case ASCII code, e.g. 65.0 for A
+
+float keyRow(float key, float row)
+{
+    return texture2D(iKeyboard, vec2((key + 0.5) / 256.0, (row + 0.5) / 3.0)).x;
+}
+
+float keyDown(float key)
+{
+    return keyRow(key, 0.0);
+}
+
+float keyPressed(float key)
+{
+    return keyRow(key, 1.0);
+}
+
+float keyToggled(float key)
+{
+    return keyRow(key, 2.0);
+}
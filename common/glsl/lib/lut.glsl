@@ -0,0 +1,26 @@
+// 3D LUT colour grading. The page uploads the LUT's blue slices side by side in a
+// (size * size) x size strip, see shader_common::lut.
+uniform sampler2D u_lut;
+uniform float u_lut_size;
+uniform vec3 u_lut_domain_min;
+uniform vec3 u_lut_domain_max;
+
+// The texture blends red and green inside a slice, mixing the two nearest slices makes it
+// trilinear
+vec3 applyLut(vec3 color)
+{
+    vec3 cell = clamp((color - u_lut_domain_min) / (u_lut_domain_max - u_lut_domain_min), 0.0, 1.0) * (u_lut_size - 1.0);
+    float slice = min(floor(cell.b), u_lut_size - 2.0);
+
+    // Texel centres, so the ends of each slice don't blend into the next one
+    vec2 uv = (cell.rg + 0.5) / vec2(u_lut_size * u_lut_size, u_lut_size);
+    vec3 low = texture2D(u_lut, uv + vec2(slice / u_lut_size, 0.0)).rgb;
+    vec3 high = texture2D(u_lut, uv + vec2((slice + 1.0) / u_lut_size, 0.0)).rgb;
+    return mix(low, high, cell.b - slice);
+}
+
+// Only part of the way to the graded colour, 0 leaves it alone
+vec3 gradeColor(vec3 color, float amount)
+{
+    return mix(color, applyLut(color), amount);
+}
@@ -0,0 +1,19 @@
+// Cosine based palette by Inigo Quilez: https://iquilezles.org/articles/palettes/
+#include "lib/constants.glsl"
+
+// a - brightness, b - contrast, c - frequency, d - phase
+vec3 palette(float t, vec3 a, vec3 b, vec3 c, vec3 d)
+{
+    return a + b * cos(TWO_PI * (c * t + d));
+}
+
+// The palette set from the page, see shader_common::palette
+uniform vec3 u_palette_a;
+uniform vec3 u_palette_b;
+uniform vec3 u_palette_c;
+uniform vec3 u_palette_d;
+
+vec3 palette(float t)
+{
+    return palette(t, u_palette_a, u_palette_b, u_palette_c, u_palette_d);
+}
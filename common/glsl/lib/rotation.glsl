@@ -0,0 +1,15 @@
+// 2D rotation helpers
+
+// Rotation matrix for `angle` radians (anti-clockwise), use as `uv = rotate2d(a) * uv`
+mat2 rotate2d(float angle)
+{
+    float s = sin(angle);
+    float c = cos(angle);
+    return mat2(c, s, -s, c);
+}
+
+// Rotate a point around `center`
+vec2 rotateAround(vec2 p, vec2 center, float angle)
+{
+    return rotate2d(angle) * (p - center) + center;
+}
@@ -0,0 +1,29 @@
+// Signed distance functions for 2D shapes, negative inside and positive outside.
+// See https://iquilezles.org/articles/distfunctions2d/
+
+float sdCircle(vec2 p, float radius)
+{
+    return length(p) - radius;
+}
+
+// `size` is the half-width and half-height of the box
+float sdBox(vec2 p, vec2 size)
+{
+    vec2 d = abs(p) - size;
+    return length(max(d, 0.0)) + min(max(d.x, d.y), 0.0);
+}
+
+// Distance to the line segment from a to b
+float sdSegment(vec2 p, vec2 a, vec2 b)
+{
+    vec2 pa = p - a;
+    vec2 ba = b - a;
+    float h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+// Turn a distance into a soft-edged mask, 1.0 inside the shape
+float sdFill(float dist, float blur)
+{
+    return smoothstep(blur, -blur, dist);
+}
@@ -0,0 +1,13 @@
+// Tunnel mapping taken from here: https://www.shadertoy.com/view/4djBRm
+#include "lib/constants.glsl"
+
+// Maps a screen position (0..1) to texture coordinates running down a tube.
+// size - smaller values for a smaller/thinner tunnel
+// time - scroll position along the tunnel, negative values ok
+vec2 tunnel(vec2 uv, float size, float time)
+{
+    vec2 p  = -1.0 + (2.0 * uv);
+    float a = atan(p.y, p.x);
+    float r = sqrt(dot(p, p));
+    return vec2(a / PI, time + (size / r));
+}
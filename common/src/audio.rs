@@ -0,0 +1,498 @@
+// Audio reactive shaders: decode a WAV or Ogg Vorbis file and work out, for any point in it,
+// what the shader needs to move with the music.
+//
+// Everything is keyed on time rather than on what's playing, so the shader follows u_time (and
+// the clip loops round). The page plays the same file in an <audio> element kept in step with it.
+// Each frame gives:
+//   - a 512x2 texture in Shadertoy's layout: row 0 is the spectrum, 0 to 1 on a decibel scale
+//     like WebAudio's AnalyserNode, row 1 is the waveform with silence at 0.5
+//   - u_bass, u_mid and u_treble, how loud each band is (0 to 1)
+//   - u_beat, 1.0 on a beat dying away to 0
+// Shaders read them with `#include "lib/audio.glsl"`.
+//
+// None of the analysis touches the browser, so it's tested natively against the files in
+// tests/fixtures.
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+
+// Samples per FFT, giving half as many frequency bins - one per texel across the texture
+pub const FFT_SIZE: usize = 1024;
+pub const TEXTURE_WIDTH: usize = FFT_SIZE / 2;
+const ROWS: usize = 2;
+
+// Decibel range mapped to 0..1, and how much of the last frame's spectrum to keep, as WebAudio
+const MIN_DB: f32 = -100.0;
+const MAX_DB: f32 = -30.0;
+const SMOOTHING: f32 = 0.8;
+
+// Frequency bands in Hz
+const BASS: (f32, f32) = (20.0, 250.0);
+const MID: (f32, f32) = (250.0, 2000.0);
+const TREBLE: (f32, f32) = (2000.0, 8000.0);
+
+// A beat is bass energy this many times the average over the last BEAT_HISTORY seconds
+const BEAT_THRESHOLD: f32 = 1.8;
+const BEAT_HISTORY: f64 = 1.0;
+// Not twice within this many seconds, and not out of near silence
+const BEAT_GAP: f64 = 0.25;
+const BEAT_MIN_ENERGY: f32 = 1e-4;
+// How quickly u_beat dies away, per second
+const BEAT_DECAY: f64 = 6.0;
+// Jumping further than this (or backwards) starts the analysis afresh
+const MAX_STEP: f64 = 0.5;
+
+// Mono samples, -1 to 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioClip {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl AudioClip {
+    // WAV or Ogg Vorbis, going by the first bytes rather than the file name
+    pub fn decode(bytes: &[u8]) -> Result<AudioClip, String> {
+        if bytes.starts_with(b"RIFF") {
+            AudioClip::decode_wav(bytes)
+        } else if bytes.starts_with(b"OggS") {
+            AudioClip::decode_ogg(bytes)
+        } else {
+            Err("Unknown audio format, expected WAV or Ogg Vorbis".to_string())
+        }
+    }
+
+    pub fn decode_wav(bytes: &[u8]) -> Result<AudioClip, String> {
+        let reader = hound::WavReader::new(Cursor::new(bytes)).map_err(|err| format!("Unable to read WAV: {}", err))?;
+        let spec = reader.spec();
+
+        let samples: Result<Vec<f32>, _> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.into_samples::<f32>().collect(),
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader.into_samples::<i32>().map(|sample| sample.map(|sample| sample as f32 * scale)).collect()
+            },
+        };
+        let samples = samples.map_err(|err| format!("Unable to read WAV: {}", err))?;
+
+        Ok(AudioClip {
+            sample_rate: spec.sample_rate,
+            samples: mix_down(&samples, spec.channels as usize),
+        })
+    }
+
+    pub fn decode_ogg(bytes: &[u8]) -> Result<AudioClip, String> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))
+            .map_err(|err| format!("Unable to read Ogg Vorbis: {}", err))?;
+
+        let mut samples = Vec::new();
+        while let Some(packet) = reader.read_dec_packet_itl().map_err(|err| format!("Unable to read Ogg Vorbis: {}", err))? {
+            samples.extend(packet.iter().map(|sample| *sample as f32 / 32768.0));
+        }
+
+        Ok(AudioClip {
+            sample_rate: reader.ident_hdr.audio_sample_rate,
+            samples: mix_down(&samples, reader.ident_hdr.audio_channels as usize),
+        })
+    }
+
+    // Seconds
+    pub fn duration(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate.max(1) as f64
+    }
+}
+
+// Average interleaved channels into one
+fn mix_down(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples.chunks_exact(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioFrame {
+    // TEXTURE_WIDTH of each, spectrum 0 to 1 and waveform -1 to 1
+    pub spectrum: Vec<f32>,
+    pub waveform: Vec<f32>,
+    pub bass: f32,
+    pub mid: f32,
+    pub treble: f32,
+    pub beat: f32,
+}
+
+impl AudioFrame {
+    pub fn silent() -> AudioFrame {
+        AudioFrame {
+            spectrum: vec![0.0; TEXTURE_WIDTH],
+            waveform: vec![0.0; TEXTURE_WIDTH],
+            bass: 0.0,
+            mid: 0.0,
+            treble: 0.0,
+            beat: 0.0,
+        }
+    }
+
+    // The texels of the 512x2 LUMINANCE texture, spectrum then waveform
+    pub fn texture_data(&self) -> Vec<u8> {
+        let spectrum = self.spectrum.iter().map(|value| to_byte(*value));
+        let waveform = self.waveform.iter().map(|value| to_byte(*value * 0.5 + 0.5));
+        spectrum.chain(waveform).collect()
+    }
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+pub struct AudioAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    // Magnitudes carried over from the last frame, see SMOOTHING
+    smoothed: Vec<f32>,
+    // Time and bass energy of recent frames, for beat detection
+    history: VecDeque<(f64, f32)>,
+    last_time: Option<f64>,
+    last_beat: Option<f64>,
+    beat: f32,
+}
+
+impl Default for AudioAnalyzer {
+    fn default() -> Self {
+        AudioAnalyzer::new()
+    }
+}
+
+impl AudioAnalyzer {
+    pub fn new() -> Self {
+        // Blackman window, as WebAudio uses
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let x = std::f32::consts::TAU * i as f32 / FFT_SIZE as f32;
+                0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+            })
+            .collect();
+
+        AudioAnalyzer {
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            smoothed: vec![0.0; TEXTURE_WIDTH],
+            history: VecDeque::new(),
+            last_time: None,
+            last_beat: None,
+            beat: 0.0,
+        }
+    }
+
+    // Forget everything carried between frames, e.g. after seeking
+    pub fn reset(&mut self) {
+        self.smoothed.iter_mut().for_each(|value| *value = 0.0);
+        self.history.clear();
+        self.last_time = None;
+        self.last_beat = None;
+        self.beat = 0.0;
+    }
+
+    // Analyse the audio leading up to `time` seconds, looping round the clip. Call once a frame
+    // with time going forwards, the smoothing and beats depend on the frames before.
+    pub fn analyze(&mut self, clip: &AudioClip, time: f64) -> AudioFrame {
+        if clip.samples.is_empty() || clip.sample_rate == 0 {
+            return AudioFrame::silent();
+        }
+
+        let dt = match self.last_time {
+            Some(last) if time >= last && time - last <= MAX_STEP => time - last,
+            Some(_) => {
+                self.reset();
+                0.0
+            },
+            None => 0.0,
+        };
+        self.last_time = Some(time);
+
+        // The FFT_SIZE samples just before `time`
+        let len = clip.samples.len() as i64;
+        let end = (time.rem_euclid(clip.duration()) * clip.sample_rate as f64) as i64;
+        let sample = |offset: usize| clip.samples[(end - FFT_SIZE as i64 + offset as i64).rem_euclid(len) as usize];
+
+        let mut buffer: Vec<Complex<f32>> = (0..FFT_SIZE).map(|i| Complex::new(sample(i) * self.window[i], 0.0)).collect();
+        self.fft.process(&mut buffer);
+
+        let bin_width = clip.sample_rate as f32 / FFT_SIZE as f32;
+        let mut spectrum = vec![0.0; TEXTURE_WIDTH];
+        let mut bass_energy = 0.0;
+        for (bin, value) in spectrum.iter_mut().enumerate() {
+            let magnitude = buffer[bin].norm() / FFT_SIZE as f32;
+            self.smoothed[bin] = SMOOTHING * self.smoothed[bin] + (1.0 - SMOOTHING) * magnitude;
+            let db = 20.0 * self.smoothed[bin].max(1e-10).log10();
+            *value = ((db - MIN_DB) / (MAX_DB - MIN_DB)).clamp(0.0, 1.0);
+
+            if in_band(bin as f32 * bin_width, BASS) {
+                bass_energy += magnitude * magnitude;
+            }
+        }
+        let band = |range| band_level(&spectrum, bin_width, range);
+        let (bass, mid, treble) = (band(BASS), band(MID), band(TREBLE));
+
+        let waveform = (FFT_SIZE - TEXTURE_WIDTH..FFT_SIZE).map(sample).collect();
+
+        self.beat *= (-BEAT_DECAY * dt).exp() as f32;
+        if self.is_beat(time, bass_energy) {
+            self.beat = 1.0;
+            self.last_beat = Some(time);
+        }
+        self.history.push_back((time, bass_energy));
+        while self.history.front().is_some_and(|(when, _)| time - when > BEAT_HISTORY) {
+            self.history.pop_front();
+        }
+
+        AudioFrame { spectrum, waveform, bass, mid, treble, beat: self.beat }
+    }
+
+    fn is_beat(&self, time: f64, energy: f32) -> bool {
+        // Wait for a little history to compare against
+        if self.history.len() < 10 || energy < BEAT_MIN_ENERGY {
+            return false;
+        }
+        if self.last_beat.is_some_and(|last| time - last < BEAT_GAP) {
+            return false;
+        }
+        let average = self.history.iter().map(|(_, energy)| energy).sum::<f32>() / self.history.len() as f32;
+        energy > BEAT_THRESHOLD * average
+    }
+}
+
+fn in_band(frequency: f32, (low, high): (f32, f32)) -> bool {
+    frequency >= low && frequency < high
+}
+
+// Average of the spectrum across a band, 0 if the sample rate is too low to reach it
+fn band_level(spectrum: &[f32], bin_width: f32, range: (f32, f32)) -> f32 {
+    let values: Vec<f32> = spectrum
+        .iter()
+        .enumerate()
+        .filter(|(bin, _)| in_band(*bin as f32 * bin_width, range))
+        .map(|(_, value)| *value)
+        .collect();
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
+
+// The texture and uniforms on the GL side
+#[derive(Default)]
+pub struct AudioUniforms {
+    texture: Option<WebGlTexture>,
+    texture_location: Option<WebGlUniformLocation>,
+    bass_location: Option<WebGlUniformLocation>,
+    mid_location: Option<WebGlUniformLocation>,
+    treble_location: Option<WebGlUniformLocation>,
+    beat_location: Option<WebGlUniformLocation>,
+}
+
+impl AudioUniforms {
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.texture_location = gl.get_uniform_location(program, "u_audio");
+        self.bass_location = gl.get_uniform_location(program, "u_bass");
+        self.mid_location = gl.get_uniform_location(program, "u_mid");
+        self.treble_location = gl.get_uniform_location(program, "u_treble");
+        self.beat_location = gl.get_uniform_location(program, "u_beat");
+    }
+
+    // Leaves TEXTURE0 active again afterwards
+    pub fn upload(&mut self, gl: &GL, frame: &AudioFrame, unit: u32) {
+        if self.texture.is_none() {
+            self.texture = gl.create_texture();
+        }
+
+        gl.active_texture(GL::TEXTURE0 + unit);
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+        let _ = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            GL::TEXTURE_2D,
+            0,
+            GL::LUMINANCE as i32,
+            TEXTURE_WIDTH as i32,
+            ROWS as i32,
+            0,
+            GL::LUMINANCE,
+            GL::UNSIGNED_BYTE,
+            Some(&frame.texture_data()),
+        );
+        // Linear across the spectrum, but only two rows so clamp rather than wrap
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+        gl.active_texture(GL::TEXTURE0);
+
+        gl.uniform1i(self.texture_location.as_ref(), unit as i32);
+        gl.uniform1f(self.bass_location.as_ref(), frame.bass);
+        gl.uniform1f(self.mid_location.as_ref(), frame.mid);
+        gl.uniform1f(self.treble_location.as_ref(), frame.treble);
+        gl.uniform1f(self.beat_location.as_ref(), frame.beat);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINE: &[u8] = include_bytes!("../tests/fixtures/sine_440.wav");
+    const KICK: &[u8] = include_bytes!("../tests/fixtures/kick_120bpm.wav");
+    const STEREO: &[u8] = include_bytes!("../tests/fixtures/stereo_float.wav");
+    const SILENCE: &[u8] = include_bytes!("../tests/fixtures/silence.ogg");
+
+    const FRAME: f64 = 1.0 / 60.0;
+
+    // Play `clip` from the start at 60 frames a second
+    fn play(clip: &AudioClip, seconds: f64) -> Vec<(f64, AudioFrame)> {
+        let mut analyzer = AudioAnalyzer::new();
+        (0..(seconds / FRAME) as usize)
+            .map(|frame| {
+                let time = frame as f64 * FRAME;
+                (time, analyzer.analyze(clip, time))
+            })
+            .collect()
+    }
+
+    fn peak_bin(frame: &AudioFrame) -> usize {
+        (0..TEXTURE_WIDTH).max_by(|a, b| frame.spectrum[*a].total_cmp(&frame.spectrum[*b])).unwrap()
+    }
+
+    #[test]
+    fn decodes_16_bit_wav() {
+        let clip = AudioClip::decode(SINE).unwrap();
+        assert_eq!(clip.sample_rate, 22050);
+        assert_eq!(clip.samples.len(), 11025);
+        assert!((clip.duration() - 0.5).abs() < 1e-9);
+        let peak = clip.samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.5).abs() < 0.01, "{}", peak);
+    }
+
+    #[test]
+    fn decodes_8_bit_wav() {
+        let clip = AudioClip::decode(KICK).unwrap();
+        assert_eq!(clip.sample_rate, 11025);
+        assert_eq!(clip.samples.len(), 22050);
+        assert!(clip.samples.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+    }
+
+    #[test]
+    fn mixes_stereo_float_wav_down() {
+        let clip = AudioClip::decode(STEREO).unwrap();
+        assert_eq!(clip.sample_rate, 8000);
+        assert_eq!(clip.samples.len(), 2000);
+        assert!(clip.samples.iter().all(|sample| *sample == 0.125));
+    }
+
+    #[test]
+    fn decodes_ogg_vorbis() {
+        let clip = AudioClip::decode(SILENCE).unwrap();
+        assert_eq!(clip.sample_rate, 22050);
+        assert_eq!(clip.samples.len(), 43 * 128);
+        assert!(clip.samples.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(AudioClip::decode(b"ID3 not really an mp3").is_err());
+        assert!(AudioClip::decode(b"RIFF but nothing after").is_err());
+        assert!(AudioClip::decode(&SILENCE[..40]).is_err());
+    }
+
+    #[test]
+    fn sine_peaks_at_its_frequency() {
+        let clip = AudioClip::decode(SINE).unwrap();
+        let (_, frame) = play(&clip, 0.25).pop().unwrap();
+
+        // 440Hz in bins of 22050 / 1024 Hz
+        let bin = peak_bin(&frame);
+        assert!((20..=21).contains(&bin), "{}", bin);
+        assert!(frame.spectrum[bin] > 0.9);
+        assert!(frame.spectrum[200] < 0.2);
+
+        assert!(frame.mid > frame.bass, "{:?}", (frame.bass, frame.mid));
+        assert!(frame.mid > frame.treble, "{:?}", (frame.mid, frame.treble));
+        assert_eq!(frame.beat, 0.0);
+    }
+
+    #[test]
+    fn waveform_follows_the_samples() {
+        let clip = AudioClip::decode(SINE).unwrap();
+        let frame = AudioAnalyzer::new().analyze(&clip, 0.25);
+        assert_eq!(frame.waveform.len(), TEXTURE_WIDTH);
+        let end = (0.25 * 22050.0) as usize;
+        assert_eq!(frame.waveform, clip.samples[end - TEXTURE_WIDTH..end]);
+    }
+
+    #[test]
+    fn kicks_are_beats() {
+        let clip = AudioClip::decode(KICK).unwrap();
+        let frames = play(&clip, 2.0);
+
+        let beats: Vec<f64> = frames.iter().filter(|(_, frame)| frame.beat == 1.0).map(|(time, _)| *time).collect();
+        // The first kick has no history to stand out against
+        assert_eq!(beats.len(), 3, "{:?}", beats);
+        for (beat, kick) in beats.iter().zip([0.5, 1.0, 1.5]) {
+            assert!(*beat >= kick && *beat < kick + 0.1, "{:?}", beats);
+        }
+
+        // Dies away in between
+        let (_, before_kick) = frames.iter().find(|(time, _)| (*time - 0.95).abs() < FRAME / 2.0).unwrap();
+        assert!(before_kick.beat < 0.1, "{}", before_kick.beat);
+        let (_, kick) = frames.iter().find(|(time, _)| *time >= 1.05).unwrap();
+        assert!(kick.bass > kick.treble);
+    }
+
+    #[test]
+    fn silence_is_silent() {
+        let clip = AudioClip::decode(SILENCE).unwrap();
+        let (_, frame) = play(&clip, 0.2).pop().unwrap();
+        assert_eq!(frame, AudioFrame::silent());
+    }
+
+    #[test]
+    fn time_loops_round_the_clip() {
+        let clip = AudioClip::decode(SINE).unwrap();
+        let first = AudioAnalyzer::new().analyze(&clip, 0.1);
+        let looped = AudioAnalyzer::new().analyze(&clip, 0.1 + 3.0 * clip.duration());
+        assert_eq!(first.waveform, looped.waveform);
+        // Before the start wraps round to the end
+        let start = AudioAnalyzer::new().analyze(&clip, 0.0);
+        assert_eq!(start.waveform, clip.samples[clip.samples.len() - TEXTURE_WIDTH..]);
+    }
+
+    #[test]
+    fn seeking_starts_afresh() {
+        let clip = AudioClip::decode(KICK).unwrap();
+        let mut analyzer = AudioAnalyzer::new();
+        for frame in 0..90 {
+            analyzer.analyze(&clip, frame as f64 * FRAME);
+        }
+        let seeked = analyzer.analyze(&clip, 0.2);
+        assert_eq!(seeked, AudioAnalyzer::new().analyze(&clip, 0.2));
+    }
+
+    #[test]
+    fn texture_layout() {
+        let data = AudioFrame::silent().texture_data();
+        assert_eq!(data.len(), TEXTURE_WIDTH * ROWS);
+        assert!(data[..TEXTURE_WIDTH].iter().all(|texel| *texel == 0));
+        assert!(data[TEXTURE_WIDTH..].iter().all(|texel| *texel == 128));
+
+        let mut frame = AudioFrame::silent();
+        frame.spectrum[3] = 1.0;
+        frame.waveform[5] = -1.0;
+        frame.waveform[6] = 1.0;
+        let data = frame.texture_data();
+        assert_eq!(data[3], 255);
+        assert_eq!(data[TEXTURE_WIDTH + 5], 0);
+        assert_eq!(data[TEXTURE_WIDTH + 6], 255);
+    }
+}
@@ -0,0 +1,229 @@
+// Local dev server for hot reloading shaders.
+//
+//   cargo run --features watch --bin shader-watch -- ../05-neon-swirls/src
+//
+// Serves the preprocessed shaders found in the given directories over HTTP (port 8090 by
+// default, change with --port) and tells every connected browser the name of any file that
+// changes over a WebSocket on the next port up. Edits to the shared library in glsl/lib are
+// picked up too, and sent as "*" as any shader might include them.
+
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use tiny_http::{Header, Response, Server};
+use tungstenite::{Message, WebSocket};
+
+use shader_common::preprocess::preprocess;
+
+const DEFAULT_PORT: u16 = 8090;
+// Sent when a library file changes - matches shader_common::hot_reload::ALL_FILES
+const ALL_FILES: &str = "*";
+
+type Clients = Arc<Mutex<Vec<WebSocket<TcpStream>>>>;
+
+fn main() {
+    let mut port = DEFAULT_PORT;
+    let mut dirs = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--port" {
+            port = args
+                .next()
+                .and_then(|port| port.parse().ok())
+                .expect("--port needs a port number");
+        } else {
+            dirs.push(PathBuf::from(arg));
+        }
+    }
+    if dirs.is_empty() {
+        eprintln!("Usage: shader-watch [--port PORT] DIR...");
+        std::process::exit(1);
+    }
+
+    let lib_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("glsl");
+    let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+
+    {
+        let clients = clients.clone();
+        let listener = TcpListener::bind(("127.0.0.1", port + 1)).expect("Unable to open WebSocket port");
+        thread::spawn(move || accept_clients(listener, clients));
+    }
+
+    {
+        let clients = clients.clone();
+        let dirs = dirs.clone();
+        let lib_dir = lib_dir.clone();
+        thread::spawn(move || watch(dirs, lib_dir, clients));
+    }
+
+    println!("Serving shaders on http://localhost:{}", port);
+    println!("Change notifications on ws://localhost:{}", port + 1);
+    serve(port, &dirs, &lib_dir);
+}
+
+fn accept_clients(listener: TcpListener, clients: Clients) {
+    for stream in listener.incoming().flatten() {
+        match tungstenite::accept(stream) {
+            Ok(socket) => clients.lock().unwrap().push(socket),
+            Err(err) => eprintln!("WebSocket handshake failed: {}", err),
+        }
+    }
+}
+
+fn watch(dirs: Vec<PathBuf>, lib_dir: PathBuf, clients: Clients) {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).expect("Unable to watch for file changes");
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .unwrap_or_else(|err| panic!("Unable to watch {}: {}", dir.display(), err));
+    }
+    watcher.watch(&lib_dir, RecursiveMode::Recursive).expect("Unable to watch the shader library");
+
+    for event in rx {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                eprintln!("Watch error: {}", err);
+                continue;
+            }
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            continue;
+        }
+
+        for path in event.paths {
+            let name = if path.starts_with(&lib_dir) {
+                ALL_FILES.to_string()
+            } else {
+                match path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) if is_shader(name) => name.to_string(),
+                    _ => continue,
+                }
+            };
+
+            println!("Changed: {}", name);
+            // Drop anyone who has gone away
+            clients.lock().unwrap().retain_mut(|socket| socket.send(Message::text(name.clone())).is_ok());
+        }
+    }
+}
+
+fn serve(port: u16, dirs: &[PathBuf], lib_dir: &Path) {
+    let server = Server::http(("127.0.0.1", port)).expect("Unable to open HTTP port");
+    // The page is served by Trunk on a different port
+    let cors = Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap();
+
+    for request in server.incoming_requests() {
+        let name = request.url().trim_start_matches('/').to_string();
+        let response = match load(&name, dirs, lib_dir) {
+            Ok(source) => Response::from_string(source).with_status_code(200),
+            Err(err) => {
+                eprintln!("{}", err);
+                Response::from_string(err).with_status_code(404)
+            }
+        };
+        let _ = request.respond(response.with_header(cors.clone()));
+    }
+}
+
+// Find `name` in one of the watched directories and expand its includes
+fn load(name: &str, dirs: &[PathBuf], lib_dir: &Path) -> Result<String, String> {
+    if !is_shader(name) || name.contains("..") || name.contains('/') {
+        return Err(format!("Not a shader: {}", name));
+    }
+
+    let dir = dirs
+        .iter()
+        .find(|dir| dir.join(name).is_file())
+        .ok_or_else(|| format!("Not found: {}", name))?;
+    let source = fs::read_to_string(dir.join(name)).map_err(|err| err.to_string())?;
+
+    // Read the library from disk rather than the copy built into shader-common, so edits to
+    // it are picked up without restarting.
+    let processed = preprocess(name, &source, |include| {
+        fs::read_to_string(lib_dir.join(include))
+            .or_else(|_| fs::read_to_string(dir.join(include)))
+            .ok()
+    })
+    .map_err(|err| err.to_string())?;
+
+    Ok(processed.source)
+}
+
+fn is_shader(name: &str) -> bool {
+    name.ends_with(".vert") || name.ends_with(".frag")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed again when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir().join(format!("shader-watch-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn write(&self, name: &str, contents: &str) {
+            let path = self.0.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn only_serves_shaders_from_the_watched_directories() {
+        let root = TempDir::new("names");
+        root.write("src/main.frag", "void main() {}\n");
+        root.write("secret.frag", "void main() {}\n");
+        root.write("src/notes.txt", "not a shader\n");
+        let dirs = [root.0.join("src")];
+
+        assert!(load("main.frag", &dirs, &root.0).is_ok());
+        for name in ["../secret.frag", "..frag", "src/main.frag", "/main.frag", "notes.txt", "main.glsl", "main.frag.bak", ""] {
+            assert_eq!(load(name, &dirs, &root.0), Err(format!("Not a shader: {}", name)));
+        }
+        assert_eq!(load("missing.frag", &dirs, &root.0), Err("Not found: missing.frag".to_string()));
+    }
+
+    #[test]
+    fn expands_includes_from_the_library_and_the_shader_directory() {
+        let root = TempDir::new("includes");
+        root.write("lib/lib/shape.glsl", "float shape() { return 1.0; }\n");
+        root.write("first/other.frag", "void main() {}\n");
+        root.write("second/main.frag", "#include \"lib/shape.glsl\"\n#include \"local.glsl\"\nvoid main() {}\n");
+        root.write("second/local.glsl", "float local() { return 2.0; }\n");
+        let dirs = [root.0.join("first"), root.0.join("second")];
+
+        let source = load("main.frag", &dirs, &root.0.join("lib")).unwrap();
+        assert!(source.contains("float shape() { return 1.0; }"));
+        assert!(source.contains("float local() { return 2.0; }"));
+        assert!(source.contains("void main() {}"));
+        assert!(!source.contains("#include"));
+
+        // Picked up from disk each time, not cached
+        root.write("second/local.glsl", "float local() { return 3.0; }\n");
+        assert!(load("main.frag", &dirs, &root.0.join("lib")).unwrap().contains("return 3.0;"));
+
+        root.write("second/broken.frag", "#include \"lib/missing.glsl\"\n");
+        assert!(load("broken.frag", &dirs, &root.0.join("lib")).is_err());
+    }
+}
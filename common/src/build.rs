@@ -0,0 +1,139 @@
+// Helpers for the tutorials' build.rs scripts.
+//
+// Every .vert and .frag file has its includes expanded and is written to OUT_DIR, ready to be
+// baked into the binary with include_str!. On the way through it gets parsed and type-checked,
+// so a typo fails `cargo build` (and `trunk serve`) with a file:line pointing at the problem
+// rather than only showing up in the browser console. Shaders with build-up steps (see
+// steps.rs) are checked as they'd be at each step as well.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::library;
+use crate::preprocess::preprocess;
+use crate::steps::ShaderSteps;
+use crate::validate::{validate, Stage};
+
+pub struct ShaderBuild {
+    dir: PathBuf,
+    attributes: Vec<(String, u32)>,
+    out_subdir: Option<PathBuf>,
+}
+
+impl ShaderBuild {
+    // Build every .vert and .frag file found in `dir` (relative to the crate root)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ShaderBuild {
+            dir: dir.into(),
+            attributes: Vec::new(),
+            out_subdir: None,
+        }
+    }
+
+    // Declare a vertex attribute the Rust side fills in, and how many floats per vertex it gets
+    pub fn attribute(mut self, name: &str, components: u32) -> Self {
+        self.attributes.push((name.to_string(), components));
+        self
+    }
+
+    // Write the shaders to a directory of their own inside OUT_DIR, so several crates' shaders
+    // can be built into one without their file names clashing (see the gallery)
+    pub fn out_subdir(mut self, name: impl Into<PathBuf>) -> Self {
+        self.out_subdir = Some(name.into());
+        self
+    }
+
+    pub fn run(self) {
+        println!("cargo:rerun-if-changed={}", self.dir.display());
+
+        let mut out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is only set when run from build.rs"));
+        if let Some(subdir) = &self.out_subdir {
+            out_dir = out_dir.join(subdir);
+            fs::create_dir_all(&out_dir).unwrap_or_else(|err| panic!("Failed to create {}: {}", out_dir.display(), err));
+        }
+        let attributes: Vec<(&str, u32)> =
+            self.attributes.iter().map(|(name, count)| (name.as_str(), *count)).collect();
+
+        let mut failed = false;
+        for path in shader_files(&self.dir) {
+            println!("cargo:rerun-if-changed={}", path.display());
+
+            let stage = match Stage::from_path(&path) {
+                Some(stage) => stage,
+                None => continue,
+            };
+            let source = read(&path);
+            let name = path.display().to_string();
+
+            // Library files come from shader-common, anything else is relative to the shader
+            let processed = preprocess(&name, &source, |include| match library::resolve(include) {
+                Some(lib) => Some(lib.to_string()),
+                None => {
+                    let local = self.dir.join(include);
+                    println!("cargo:rerun-if-changed={}", local.display());
+                    fs::read_to_string(local).ok()
+                }
+            });
+            let processed = match processed {
+                Ok(processed) => processed,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    failed = true;
+                    continue;
+                }
+            };
+
+            // Shaders marked with build-up steps are checked at every step too, the last one is
+            // the whole shader so doesn't need doing twice
+            let mut variants = vec![(String::new(), processed.source.clone())];
+            // Parsed before the includes go in too, so a bad marker's line number is the file's
+            match ShaderSteps::parse(&source).and_then(|_| ShaderSteps::parse(&processed.source)) {
+                Ok(steps) => {
+                    let earlier = steps.steps().len().saturating_sub(1);
+                    for step in &steps.steps()[..earlier] {
+                        variants.push((format!(" (at step {})", step.number), steps.up_to(step.number)));
+                    }
+                },
+                Err(err) => {
+                    eprintln!("{}: {}", name, err);
+                    failed = true;
+                }
+            }
+
+            for (at_step, source) in &variants {
+                for diag in validate(source, stage, &attributes) {
+                    match processed.source_map.locate(diag.line) {
+                        Some((file, line)) => eprintln!("{}:{}:{}: {}{}", file, line, diag.column, diag.message, at_step),
+                        None => eprintln!("{}:{}{}", name, diag, at_step),
+                    }
+                    failed = true;
+                }
+            }
+
+            let file_name = path.file_name().expect("shader files always have a name");
+            fs::write(out_dir.join(file_name), processed.source)
+                .unwrap_or_else(|err| panic!("Failed to write {:?}: {}", file_name, err));
+        }
+
+        if failed {
+            eprintln!("Shader validation failed");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err))
+}
+
+fn shader_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = fs::read_dir(dir).unwrap_or_else(|err| panic!("Failed to read {}: {}", dir.display(), err));
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| Stage::from_path(path).is_some())
+        .collect();
+    files.sort();
+    files
+}
@@ -0,0 +1,360 @@
+// A 2D camera for exploring a pattern: where it's looking, how far in and which way up.
+//
+// The camera works on the centred screen position most of the shaders start from,
+//
+//   vec2 p = (gl_FragCoord.xy - 0.5 * canvasSize) / canvasSize.y;
+//
+// and sends a matrix taking that to the point in the pattern to draw, as `uniform mat3 u_camera`
+// (`#include "lib/camera.glsl"` has a helper). Dragging, pinching and rotating move it directly;
+// a drag that's let go while moving carries on and slows down, and the mouse wheel zooms
+// smoothly towards the cursor.
+
+use std::f64::consts::TAU;
+
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+
+pub const MIN_ZOOM: f64 = 0.25;
+// Much further than this and f32 in the shader runs out of precision, see max_zoom
+pub const MAX_ZOOM: f64 = 100_000.0;
+
+// How quickly movement dies away after letting go, per second
+const FRICTION: f64 = 4.0;
+// Log zoom per pixel of mouse wheel, about 20% for a notch of the wheel (100 pixels)
+const WHEEL_ZOOM: f64 = 0.002;
+// Below this the camera counts as stopped
+const MIN_SPEED: f64 = 1e-4;
+
+#[derive(Clone, Debug)]
+pub struct Camera2d {
+    // Point in the pattern at the middle of the screen
+    pub center: (f64, f64),
+    pub zoom: f64,
+    // Radians anti-clockwise the camera is turned, so the pattern appears turned clockwise
+    pub rotation: f64,
+    max_zoom: f64,
+    // Canvas size in pixels
+    viewport: (f64, f64),
+    dragging: bool,
+    // Pattern units per second
+    velocity: (f64, f64),
+    // Log zoom per second, and the screen position it zooms around
+    zoom_velocity: f64,
+    zoom_anchor: (f64, f64),
+    matrix_location: Option<WebGlUniformLocation>,
+}
+
+impl Default for Camera2d {
+    fn default() -> Self {
+        Camera2d::new()
+    }
+}
+
+impl Camera2d {
+    pub fn new() -> Self {
+        Camera2d {
+            center: (0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            max_zoom: MAX_ZOOM,
+            viewport: (1.0, 1.0),
+            dragging: false,
+            velocity: (0.0, 0.0),
+            zoom_velocity: 0.0,
+            zoom_anchor: (0.0, 0.0),
+            matrix_location: None,
+        }
+    }
+
+    // For shaders that keep more precision than an f32, like the deep zoom Mandelbrot
+    pub fn max_zoom(mut self, zoom: f64) -> Self {
+        self.max_zoom = zoom.max(MIN_ZOOM);
+        self
+    }
+
+    pub fn reset(&mut self) {
+        let viewport = self.viewport;
+        let matrix_location = self.matrix_location.take();
+        *self = Camera2d {
+            max_zoom: self.max_zoom,
+            viewport,
+            matrix_location,
+            ..Camera2d::new()
+        };
+    }
+
+    pub fn set_viewport(&mut self, width: f64, height: f64) {
+        self.viewport = (width.max(1.0), height.max(1.0));
+    }
+
+    pub fn viewport(&self) -> (f64, f64) {
+        self.viewport
+    }
+
+    // Canvas pixels (y up) to the centred screen position the shader starts from
+    pub fn screen_point(&self, pixel: (f64, f64)) -> (f64, f64) {
+        let (width, height) = self.viewport;
+        ((pixel.0 - 0.5 * width) / height, (pixel.1 - 0.5 * height) / height)
+    }
+
+    // Which point in the pattern is at `pixel`
+    pub fn to_world(&self, pixel: (f64, f64)) -> (f64, f64) {
+        self.screen_to_world(self.screen_point(pixel))
+    }
+
+    fn screen_to_world(&self, screen: (f64, f64)) -> (f64, f64) {
+        let (x, y) = rotate(screen, self.rotation);
+        (x / self.zoom + self.center.0, y / self.zoom + self.center.1)
+    }
+
+    // Move the camera so `world` ends up at `screen` again after a zoom or turn
+    fn pin(&mut self, world: (f64, f64), screen: (f64, f64)) {
+        let (x, y) = rotate(screen, self.rotation);
+        self.center = (world.0 - x / self.zoom, world.1 - y / self.zoom);
+    }
+
+    pub fn begin_drag(&mut self) {
+        self.dragging = true;
+        self.velocity = (0.0, 0.0);
+        self.zoom_velocity = 0.0;
+    }
+
+    // Move by a pointer movement in canvas pixels (y up), so the pattern follows the pointer.
+    // `dt` is the seconds since the last movement, for working out how fast to carry on after.
+    pub fn drag_by(&mut self, dx: f64, dy: f64, dt: f64) {
+        let height = self.viewport.1;
+        let (x, y) = rotate((dx / height, dy / height), self.rotation);
+        let moved = (-x / self.zoom, -y / self.zoom);
+        self.center.0 += moved.0;
+        self.center.1 += moved.1;
+
+        if dt > 0.0 {
+            // Smooth it out a little, pointer events don't arrive evenly
+            self.velocity.0 = 0.5 * self.velocity.0 + 0.5 * moved.0 / dt;
+            self.velocity.1 = 0.5 * self.velocity.1 + 0.5 * moved.1 / dt;
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.dragging = false;
+    }
+
+    // Zoom in by `factor` keeping the point under `pixel` where it is
+    pub fn zoom_at(&mut self, factor: f64, pixel: (f64, f64)) {
+        let screen = self.screen_point(pixel);
+        let world = self.screen_to_world(screen);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, self.max_zoom);
+        self.pin(world, screen);
+    }
+
+    // Turn the pattern `angle` radians anti-clockwise around `pixel`
+    pub fn rotate_at(&mut self, angle: f64, pixel: (f64, f64)) {
+        let screen = self.screen_point(pixel);
+        let world = self.screen_to_world(screen);
+        self.rotation = (self.rotation - angle).rem_euclid(TAU);
+        self.pin(world, screen);
+    }
+
+    // Mouse wheel movement in pixels, positive is away from the user (zoom out) as in WheelEvent
+    pub fn wheel(&mut self, delta: f64, pixel: (f64, f64)) {
+        self.zoom_velocity -= delta * WHEEL_ZOOM * FRICTION;
+        self.zoom_anchor = self.screen_point(pixel);
+    }
+
+    // Carry on any movement, call once a frame
+    pub fn update(&mut self, dt: f64) {
+        let decay = (-FRICTION * dt).exp();
+
+        if self.dragging {
+            // Holding still before letting go shouldn't fling it
+            self.velocity.0 *= decay * decay;
+            self.velocity.1 *= decay * decay;
+        } else if self.velocity.0.hypot(self.velocity.1) * self.zoom > MIN_SPEED {
+            self.center.0 += self.velocity.0 * dt;
+            self.center.1 += self.velocity.1 * dt;
+            self.velocity.0 *= decay;
+            self.velocity.1 *= decay;
+        } else {
+            self.velocity = (0.0, 0.0);
+        }
+
+        if self.zoom_velocity.abs() > MIN_SPEED {
+            let world = self.screen_to_world(self.zoom_anchor);
+            let zoom = (self.zoom * (self.zoom_velocity * dt).exp()).clamp(MIN_ZOOM, self.max_zoom);
+            if zoom == self.zoom {
+                self.zoom_velocity = 0.0;
+            }
+            self.zoom = zoom;
+            self.pin(world, self.zoom_anchor);
+            self.zoom_velocity *= decay;
+        } else {
+            self.zoom_velocity = 0.0;
+        }
+    }
+
+    // Column major, for uniformMatrix3fv: turn and scale the screen position then move it
+    pub fn matrix(&self) -> [f32; 9] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let scale = 1.0 / self.zoom;
+        [
+            (cos * scale) as f32, (sin * scale) as f32, 0.0,
+            (-sin * scale) as f32, (cos * scale) as f32, 0.0,
+            self.center.0 as f32, self.center.1 as f32, 1.0,
+        ]
+    }
+
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.matrix_location = gl.get_uniform_location(program, "u_camera");
+    }
+
+    pub fn upload(&self, gl: &GL) {
+        gl.uniform_matrix3fv_with_f32_array(self.matrix_location.as_ref(), false, &self.matrix());
+    }
+}
+
+// Anti-clockwise by `angle`
+fn rotate((x, y): (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f64 = 1.0 / 60.0;
+
+    fn camera() -> Camera2d {
+        let mut camera = Camera2d::new();
+        camera.set_viewport(800.0, 600.0);
+        camera.center = (0.3, -0.2);
+        camera.zoom = 2.0;
+        camera.rotation = 0.5;
+        camera
+    }
+
+    fn assert_near(a: (f64, f64), b: (f64, f64)) {
+        assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    // Frames until it stops, panics if it never does
+    fn settle(camera: &mut Camera2d) -> usize {
+        for frame in 0..1000 {
+            if camera.velocity == (0.0, 0.0) && camera.zoom_velocity == 0.0 {
+                return frame;
+            }
+            camera.update(FRAME);
+        }
+        panic!("still moving: {:?} {}", camera.velocity, camera.zoom_velocity);
+    }
+
+    #[test]
+    fn zoom_at_keeps_the_point_under_the_cursor() {
+        let mut camera = camera();
+        let pixel = (650.0, 120.0);
+        let under = camera.to_world(pixel);
+        camera.zoom_at(3.0, pixel);
+        assert!((camera.zoom - 6.0).abs() < 1e-12);
+        assert_near(camera.to_world(pixel), under);
+        camera.zoom_at(0.2, pixel);
+        assert_near(camera.to_world(pixel), under);
+    }
+
+    #[test]
+    fn rotate_at_keeps_the_point_under_the_cursor() {
+        let mut camera = camera();
+        let pixel = (100.0, 500.0);
+        let under = camera.to_world(pixel);
+        camera.rotate_at(1.2, pixel);
+        assert_near(camera.to_world(pixel), under);
+        // The camera turns the other way to the pattern
+        assert!((camera.rotation - (0.5 - 1.2f64).rem_euclid(TAU)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zoom_stays_within_the_limits() {
+        let mut camera = camera();
+        camera.zoom_at(1e-9, (400.0, 300.0));
+        assert_eq!(camera.zoom, MIN_ZOOM);
+        camera.zoom_at(1e12, (400.0, 300.0));
+        assert_eq!(camera.zoom, MAX_ZOOM);
+
+        let mut deep = Camera2d::new().max_zoom(1e9);
+        deep.zoom_at(1e12, (0.5, 0.5));
+        assert_eq!(deep.zoom, 1e9);
+        // A limit below the minimum is the minimum
+        assert_eq!(Camera2d::new().max_zoom(0.0).max_zoom, MIN_ZOOM);
+
+        // The wheel stops at the limit rather than pushing against it
+        camera.wheel(1e6, (400.0, 300.0));
+        settle(&mut camera);
+        assert_eq!(camera.zoom, MIN_ZOOM);
+    }
+
+    #[test]
+    fn wheel_zoom_glides_around_the_cursor() {
+        let mut camera = camera();
+        let pixel = (200.0, 450.0);
+        let under = camera.to_world(pixel);
+        // A notch towards the user zooms in
+        camera.wheel(-100.0, pixel);
+        camera.update(FRAME);
+        let first = camera.zoom;
+        assert!(first > 2.0);
+        camera.update(FRAME);
+        assert!(camera.zoom > first);
+        settle(&mut camera);
+        assert!((1.15..1.25).contains(&(camera.zoom / 2.0)), "{}", camera.zoom);
+        assert_near(camera.to_world(pixel), under);
+    }
+
+    #[test]
+    fn a_flung_drag_slows_to_a_stop() {
+        let mut camera = camera();
+        camera.rotation = 0.0;
+        camera.begin_drag();
+        for _ in 0..10 {
+            camera.drag_by(-12.0, 0.0, FRAME);
+        }
+        camera.end_drag();
+
+        // Dragging left moves the camera right, and it carries on that way
+        let let_go = camera.center;
+        camera.update(FRAME);
+        assert!(camera.center.0 > let_go.0);
+        let frames = settle(&mut camera);
+        assert!(frames > 10, "stopped after {} frames", frames);
+        let stopped = camera.center;
+        camera.update(FRAME);
+        assert_eq!(camera.center, stopped);
+        assert_eq!(camera.center.1, let_go.1);
+    }
+
+    #[test]
+    fn holding_still_before_letting_go_doesnt_fling() {
+        // How far it carries on after letting go, having held still for `frames` first
+        let coast = |frames: usize| {
+            let mut camera = camera();
+            camera.begin_drag();
+            camera.drag_by(-30.0, 10.0, FRAME);
+            for _ in 0..frames {
+                camera.update(FRAME);
+            }
+            camera.end_drag();
+            let let_go = camera.center;
+            settle(&mut camera);
+            (camera.center.0 - let_go.0).hypot(camera.center.1 - let_go.1)
+        };
+        // Half a second is plenty to stop it
+        assert!(coast(30) < coast(0) / 20.0, "{} {}", coast(30), coast(0));
+    }
+
+    #[test]
+    fn reset_keeps_the_viewport_and_limit() {
+        let mut camera = camera().max_zoom(50.0);
+        camera.wheel(-100.0, (0.0, 0.0));
+        camera.reset();
+        assert_eq!((camera.center, camera.zoom, camera.rotation), ((0.0, 0.0), 1.0, 0.0));
+        assert_eq!(camera.zoom_velocity, 0.0);
+        assert_eq!((camera.viewport(), camera.max_zoom), ((800.0, 600.0), 50.0));
+    }
+}
@@ -0,0 +1,153 @@
+// Mouse, touch and wheel input driving a Camera2d, so a tutorial only has to pass events on.
+//
+// One pointer (the mouse, or a single finger) drags, two fingers pan, pinch and turn, and the
+// wheel zooms towards the cursor. The pointer state is kept too, so a tutorial can still use
+// clicks for something else.
+
+use crate::camera::Camera2d;
+use crate::gesture::{Gesture, GestureRecognizer, TouchPoint};
+use crate::pointer::{PointerAction, PointerState, PointerUpdate};
+
+pub struct CameraControls {
+    pub camera: Camera2d,
+    pub pointer: PointerState,
+    gestures: GestureRecognizer,
+    // When the pointer last moved, in milliseconds
+    last_move: f64,
+}
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        CameraControls {
+            camera: Camera2d::new(),
+            pointer: PointerState::default(),
+            // One finger is handled as a pointer drag
+            gestures: GestureRecognizer::new().pan_touches(2),
+            last_move: 0.0,
+        }
+    }
+}
+
+impl CameraControls {
+    // `now` in milliseconds, e.g. from instant::now()
+    pub fn pointer(&mut self, update: PointerUpdate, now: f64) {
+        let before = self.pointer.position();
+        let was_down = self.pointer.is_down();
+        self.pointer.update(update);
+
+        match update.action {
+            PointerAction::Down if !was_down => {
+                self.camera.begin_drag();
+                self.last_move = now;
+            },
+            // Leave the two finger gestures to the recognizer
+            PointerAction::Move if was_down && self.gestures.touch_count() < 2 => {
+                let (x, y) = self.pointer.position();
+                let dt = (now - self.last_move) / 1000.0;
+                self.camera.drag_by((x - before.0) as f64, (y - before.1) as f64, dt);
+                self.last_move = now;
+            },
+            PointerAction::Up | PointerAction::Leave if !self.pointer.is_down() => {
+                self.camera.end_drag();
+            },
+            _ => (),
+        }
+    }
+
+    // Every touch currently down, in canvas pixels y down as gesture::touch_points gives them
+    pub fn touches(&mut self, touches: &[TouchPoint]) {
+        // The camera wants y up
+        let height = self.camera.viewport().1;
+        for gesture in self.gestures.update(touches) {
+            match gesture {
+                Gesture::Pan { dx, dy } => self.camera.drag_by(dx, -dy, 0.0),
+                Gesture::Pinch { scale, center } => self.camera.zoom_at(scale, (center.0, height - center.1)),
+                // Clockwise on a y down screen is anti-clockwise going the other way
+                Gesture::Rotate { angle, center } => self.camera.rotate_at(-angle, (center.0, height - center.1)),
+                Gesture::End => (),
+            }
+        }
+    }
+
+    // `delta` in pixels, see pointer::wheel_pixels
+    pub fn wheel(&mut self, delta: f64, pixel: (f32, f32)) {
+        self.camera.wheel(delta, (pixel.0 as f64, pixel.1 as f64));
+    }
+
+    // Call once a frame before drawing, with the seconds since the last one
+    pub fn update(&mut self, dt: f64) {
+        self.camera.update(dt);
+    }
+
+    // Call after drawing
+    pub fn end_frame(&mut self) {
+        self.pointer.end_frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_MS: f64 = 1000.0 / 60.0;
+
+    fn pointer(controls: &mut CameraControls, action: PointerAction, position: (f32, f32), now: f64) {
+        let buttons = if matches!(action, PointerAction::Up) { 0 } else { 1 };
+        controls.pointer(PointerUpdate { action, position, buttons }, now);
+    }
+
+    fn controls() -> CameraControls {
+        let mut controls = CameraControls::default();
+        controls.camera.set_viewport(800.0, 600.0);
+        controls
+    }
+
+    #[test]
+    fn dragging_moves_and_flings() {
+        let mut controls = controls();
+        let start = controls.camera.to_world((400.0, 300.0));
+        pointer(&mut controls, PointerAction::Down, (400.0, 300.0), 0.0);
+        for step in 1..=10 {
+            pointer(&mut controls, PointerAction::Move, (400.0 + 10.0 * step as f32, 300.0), step as f64 * FRAME_MS);
+            controls.update(FRAME_MS / 1000.0);
+        }
+        // The point that was under the pointer is still under it
+        let under = controls.camera.to_world((500.0, 300.0));
+        assert!((under.0 - start.0).abs() < 1e-9 && (under.1 - start.1).abs() < 1e-9);
+
+        pointer(&mut controls, PointerAction::Up, (500.0, 300.0), 11.0 * FRAME_MS);
+        let let_go = controls.camera.center;
+        controls.update(FRAME_MS / 1000.0);
+        assert!(controls.camera.center.0 < let_go.0);
+
+        let mut last = controls.camera.center;
+        for _ in 0..600 {
+            controls.update(FRAME_MS / 1000.0);
+            last = controls.camera.center;
+        }
+        controls.update(FRAME_MS / 1000.0);
+        assert_eq!(controls.camera.center, last);
+    }
+
+    #[test]
+    fn moving_without_a_button_does_nothing() {
+        let mut controls = controls();
+        pointer(&mut controls, PointerAction::Move, (100.0, 100.0), 0.0);
+        pointer(&mut controls, PointerAction::Move, (300.0, 200.0), FRAME_MS);
+        controls.update(FRAME_MS / 1000.0);
+        assert_eq!(controls.camera.center, (0.0, 0.0));
+    }
+
+    #[test]
+    fn wheel_zooms_towards_the_cursor() {
+        let mut controls = controls();
+        let under = controls.camera.to_world((600.0, 150.0));
+        controls.wheel(-300.0, (600.0, 150.0));
+        for _ in 0..600 {
+            controls.update(FRAME_MS / 1000.0);
+        }
+        assert!(controls.camera.zoom > 1.5);
+        let still = controls.camera.to_world((600.0, 150.0));
+        assert!((still.0 - under.0).abs() < 1e-9 && (still.1 - under.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,191 @@
+// Side by side diffs of two versions of a file, e.g. one tutorial's shader against the next's.
+//
+// Removed and added lines that sit together are paired up on the same row, so a line that was
+// edited shows as its old and new versions next to each other. Runs of unchanged lines further
+// than `context` lines from a change are collapsed into a single Skipped row.
+
+use similar::{DiffTag, TextDiff};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RowKind {
+    Same,
+    Changed,
+    Removed,
+    Added,
+}
+
+impl RowKind {
+    pub fn class(&self) -> &'static str {
+        match self {
+            RowKind::Same => "diff_same",
+            RowKind::Changed => "diff_changed",
+            RowKind::Removed => "diff_removed",
+            RowKind::Added => "diff_added",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffRow {
+    // Line number (from 1) and text on each side, None where that side has no line
+    Lines {
+        kind: RowKind,
+        old: Option<(usize, String)>,
+        new: Option<(usize, String)>,
+    },
+    // This many unchanged lines left out
+    Skipped(usize),
+}
+
+pub fn side_by_side(old: &str, new: &str, context: usize) -> Vec<DiffRow> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let diff = TextDiff::from_slices(&old_lines, &new_lines);
+    let line = |lines: &[&str], index: usize| Some((index + 1, lines[index].to_string()));
+
+    let mut rows = Vec::new();
+    // How far through the old file the rows have got
+    let mut shown = 0;
+    for group in diff.grouped_ops(context) {
+        for op in group {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            if old_range.start > shown {
+                rows.push(DiffRow::Skipped(old_range.start - shown));
+            }
+            shown = old_range.end;
+
+            match tag {
+                DiffTag::Equal => {
+                    for (old_index, new_index) in old_range.zip(new_range) {
+                        rows.push(DiffRow::Lines { kind: RowKind::Same, old: line(&old_lines, old_index), new: line(&new_lines, new_index) });
+                    }
+                },
+                DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => {
+                    for offset in 0..old_range.len().max(new_range.len()) {
+                        let old = (offset < old_range.len()).then(|| old_range.start + offset).and_then(|index| line(&old_lines, index));
+                        let new = (offset < new_range.len()).then(|| new_range.start + offset).and_then(|index| line(&new_lines, index));
+                        let kind = match (&old, &new) {
+                            (Some(_), Some(_)) => RowKind::Changed,
+                            (Some(_), None) => RowKind::Removed,
+                            _ => RowKind::Added,
+                        };
+                        rows.push(DiffRow::Lines { kind, old, new });
+                    }
+                },
+            }
+        }
+    }
+    if old_lines.len() > shown {
+        rows.push(DiffRow::Skipped(old_lines.len() - shown));
+    }
+    rows
+}
+
+// Lines taken out and put in, an edited line counting as one of each
+pub fn line_counts(rows: &[DiffRow]) -> (usize, usize) {
+    rows.iter().fold((0, 0), |(removed, added), row| match row {
+        DiffRow::Lines { kind: RowKind::Changed, .. } => (removed + 1, added + 1),
+        DiffRow::Lines { kind: RowKind::Removed, .. } => (removed + 1, added),
+        DiffRow::Lines { kind: RowKind::Added, .. } => (removed, added + 1),
+        _ => (removed, added),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(rows: &[DiffRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| match row {
+                DiffRow::Lines { kind, .. } => format!("{:?}", kind),
+                DiffRow::Skipped(count) => format!("Skipped({})", count),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_files_are_one_skip() {
+        let text = "a\nb\nc\n";
+        assert_eq!(side_by_side(text, text, 3), vec![DiffRow::Skipped(3)]);
+        assert_eq!(line_counts(&side_by_side(text, text, 3)), (0, 0));
+        assert!(side_by_side("", "", 3).is_empty());
+    }
+
+    #[test]
+    fn pairs_up_replaced_lines() {
+        let rows = side_by_side("a\nb\nc\nd\n", "a\nB\nC\nD\nE\nd\n", 1);
+        assert_eq!(kinds(&rows), vec!["Same", "Changed", "Changed", "Added", "Added", "Same"]);
+        assert_eq!(
+            rows[1],
+            DiffRow::Lines { kind: RowKind::Changed, old: Some((2, "b".to_string())), new: Some((2, "B".to_string())) }
+        );
+        assert_eq!(rows[3], DiffRow::Lines { kind: RowKind::Added, old: None, new: Some((4, "D".to_string())) });
+        assert_eq!(line_counts(&rows), (2, 4));
+
+        let rows = side_by_side("a\nb\nc\nd\n", "a\nB\nd\n", 1);
+        assert_eq!(kinds(&rows), vec!["Same", "Changed", "Removed", "Same"]);
+        assert_eq!(rows[2], DiffRow::Lines { kind: RowKind::Removed, old: Some((3, "c".to_string())), new: None });
+        assert_eq!(line_counts(&rows), (2, 1));
+    }
+
+    #[test]
+    fn skips_between_and_after_changes() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new = old.replace("\n5\n", "\nfive\n").replace("\n15\n", "\nfifteen\n");
+        let rows = side_by_side(&old, &new, 1);
+        assert_eq!(
+            kinds(&rows),
+            vec!["Skipped(3)", "Same", "Changed", "Same", "Skipped(7)", "Same", "Changed", "Same", "Skipped(4)"]
+        );
+        assert_eq!(rows[1], DiffRow::Lines { kind: RowKind::Same, old: Some((4, "4".to_string())), new: Some((4, "4".to_string())) });
+    }
+
+    // The top of 04's fragment shader going to 04.1's, where the #ifndef defaults became
+    // annotated uniforms
+    const TUNNEL: &str = "// Taken from here: https://www.shadertoy.com/view/4djBRm
+precision mediump float;
+
+// Defaults for when the Rust side doesn't supply its own values
+#ifndef TUNNEL_SIZE
+#define TUNNEL_SIZE 0.25
+#endif
+#ifndef TUNNEL_SPEED
+#define TUNNEL_SPEED 0.5
+#endif
+
+#include \"lib/tunnel.glsl\"
+
+uniform float u_time;
+";
+    const LIGHT_RAYS: &str = "// Taken from here: https://www.shadertoy.com/view/4djBRm
+precision mediump float;
+
+uniform float TUNNEL_SIZE; // @range 0.05..1 @default 0.25 @label Tunnel size
+uniform float TUNNEL_SPEED; // @range -0.1..0.1 @default 0.025 @step 0.005 @label Tunnel speed
+
+#include \"lib/tunnel.glsl\"
+
+uniform float u_time;
+uniform vec2 canvasSize;
+";
+
+    #[test]
+    fn tunnel_to_light_rays() {
+        let rows = side_by_side(TUNNEL, LIGHT_RAYS, 0);
+        assert_eq!(
+            kinds(&rows),
+            vec!["Skipped(3)", "Changed", "Changed", "Removed", "Removed", "Removed", "Removed", "Removed", "Skipped(4)", "Added"]
+        );
+        assert_eq!(line_counts(&rows), (7, 3));
+        assert_eq!(
+            rows[1],
+            DiffRow::Lines {
+                kind: RowKind::Changed,
+                old: Some((4, "// Defaults for when the Rust side doesn't supply its own values".to_string())),
+                new: Some((4, LIGHT_RAYS.lines().nth(3).unwrap().to_string())),
+            }
+        );
+        assert_eq!(rows[9], DiffRow::Lines { kind: RowKind::Added, old: None, new: Some((10, "uniform vec2 canvasSize;".to_string())) });
+    }
+}
@@ -0,0 +1,76 @@
+// Two versions of a file next to each other, highlighted, with what changed between them marked
+// (see diff.rs).
+
+use yew::prelude::*;
+
+use crate::diff::{side_by_side, DiffRow};
+use crate::highlight::{highlight, Language};
+
+// Unchanged lines kept either side of a change
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct DiffViewProps {
+    pub old_name: AttrValue,
+    pub old: AttrValue,
+    pub new_name: AttrValue,
+    pub new: AttrValue,
+}
+
+#[function_component(DiffView)]
+pub fn diff_view(props: &DiffViewProps) -> Html {
+    let rows = use_memo((props.old.clone(), props.new.clone()), |(old, new)| side_by_side(old, new, CONTEXT_LINES));
+    let language = Language::from_name(&props.new_name).or_else(|| Language::from_name(&props.old_name));
+
+    let code = |line: &Option<(usize, String)>| match line {
+        Some((number, text)) => {
+            let tokens = match language {
+                Some(language) => highlight(text, language)
+                    .into_iter()
+                    .map(|(kind, text)| html! { <span class={kind.class()}>{ text }</span> })
+                    .collect::<Html>(),
+                None => html! { text },
+            };
+            html! {
+                <>
+                    <td class="diff_number">{ number }</td>
+                    <td class="diff_code">{ tokens }</td>
+                </>
+            }
+        },
+        None => html! { <><td class="diff_number"></td><td class="diff_code"></td></> },
+    };
+
+    let lines = rows.iter().map(|row| match row {
+        DiffRow::Lines { kind, old, new } => html! {
+            <tr class={kind.class()}>
+                { code(old) }
+                { code(new) }
+            </tr>
+        },
+        DiffRow::Skipped(count) => html! {
+            <tr class="diff_skipped">
+                <td colspan="4">{ format!("{} unchanged line{}", count, if *count == 1 { "" } else { "s" }) }</td>
+            </tr>
+        },
+    });
+    let unchanged = rows.iter().all(|row| matches!(row, DiffRow::Skipped(_)));
+
+    html! {
+        <table class="diff_view">
+            <thead>
+                <tr>
+                    <th colspan="2">{ &props.old_name }</th>
+                    <th colspan="2">{ &props.new_name }</th>
+                </tr>
+            </thead>
+            <tbody>
+                if unchanged {
+                    <tr class="diff_skipped"><td colspan="4">{ "No differences" }</td></tr>
+                } else {
+                    { for lines }
+                }
+            </tbody>
+        </table>
+    }
+}
@@ -0,0 +1,36 @@
+// Saving things from the page as files - the browser decides where they go.
+
+use js_sys::Array;
+use wasm_bindgen::JsCast;
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, HtmlCanvasElement, Url};
+
+// Download `contents` as a file of the given MIME type
+pub fn download_text(file_name: &str, contents: &str, mime_type: &str) -> Result<(), String> {
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&Array::of1(&contents.into()), &options).map_err(|err| format!("{:?}", err))?;
+    let url = Url::create_object_url_with_blob(&blob).map_err(|err| format!("{:?}", err))?;
+
+    download_url(file_name, &url)?;
+    Url::revoke_object_url(&url).map_err(|err| format!("{:?}", err))
+}
+
+// Save whatever is on the canvas as a PNG. Needs calling straight after drawing, before the
+// browser gets the chance to clear the drawing buffer.
+pub fn save_canvas(canvas: &HtmlCanvasElement, file_name: &str) -> Result<(), String> {
+    let url = canvas.to_data_url().map_err(|err| format!("Unable to read the canvas: {:?}", err))?;
+    download_url(file_name, &url)
+}
+
+// Point a link at `url` and click it
+pub fn download_url(file_name: &str, url: &str) -> Result<(), String> {
+    let document = web_sys::window().and_then(|window| window.document()).ok_or("No document to download from")?;
+    let anchor: HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|err| format!("{:?}", err))?
+        .unchecked_into();
+    anchor.set_href(url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Ok(())
+}
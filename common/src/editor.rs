@@ -0,0 +1,255 @@
+// An editable view of a shader's source, for live coding during teaching sessions.
+//
+// The editor only deals with text: edits are sent to the parent (debounced, so we aren't
+// recompiling on every key press) and the parent hands back the compile log, which is shown
+// against the offending lines. It also keeps the shipped source so it can revert to it, or show
+// a diff of what's been changed.
+
+use gloo_timers::callback::Timeout;
+use similar::{ChangeTag, TextDiff};
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+// How long to wait after the last key press before recompiling
+const DEBOUNCE_MS: u32 = 500;
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct ShaderEditorProps {
+    // File name, used to pick out the errors that belong to this file in the log
+    pub name: AttrValue,
+    // The source as shipped with the tutorial
+    pub original: AttrValue,
+    // Compile log for the last edit, None when it compiled
+    #[prop_or_default]
+    pub error: Option<AttrValue>,
+    pub on_change: Callback<String>,
+}
+
+pub enum ShaderEditorMsg {
+    Input(String),
+    Commit,
+    Revert,
+    ToggleDiff,
+}
+
+pub struct ShaderEditor {
+    source: String,
+    show_diff: bool,
+    pending: Option<Timeout>,
+}
+
+// An error from the compile log, with the line in our file if there is one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Component for ShaderEditor {
+    type Message = ShaderEditorMsg;
+    type Properties = ShaderEditorProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ShaderEditor {
+            source: ctx.props().original.to_string(),
+            show_diff: false,
+            pending: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ShaderEditorMsg::Input(source) => {
+                self.source = source;
+
+                // Replacing the timeout cancels the previous one
+                let link = ctx.link().clone();
+                self.pending = Some(Timeout::new(DEBOUNCE_MS, move || link.send_message(ShaderEditorMsg::Commit)));
+                true
+            },
+            ShaderEditorMsg::Commit => {
+                self.pending = None;
+                ctx.props().on_change.emit(self.source.clone());
+                false
+            },
+            ShaderEditorMsg::Revert => {
+                self.pending = None;
+                self.source = ctx.props().original.to_string();
+                ctx.props().on_change.emit(self.source.clone());
+                true
+            },
+            ShaderEditorMsg::ToggleDiff => {
+                self.show_diff = !self.show_diff;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let errors = props
+            .error
+            .as_ref()
+            .map(|log| parse_log(log, &props.name))
+            .unwrap_or_default();
+
+        let oninput = ctx.link().callback(|evt: InputEvent| {
+            let textarea: HtmlTextAreaElement = evt.target_unchecked_into();
+            ShaderEditorMsg::Input(textarea.value())
+        });
+        let onrevert = ctx.link().callback(|_| ShaderEditorMsg::Revert);
+        let ontogglediff = ctx.link().callback(|_| ShaderEditorMsg::ToggleDiff);
+
+        let modified = self.source != props.original.as_str();
+        let status = if !errors.is_empty() {
+            "Error - still running the last version that compiled"
+        } else if modified {
+            "Modified"
+        } else {
+            "Original"
+        };
+
+        let body = if self.show_diff {
+            self.view_diff(&props.original)
+        } else {
+            let line_count = self.source.lines().count().max(1);
+            let gutter = (1..=line_count).map(|line| {
+                let message = errors
+                    .iter()
+                    .filter(|err| err.line == Some(line))
+                    .map(|err| err.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let class = if message.is_empty() { "editor_line" } else { "editor_line editor_error_line" };
+                html! { <div class={class} title={message}>{ line }</div> }
+            });
+
+            html! {
+                <div class="editor_code">
+                    <div class="editor_gutter">{ for gutter }</div>
+                    <textarea
+                        class="editor_text"
+                        spellcheck="false"
+                        wrap="off"
+                        rows={(line_count + 1).to_string()}
+                        value={self.source.clone()}
+                        oninput={oninput}
+                    ></textarea>
+                </div>
+            }
+        };
+
+        html! {
+            <div class="shader_editor">
+                <div class="editor_toolbar">
+                    <span class="editor_name">{ &props.name }</span>
+                    <span class="editor_status">{ status }</span>
+                    <button onclick={ontogglediff}>{ if self.show_diff { "Edit" } else { "Diff" } }</button>
+                    <button onclick={onrevert} disabled={!modified}>{ "Revert" }</button>
+                </div>
+                { body }
+                <div class="editor_errors">
+                    { for errors.iter().map(|err| html! {
+                        <div class="editor_error">
+                            { match err.line { Some(line) => format!("{}: {}", line, err.message), None => err.message.clone() } }
+                        </div>
+                    }) }
+                </div>
+            </div>
+        }
+    }
+}
+
+impl ShaderEditor {
+    fn view_diff(&self, original: &str) -> Html {
+        let diff = TextDiff::from_lines(original, self.source.as_str());
+        let lines = diff.iter_all_changes().map(|change| {
+            let (class, sign) = match change.tag() {
+                ChangeTag::Delete => ("diff_line diff_delete", "-"),
+                ChangeTag::Insert => ("diff_line diff_insert", "+"),
+                ChangeTag::Equal => ("diff_line", " "),
+            };
+            html! { <div class={class}>{ format!("{} {}", sign, change.value().trim_end_matches('\n')) }</div> }
+        });
+
+        html! { <div class="editor_diff">{ for lines }</div> }
+    }
+}
+
+// Pull the errors out of a compile log. The log has been through SourceMap::remap_log so
+// locations look like `ERROR: swirl.frag:12: message` - errors in `name` get a line number,
+// anything else (an included file, or a link error) is kept with the location in the message.
+pub fn parse_log(log: &str, name: &str) -> Vec<LogError> {
+    log.lines()
+        // Some drivers leave a NUL on the end of the log
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|line| !line.is_empty() && !line.ends_with("failed to compile:"))
+        .map(|line| {
+            let located = line.split_once(": ").and_then(|(_, rest)| {
+                let rest = rest.strip_prefix(name)?.strip_prefix(':')?;
+                let (line_number, message) = rest.split_once(':')?;
+                Some(LogError {
+                    line: Some(line_number.trim().parse().ok()?),
+                    message: message.trim().to_string(),
+                })
+            });
+            located.unwrap_or_else(|| LogError {
+                line: None,
+                message: line.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn located(line: usize, message: &str) -> LogError {
+        LogError { line: Some(line), message: message.to_string() }
+    }
+
+    fn unlocated(message: &str) -> LogError {
+        LogError { line: None, message: message.to_string() }
+    }
+
+    #[test]
+    fn reads_angle_logs() {
+        // Chrome, after compile_program's heading and remap_log
+        let log = "Fragment shader failed to compile:\nERROR: swirl.frag:12: 'x' : undeclared identifier\nERROR: 1 compilation errors.  No code generated.\n\n";
+        assert_eq!(
+            parse_log(log, "swirl.frag"),
+            vec![located(12, "'x' : undeclared identifier"), unlocated("ERROR: 1 compilation errors.  No code generated.")]
+        );
+    }
+
+    #[test]
+    fn reads_firefox_logs() {
+        // Firefox has no summary line, but can leave a NUL on the end
+        let log = "Fragment shader failed to compile:\nERROR: swirl.frag:7: 'vec3' : cannot convert from 'float' to 'vec3'\n\0";
+        assert_eq!(parse_log(log, "swirl.frag"), vec![located(7, "'vec3' : cannot convert from 'float' to 'vec3'")]);
+    }
+
+    #[test]
+    fn keeps_every_error_on_the_same_line() {
+        let log = "ERROR: swirl.frag:3: 'a' : undeclared identifier\nERROR: swirl.frag:3: 'b' : undeclared identifier\nWARNING: swirl.frag:3: 'c' : unused";
+        assert_eq!(
+            parse_log(log, "swirl.frag"),
+            vec![located(3, "'a' : undeclared identifier"), located(3, "'b' : undeclared identifier"), located(3, "'c' : unused")]
+        );
+    }
+
+    #[test]
+    fn keeps_what_isnt_in_this_file_as_it_is() {
+        let lines = [
+            // Another file, one that only starts with the name, one remap_log couldn't place
+            "ERROR: noise.glsl:2: 'q' : undeclared identifier",
+            "ERROR: swirl.frag.orig:4: 'x' : undeclared identifier",
+            "ERROR: 0:99: 'y' : off the end",
+            // No number where the line should be, and a link error
+            "ERROR: swirl.frag:main: 'z' : no line",
+            "ERROR: Missing main()",
+        ];
+        assert_eq!(parse_log(&lines.join("\n"), "swirl.frag"), lines.map(unlocated).to_vec());
+    }
+}
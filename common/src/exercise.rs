@@ -0,0 +1,260 @@
+// Shader exercises: the learner is given a shader to start from and edits it until it draws the
+// same animation as a reference shader they can't see. The exercises are listed in a manifest:
+//
+//   {
+//     "version": 1,
+//     "exercises": [
+//       {
+//         "id": "rings",
+//         "title": "Ripples",
+//         "description": "Turn the circle into rings that move outwards.",
+//         "start": "rings_start.frag",
+//         "target": "rings_target.frag",
+//         "texture": "noise",
+//         "checkpoints": [0, 1.5, 3],
+//         "pass_score": 95
+//       }
+//     ]
+//   }
+//
+// Shader files are relative to the manifest. "texture" (none or noise) is what the shaders'
+// sampler gets, "checkpoints" are the times in seconds the answer is checked at and "pass_score"
+// is how well it has to match at every one of them. All three can be left out.
+//
+// Frames are compared a pixel at a time in OKLab, where the distance between two colours follows
+// how different they look. A pixel matches when it's closer to the target than MATCH_DISTANCE,
+// and the score is the share of pixels that match, so rounding differences between GPUs don't
+// cost anything but a wrong colour anywhere does.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::color::{linear_to_oklab, srgb_to_linear};
+
+pub const SCHEMA_VERSION: u64 = 1;
+
+// Pixels are compared one by one, but mismatches are reported for squares this many pixels across
+pub const BLOCK_SIZE: usize = 8;
+
+// About the smallest OKLab distance that can be seen
+const MATCH_DISTANCE: f32 = 0.02;
+// A block this far from the target on average is as mismatched as it gets
+const FULL_MISMATCH: f32 = 0.2;
+
+const DEFAULT_CHECKPOINTS: [f32; 3] = [0.0, 2.0, 4.0];
+const DEFAULT_PASS_SCORE: f32 = 95.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExerciseTexture {
+    #[default]
+    None,
+    // The seeded noise the tutorials use
+    Noise,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Exercise {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub start: String,
+    pub target: String,
+    #[serde(default)]
+    pub texture: ExerciseTexture,
+    #[serde(default = "default_checkpoints")]
+    pub checkpoints: Vec<f32>,
+    #[serde(default = "default_pass_score")]
+    pub pass_score: f32,
+}
+
+fn default_checkpoints() -> Vec<f32> {
+    DEFAULT_CHECKPOINTS.to_vec()
+}
+
+fn default_pass_score() -> f32 {
+    DEFAULT_PASS_SCORE
+}
+
+impl Exercise {
+    // Passed when every checkpoint scored at least pass_score
+    pub fn passed(&self, scores: &[f32]) -> bool {
+        scores.len() == self.checkpoints.len() && scores.iter().all(|score| *score >= self.pass_score)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Manifest {
+    pub version: u64,
+    pub exercises: Vec<Exercise>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Manifest, String> {
+        let manifest: Manifest = serde_json::from_str(text).map_err(|err| format!("Not an exercise manifest: {}", err))?;
+        if manifest.version > SCHEMA_VERSION {
+            return Err(format!("Exercise manifest is version {}, this page only knows up to {}", manifest.version, SCHEMA_VERSION));
+        }
+        if manifest.exercises.is_empty() {
+            return Err("The manifest doesn't have any exercises".to_string());
+        }
+
+        let mut ids = HashSet::new();
+        for exercise in &manifest.exercises {
+            if exercise.id.is_empty() {
+                return Err(format!("Exercise {:?} needs an id", exercise.title));
+            }
+            if !ids.insert(exercise.id.as_str()) {
+                return Err(format!("Exercise id {:?} is used twice", exercise.id));
+            }
+            if exercise.checkpoints.is_empty() {
+                return Err(format!("Exercise {:?} needs at least one checkpoint", exercise.id));
+            }
+            if exercise.checkpoints.iter().any(|time| !time.is_finite() || *time < 0.0) {
+                return Err(format!("Exercise {:?} has a checkpoint before the start", exercise.id));
+            }
+            if !(0.0..=100.0).contains(&exercise.pass_score) {
+                return Err(format!("Exercise {:?} has a pass score of {}, it should be from 0 to 100", exercise.id, exercise.pass_score));
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn find(&self, id: &str) -> Option<&Exercise> {
+        self.exercises.iter().find(|exercise| exercise.id == id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameScore {
+    // From 0 to 100, the percentage of pixels that match
+    pub score: f32,
+    pub columns: usize,
+    pub rows: usize,
+    // Mean OKLab distance over each block, a row at a time
+    blocks: Vec<f32>,
+}
+
+impl FrameScore {
+    // (column, row, strength) for the blocks that don't match, strength from 0 to 1
+    pub fn mismatches(&self) -> impl Iterator<Item = (usize, usize, f32)> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, distance)| **distance > MATCH_DISTANCE)
+            .map(|(index, distance)| (index % self.columns, index / self.columns, (distance / FULL_MISMATCH).min(1.0)))
+    }
+}
+
+// Compare two RGBA frames of `width` by `height`, as read back from the canvas. Blocks are in the
+// same row order as the pixels, so bottom up for WebGL's read_pixels. Alpha is ignored.
+pub fn score_frame(frame: &[u8], target: &[u8], width: usize, height: usize) -> FrameScore {
+    let columns = width.div_ceil(BLOCK_SIZE);
+    let rows = height.div_ceil(BLOCK_SIZE);
+    let mut blocks = vec![0.0; columns * rows];
+    let mut matching = 0;
+
+    let oklab = |pixel: &[u8]| linear_to_oklab(srgb_to_linear([pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0)));
+    for y in 0..height {
+        for x in 0..width {
+            let offset = (y * width + x) * 4;
+            let (a, b) = (oklab(&frame[offset..offset + 4]), oklab(&target[offset..offset + 4]));
+            let distance = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+            if distance < MATCH_DISTANCE {
+                matching += 1;
+            }
+            blocks[(y / BLOCK_SIZE) * columns + x / BLOCK_SIZE] += distance;
+        }
+    }
+
+    // Blocks along the right and top edges can be smaller than the rest
+    for (index, total) in blocks.iter_mut().enumerate() {
+        let block_width = BLOCK_SIZE.min(width - (index % columns) * BLOCK_SIZE);
+        let block_height = BLOCK_SIZE.min(height - (index / columns) * BLOCK_SIZE);
+        *total /= (block_width * block_height) as f32;
+    }
+
+    let pixels = width * height;
+    let score = if pixels == 0 { 100.0 } else { 100.0 * matching as f32 / pixels as f32 };
+    FrameScore { score, columns, rows, blocks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: usize, height: usize, color: impl Fn(usize, usize) -> [u8; 3]) -> Vec<u8> {
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| {
+            let [r, g, b] = color(x, y);
+            [r, g, b, 255]
+        }).collect()
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let manifest = Manifest::parse(r#"{
+            "version": 1,
+            "exercises": [ { "id": "rings", "title": "Ripples", "start": "a.frag", "target": "b.frag" } ]
+        }"#).unwrap();
+        let exercise = manifest.find("rings").unwrap();
+        assert_eq!(exercise.texture, ExerciseTexture::None);
+        assert_eq!(exercise.checkpoints, DEFAULT_CHECKPOINTS);
+        assert_eq!(exercise.pass_score, DEFAULT_PASS_SCORE);
+        assert!(manifest.find("tunnel").is_none());
+    }
+
+    #[test]
+    fn rejects_bad_manifests() {
+        let exercise = |id: &str, extra: &str| format!(r#"{{ "id": "{}", "title": "T", "start": "a.frag", "target": "b.frag"{} }}"#, id, extra);
+        let manifest = |version: u64, exercises: &[String]| format!(r#"{{ "version": {}, "exercises": [{}] }}"#, version, exercises.join(","));
+
+        assert!(Manifest::parse(&manifest(2, &[exercise("a", "")])).unwrap_err().contains("version 2"));
+        assert!(Manifest::parse(&manifest(1, &[])).is_err());
+        assert!(Manifest::parse(&manifest(1, &[exercise("a", ""), exercise("a", "")])).unwrap_err().contains("used twice"));
+        assert!(Manifest::parse(&manifest(1, &[exercise("a", r#", "checkpoints": []"#)])).is_err());
+        assert!(Manifest::parse(&manifest(1, &[exercise("a", r#", "checkpoints": [-1]"#)])).is_err());
+        assert!(Manifest::parse(&manifest(1, &[exercise("a", r#", "pass_score": 101"#)])).is_err());
+        assert!(Manifest::parse(&manifest(1, &[exercise("a", r#", "texture": "video""#)])).is_err());
+        assert!(Manifest::parse("[]").unwrap_err().starts_with("Not an exercise manifest"));
+    }
+
+    #[test]
+    fn identical_frames_match() {
+        let target = frame(20, 10, |x, y| [(x * 12) as u8, (y * 25) as u8, 90]);
+        let score = score_frame(&target, &target, 20, 10);
+        assert_eq!(score.score, 100.0);
+        assert_eq!((score.columns, score.rows), (3, 2));
+        assert_eq!(score.mismatches().count(), 0);
+    }
+
+    #[test]
+    fn small_differences_still_match() {
+        let target = frame(16, 16, |_, _| [120, 60, 200]);
+        let close = frame(16, 16, |_, _| [121, 60, 199]);
+        assert_eq!(score_frame(&close, &target, 16, 16).score, 100.0);
+    }
+
+    #[test]
+    fn finds_mismatched_blocks() {
+        // Right half wrong
+        let target = frame(16, 8, |_, _| [0, 0, 0]);
+        let attempt = frame(16, 8, |x, _| if x < 8 { [0, 0, 0] } else { [255, 255, 255] });
+        let score = score_frame(&attempt, &target, 16, 8);
+        assert_eq!(score.score, 50.0);
+        assert_eq!(score.mismatches().collect::<Vec<_>>(), vec![(1, 0, 1.0)]);
+    }
+
+    #[test]
+    fn passes_on_every_checkpoint() {
+        let manifest = Manifest::parse(r#"{
+            "version": 1,
+            "exercises": [ { "id": "a", "title": "T", "start": "a.frag", "target": "b.frag", "checkpoints": [0, 1], "pass_score": 90 } ]
+        }"#).unwrap();
+        let exercise = &manifest.exercises[0];
+        assert!(exercise.passed(&[95.0, 90.0]));
+        assert!(!exercise.passed(&[95.0, 89.0]));
+        assert!(!exercise.passed(&[95.0]));
+    }
+}
@@ -0,0 +1,367 @@
+// Multi-touch gestures: pan, pinch-zoom and two finger rotation.
+//
+// Feed the recognizer every touch that's currently down each time a touch event arrives (start,
+// move, end and cancel alike) and it hands back what changed since last time as Gestures. It also
+// keeps a running total, which GestureUniforms sends to the shader as
+//
+//   uniform vec2 u_pan;       // canvas pixels, y up to match gl_FragCoord
+//   uniform float u_zoom;     // 1.0 to start with, bigger is zoomed in
+//   uniform float u_rotation; // radians, anti-clockwise
+//
+// Each gesture only starts once the fingers have moved far enough that it's clearly meant, so
+// a pinch doesn't also nudge the rotation. Fingers being added or lifted part way through don't
+// make anything jump.
+
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+
+// How far the fingers have to move before each gesture is recognized
+pub const PAN_SLOP: f64 = 10.0;
+pub const PINCH_SLOP: f64 = 20.0;
+pub const ROTATE_SLOP: f64 = 0.15;
+
+// A finger on the screen, in canvas pixels but y down as touch events give them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchPoint {
+    pub id: i32,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    // Movement of the centre of the touches
+    Pan { dx: f64, dy: f64 },
+    // Change in the distance between the first two fingers, as a factor
+    Pinch { scale: f64, center: (f64, f64) },
+    // Change in the angle between the first two fingers, clockwise on screen
+    Rotate { angle: f64, center: (f64, f64) },
+    // The last finger has been lifted
+    End,
+}
+
+// Everything added up since the recognizer was made (or reset)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureState {
+    pub pan: (f64, f64),
+    pub zoom: f64,
+    pub rotation: f64,
+}
+
+impl Default for GestureState {
+    fn default() -> Self {
+        GestureState {
+            pan: (0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GestureRecognizer {
+    // Sorted by id
+    touches: Vec<TouchPoint>,
+    // Fewest fingers that count as a pan, so one finger can be left for something else
+    pan_touches: usize,
+    panning: bool,
+    pinching: bool,
+    rotating: bool,
+    // Where each gesture is measured from: where the touches were when they went down until the
+    // gesture is recognized, then the last update
+    pan_from: (f64, f64),
+    pinch_from: f64,
+    rotate_from: f64,
+    state: GestureState,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        GestureRecognizer::new()
+    }
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        GestureRecognizer {
+            touches: Vec::new(),
+            pan_touches: 1,
+            panning: false,
+            pinching: false,
+            rotating: false,
+            pan_from: (0.0, 0.0),
+            pinch_from: 0.0,
+            rotate_from: 0.0,
+            state: GestureState::default(),
+        }
+    }
+
+    pub fn pan_touches(mut self, count: usize) -> Self {
+        self.pan_touches = count.max(1);
+        self
+    }
+
+    pub fn state(&self) -> GestureState {
+        self.state
+    }
+
+    pub fn reset(&mut self) {
+        self.state = GestureState::default();
+    }
+
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    // `touches` is every touch currently down
+    pub fn update(&mut self, touches: &[TouchPoint]) -> Vec<Gesture> {
+        let mut touches = touches.to_vec();
+        touches.sort_by_key(|touch| touch.id);
+
+        let same_fingers = touches.len() == self.touches.len()
+            && touches.iter().zip(&self.touches).all(|(new, old)| new.id == old.id);
+        if !same_fingers {
+            let ended = touches.is_empty() && !self.touches.is_empty();
+            self.restart(touches);
+            return if ended { vec![Gesture::End] } else { Vec::new() };
+        }
+
+        self.touches = touches;
+        let mut gestures = Vec::new();
+        let center = centroid(&self.touches);
+
+        if self.touches.len() >= self.pan_touches {
+            let (dx, dy) = (center.0 - self.pan_from.0, center.1 - self.pan_from.1);
+            if !self.panning && dx.hypot(dy) > PAN_SLOP {
+                self.panning = true;
+            }
+            if self.panning && (dx != 0.0 || dy != 0.0) {
+                self.pan_from = center;
+                self.state.pan.0 += dx;
+                self.state.pan.1 += dy;
+                gestures.push(Gesture::Pan { dx, dy });
+            }
+        }
+
+        if let [first, second, ..] = self.touches[..] {
+            let distance = (second.x - first.x).hypot(second.y - first.y);
+            let angle = (second.y - first.y).atan2(second.x - first.x);
+
+            if !self.pinching && (distance - self.pinch_from).abs() > PINCH_SLOP {
+                self.pinching = true;
+            }
+            if self.pinching && distance != self.pinch_from && self.pinch_from > 0.0 && distance > 0.0 {
+                let scale = distance / self.pinch_from;
+                self.pinch_from = distance;
+                self.state.zoom *= scale;
+                gestures.push(Gesture::Pinch { scale, center });
+            }
+
+            let turned = wrap_angle(angle - self.rotate_from);
+            if !self.rotating && turned.abs() > ROTATE_SLOP {
+                self.rotating = true;
+            }
+            if self.rotating && turned != 0.0 {
+                self.rotate_from = angle;
+                self.state.rotation += turned;
+                gestures.push(Gesture::Rotate { angle: turned, center });
+            }
+        }
+
+        gestures
+    }
+
+    // The fingers have changed, measure from where they are now. Anything already recognized
+    // carries on, as long as there are still enough fingers for it.
+    fn restart(&mut self, touches: Vec<TouchPoint>) {
+        self.touches = touches;
+        self.pan_from = centroid(&self.touches);
+        self.panning &= self.touches.len() >= self.pan_touches;
+
+        match self.touches[..] {
+            [first, second, ..] => {
+                self.pinch_from = (second.x - first.x).hypot(second.y - first.y);
+                self.rotate_from = (second.y - first.y).atan2(second.x - first.x);
+            },
+            _ => {
+                self.pinching = false;
+                self.rotating = false;
+            },
+        }
+    }
+}
+
+fn centroid(touches: &[TouchPoint]) -> (f64, f64) {
+    if touches.is_empty() {
+        return (0.0, 0.0);
+    }
+    let count = touches.len() as f64;
+    let (x, y) = touches.iter().fold((0.0, 0.0), |(x, y), touch| (x + touch.x, y + touch.y));
+    (x / count, y / count)
+}
+
+// Into -PI..PI, so going past the back of atan2 isn't a full turn
+fn wrap_angle(angle: f64) -> f64 {
+    use std::f64::consts::{PI, TAU};
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+// Every touch in a TouchList, e.g. TouchEvent::touches(), relative to `canvas`
+#[cfg(feature = "touch")]
+pub fn touch_points(list: &web_sys::TouchList, canvas: &web_sys::HtmlCanvasElement) -> Vec<TouchPoint> {
+    (0..list.length())
+        .filter_map(|idx| list.get(idx))
+        .map(|touch| {
+            let (x, y) = crate::pointer::to_canvas(canvas, touch.client_x() as f64, touch.client_y() as f64);
+            TouchPoint {
+                id: touch.identifier(),
+                x,
+                y,
+            }
+        })
+        .collect()
+}
+
+// Sends a GestureState to u_pan, u_zoom and u_rotation
+#[derive(Clone, Debug, Default)]
+pub struct GestureUniforms {
+    pan: Option<WebGlUniformLocation>,
+    zoom: Option<WebGlUniformLocation>,
+    rotation: Option<WebGlUniformLocation>,
+}
+
+impl GestureUniforms {
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.pan = gl.get_uniform_location(program, "u_pan");
+        self.zoom = gl.get_uniform_location(program, "u_zoom");
+        self.rotation = gl.get_uniform_location(program, "u_rotation");
+    }
+
+    pub fn upload(&self, gl: &GL, state: &GestureState) {
+        // Touches are y down and clockwise, GL is y up and anti-clockwise
+        gl.uniform2f(self.pan.as_ref(), state.pan.0 as f32, -state.pan.1 as f32);
+        gl.uniform1f(self.zoom.as_ref(), state.zoom as f32);
+        gl.uniform1f(self.rotation.as_ref(), -state.rotation as f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn touch(id: i32, x: f64, y: f64) -> TouchPoint {
+        TouchPoint { id, x, y }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn one_finger_pans_once_past_the_slop() {
+        let mut recognizer = GestureRecognizer::new();
+        assert!(recognizer.update(&[touch(1, 100.0, 100.0)]).is_empty());
+        // Within the slop, nothing yet
+        assert!(recognizer.update(&[touch(1, 105.0, 100.0)]).is_empty());
+        // Past it - the pan includes the movement inside the slop
+        assert_eq!(recognizer.update(&[touch(1, 115.0, 100.0)]), vec![Gesture::Pan { dx: 15.0, dy: 0.0 }]);
+        // And small movements count from now on
+        assert_eq!(recognizer.update(&[touch(1, 116.0, 102.0)]), vec![Gesture::Pan { dx: 1.0, dy: 2.0 }]);
+        assert_eq!(recognizer.update(&[]), vec![Gesture::End]);
+
+        assert_eq!(recognizer.state().pan, (16.0, 2.0));
+        assert_eq!(recognizer.state().zoom, 1.0);
+    }
+
+    #[test]
+    fn pinch_zooms_without_panning_or_rotating() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.update(&[touch(1, 100.0, 100.0), touch(2, 200.0, 100.0)]);
+
+        // Both fingers move apart symmetrically, the centre stays put
+        let gestures = recognizer.update(&[touch(1, 50.0, 100.0), touch(2, 250.0, 100.0)]);
+        assert_eq!(gestures, vec![Gesture::Pinch { scale: 2.0, center: (150.0, 100.0) }]);
+
+        recognizer.update(&[touch(1, 100.0, 100.0), touch(2, 200.0, 100.0)]);
+        let state = recognizer.state();
+        assert_close(state.zoom, 1.0);
+        assert_eq!(state.pan, (0.0, 0.0));
+        assert_eq!(state.rotation, 0.0);
+    }
+
+    #[test]
+    fn two_finger_rotation() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.update(&[touch(1, 100.0, 100.0), touch(2, 200.0, 100.0)]);
+
+        // Turn a quarter clockwise on screen (y down) around the centre, in a few steps
+        let steps = 6;
+        for step in 1..=steps {
+            let angle = FRAC_PI_2 * step as f64 / steps as f64;
+            let (dx, dy) = (50.0 * angle.cos(), 50.0 * angle.sin());
+            recognizer.update(&[touch(1, 150.0 - dx, 100.0 - dy), touch(2, 150.0 + dx, 100.0 + dy)]);
+        }
+
+        let state = recognizer.state();
+        assert_close(state.rotation, FRAC_PI_2);
+        assert_close(state.zoom, 1.0);
+        assert_eq!(state.pan, (0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_wraps_past_half_a_turn() {
+        let mut recognizer = GestureRecognizer::new();
+        // Second finger just above the left of the first, angle close to -PI
+        recognizer.update(&[touch(1, 100.0, 100.0), touch(2, 0.0, 99.0)]);
+        // ...and then just below, close to +PI. That's a small turn, not nearly a whole one.
+        recognizer.update(&[touch(1, 100.0, 100.0), touch(2, 0.0, 101.0)]);
+        assert_eq!(recognizer.state().rotation, 0.0);
+    }
+
+    #[test]
+    fn adding_and_lifting_fingers_doesnt_jump() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.update(&[touch(1, 100.0, 100.0)]);
+        recognizer.update(&[touch(1, 130.0, 100.0)]);
+        assert_eq!(recognizer.state().pan, (30.0, 0.0));
+
+        // A second finger far away moves the centroid, but that's not a pan
+        assert!(recognizer.update(&[touch(1, 130.0, 100.0), touch(7, 330.0, 100.0)]).is_empty());
+        // Lifting the first finger moves the centroid again
+        assert!(recognizer.update(&[touch(7, 330.0, 100.0)]).is_empty());
+        // The pan that was already going carries on straight away
+        assert_eq!(recognizer.update(&[touch(7, 335.0, 100.0)]), vec![Gesture::Pan { dx: 5.0, dy: 0.0 }]);
+
+        assert_eq!(recognizer.state().pan, (35.0, 0.0));
+        assert_eq!(recognizer.state().zoom, 1.0);
+    }
+
+    #[test]
+    fn order_of_touches_doesnt_matter() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.update(&[touch(2, 200.0, 100.0), touch(1, 100.0, 100.0)]);
+        let gestures = recognizer.update(&[touch(1, 50.0, 100.0), touch(2, 250.0, 100.0)]);
+        assert_eq!(gestures, vec![Gesture::Pinch { scale: 2.0, center: (150.0, 100.0) }]);
+    }
+
+    #[test]
+    fn pan_can_need_two_fingers() {
+        let mut recognizer = GestureRecognizer::new().pan_touches(2);
+        recognizer.update(&[touch(1, 100.0, 100.0)]);
+        assert!(recognizer.update(&[touch(1, 200.0, 100.0)]).is_empty());
+
+        recognizer.update(&[touch(1, 200.0, 100.0), touch(2, 300.0, 100.0)]);
+        let gestures = recognizer.update(&[touch(1, 200.0, 150.0), touch(2, 300.0, 150.0)]);
+        assert_eq!(gestures, vec![Gesture::Pan { dx: 0.0, dy: 50.0 }]);
+    }
+
+    #[test]
+    fn end_only_when_the_last_finger_lifts() {
+        let mut recognizer = GestureRecognizer::new();
+        assert!(recognizer.update(&[]).is_empty());
+        recognizer.update(&[touch(1, 0.0, 0.0), touch(2, 10.0, 0.0)]);
+        assert!(recognizer.update(&[touch(2, 10.0, 0.0)]).is_empty());
+        assert_eq!(recognizer.update(&[]), vec![Gesture::End]);
+        assert_eq!(recognizer.touch_count(), 0);
+    }
+}
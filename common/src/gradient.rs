@@ -0,0 +1,603 @@
+// Gradients made of colour stops, baked into a 256x1 texture for shaders to look colours up in.
+//
+//   {
+//     "version": 1,
+//     "name": "Ultra",
+//     "interpolation": "oklab",
+//     "stops": [ { "position": 0, "color": "#000764" }, { "position": 0.16, "color": "#206bcb" } ]
+//   }
+//
+// Stop colours are sRGB, as picked. Between stops the colour is blended in linear light, in
+// sRGB (the way CSS gradients do it) or in OKLab, which keeps the brightness even. Two stops at
+// the same position make a hard edge. GIMP's .ggr files can be read and written too, see
+// from_ggr and to_ggr.
+
+use serde::{Deserialize, Serialize};
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+
+use crate::color::{linear_to_oklab, linear_to_srgb, oklab_to_linear, srgb_to_linear};
+use crate::params::{color_to_hex, parse_color};
+
+pub const SCHEMA_VERSION: u64 = 1;
+pub const TEXTURE_WIDTH: usize = 256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    Linear,
+    Srgb,
+    #[default]
+    Oklab,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 3] = [Interpolation::Linear, Interpolation::Srgb, Interpolation::Oklab];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::Linear => "Linear RGB",
+            Interpolation::Srgb => "sRGB",
+            Interpolation::Oklab => "OKLab",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: [f32; 3],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub name: String,
+    // Always sorted by position
+    stops: Vec<GradientStop>,
+    pub interpolation: Interpolation,
+}
+
+impl Gradient {
+    // Needs at least one stop
+    pub fn new(name: &str, stops: Vec<GradientStop>, interpolation: Interpolation) -> Result<Gradient, String> {
+        if stops.is_empty() {
+            return Err("A gradient needs at least one stop".to_string());
+        }
+        let mut gradient = Gradient { name: name.to_string(), stops, interpolation };
+        for stop in &mut gradient.stops {
+            stop.position = stop.position.clamp(0.0, 1.0);
+            stop.color = stop.color.map(|c| c.clamp(0.0, 1.0));
+        }
+        gradient.sort();
+        Ok(gradient)
+    }
+
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    // Index of the new stop, which may not be the last after sorting
+    pub fn add_stop(&mut self, stop: GradientStop) -> usize {
+        self.stops.push(GradientStop { position: stop.position.clamp(0.0, 1.0), color: stop.color });
+        self.sort_keeping(self.stops.len() - 1)
+    }
+
+    // Moving a stop can change its place in the list, so this returns where it ended up
+    pub fn set_stop(&mut self, index: usize, stop: GradientStop) -> usize {
+        if index >= self.stops.len() {
+            return index;
+        }
+        self.stops[index] = GradientStop { position: stop.position.clamp(0.0, 1.0), color: stop.color };
+        self.sort_keeping(index)
+    }
+
+    // The last stop can't go
+    pub fn remove_stop(&mut self, index: usize) {
+        if self.stops.len() > 1 && index < self.stops.len() {
+            self.stops.remove(index);
+        }
+    }
+
+    // Halfway across the widest gap between stops, where a new one is most useful
+    pub fn widest_gap(&self) -> f32 {
+        let mut edges: Vec<f32> = vec![0.0];
+        edges.extend(self.stops.iter().map(|stop| stop.position));
+        edges.push(1.0);
+        edges
+            .windows(2)
+            .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+            .map(|gap| 0.5 * (gap[0] + gap[1]))
+            .unwrap_or(0.5)
+    }
+
+    // sRGB colour at `t`, from 0 to 1
+    pub fn color(&self, t: f32) -> [f32; 3] {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if t <= first.position {
+            return first.color;
+        }
+        if t >= last.position {
+            return last.color;
+        }
+        // The last stop at or before t, so a hard edge takes the colour after it
+        let after = self.stops.iter().position(|stop| stop.position > t).unwrap_or(self.stops.len() - 1);
+        let (left, right) = (self.stops[after - 1], self.stops[after]);
+        let mix = (t - left.position) / (right.position - left.position);
+        self.interpolate(left.color, right.color, mix)
+    }
+
+    fn interpolate(&self, a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+        let lerp = |a: [f32; 3], b: [f32; 3]| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        let color = match self.interpolation {
+            Interpolation::Srgb => lerp(a, b),
+            Interpolation::Linear => linear_to_srgb(lerp(srgb_to_linear(a), srgb_to_linear(b))),
+            Interpolation::Oklab => linear_to_srgb(oklab_to_linear(lerp(linear_to_oklab(srgb_to_linear(a)), linear_to_oklab(srgb_to_linear(b))))),
+        };
+        color.map(|c| c.clamp(0.0, 1.0))
+    }
+
+    // RGBA bytes for a TEXTURE_WIDTH x 1 texture, each texel sampled at its middle
+    pub fn bake(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(TEXTURE_WIDTH * 4);
+        for x in 0..TEXTURE_WIDTH {
+            let color = self.color((x as f32 + 0.5) / TEXTURE_WIDTH as f32);
+            data.extend(color.map(|c| (c * 255.0).round() as u8));
+            data.push(255);
+        }
+        data
+    }
+
+    // A CSS linear-gradient through `stops` samples, to show it on the page
+    pub fn css_gradient(&self, stops: usize) -> String {
+        let stops = stops.max(2);
+        let colors: Vec<String> = (0..stops).map(|i| color_to_hex(self.color(i as f32 / (stops - 1) as f32))).collect();
+        format!("linear-gradient(to right, {})", colors.join(", "))
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = GradientFile {
+            version: SCHEMA_VERSION,
+            name: self.name.clone(),
+            interpolation: self.interpolation,
+            stops: self.stops.iter().map(|stop| StoredStop { position: stop.position, color: color_to_hex(stop.color) }).collect(),
+        };
+        serde_json::to_string_pretty(&file).expect("Gradients always serialize")
+    }
+
+    pub fn from_json(text: &str) -> Result<Gradient, String> {
+        let file: GradientFile = serde_json::from_str(text).map_err(|err| format!("Not a gradient file: {}", err))?;
+        if file.version > SCHEMA_VERSION {
+            return Err(format!("Gradient file is version {}, this page only knows up to {}", file.version, SCHEMA_VERSION));
+        }
+        let stops = file
+            .stops
+            .iter()
+            .map(|stop| {
+                let color = parse_color(&stop.color).ok_or_else(|| format!("Bad colour {:?}", stop.color))?;
+                Ok(GradientStop { position: stop.position, color })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Gradient::new(&file.name, stops, file.interpolation)
+    }
+
+    // Either format, going by the first line
+    pub fn import(text: &str) -> Result<Gradient, String> {
+        if text.trim_start().starts_with("GIMP Gradient") {
+            Gradient::from_ggr(text)
+        } else {
+            Gradient::from_json(text)
+        }
+    }
+
+    // GIMP gradients are made of segments, each with a colour at either end, a midpoint and a
+    // blending curve. Plain linear RGB segments become a stop at each end (and the midpoint, if
+    // it has moved); anything curved or blended in HSV is sampled into extra stops.
+    pub fn from_ggr(text: &str) -> Result<Gradient, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some("GIMP Gradient") {
+            return Err("Not a GIMP gradient".to_string());
+        }
+        let mut line = lines.next().ok_or("GIMP gradient ends early")?;
+        let mut name = String::new();
+        if let Some(rest) = line.strip_prefix("Name:") {
+            name = rest.trim().to_string();
+            line = lines.next().ok_or("GIMP gradient ends early")?;
+        }
+        let count: usize = line.parse().map_err(|_| format!("Expected a segment count, not {:?}", line))?;
+
+        let mut stops = Vec::new();
+        for index in 0..count {
+            let line = lines.next().ok_or_else(|| format!("GIMP gradient has {} of {} segments", index, count))?;
+            let segment = GgrSegment::parse(line).ok_or_else(|| format!("Bad GIMP gradient segment {:?}", line))?;
+            segment.append_stops(&mut stops);
+        }
+        if lines.next().is_some() {
+            return Err(format!("GIMP gradient has more than {} segments", count));
+        }
+        // Neighbouring segments usually share a colour at the join
+        stops.dedup();
+        Gradient::new(&name, stops, Interpolation::Srgb)
+    }
+
+    // Written as linear RGB segments. GIMP blends those in sRGB, so for the other kinds of
+    // interpolation each gap is split up until it looks the same.
+    pub fn to_ggr(&self) -> String {
+        let mut points = vec![GradientStop { position: 0.0, color: self.color(0.0) }];
+        for stop in &self.stops {
+            let from = points[points.len() - 1];
+            if stop.position > from.position {
+                let to = GradientStop { position: stop.position, color: self.color_before(stop.position) };
+                self.push_straight(&mut points, from, to, 0);
+            }
+            // Keeps hard edges: the colour before and after the stop can differ
+            points.push(GradientStop { position: stop.position, color: stop.color });
+        }
+        let last = points[points.len() - 1];
+        if last.position < 1.0 {
+            points.push(GradientStop { position: 1.0, color: last.color });
+        }
+
+        let segments: Vec<String> = points
+            .windows(2)
+            .filter(|pair| pair[1].position > pair[0].position)
+            .map(|pair| {
+                let (left, right) = (pair[0], pair[1]);
+                let [lr, lg, lb] = left.color;
+                let [rr, rg, rb] = right.color;
+                format!(
+                    "{:.6} {:.6} {:.6} {:.6} {:.6} {:.6} 1.000000 {:.6} {:.6} {:.6} 1.000000 0 0 0 0",
+                    left.position,
+                    0.5 * (left.position + right.position),
+                    right.position,
+                    lr, lg, lb, rr, rg, rb,
+                )
+            })
+            .collect();
+        let name = if self.name.is_empty() { "Untitled" } else { &self.name };
+        format!("GIMP Gradient\nName: {}\n{}\n{}\n", name, segments.len(), segments.join("\n"))
+    }
+
+    // Halve the run from `from` to `to` until a straight sRGB blend is within GGR_TOLERANCE of
+    // the real colour at its middle, then add its end to `points`
+    fn push_straight(&self, points: &mut Vec<GradientStop>, from: GradientStop, to: GradientStop, depth: u32) {
+        let middle = 0.5 * (from.position + to.position);
+        let color = self.color(middle);
+        let straight = [0, 1, 2].map(|i| 0.5 * (from.color[i] + to.color[i]));
+        if depth < GGR_MAX_DEPTH && (0..3).any(|i| (color[i] - straight[i]).abs() > GGR_TOLERANCE) {
+            let split = GradientStop { position: middle, color };
+            self.push_straight(points, from, split, depth + 1);
+            self.push_straight(points, split, to, depth + 1);
+        } else {
+            points.push(to);
+        }
+    }
+
+    // The colour coming up to `position` from the left, which differs at a hard edge
+    fn color_before(&self, position: f32) -> [f32; 3] {
+        match self.stops.iter().position(|stop| stop.position >= position) {
+            Some(index) if index > 0 => {
+                let (left, right) = (self.stops[index - 1], self.stops[index]);
+                if right.position > left.position {
+                    self.interpolate(left.color, right.color, (position - left.position) / (right.position - left.position))
+                } else {
+                    left.color
+                }
+            },
+            Some(index) => self.stops[index].color,
+            None => self.color(position),
+        }
+    }
+
+    fn sort(&mut self) {
+        // Stable, so stops at the same place keep their order
+        self.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    }
+
+    fn sort_keeping(&mut self, index: usize) -> usize {
+        let stop = self.stops[index];
+        self.sort();
+        self.stops.iter().position(|other| *other == stop).unwrap_or(index)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GradientFile {
+    version: u64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    interpolation: Interpolation,
+    stops: Vec<StoredStop>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredStop {
+    position: f32,
+    color: String,
+}
+
+// One line of a .ggr: left middle right, left RGBA, right RGBA, blending, colouring, and
+// sometimes the end colour types, which are ignored
+struct GgrSegment {
+    left: f32,
+    middle: f32,
+    right: f32,
+    left_color: [f32; 3],
+    right_color: [f32; 3],
+    blending: u32,
+    coloring: u32,
+}
+
+impl GgrSegment {
+    fn parse(line: &str) -> Option<GgrSegment> {
+        let fields: Vec<f32> = line.split_whitespace().map(|field| field.parse().ok()).collect::<Option<_>>()?;
+        if fields.len() < 13 {
+            return None;
+        }
+        Some(GgrSegment {
+            left: fields[0],
+            middle: fields[1],
+            right: fields[2],
+            left_color: [fields[3], fields[4], fields[5]],
+            right_color: [fields[7], fields[8], fields[9]],
+            blending: fields[11] as u32,
+            coloring: fields[12] as u32,
+        })
+    }
+
+    fn append_stops(&self, stops: &mut Vec<GradientStop>) {
+        let width = self.right - self.left;
+        let mut add = |position: f32| {
+            let t = if width > 0.0 { (position - self.left) / width } else { 0.0 };
+            stops.push(GradientStop { position, color: self.color(t) });
+        };
+
+        add(self.left);
+        match (self.blending, self.coloring) {
+            // Linear RGB, only the middle needs a stop of its own
+            (0, 0) => {
+                if (self.middle - 0.5 * (self.left + self.right)).abs() > 1e-4 {
+                    add(self.middle);
+                }
+            },
+            // Step: a hard edge at the middle
+            (5, _) => {
+                stops.push(GradientStop { position: self.middle, color: self.left_color });
+                stops.push(GradientStop { position: self.middle, color: self.right_color });
+            },
+            _ => {
+                for step in 1..GGR_SAMPLES {
+                    add(self.left + width * step as f32 / GGR_SAMPLES as f32);
+                }
+            },
+        }
+        // The exact colour at the right hand end, whatever the curve does
+        stops.push(GradientStop { position: self.right, color: self.right_color });
+    }
+
+    // Colour `t` of the way along, following GIMP's gimp_gradient_get_color_at
+    fn color(&self, t: f32) -> [f32; 3] {
+        let width = self.right - self.left;
+        let middle = if width > 0.0 { ((self.middle - self.left) / width).clamp(1e-4, 1.0 - 1e-4) } else { 0.5 };
+        let linear = if t <= middle { 0.5 * t / middle } else { 0.5 + 0.5 * (t - middle) / (1.0 - middle) };
+        let factor = match self.blending {
+            1 => t.max(0.0).powf(0.5f32.ln() / middle.ln()),
+            2 => (((-0.5 + linear) * std::f32::consts::PI).sin() + 1.0) * 0.5,
+            3 => (1.0 - (linear - 1.0) * (linear - 1.0)).sqrt(),
+            4 => 1.0 - (1.0 - linear * linear).max(0.0).sqrt(),
+            5 => if t >= middle { 1.0 } else { 0.0 },
+            _ => linear,
+        };
+
+        let (a, b) = (self.left_color, self.right_color);
+        match self.coloring {
+            // HSV, anticlockwise then clockwise round the hue circle
+            1 | 2 => {
+                let (ha, hb) = (rgb_to_hsv(a), rgb_to_hsv(b));
+                let mut hue_change = hb[0] - ha[0];
+                if self.coloring == 1 && hue_change < 0.0 {
+                    hue_change += 1.0;
+                } else if self.coloring == 2 && hue_change > 0.0 {
+                    hue_change -= 1.0;
+                }
+                let hue = (ha[0] + hue_change * factor).rem_euclid(1.0);
+                hsv_to_rgb([hue, ha[1] + (hb[1] - ha[1]) * factor, ha[2] + (hb[2] - ha[2]) * factor])
+            },
+            _ => [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * factor),
+        }
+    }
+}
+
+// Stops per curved segment read from a .ggr
+const GGR_SAMPLES: usize = 8;
+// How far a .ggr's straight segments can be from the gradient they're written for, half of one
+// step in an 8 bit channel, and how many times a gap can be halved to get there
+const GGR_TOLERANCE: f32 = 0.5 / 255.0;
+const GGR_MAX_DEPTH: u32 = 8;
+
+// Hue, saturation and value all from 0 to 1
+fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    let saturation = if max > 0.0 { delta / max } else { 0.0 };
+    [hue, saturation, max]
+}
+
+fn hsv_to_rgb([hue, saturation, value]: [f32; 3]) -> [f32; 3] {
+    let channel = |n: f32| {
+        let k = (n + hue * 6.0).rem_euclid(6.0);
+        value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0)
+    };
+    [channel(5.0), channel(3.0), channel(1.0)]
+}
+
+// The baked gradient as `u_gradient`, only uploaded again when it changes
+#[derive(Default)]
+pub struct GradientTexture {
+    texture: Option<WebGlTexture>,
+    location: Option<WebGlUniformLocation>,
+    baked: Option<Gradient>,
+}
+
+impl GradientTexture {
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.location = gl.get_uniform_location(program, "u_gradient");
+    }
+
+    // Leaves TEXTURE0 active again afterwards
+    pub fn upload(&mut self, gl: &GL, gradient: &Gradient, unit: u32) {
+        if self.texture.is_none() {
+            self.texture = gl.create_texture();
+        }
+
+        gl.active_texture(GL::TEXTURE0 + unit);
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+        if self.baked.as_ref() != Some(gradient) {
+            let _ = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                TEXTURE_WIDTH as i32,
+                1,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                Some(&gradient.bake()),
+            );
+            // 256 is a power of two, so it can wrap for colours that cycle
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::REPEAT as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            self.baked = Some(gradient.clone());
+        }
+        gl.active_texture(GL::TEXTURE0);
+
+        gl.uniform1i(self.location.as_ref(), unit as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err))
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tolerance: f32) {
+        assert!((0..3).all(|i| (a[i] - b[i]).abs() <= tolerance), "{:?} != {:?}", a, b);
+    }
+
+    fn stop(position: f32, color: [u8; 3]) -> GradientStop {
+        GradientStop { position, color: color.map(|c| c as f32 / 255.0) }
+    }
+
+    fn sunset(interpolation: Interpolation) -> Gradient {
+        let stops = vec![stop(0.1, [0, 7, 100]), stop(0.4, [237, 255, 255]), stop(0.8, [255, 170, 0])];
+        Gradient::new("Sunset", stops, interpolation).unwrap()
+    }
+
+    // Red to blue with a hard edge to green in the middle
+    fn hard_edge() -> Gradient {
+        let stops = vec![stop(0.0, [255, 0, 0]), stop(0.5, [0, 0, 255]), stop(0.5, [0, 255, 0]), stop(1.0, [0, 255, 0])];
+        Gradient::new("Edge", stops, Interpolation::Srgb).unwrap()
+    }
+
+    #[test]
+    fn ggr_round_trip() {
+        for interpolation in Interpolation::ALL {
+            let gradient = sunset(interpolation);
+            let read = Gradient::from_ggr(&gradient.to_ggr()).unwrap();
+            assert_eq!(read.name, "Sunset");
+            // GIMP only blends in sRGB, the other kinds are split up finely enough to look the same
+            for i in 0..=100 {
+                let t = i as f32 / 100.0;
+                assert_close(read.color(t), gradient.color(t), 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_gimp_file() {
+        // Laid out as GIMP 2.10 saves it, with the end colour types after each segment
+        let gradient = Gradient::import(&fixture("gimp_sunset.ggr")).unwrap();
+        assert_eq!(gradient.name, "Fixture Sunset");
+        assert_eq!(gradient.interpolation, Interpolation::Srgb);
+        assert_close(gradient.color(0.0), [0.0, 0.0, 0.2], 1e-5);
+        assert_close(gradient.color(1.0), [1.0, 1.0, 1.0], 1e-5);
+        // The first segment's middle is off centre, so it gets a stop of its own
+        assert!(gradient.stops().iter().any(|stop| stop.position == 0.2));
+        assert_close(gradient.color(0.2), [0.4, 0.05, 0.25], 1e-5);
+        // HSV anticlockwise from orange to blue goes round through green, not magenta
+        assert_close(gradient.color(0.7), [0.0, 1.0, 0.1], 0.02);
+        // The last segment is a step at its middle
+        assert_close(gradient.color(0.89), [0.0, 0.4, 1.0], 1e-5);
+        assert_close(gradient.color(0.91), [1.0, 1.0, 1.0], 1e-5);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for interpolation in Interpolation::ALL {
+            let gradient = sunset(interpolation);
+            assert_eq!(Gradient::from_json(&gradient.to_json()).unwrap(), gradient);
+        }
+    }
+
+    #[test]
+    fn hard_edge_round_trip() {
+        let gradient = hard_edge();
+        assert_close(gradient.color(0.49), [0.02, 0.0, 0.98], 0.01);
+        assert_close(gradient.color(0.5), [0.0, 1.0, 0.0], 1e-5);
+
+        assert_eq!(Gradient::from_json(&gradient.to_json()).unwrap(), gradient);
+
+        let read = Gradient::from_ggr(&gradient.to_ggr()).unwrap();
+        assert_close(read.color(0.49), gradient.color(0.49), 0.01);
+        assert_close(read.color(0.51), [0.0, 1.0, 0.0], 1e-5);
+    }
+
+    #[test]
+    fn bakes_texture() {
+        // Flat at both ends, as the texels are sampled at their middles
+        let stops = vec![stop(0.25, [0, 0, 0]), stop(0.75, [255, 255, 255])];
+        let gradient = Gradient::new("Grey", stops, Interpolation::Oklab).unwrap();
+        let data = gradient.bake();
+        assert_eq!(data.len(), TEXTURE_WIDTH * 4);
+        assert_eq!(&data[..4], &[0, 0, 0, 255]);
+        assert_eq!(&data[data.len() - 4..], &[255, 255, 255, 255]);
+
+        // Halfway in OKLab is lightness 0.5, 0.125 in linear light and 0.389 in sRGB, rather
+        // than the 0.5 an sRGB blend gives
+        assert_close(gradient.color(0.5), [0.3886; 3], 1e-3);
+        let middle = &data[(TEXTURE_WIDTH / 2) * 4..(TEXTURE_WIDTH / 2) * 4 + 3];
+        assert!(middle.iter().all(|c| (98..=101).contains(c)), "{:?}", middle);
+    }
+
+    #[test]
+    fn rejects_bad_ggr() {
+        let segment = "0.000000 0.500000 1.000000 0 0 0 1 1 1 1 1 0 0";
+        assert_eq!(Gradient::from_ggr("GIMP Palette\n1\n").unwrap_err(), "Not a GIMP gradient");
+        assert!(Gradient::from_ggr("GIMP Gradient\nName: Short\n1\n0.0 0.5 1.0 0 0 0 1\n").unwrap_err().starts_with("Bad GIMP gradient segment"));
+        assert_eq!(
+            Gradient::from_ggr(&format!("GIMP Gradient\n2\n{}\n", segment)).unwrap_err(),
+            "GIMP gradient has 1 of 2 segments"
+        );
+        assert_eq!(
+            Gradient::from_ggr(&format!("GIMP Gradient\n1\n{}\n{}\n", segment, segment)).unwrap_err(),
+            "GIMP gradient has more than 1 segments"
+        );
+        assert!(Gradient::from_ggr("GIMP Gradient\nlots\n").unwrap_err().starts_with("Expected a segment count"));
+        assert_eq!(Gradient::from_ggr("GIMP Gradient\n").unwrap_err(), "GIMP gradient ends early");
+    }
+}
@@ -0,0 +1,186 @@
+// Editing a stop based gradient (see gradient.rs): a preview with a marker for each stop, the
+// selected stop's position and colour, how to blend between stops, and import/export as JSON
+// or GIMP .ggr files. The tutorial keeps the gradient and bakes it, this only hands back edits.
+
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{File, HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::download::download_text;
+use crate::gradient::{Gradient, GradientStop, Interpolation};
+use crate::params::{color_to_hex, parse_color};
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct GradientEditorProps {
+    // Used to name exported files
+    pub tutorial: AttrValue,
+    pub gradient: Gradient,
+    pub on_change: Callback<Gradient>,
+}
+
+pub enum GradientEditorMsg {
+    Select(usize),
+    Position(f32),
+    Color([f32; 3]),
+    Add,
+    Remove,
+    Interpolation(Interpolation),
+    ExportJson,
+    ExportGgr,
+    Import(File),
+    Imported(Result<String, String>),
+}
+
+pub struct GradientEditor {
+    selected: usize,
+    error: Option<String>,
+}
+
+impl Component for GradientEditor {
+    type Message = GradientEditorMsg;
+    type Properties = GradientEditorProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        GradientEditor { selected: 0, error: None }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let props = ctx.props();
+        let mut gradient = props.gradient.clone();
+        self.error = None;
+
+        match msg {
+            GradientEditorMsg::Select(index) => {
+                self.selected = index;
+                return true;
+            },
+            GradientEditorMsg::Position(position) => {
+                if let Some(stop) = gradient.stops().get(self.selected) {
+                    self.selected = gradient.set_stop(self.selected, GradientStop { position, color: stop.color });
+                }
+            },
+            GradientEditorMsg::Color(color) => {
+                if let Some(stop) = gradient.stops().get(self.selected) {
+                    self.selected = gradient.set_stop(self.selected, GradientStop { position: stop.position, color });
+                }
+            },
+            GradientEditorMsg::Add => {
+                let position = gradient.widest_gap();
+                self.selected = gradient.add_stop(GradientStop { position, color: gradient.color(position) });
+            },
+            GradientEditorMsg::Remove => {
+                gradient.remove_stop(self.selected);
+                self.selected = self.selected.min(gradient.stops().len() - 1);
+            },
+            GradientEditorMsg::Interpolation(interpolation) => {
+                gradient.interpolation = interpolation;
+            },
+            GradientEditorMsg::ExportJson => {
+                if let Err(err) = download_text(&format!("{}-gradient.json", props.tutorial), &gradient.to_json(), "application/json") {
+                    self.error = Some(err);
+                }
+                return true;
+            },
+            GradientEditorMsg::ExportGgr => {
+                if let Err(err) = download_text(&format!("{}-gradient.ggr", props.tutorial), &gradient.to_ggr(), "text/plain") {
+                    self.error = Some(err);
+                }
+                return true;
+            },
+            GradientEditorMsg::Import(file) => {
+                ctx.link().send_future(async move {
+                    let text = JsFuture::from(file.text()).await.map_err(|err| format!("Unable to read {}: {:?}", file.name(), err));
+                    GradientEditorMsg::Imported(text.and_then(|text| text.as_string().ok_or_else(|| "File isn't text".to_string())))
+                });
+                return false;
+            },
+            GradientEditorMsg::Imported(text) => {
+                match text.and_then(|text| Gradient::import(&text)) {
+                    Ok(imported) => {
+                        gradient = imported;
+                        self.selected = 0;
+                    },
+                    Err(err) => {
+                        self.error = Some(err);
+                        return true;
+                    },
+                }
+            },
+        }
+        props.on_change.emit(gradient);
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let gradient = &ctx.props().gradient;
+        let stops = gradient.stops();
+        let selected = self.selected.min(stops.len() - 1);
+        let stop = stops[selected];
+
+        let markers = stops.iter().enumerate().map(|(index, stop)| {
+            let onclick = link.callback(move |_: MouseEvent| GradientEditorMsg::Select(index));
+            html! {
+                <div class={classes!("gradient_stop", (index == selected).then_some("selected"))}
+                    style={format!("left: {:.2}%; background: {};", stop.position * 100.0, color_to_hex(stop.color))}
+                    title={format!("{:.3}", stop.position)}
+                    onclick={onclick}
+                ></div>
+            }
+        });
+
+        let onposition = link.batch_callback(|evt: InputEvent| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            input.value().parse().ok().map(GradientEditorMsg::Position)
+        });
+        let oncolor = link.batch_callback(|evt: InputEvent| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            parse_color(&input.value()).map(GradientEditorMsg::Color)
+        });
+        let oninterpolation = link.batch_callback(|evt: Event| {
+            let select: HtmlSelectElement = evt.target_unchecked_into();
+            Interpolation::ALL.iter().find(|interpolation| interpolation.name() == select.value()).copied().map(GradientEditorMsg::Interpolation)
+        });
+        let onimport = link.batch_callback(|evt: Event| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            let file = input.files().and_then(|files| files.get(0));
+            // Clear it so choosing the same file again still fires a change
+            input.set_value("");
+            file.map(GradientEditorMsg::Import)
+        });
+
+        html! {
+            <div class="gradient_editor">
+                <div class="gradient_preview" style={format!("background: {};", gradient.css_gradient(64))}></div>
+                <div class="gradient_stops">
+                    { for markers }
+                </div>
+                <div class="param_row">
+                    <label>{ "Position" }</label>
+                    <input type="range" min="0" max="1" step="0.001" value={stop.position.to_string()} oninput={onposition} />
+                    <input type="color" value={color_to_hex(stop.color)} oninput={oncolor} />
+                </div>
+                <div class="gradient_row">
+                    <button onclick={link.callback(|_| GradientEditorMsg::Add)}>{ "Add stop" }</button>
+                    <button onclick={link.callback(|_| GradientEditorMsg::Remove)} disabled={stops.len() < 2}>{ "Remove stop" }</button>
+                    <select onchange={oninterpolation}>
+                        { for Interpolation::ALL.iter().map(|interpolation| html! {
+                            <option selected={*interpolation == gradient.interpolation} value={interpolation.name()}>{ interpolation.name() }</option>
+                        }) }
+                    </select>
+                </div>
+                <div class="gradient_row">
+                    <button onclick={link.callback(|_| GradientEditorMsg::ExportJson)}>{ "Export JSON" }</button>
+                    <button onclick={link.callback(|_| GradientEditorMsg::ExportGgr)}>{ "Export .ggr" }</button>
+                    <label class="preset_import">
+                        { "Import" }
+                        <input type="file" accept=".json,.ggr,application/json" onchange={onimport} />
+                    </label>
+                </div>
+                if let Some(error) = &self.error {
+                    <div class="preset_error">{ error }</div>
+                }
+            </div>
+        }
+    }
+}
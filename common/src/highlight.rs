@@ -0,0 +1,231 @@
+// Just enough syntax highlighting for showing GLSL and Rust source a line at a time: comments,
+// strings, numbers, keywords, types and preprocessor lines or attributes. It works line by line,
+// so a /* */ comment spanning several lines is only coloured on the lines with the markers.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Glsl,
+    Rust,
+}
+
+impl Language {
+    // From the file extension, None for anything else
+    pub fn from_name(name: &str) -> Option<Language> {
+        match name.rsplit_once('.')?.1 {
+            "frag" | "vert" | "glsl" => Some(Language::Glsl),
+            "rs" => Some(Language::Rust),
+            _ => None,
+        }
+    }
+
+    fn is_keyword(&self, word: &str) -> bool {
+        match self {
+            Language::Glsl => GLSL_KEYWORDS.contains(&word),
+            Language::Rust => RUST_KEYWORDS.contains(&word),
+        }
+    }
+
+    fn is_type(&self, word: &str) -> bool {
+        match self {
+            Language::Glsl => GLSL_TYPES.contains(&word),
+            Language::Rust => RUST_TYPES.contains(&word) || word.starts_with(|c: char| c.is_ascii_uppercase()),
+        }
+    }
+}
+
+const GLSL_KEYWORDS: &[&str] = &[
+    "attribute", "break", "const", "continue", "discard", "do", "else", "false", "for", "highp", "if", "in", "inout",
+    "lowp", "mediump", "out", "precision", "return", "struct", "true", "uniform", "varying", "while",
+];
+const GLSL_TYPES: &[&str] = &[
+    "bool", "bvec2", "bvec3", "bvec4", "float", "int", "ivec2", "ivec3", "ivec4", "mat2", "mat3", "mat4", "sampler2D",
+    "samplerCube", "vec2", "vec3", "vec4", "void",
+];
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "false", "fn", "for", "if",
+    "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "static", "struct",
+    "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+const RUST_TYPES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "isize", "str", "u8", "u16", "u32", "u64", "usize",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    Type,
+    Number,
+    Str,
+    Comment,
+    // GLSL's # lines and Rust's #[attributes]
+    Directive,
+}
+
+impl TokenKind {
+    pub fn class(&self) -> &'static str {
+        match self {
+            TokenKind::Plain => "",
+            TokenKind::Keyword => "hl_keyword",
+            TokenKind::Type => "hl_type",
+            TokenKind::Number => "hl_number",
+            TokenKind::Str => "hl_string",
+            TokenKind::Comment => "hl_comment",
+            TokenKind::Directive => "hl_directive",
+        }
+    }
+}
+
+// Split `line` into pieces that together make up the whole line
+pub fn highlight(line: &str, language: Language) -> Vec<(TokenKind, &str)> {
+    // (kind, start, end) in bytes, neighbouring plain text is merged into one piece
+    let mut pieces: Vec<(TokenKind, usize, usize)> = Vec::new();
+    let bytes = line.as_bytes();
+    let mut start = 0;
+
+    if language == Language::Glsl && line.trim_start().starts_with('#') {
+        pieces.push((TokenKind::Directive, 0, line.find("//").unwrap_or(line.len())));
+        start = pieces[0].2;
+    }
+
+    while start < line.len() {
+        let rest = &line[start..];
+        let (kind, len) = if rest.starts_with("//") {
+            (TokenKind::Comment, rest.len())
+        } else if rest.starts_with("/*") {
+            (TokenKind::Comment, rest.find("*/").map(|end| end + 2).unwrap_or(rest.len()))
+        } else if rest.starts_with('"') {
+            (TokenKind::Str, string_len(rest))
+        } else if language == Language::Rust && (rest.starts_with("#[") || rest.starts_with("#![")) {
+            (TokenKind::Directive, rest.find(']').map(|end| end + 1).unwrap_or(rest.len()))
+        } else if bytes[start].is_ascii_digit() || (bytes[start] == b'.' && !line[..start].ends_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            (TokenKind::Number, number_len(rest))
+        } else if bytes[start].is_ascii_alphabetic() || bytes[start] == b'_' {
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let kind = if language.is_keyword(word) {
+                TokenKind::Keyword
+            } else if language.is_type(word) {
+                TokenKind::Type
+            } else {
+                TokenKind::Plain
+            };
+            (kind, len)
+        } else {
+            (TokenKind::Plain, rest.chars().next().map(char::len_utf8).unwrap_or(1))
+        };
+
+        match pieces.last_mut() {
+            Some((TokenKind::Plain, _, end)) if kind == TokenKind::Plain => *end += len,
+            _ => pieces.push((kind, start, start + len)),
+        }
+        start += len;
+    }
+
+    pieces.into_iter().map(|(kind, start, end)| (kind, &line[start..end])).collect()
+}
+
+// Up to and including the closing quote, skipping escaped ones
+fn string_len(rest: &str) -> usize {
+    let mut escaped = false;
+    for (index, c) in rest.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return index + 1,
+            _ => escaped = false,
+        }
+    }
+    rest.len()
+}
+
+// Digits, suffixes like `f32` or `u` and a decimal point, but not the `..` of a Rust range
+fn number_len(rest: &str) -> usize {
+    let mut len = 0;
+    for (index, c) in rest.char_indices() {
+        let decimal_point = c == '.' && !rest[index + 1..].starts_with('.');
+        if c.is_ascii_alphanumeric() || c == '_' || decimal_point {
+            len = index + 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(line: &str, language: Language) -> Vec<(TokenKind, &str)> {
+        let pieces = highlight(line, language);
+        assert_eq!(pieces.iter().map(|(_, text)| *text).collect::<String>(), line);
+        pieces
+    }
+
+    #[test]
+    fn covers_the_whole_line() {
+        for (line, language) in [
+            ("#define X 1 // c", Language::Glsl),
+            ("for i in 1..=5 {", Language::Rust),
+            (r#"let quote = "\"";"#, Language::Rust),
+            ("  uv *= 3.; /* scale */ uv.x -= .5;", Language::Glsl),
+            ("\tlet unfinished = \"no end", Language::Rust),
+            ("", Language::Glsl),
+            ("é = 1.0;", Language::Glsl),
+        ] {
+            pieces(line, language);
+        }
+    }
+
+    #[test]
+    fn glsl_tokens() {
+        assert_eq!(
+            pieces("#define X 1 // c", Language::Glsl),
+            vec![(TokenKind::Directive, "#define X 1 "), (TokenKind::Comment, "// c")]
+        );
+        assert_eq!(
+            pieces("uniform vec2 uv = .5;", Language::Glsl),
+            vec![
+                (TokenKind::Keyword, "uniform"),
+                (TokenKind::Plain, " "),
+                (TokenKind::Type, "vec2"),
+                (TokenKind::Plain, " uv = "),
+                (TokenKind::Number, ".5"),
+                (TokenKind::Plain, ";"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_tokens() {
+        assert_eq!(
+            pieces("1..=5", Language::Rust),
+            vec![(TokenKind::Number, "1"), (TokenKind::Plain, "..="), (TokenKind::Number, "5")]
+        );
+        assert_eq!(
+            pieces(r#"x("\"")"#, Language::Rust),
+            vec![(TokenKind::Plain, "x("), (TokenKind::Str, r#""\"""#), (TokenKind::Plain, ")")]
+        );
+        assert_eq!(
+            pieces("#[derive(Clone)] let v: Vec<f32>", Language::Rust),
+            vec![
+                (TokenKind::Directive, "#[derive(Clone)]"),
+                (TokenKind::Plain, " "),
+                (TokenKind::Keyword, "let"),
+                (TokenKind::Plain, " v: "),
+                (TokenKind::Type, "Vec"),
+                (TokenKind::Plain, "<"),
+                (TokenKind::Type, "f32"),
+                (TokenKind::Plain, ">"),
+            ]
+        );
+    }
+
+    #[test]
+    fn languages_from_names() {
+        assert_eq!(Language::from_name("basic.frag"), Some(Language::Glsl));
+        assert_eq!(Language::from_name("src/lib.rs"), Some(Language::Rust));
+        assert_eq!(Language::from_name("README.md"), None);
+        assert_eq!(Language::from_name("Makefile"), None);
+    }
+}
@@ -0,0 +1,96 @@
+// Dev mode shader reloading, the browser half of the `shader-watch` binary.
+//
+// Normally shaders are baked in with include_str!, so changing one means a full Trunk rebuild.
+// With the hot-reload feature enabled a tutorial connects to shader-watch over a WebSocket, and
+// whenever a shader changes on disk it fetches the new (already preprocessed) source over HTTP
+// and recompiles in place. If the new source doesn't compile the tutorial keeps drawing with the
+// last program that did.
+
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{MessageEvent, Request, RequestInit, RequestMode, Response, WebSocket};
+
+// Where shader-watch listens by default, see src/bin/shader-watch.rs
+pub const DEFAULT_HTTP_URL: &str = "http://localhost:8090";
+pub const DEFAULT_WS_URL: &str = "ws://localhost:8091";
+
+// Sent instead of a file name when a library snippet changes, as any shader might include it
+pub const ALL_FILES: &str = "*";
+
+pub struct HotReload {
+    socket: WebSocket,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl HotReload {
+    // Connect to shader-watch. `on_change` is called with the name of each file that changes.
+    pub fn connect(url: &str, mut on_change: impl FnMut(String) + 'static) -> Result<HotReload, String> {
+        let socket = WebSocket::new(url).map_err(|err| format!("Unable to connect to {}: {:?}", url, err))?;
+
+        let onmessage = Closure::wrap(Box::new(move |evt: MessageEvent| {
+            if let Some(name) = evt.data().as_string() {
+                on_change(name);
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        socket.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+        Ok(HotReload {
+            socket,
+            _onmessage: onmessage,
+        })
+    }
+}
+
+impl Drop for HotReload {
+    fn drop(&mut self) {
+        // Make sure the socket can't call back into the closure once it's gone
+        self.socket.set_onmessage(None);
+        let _ = self.socket.close();
+    }
+}
+
+// Does a change notification mean the shader called `name` needs fetching again?
+pub fn affects(changed: &str, name: &str) -> bool {
+    changed == ALL_FILES || changed == name
+}
+
+// Fetch the current source of a shader from shader-watch
+pub async fn fetch_source(base_url: &str, name: &str) -> Result<String, String> {
+    let url = format!("{}/{}", base_url, name);
+    let window = web_sys::window().ok_or("No window to fetch from")?;
+
+    let opts = RequestInit::new();
+    opts.set_mode(RequestMode::Cors);
+    let request = Request::new_with_str_and_init(&url, &opts).map_err(|err| format!("{:?}", err))?;
+
+    let response = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(|err| format!("Unable to fetch {}: {:?}", url, err))?;
+    let response: Response = response.dyn_into().map_err(|err| format!("{:?}", err))?;
+    if !response.ok() {
+        return Err(format!("Unable to fetch {}: {}", url, response.status()));
+    }
+
+    let text = response.text().map_err(|err| format!("{:?}", err))?;
+    let text = JsFuture::from(text).await.map_err(|err| format!("{:?}", err))?;
+    text.as_string().ok_or_else(|| format!("{} didn't return any text", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_changed_shader_is_affected() {
+        assert!(affects("swirl.frag", "swirl.frag"));
+        assert!(!affects("swirl.frag", "basic.vert"));
+        assert!(!affects("swirl", "swirl.frag"));
+        assert!(!affects("", "swirl.frag"));
+    }
+
+    #[test]
+    fn library_changes_affect_everything() {
+        assert!(affects(ALL_FILES, "swirl.frag"));
+        assert!(affects(ALL_FILES, "basic.vert"));
+    }
+}
@@ -0,0 +1,70 @@
+// The key bindings (see keyboard.rs), one row per action with the keys that trigger it. "Change"
+// waits for the next key pressed on the button and binds that instead; Escape gives up. Sits
+// inside the ParamPanel like PresetPanel.
+
+use yew::prelude::*;
+
+use crate::keyboard::{key_label, AppAction, KeyBindings};
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct KeyBindingPanelProps {
+    pub bindings: KeyBindings,
+    // The action and the KeyboardEvent.code to bind it to
+    pub on_bind: Callback<(AppAction, String)>,
+}
+
+#[function_component(KeyBindingPanel)]
+pub fn key_binding_panel(props: &KeyBindingPanelProps) -> Html {
+    // The action waiting for a key, if any
+    let listening = use_state(|| None::<AppAction>);
+
+    let rows = AppAction::ALL.iter().map(|action| {
+        let action = *action;
+        let keys = props.bindings.keys_for(action);
+        let keys = if keys.is_empty() { "-".to_string() } else { keys.into_iter().map(key_label).collect::<Vec<_>>().join(", ") };
+        let waiting = *listening == Some(action);
+
+        let onclick = {
+            let listening = listening.clone();
+            Callback::from(move |_| listening.set(Some(action)))
+        };
+        let onkeydown = {
+            let listening = listening.clone();
+            let on_bind = props.on_bind.clone();
+            Callback::from(move |evt: KeyboardEvent| {
+                if *listening != Some(action) {
+                    return;
+                }
+                // Keep the key from the canvas and the page, it's being bound rather than used
+                evt.prevent_default();
+                evt.stop_propagation();
+                if evt.code() != "Escape" {
+                    on_bind.emit((action, evt.code()));
+                }
+                listening.set(None);
+            })
+        };
+        let onblur = {
+            let listening = listening.clone();
+            Callback::from(move |_: FocusEvent| {
+                if *listening == Some(action) {
+                    listening.set(None);
+                }
+            })
+        };
+
+        html! {
+            <div class="param_row">
+                <label>{ action.label() }</label>
+                <span class="key_binding">{ if waiting { "Press a key...".to_string() } else { keys } }</span>
+                <button onclick={onclick} onkeydown={onkeydown} onblur={onblur}>{ "Change" }</button>
+            </div>
+        }
+    });
+
+    html! {
+        <div class="key_binding_panel">
+            { for rows }
+        </div>
+    }
+}
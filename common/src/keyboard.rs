@@ -0,0 +1,316 @@
+// Keyboard input for the tutorials: bindings from keys to things the page does (pause,
+// screenshot, ...) and, for every other key, a Shadertoy style texture so shaders can react.
+//
+// The texture is 256x3, one texel per JavaScript key code: row 0 is 1.0 while the key is held,
+// row 1 is 1.0 only on the frame it went down and row 2 flips each time it's pressed. Shaders
+// read it with `#include "lib/keyboard.glsl"`.
+
+use std::collections::HashMap;
+
+use web_sys::{KeyboardEvent, WebGlRenderingContext as GL, WebGlTexture};
+
+pub const KEY_COUNT: usize = 256;
+const ROWS: usize = 3;
+const DOWN_ROW: usize = 0;
+const PRESSED_ROW: usize = 1;
+const TOGGLE_ROW: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppAction {
+    Pause,
+    Screenshot,
+    NextTutorial,
+    ToggleHud,
+}
+
+impl AppAction {
+    // In the order they're listed on the page
+    pub const ALL: [AppAction; 4] = [AppAction::Pause, AppAction::Screenshot, AppAction::NextTutorial, AppAction::ToggleHud];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AppAction::Pause => "Pause",
+            AppAction::Screenshot => "Screenshot",
+            AppAction::NextTutorial => "Next tutorial",
+            AppAction::ToggleHud => "Frame stats",
+        }
+    }
+}
+
+// The parts of a KeyboardEvent we need, pulled out in the view so messages stay plain data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyInput {
+    // Physical key, e.g. "KeyP" or "Space", used for bindings
+    pub code: String,
+    // JavaScript key code, used for the texture as Shadertoy does
+    pub key_code: u32,
+    pub repeat: bool,
+}
+
+impl From<&KeyboardEvent> for KeyInput {
+    fn from(evt: &KeyboardEvent) -> Self {
+        KeyInput {
+            code: evt.code(),
+            key_code: evt.key_code(),
+            repeat: evt.repeat(),
+        }
+    }
+}
+
+// Which keys trigger which app actions. Bound keys aren't passed on to the shader.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: HashMap<String, AppAction>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::new()
+            .bind("KeyP", AppAction::Pause)
+            .bind("KeyC", AppAction::Screenshot)
+            .bind("KeyN", AppAction::NextTutorial)
+            .bind("KeyH", AppAction::ToggleHud)
+    }
+}
+
+impl KeyBindings {
+    // No bindings at all, every key goes to the shader
+    pub fn new() -> Self {
+        KeyBindings { bindings: HashMap::new() }
+    }
+
+    // `code` is a KeyboardEvent.code, e.g. "KeyP", "Space" or "F2"
+    pub fn bind(mut self, code: &str, action: AppAction) -> Self {
+        self.bindings.insert(code.to_string(), action);
+        self
+    }
+
+    // Make `code` the only key for `action`. If it was bound to something else it isn't now.
+    pub fn rebind(&mut self, action: AppAction, code: &str) {
+        self.bindings.retain(|_, bound| *bound != action);
+        self.bindings.insert(code.to_string(), action);
+    }
+
+    pub fn action(&self, code: &str) -> Option<AppAction> {
+        self.bindings.get(code).copied()
+    }
+
+    // For showing the bindings on the page, sorted so the list doesn't jump around
+    pub fn keys_for(&self, action: AppAction) -> Vec<&str> {
+        let mut keys: Vec<&str> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(code, _)| code.as_str())
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+// How a KeyboardEvent.code is shown on the page: "KeyP" is P and "Digit1" is 1, anything else
+// ("Space", "F2", "ArrowUp") as it is
+pub fn key_label(code: &str) -> &str {
+    code.strip_prefix("Key").or_else(|| code.strip_prefix("Digit")).filter(|rest| !rest.is_empty()).unwrap_or(code)
+}
+
+pub struct KeyboardState {
+    texels: [u8; KEY_COUNT * ROWS],
+    texture: Option<WebGlTexture>,
+    // Whether the texels have changed since the last upload
+    dirty: bool,
+}
+
+impl Default for KeyboardState {
+    fn default() -> Self {
+        KeyboardState {
+            texels: [0; KEY_COUNT * ROWS],
+            texture: None,
+            dirty: true,
+        }
+    }
+}
+
+impl KeyboardState {
+    pub fn key_down(&mut self, key_code: u32) {
+        let Some(key) = index(key_code) else { return };
+        // Ignore auto-repeat, the key is already down
+        if self.texels[DOWN_ROW * KEY_COUNT + key] != 0 {
+            return;
+        }
+        self.texels[DOWN_ROW * KEY_COUNT + key] = 255;
+        self.texels[PRESSED_ROW * KEY_COUNT + key] = 255;
+        self.texels[TOGGLE_ROW * KEY_COUNT + key] ^= 255;
+        self.dirty = true;
+    }
+
+    pub fn key_up(&mut self, key_code: u32) {
+        let Some(key) = index(key_code) else { return };
+        self.texels[DOWN_ROW * KEY_COUNT + key] = 0;
+        self.dirty = true;
+    }
+
+    // When the canvas loses focus we won't hear about keys being let go
+    pub fn release_all(&mut self) {
+        self.texels[..2 * KEY_COUNT].fill(0);
+        self.dirty = true;
+    }
+
+    // Call after drawing, so "pressed" only lasts one frame
+    pub fn end_frame(&mut self) {
+        let pressed = &mut self.texels[PRESSED_ROW * KEY_COUNT..TOGGLE_ROW * KEY_COUNT];
+        if pressed.iter().any(|texel| *texel != 0) {
+            pressed.fill(0);
+            self.dirty = true;
+        }
+    }
+
+    // Bind the keyboard texture to texture `unit`, sending it to the GPU if anything has changed.
+    // Leaves unit 0 active again so other texture code doesn't need to know about this.
+    pub fn upload(&mut self, gl: &GL, unit: u32) {
+        if self.texture.is_none() {
+            self.texture = gl.create_texture();
+            self.dirty = true;
+        }
+
+        gl.active_texture(GL::TEXTURE0 + unit);
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+
+        if self.dirty {
+            let _ = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::LUMINANCE as i32,
+                KEY_COUNT as i32,
+                ROWS as i32,
+                0,
+                GL::LUMINANCE,
+                GL::UNSIGNED_BYTE,
+                Some(&self.texels),
+            );
+            // Not a power of two in height, so no mipmaps or wrapping in WebGL 1
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::NEAREST as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            self.dirty = false;
+        }
+
+        gl.active_texture(GL::TEXTURE0);
+    }
+}
+
+fn index(key_code: u32) -> Option<usize> {
+    let key = key_code as usize;
+    (key < KEY_COUNT).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACE: u32 = 32;
+
+    // (down, pressed, toggled) for a key, as the shader would read them
+    fn key(state: &KeyboardState, key_code: u32) -> (bool, bool, bool) {
+        let texel = |row: usize| state.texels[row * KEY_COUNT + key_code as usize] != 0;
+        (texel(DOWN_ROW), texel(PRESSED_ROW), texel(TOGGLE_ROW))
+    }
+
+    #[test]
+    fn pressed_lasts_one_frame() {
+        let mut state = KeyboardState::default();
+        state.key_down(SPACE);
+        assert_eq!(key(&state, SPACE), (true, true, true));
+        state.end_frame();
+        assert_eq!(key(&state, SPACE), (true, false, true));
+        state.key_up(SPACE);
+        assert_eq!(key(&state, SPACE), (false, false, true));
+    }
+
+    #[test]
+    fn repeats_are_ignored() {
+        let mut state = KeyboardState::default();
+        state.key_down(SPACE);
+        state.end_frame();
+        // Auto-repeat while held, no new press and the toggle doesn't flip back
+        state.key_down(SPACE);
+        assert_eq!(key(&state, SPACE), (true, false, true));
+    }
+
+    #[test]
+    fn toggles_flip_on_each_press() {
+        let mut state = KeyboardState::default();
+        for toggled in [true, false, true] {
+            state.key_down(SPACE);
+            state.key_up(SPACE);
+            state.end_frame();
+            assert_eq!(key(&state, SPACE), (false, false, toggled));
+        }
+    }
+
+    #[test]
+    fn only_uploads_when_something_changed() {
+        let mut state = KeyboardState { dirty: false, ..KeyboardState::default() };
+        state.end_frame();
+        assert!(!state.dirty);
+        state.key_down(SPACE);
+        assert!(state.dirty);
+        state.dirty = false;
+        state.end_frame();
+        assert!(state.dirty);
+    }
+
+    #[test]
+    fn release_all_keeps_toggles() {
+        let mut state = KeyboardState::default();
+        state.key_down(SPACE);
+        state.key_down(65);
+        state.release_all();
+        assert_eq!(key(&state, SPACE), (false, false, true));
+        assert_eq!(key(&state, 65), (false, false, true));
+    }
+
+    #[test]
+    fn ignores_codes_off_the_texture() {
+        let mut state = KeyboardState::default();
+        state.key_down(KEY_COUNT as u32);
+        state.key_up(1000);
+        assert!(state.texels.iter().all(|texel| *texel == 0));
+    }
+
+    #[test]
+    fn default_bindings() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.action("KeyP"), Some(AppAction::Pause));
+        assert_eq!(bindings.action("KeyH"), Some(AppAction::ToggleHud));
+        assert_eq!(bindings.action("Space"), None);
+        for action in AppAction::ALL {
+            assert_eq!(bindings.keys_for(action).len(), 1, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn rebinding() {
+        let mut bindings = KeyBindings::new().bind("KeyP", AppAction::Pause).bind("Space", AppAction::Pause).bind("KeyC", AppAction::Screenshot);
+        assert_eq!(bindings.keys_for(AppAction::Pause), vec!["KeyP", "Space"]);
+
+        // The old keys go back to the shader
+        bindings.rebind(AppAction::Pause, "F2");
+        assert_eq!(bindings.keys_for(AppAction::Pause), vec!["F2"]);
+        assert_eq!(bindings.action("KeyP"), None);
+
+        // Taking another action's key leaves that one without
+        bindings.rebind(AppAction::Pause, "KeyC");
+        assert_eq!(bindings.action("KeyC"), Some(AppAction::Pause));
+        assert!(bindings.keys_for(AppAction::Screenshot).is_empty());
+    }
+
+    #[test]
+    fn key_labels() {
+        assert_eq!(key_label("KeyP"), "P");
+        assert_eq!(key_label("Digit1"), "1");
+        assert_eq!(key_label("Space"), "Space");
+        assert_eq!(key_label("Key"), "Key");
+    }
+}
@@ -0,0 +1,282 @@
+// Lessons: markdown shown beside the canvas, split into steps that each point at some lines of
+// the shader and set it up to show one idea at a time.
+//
+//   # Fractal pattern
+//   ## Folding space
+//   <!-- lines 36-44 -->
+//   <!-- define DEPTH_COUNT 1..20 -->
+//   <!-- uniform u_time 0 -->
+//   Every pass through the loop folds the plane in half...
+//
+// `#` is the lesson's title and each `##` starts a step, anything before the first one is an
+// introduction. The comments are directives and aren't shown: `lines` highlights shader lines
+// (counted from 1 in the file as written), `define` compiles the shader with a value, or a
+// range to pick from with a slider, and `uniform` holds a float uniform at a value. Any other
+// comment is left in the text.
+
+use std::ops::RangeInclusive;
+
+use pulldown_cmark::{html, Options, Parser};
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+
+use crate::program::Defines;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DefineSetting {
+    Fixed(String),
+    // Both ends included, the step starts at the low end
+    Range(i32, i32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LessonStep {
+    pub title: String,
+    // The step's text, already rendered from markdown
+    pub html: String,
+    pub lines: Vec<RangeInclusive<usize>>,
+    pub defines: Vec<(String, DefineSetting)>,
+    pub uniforms: Vec<(String, f32)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lesson {
+    pub title: String,
+    pub steps: Vec<LessonStep>,
+}
+
+impl Lesson {
+    // Errors give the line of the lesson they're on, counted from 1
+    pub fn parse(markdown: &str) -> Result<Lesson, String> {
+        let mut title = String::new();
+        let mut steps = Vec::new();
+        let mut step = LessonStep::new("Introduction");
+        let mut text = String::new();
+        let mut in_code = false;
+        // Whether the first ## has been seen yet
+        let mut started = false;
+
+        for (index, line) in markdown.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code = !in_code;
+            }
+
+            if !in_code {
+                if let Some(heading) = line.strip_prefix("# ") {
+                    title = heading.trim().to_string();
+                    continue;
+                }
+                if let Some(heading) = line.strip_prefix("## ") {
+                    step.finish(&text);
+                    // An introduction with nothing in it isn't a step
+                    if started || !step.is_empty() {
+                        steps.push(step);
+                    }
+                    step = LessonStep::new(heading.trim());
+                    started = true;
+                    text.clear();
+                    continue;
+                }
+                if step.directive(trimmed).map_err(|err| format!("Line {}: {}", index + 1, err))? {
+                    continue;
+                }
+            }
+
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        step.finish(&text);
+        if started || !step.is_empty() {
+            steps.push(step);
+        }
+        if steps.is_empty() {
+            return Err("The lesson is empty".to_string());
+        }
+        Ok(Lesson { title, steps })
+    }
+}
+
+impl LessonStep {
+    fn new(title: &str) -> LessonStep {
+        LessonStep {
+            title: title.to_string(),
+            html: String::new(),
+            lines: Vec::new(),
+            defines: Vec::new(),
+            uniforms: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.html.trim().is_empty() && self.lines.is_empty() && self.defines.is_empty() && self.uniforms.is_empty()
+    }
+
+    fn finish(&mut self, text: &str) {
+        let mut html = String::new();
+        html::push_html(&mut html, Parser::new_ext(text, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH));
+        self.html = html;
+    }
+
+    // Ok(true) if the line was a directive and has been used up
+    fn directive(&mut self, line: &str) -> Result<bool, String> {
+        let Some(inner) = line.strip_prefix("<!--").and_then(|line| line.strip_suffix("-->")) else {
+            return Ok(false);
+        };
+        let inner = inner.trim();
+        let (keyword, args) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+        let args = args.trim();
+
+        match keyword {
+            "lines" => {
+                for part in args.split(',').map(str::trim) {
+                    let range = match part.split_once('-') {
+                        Some((start, end)) => parse_line(start)?..=parse_line(end)?,
+                        None => parse_line(part)?..=parse_line(part)?,
+                    };
+                    if range.is_empty() {
+                        return Err(format!("Line range {} runs backwards", part));
+                    }
+                    self.lines.push(range);
+                }
+            },
+            "define" => {
+                let (name, value) = args.split_once(char::is_whitespace).ok_or("define needs a name and a value")?;
+                let value = value.trim();
+                let setting = match value.split_once("..") {
+                    Some((min, max)) => {
+                        let min = parse_int(min)?;
+                        let max = parse_int(max)?;
+                        if min > max {
+                            return Err(format!("Range {} runs backwards", value));
+                        }
+                        DefineSetting::Range(min, max)
+                    },
+                    None => DefineSetting::Fixed(value.to_string()),
+                };
+                self.defines.push((name.to_string(), setting));
+            },
+            "uniform" => {
+                let (name, value) = args.split_once(char::is_whitespace).ok_or("uniform needs a name and a value")?;
+                let value = value.trim().parse().map_err(|_| format!("{} isn't a number", value.trim()))?;
+                self.uniforms.push((name.to_string(), value));
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn highlights(&self, line: usize) -> bool {
+        self.lines.iter().any(|range| range.contains(&line))
+    }
+
+    // The defines this step starts with, ranges at their lowest
+    pub fn defines(&self) -> Defines {
+        self.defines.iter().fold(Defines::new(), |defines, (name, setting)| match setting {
+            DefineSetting::Fixed(value) => defines.set(name, value),
+            DefineSetting::Range(min, _) => defines.set_int(name, *min),
+        })
+    }
+}
+
+fn parse_line(text: &str) -> Result<usize, String> {
+    match text.trim().parse() {
+        Ok(line) if line > 0 => Ok(line),
+        _ => Err(format!("{} isn't a line number", text.trim())),
+    }
+}
+
+fn parse_int(text: &str) -> Result<i32, String> {
+    text.trim().parse().map_err(|_| format!("{} isn't a whole number", text.trim()))
+}
+
+// Holds a step's uniforms at their values. Upload after the page's own uniforms so these win.
+#[derive(Default)]
+pub struct UniformOverrides {
+    values: Vec<(String, f32)>,
+    locations: Vec<Option<WebGlUniformLocation>>,
+}
+
+impl UniformOverrides {
+    // The program still needs locating after this
+    pub fn set(&mut self, values: &[(String, f32)]) {
+        self.values = values.to_vec();
+        self.locations.clear();
+    }
+
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.locations = self.values.iter().map(|(name, _)| gl.get_uniform_location(program, name)).collect();
+    }
+
+    pub fn upload(&self, gl: &GL) {
+        for ((_, value), location) in self.values.iter().zip(&self.locations) {
+            gl.uniform1f(location.as_ref(), *value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_an_empty_introduction() {
+        let lesson = Lesson::parse("# Title\n\n## First\nText\n<!-- a note -->\n").unwrap();
+        assert_eq!(lesson.title, "Title");
+        assert_eq!(lesson.steps.len(), 1);
+        assert_eq!(lesson.steps[0].title, "First");
+        // Comments that aren't directives stay in the text
+        assert!(lesson.steps[0].html.contains("<!-- a note -->"));
+
+        let lesson = Lesson::parse("# Title\nSome words first\n## First\nText\n").unwrap();
+        assert_eq!(lesson.steps.iter().map(|step| step.title.as_str()).collect::<Vec<_>>(), vec!["Introduction", "First"]);
+        assert!(lesson.steps[0].html.contains("Some words first"));
+    }
+
+    #[test]
+    fn headings_in_code_blocks_are_code() {
+        let lesson = Lesson::parse("## First\n```\n## not a step\n<!-- lines 1 -->\n```\n~~~\n# nor a title\n~~~\n").unwrap();
+        assert_eq!(lesson.title, "");
+        assert_eq!(lesson.steps.len(), 1);
+        assert!(lesson.steps[0].html.contains("## not a step"));
+        assert!(lesson.steps[0].lines.is_empty());
+    }
+
+    #[test]
+    fn reads_directives() {
+        let lesson = Lesson::parse(
+            "## Fold\n<!-- lines 23-24, 44 -->\n<!-- define X 0..20 -->\n<!-- define MODE fast -->\n<!-- uniform u_time 1.5 -->\nText\n",
+        )
+        .unwrap();
+        let step = &lesson.steps[0];
+        assert_eq!(step.lines, vec![23..=24, 44..=44]);
+        assert!(step.highlights(24) && step.highlights(44));
+        assert!(!step.highlights(25) && !step.highlights(22));
+        assert_eq!(
+            step.defines,
+            vec![("X".to_string(), DefineSetting::Range(0, 20)), ("MODE".to_string(), DefineSetting::Fixed("fast".to_string()))]
+        );
+        assert_eq!(step.uniforms, vec![("u_time".to_string(), 1.5)]);
+        assert!(!step.html.contains("<!--"));
+    }
+
+    #[test]
+    fn ranges_start_at_the_low_end() {
+        let lesson = Lesson::parse("## Fold\n<!-- define X 3..20 -->\n<!-- define Y -2 -->\n").unwrap();
+        let defines = lesson.steps[0].defines();
+        assert_eq!(defines.get("X"), Some("3"));
+        assert_eq!(defines.get("Y"), Some("-2"));
+    }
+
+    #[test]
+    fn errors_say_which_line() {
+        let error = |markdown: &str| Lesson::parse(markdown).unwrap_err();
+        assert_eq!(error("## Fold\n\n<!-- define X 20..0 -->\n"), "Line 3: Range 20..0 runs backwards");
+        assert_eq!(error("## Fold\n<!-- define X 0..many -->\n"), "Line 2: many isn't a whole number");
+        assert_eq!(error("## Fold\n<!-- define X -->\n"), "Line 2: define needs a name and a value");
+        assert_eq!(error("## Fold\n<!-- uniform u_time soon -->\n"), "Line 2: soon isn't a number");
+        assert_eq!(error("## Fold\n<!-- lines 44-23 -->\n"), "Line 2: Line range 44-23 runs backwards");
+        assert_eq!(error("## Fold\n<!-- lines 0 -->\n"), "Line 2: 0 isn't a line number");
+        assert_eq!(error("# Just a title\n"), "The lesson is empty");
+    }
+}
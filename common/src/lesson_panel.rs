@@ -0,0 +1,112 @@
+// The lesson beside the canvas: the current step's text, a slider for each define it ranges
+// over, and the shader source with the step's lines highlighted (see lesson.rs).
+
+use std::rc::Rc;
+
+use web_sys::{Element, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::lesson::{DefineSetting, Lesson};
+use crate::program::Defines;
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct LessonPanelProps {
+    pub lesson: Rc<Lesson>,
+    pub step: usize,
+    // What the shader is compiled with now, for the sliders' positions
+    pub defines: Defines,
+    // The shader as written, so the line numbers match the lesson's
+    pub source: AttrValue,
+    pub on_step: Callback<usize>,
+    pub on_define: Callback<(String, i32)>,
+    #[prop_or_default]
+    pub on_close: Option<Callback<()>>,
+}
+
+#[function_component(LessonPanel)]
+pub fn lesson_panel(props: &LessonPanelProps) -> Html {
+    let first_highlight = use_node_ref();
+    {
+        // Bring the step's lines into view whenever the step changes
+        let first_highlight = first_highlight.clone();
+        use_effect_with(props.step, move |_| {
+            if let Some(line) = first_highlight.cast::<Element>() {
+                line.scroll_into_view();
+            }
+        });
+    }
+
+    let Some(step) = props.lesson.steps.get(props.step) else {
+        return html! {};
+    };
+    let count = props.lesson.steps.len();
+
+    let sliders = step.defines.iter().filter_map(|(name, setting)| {
+        let DefineSetting::Range(min, max) = setting else {
+            return None;
+        };
+        let value: i32 = props.defines.get(name).and_then(|value| value.parse().ok()).unwrap_or(*min);
+        let on_define = props.on_define.clone();
+        let define = name.clone();
+        let oninput = Callback::from(move |evt: InputEvent| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            if let Ok(value) = input.value().parse() {
+                on_define.emit((define.clone(), value));
+            }
+        });
+        Some(html! {
+            <div class="param_row">
+                <label>{ name }</label>
+                <input type="range"
+                    min={min.to_string()}
+                    max={max.to_string()}
+                    step="1"
+                    value={value.to_string()}
+                    oninput={oninput}
+                />
+                <span class="param_value">{ value }</span>
+            </div>
+        })
+    });
+
+    let first_line = step.lines.iter().map(|range| *range.start()).min();
+    let lines = props.source.lines().enumerate().map(|(index, line)| {
+        let number = index + 1;
+        let node_ref = if Some(number) == first_line { first_highlight.clone() } else { NodeRef::default() };
+        html! {
+            <div class={classes!("lesson_line", step.highlights(number).then_some("highlight"))} ref={node_ref}>
+                <span class="lesson_line_number">{ number }</span>
+                { line }
+            </div>
+        }
+    });
+
+    let step_to = |index: usize| {
+        let on_step = props.on_step.clone();
+        Callback::from(move |_: MouseEvent| on_step.emit(index))
+    };
+    let close = props.on_close.clone().map(|on_close| {
+        let onclick = Callback::from(move |_: MouseEvent| on_close.emit(()));
+        html! { <button class="lesson_close" onclick={onclick}>{ "Close" }</button> }
+    });
+
+    html! {
+        <div class="lesson_panel">
+            <div class="lesson_header">
+                <h1>{ &props.lesson.title }</h1>
+                { for close }
+            </div>
+            <h2>{ &step.title }</h2>
+            <div class="lesson_text">
+                { Html::from_html_unchecked(AttrValue::from(step.html.clone())) }
+            </div>
+            { for sliders }
+            <div class="lesson_nav">
+                <button disabled={props.step == 0} onclick={step_to(props.step.saturating_sub(1))}>{ "Previous" }</button>
+                <span>{ format!("Step {} of {}", props.step + 1, count) }</span>
+                <button disabled={props.step + 1 >= count} onclick={step_to(props.step + 1)}>{ "Next" }</button>
+            </div>
+            <pre class="lesson_source">{ for lines }</pre>
+        </div>
+    }
+}
@@ -0,0 +1,70 @@
+// Code shared between the tutorials. Each tutorial is still its own crate so it
+// can be built with Trunk on its own, these are just the bits that would
+// otherwise be copy-pasted into every directory.
+
+pub mod camera;
+pub mod color;
+pub mod gesture;
+pub mod library;
+pub mod palette;
+pub mod params;
+pub mod preprocess;
+pub mod program;
+pub mod steps;
+pub mod url_state;
+
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "pointer")]
+pub mod camera_controls;
+#[cfg(feature = "diff")]
+pub mod diff;
+#[cfg(feature = "diff")]
+pub mod diff_view;
+#[cfg(feature = "download")]
+pub mod download;
+#[cfg(feature = "editor")]
+pub mod editor;
+#[cfg(feature = "exercise")]
+pub mod exercise;
+#[cfg(feature = "gradient")]
+pub mod gradient;
+#[cfg(feature = "gradient-editor")]
+pub mod gradient_editor;
+#[cfg(feature = "diff")]
+pub mod highlight;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+#[cfg(feature = "keyboard")]
+pub mod key_binding_panel;
+#[cfg(feature = "keyboard")]
+pub mod keyboard;
+#[cfg(feature = "lesson")]
+pub mod lesson;
+#[cfg(feature = "lesson")]
+pub mod lesson_panel;
+#[cfg(feature = "lut")]
+pub mod lut;
+#[cfg(feature = "noise")]
+pub mod noise;
+#[cfg(feature = "palette")]
+pub mod palette_editor;
+#[cfg(feature = "params")]
+pub mod param_panel;
+#[cfg(feature = "perf")]
+pub mod perf;
+#[cfg(feature = "perf")]
+pub mod perf_hud;
+#[cfg(feature = "pointer")]
+pub mod pointer;
+#[cfg(feature = "presets")]
+pub mod preset_panel;
+#[cfg(feature = "presets")]
+pub mod presets;
+#[cfg(feature = "video")]
+pub mod video;
+
+#[cfg(feature = "validate")]
+pub mod build;
+#[cfg(feature = "validate")]
+pub mod validate;
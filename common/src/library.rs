@@ -0,0 +1,20 @@
+// The shared GLSL snippets from glsl/lib, baked in so they can be included both by the build
+// scripts and at runtime. Shaders pull them in with `#include "lib/tunnel.glsl"`.
+
+const FILES: &[(&str, &str)] = &[
+    ("lib/audio.glsl", include_str!("../glsl/lib/audio.glsl")),
+    ("lib/camera.glsl", include_str!("../glsl/lib/camera.glsl")),
+    ("lib/constants.glsl", include_str!("../glsl/lib/constants.glsl")),
+    ("lib/double_single.glsl", include_str!("../glsl/lib/double_single.glsl")),
+    ("lib/keyboard.glsl", include_str!("../glsl/lib/keyboard.glsl")),
+    ("lib/lut.glsl", include_str!("../glsl/lib/lut.glsl")),
+    ("lib/noise.glsl", include_str!("../glsl/lib/noise.glsl")),
+    ("lib/palette.glsl", include_str!("../glsl/lib/palette.glsl")),
+    ("lib/rotation.glsl", include_str!("../glsl/lib/rotation.glsl")),
+    ("lib/sdf.glsl", include_str!("../glsl/lib/sdf.glsl")),
+    ("lib/tunnel.glsl", include_str!("../glsl/lib/tunnel.glsl")),
+];
+
+pub fn resolve(path: &str) -> Option<&'static str> {
+    FILES.iter().find(|(name, _)| *name == path).map(|(_, source)| *source)
+}
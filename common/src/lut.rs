@@ -0,0 +1,383 @@
+// 3D colour lookup tables from Adobe/Resolve .cube files, for grading the final image.
+//
+//   TITLE "Teal and orange"
+//   LUT_3D_SIZE 17
+//   DOMAIN_MIN 0 0 0
+//   DOMAIN_MAX 1 1 1
+//   0.0000 0.0000 0.0000
+//   ...
+//
+// After the keywords come size^3 output colours, red changing fastest, then green, then blue.
+// For the GPU the cube is cut into its blue slices, laid side by side in a (size * size) x size
+// strip. lib/lut.glsl looks a colour up with the hardware blending red and green inside a slice
+// and the shader blending between the two nearest slices, which is trilinear interpolation.
+//
+// The parser doesn't touch the browser, so it's tested natively against the .cube files in
+// tests/fixtures.
+
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+
+// The limits from the spec
+const MIN_SIZE: usize = 2;
+const MAX_SIZE: usize = 256;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeLut {
+    pub title: String,
+    pub size: usize,
+    // Input colours are mapped from this range onto the cube
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    // size^3 output colours, red fastest
+    pub data: Vec<[f32; 3]>,
+}
+
+impl CubeLut {
+    // Every colour maps to itself
+    pub fn identity(size: usize) -> CubeLut {
+        let size = size.clamp(MIN_SIZE, MAX_SIZE);
+        let step = |i: usize| i as f32 / (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([step(r), step(g), step(b)]);
+                }
+            }
+        }
+        CubeLut { title: String::new(), size, domain_min: [0.0; 3], domain_max: [1.0; 3], data }
+    }
+
+    // Errors say which line (counting from 1) was the problem
+    pub fn parse(text: &str) -> Result<CubeLut, String> {
+        let mut title = String::new();
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut data = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let error = |message: String| format!("Line {}: {}", number, message);
+            // Comments can also follow a data row
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.') {
+                let size = size.ok_or_else(|| error("colour data before LUT_3D_SIZE".to_string()))?;
+                if data.len() == size * size * size {
+                    return Err(error(format!("more than the {} rows a size {} LUT has", size * size * size, size)));
+                }
+                data.push(parse_triple(line).map_err(error)?);
+                continue;
+            }
+            if !data.is_empty() {
+                return Err(error(format!("{} after the colour data", keyword)));
+            }
+
+            match keyword {
+                "TITLE" => {
+                    title = rest.trim_matches('"').to_string();
+                },
+                "LUT_3D_SIZE" => {
+                    if size.is_some() {
+                        return Err(error("LUT_3D_SIZE given twice".to_string()));
+                    }
+                    let value: usize = rest.parse().map_err(|_| error(format!("{:?} isn't a LUT size", rest)))?;
+                    if !(MIN_SIZE..=MAX_SIZE).contains(&value) {
+                        return Err(error(format!("LUT_3D_SIZE {} is outside {} to {}", value, MIN_SIZE, MAX_SIZE)));
+                    }
+                    size = Some(value);
+                },
+                "LUT_1D_SIZE" => {
+                    return Err(error("1D LUTs aren't supported, only 3D".to_string()));
+                },
+                "DOMAIN_MIN" => domain_min = parse_triple(rest).map_err(error)?,
+                "DOMAIN_MAX" => domain_max = parse_triple(rest).map_err(error)?,
+                // Resolve's older way of giving the domain, the same for every channel
+                "LUT_3D_INPUT_RANGE" => {
+                    let values = parse_numbers(rest).map_err(error)?;
+                    if values.len() != 2 {
+                        return Err(error(format!("LUT_3D_INPUT_RANGE needs 2 numbers, not {}", values.len())));
+                    }
+                    domain_min = [values[0]; 3];
+                    domain_max = [values[1]; 3];
+                },
+                // e.g. Resolve's LUT_IN_VIDEO_RANGE, which only matter to video software
+                _ if keyword.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') => {},
+                _ => return Err(error(format!("{:?} isn't a .cube keyword or colour", keyword))),
+            }
+        }
+
+        let size = size.ok_or("No LUT_3D_SIZE, not a 3D .cube file")?;
+        if data.len() != size * size * size {
+            return Err(format!("A size {} LUT needs {} rows, found {}", size, size * size * size, data.len()));
+        }
+        if (0..3).any(|i| domain_min[i] >= domain_max[i]) {
+            return Err(format!("DOMAIN_MIN {:?} has to be below DOMAIN_MAX {:?}", domain_min, domain_max));
+        }
+        Ok(CubeLut { title, size, domain_min, domain_max, data })
+    }
+
+    fn at(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[r + self.size * (g + self.size * b)]
+    }
+
+    // The graded colour, blended between the 8 nearest entries like the shader does
+    pub fn sample(&self, color: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let scaled: [f32; 3] =
+            [0, 1, 2].map(|i| ((color[i] - self.domain_min[i]) / (self.domain_max[i] - self.domain_min[i])).clamp(0.0, 1.0) * last);
+        let low = scaled.map(|c| (c.floor() as usize).min(self.size - 2));
+        let [fr, fg, fb] = [0, 1, 2].map(|i| scaled[i] - low[i] as f32);
+
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        let [r, g, b] = low;
+        let slice = |b: usize| {
+            let bottom = lerp(self.at(r, g, b), self.at(r + 1, g, b), fr);
+            let top = lerp(self.at(r, g + 1, b), self.at(r + 1, g + 1, b), fr);
+            lerp(bottom, top, fg)
+        };
+        lerp(slice(b), slice(b + 1), fb)
+    }
+
+    // Width and height of the packed texture
+    pub fn strip_size(&self) -> (usize, usize) {
+        (self.size * self.size, self.size)
+    }
+
+    // RGBA bytes, blue slice n across from x = n * size, red along each slice and green up it
+    pub fn strip(&self) -> Vec<u8> {
+        let (width, height) = self.strip_size();
+        let mut bytes = vec![255; width * height * 4];
+        for b in 0..self.size {
+            for g in 0..self.size {
+                for r in 0..self.size {
+                    let texel = (g * width + b * self.size + r) * 4;
+                    let color = self.at(r, g, b).map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                    bytes[texel..texel + 3].copy_from_slice(&color);
+                }
+            }
+        }
+        bytes
+    }
+}
+
+fn parse_numbers(text: &str) -> Result<Vec<f32>, String> {
+    text.split_whitespace()
+        .map(|word| match word.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("{:?} isn't a number", word)),
+        })
+        .collect()
+}
+
+fn parse_triple(text: &str) -> Result<[f32; 3], String> {
+    let values = parse_numbers(text)?;
+    match values[..] {
+        [r, g, b] => Ok([r, g, b]),
+        _ => Err(format!("expected 3 numbers, found {}", values.len())),
+    }
+}
+
+// The LUT strip as `u_lut`, with `u_lut_size` and the domain for lib/lut.glsl
+#[derive(Default)]
+pub struct LutTexture {
+    texture: Option<WebGlTexture>,
+    size: usize,
+    domain: ([f32; 3], [f32; 3]),
+    lut_location: Option<WebGlUniformLocation>,
+    size_location: Option<WebGlUniformLocation>,
+    domain_min_location: Option<WebGlUniformLocation>,
+    domain_max_location: Option<WebGlUniformLocation>,
+}
+
+impl LutTexture {
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.lut_location = gl.get_uniform_location(program, "u_lut");
+        self.size_location = gl.get_uniform_location(program, "u_lut_size");
+        self.domain_min_location = gl.get_uniform_location(program, "u_lut_domain_min");
+        self.domain_max_location = gl.get_uniform_location(program, "u_lut_domain_max");
+    }
+
+    // Only needed when the LUT changes. Fails if the strip is wider than the GPU allows, 65 and
+    // up is too big for some. Leaves TEXTURE0 active again afterwards.
+    pub fn upload(&mut self, gl: &GL, lut: &CubeLut, unit: u32) -> Result<(), String> {
+        let (width, height) = lut.strip_size();
+        let max = gl.get_parameter(GL::MAX_TEXTURE_SIZE).ok().and_then(|max| max.as_f64()).unwrap_or(2048.0) as usize;
+        if width > max {
+            return Err(format!("A size {} LUT needs a {} pixel wide texture, this GPU allows {}", lut.size, width, max));
+        }
+
+        if self.texture.is_none() {
+            self.texture = gl.create_texture();
+        }
+        gl.active_texture(GL::TEXTURE0 + unit);
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+        let result = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            GL::TEXTURE_2D,
+            0,
+            GL::RGBA as i32,
+            width as i32,
+            height as i32,
+            0,
+            GL::RGBA,
+            GL::UNSIGNED_BYTE,
+            Some(&lut.strip()),
+        );
+        // Not a power of two, so no mipmaps or wrapping in WebGL 1
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+        gl.active_texture(GL::TEXTURE0);
+        result.map_err(|err| format!("Unable to upload the LUT: {:?}", err))?;
+
+        self.size = lut.size;
+        self.domain = (lut.domain_min, lut.domain_max);
+        Ok(())
+    }
+
+    // Each frame, with the grading program in use. Leaves TEXTURE0 active again afterwards.
+    pub fn bind(&self, gl: &GL, unit: u32) {
+        gl.active_texture(GL::TEXTURE0 + unit);
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+        gl.active_texture(GL::TEXTURE0);
+
+        let ([min_r, min_g, min_b], [max_r, max_g, max_b]) = self.domain;
+        gl.uniform1i(self.lut_location.as_ref(), unit as i32);
+        gl.uniform1f(self.size_location.as_ref(), self.size as f32);
+        gl.uniform3f(self.domain_min_location.as_ref(), min_r, min_g, min_b);
+        gl.uniform3f(self.domain_max_location.as_ref(), max_r, max_g, max_b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err))
+    }
+
+    fn parse_fixture(name: &str) -> Result<CubeLut, String> {
+        CubeLut::parse(&fixture(name))
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!((0..3).all(|i| (a[i] - b[i]).abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn parses_identity() {
+        let lut = parse_fixture("identity_2.cube").unwrap();
+        assert_eq!(lut.title, "Identity");
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.domain_min, [0.0; 3]);
+        assert_eq!(lut.domain_max, [1.0; 3]);
+        assert_eq!(lut, CubeLut { title: "Identity".to_string(), ..CubeLut::identity(2) });
+        for color in [[0.0, 0.0, 0.0], [0.25, 0.5, 0.75], [1.0, 0.1, 0.9]] {
+            assert_close(lut.sample(color), color);
+        }
+    }
+
+    #[test]
+    fn reads_red_fastest() {
+        // Swaps red and blue, so the order of the rows matters
+        let lut = parse_fixture("swap_red_blue.cube").unwrap();
+        assert_eq!(lut.size, 3);
+        assert_close(lut.sample([1.0, 0.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert_close(lut.sample([0.2, 0.6, 0.9]), [0.9, 0.6, 0.2]);
+    }
+
+    #[test]
+    fn reads_domain() {
+        let lut = parse_fixture("domain.cube").unwrap();
+        assert_eq!(lut.domain_min, [-0.5, 0.0, 0.0]);
+        assert_eq!(lut.domain_max, [1.5, 2.0, 1.0]);
+        // Halfway through the domain is halfway through the cube
+        assert_close(lut.sample([0.5, 1.0, 0.5]), [0.5, 0.5, 0.5]);
+        // Outside it clamps
+        assert_close(lut.sample([-3.0, 5.0, 0.0]), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn reads_resolve_input_range() {
+        let lut = parse_fixture("resolve_input_range.cube").unwrap();
+        assert_eq!(lut.title, "");
+        assert_eq!(lut.domain_min, [0.0; 3]);
+        assert_eq!(lut.domain_max, [4.0; 3]);
+        assert_close(lut.sample([2.0, 2.0, 2.0]), [0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn handles_comments_blank_lines_and_crlf() {
+        let lut = parse_fixture("comments_crlf.cube").unwrap();
+        assert_eq!(lut.title, "With comments");
+        assert_eq!(lut, CubeLut { title: "With comments".to_string(), ..CubeLut::identity(2) });
+    }
+
+    #[test]
+    fn interpolates_trilinearly() {
+        // Every entry is black except white at (1, 1, 1), so the middle of the cube gets an
+        // eighth of it
+        let mut lut = CubeLut::identity(2);
+        lut.data = vec![[0.0; 3]; 8];
+        lut.data[7] = [1.0; 3];
+        assert_close(lut.sample([0.5, 0.5, 0.5]), [0.125; 3]);
+        assert_close(lut.sample([1.0, 1.0, 0.5]), [0.5; 3]);
+    }
+
+    #[test]
+    fn packs_blue_slices_side_by_side() {
+        let lut = parse_fixture("swap_red_blue.cube").unwrap();
+        assert_eq!(lut.strip_size(), (9, 3));
+        let strip = lut.strip();
+        assert_eq!(strip.len(), 9 * 3 * 4);
+        let texel = |x: usize, y: usize| &strip[(y * 9 + x) * 4..(y * 9 + x) * 4 + 4];
+        // Input (r, g, b) = (2, 1, 0) is at x = 0 * 3 + 2, y = 1 and holds (b, g, r)
+        assert_eq!(texel(2, 1), &[0, 128, 255, 255]);
+        // (0, 0, 2) at x = 6, y = 0
+        assert_eq!(texel(6, 0), &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn identity_sizes_are_clamped() {
+        assert_eq!(CubeLut::identity(1).size, MIN_SIZE);
+        assert_eq!(CubeLut::identity(17).data.len(), 17 * 17 * 17);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let cases = [
+            ("missing_size.cube", "No LUT_3D_SIZE"),
+            ("too_few_rows.cube", "needs 8 rows, found 7"),
+            ("too_many_rows.cube", "Line 12:"),
+            ("bad_number.cube", "Line 5: \"0.x\" isn't a number"),
+            ("two_columns.cube", "Line 4: expected 3 numbers, found 2"),
+            ("lut_1d.cube", "1D LUTs"),
+            ("size_too_small.cube", "LUT_3D_SIZE 1"),
+            ("data_before_size.cube", "Line 2: colour data before LUT_3D_SIZE"),
+            ("bad_domain.cube", "DOMAIN_MIN"),
+            ("keyword_after_data.cube", "Line 6: DOMAIN_MAX after the colour data"),
+            ("not_a_cube.cube", "Line 1:"),
+        ];
+        for (name, message) in cases {
+            match parse_fixture(name) {
+                Ok(_) => panic!("{} parsed", name),
+                Err(err) => assert!(err.contains(message), "{}: {:?} doesn't mention {:?}", name, err, message),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_empty_text() {
+        assert!(CubeLut::parse("").is_err());
+        assert!(CubeLut::parse("# only a comment\n").is_err());
+    }
+}
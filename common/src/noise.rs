@@ -0,0 +1,448 @@
+// Noise textures made from a seed, instead of shipping a noise.png with every tutorial.
+//
+//   NoiseTexture::new(NoiseKind::Perlin, seed).size(512).cells(8).octaves(5).upload(gl, &texture);
+//
+// `cells` is how many lattice cells fit across the texture, so how big the blobs are. More than
+// one octave gives fractal Brownian motion: each octave adds the same noise at twice the
+// frequency and half the strength. Value, Perlin and Worley noise wrap round at the edges so the
+// texture tiles; simplex noise's lattice is skewed, so it doesn't.
+//
+// Everything comes from an integer hash of the lattice point and the seed, with no trig, so the
+// same seed gives the same bytes on every machine.
+//
+// SeededNoise is a tutorial's texNoise and the seed it's made from. The seed is a control on the
+// parameter panel like the shader's own parameters, but it's the page that makes the texture, so
+// it isn't a uniform.
+
+use web_sys::{WebGlRenderingContext as GL, WebGlTexture};
+
+use crate::params::{Param, ParamKind, ParamValue};
+
+// What the tutorials start with, and what the gallery's previews use
+pub const DEFAULT_SEED: u32 = 1;
+// The seed slider goes from 0 to this
+const MAX_SEED: u32 = 99;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    // Random values at the lattice points, smoothly blended
+    Value,
+    // Random gradients at the lattice points
+    Perlin,
+    // Gradients on a triangular lattice, fewer straight line artefacts than Perlin
+    Simplex,
+    // Distance to the nearest of one random point per cell
+    Worley,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseTexture {
+    kind: NoiseKind,
+    seed: u32,
+    size: usize,
+    cells: u32,
+    octaves: u32,
+}
+
+impl NoiseTexture {
+    pub fn new(kind: NoiseKind, seed: u32) -> NoiseTexture {
+        NoiseTexture { kind, seed, size: 256, cells: 8, octaves: 1 }
+    }
+
+    // What the tutorials sample as texNoise: smooth, cloudy and tileable like the noise.png they
+    // used to ship with
+    pub fn tutorial_default(seed: u32) -> NoiseTexture {
+        NoiseTexture::new(NoiseKind::Perlin, seed).size(512).cells(4).octaves(5)
+    }
+
+    // Width and height in pixels. WebGL 1 only makes mipmaps for powers of two.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size.next_power_of_two();
+        self
+    }
+
+    pub fn cells(mut self, cells: u32) -> Self {
+        self.cells = cells.max(1);
+        self
+    }
+
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    // Noise at (u, v) in texture coordinates, from 0 to 1
+    pub fn sample(&self, u: f64, v: f64) -> f64 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut amplitudes = 0.0;
+        for octave in 0..self.octaves {
+            let cells = self.cells << octave;
+            // A different seed per octave so they don't line up at the origin
+            let seed = hash(octave, 0, self.seed);
+            let value = match self.kind {
+                NoiseKind::Value => value(u * cells as f64, v * cells as f64, cells, seed),
+                NoiseKind::Perlin => perlin(u * cells as f64, v * cells as f64, cells, seed),
+                NoiseKind::Simplex => simplex(u * cells as f64, v * cells as f64, seed),
+                NoiseKind::Worley => worley(u * cells as f64, v * cells as f64, cells, seed),
+            };
+            total += amplitude * (value - 0.5);
+            amplitudes += amplitude;
+            amplitude *= 0.5;
+        }
+        (0.5 + total / amplitudes).clamp(0.0, 1.0)
+    }
+
+    // One byte per pixel, row by row, sampled at the middle of each pixel
+    pub fn generate(&self) -> Vec<u8> {
+        let size = self.size as f64;
+        let mut pixels = Vec::with_capacity(self.size * self.size);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let noise = self.sample((x as f64 + 0.5) / size, (y as f64 + 0.5) / size);
+                pixels.push((noise * 255.0).round() as u8);
+            }
+        }
+        pixels
+    }
+
+    // Fill `texture` on the active unit, grey in red, green and blue like the old noise.png
+    pub fn upload(&self, gl: &GL, texture: &WebGlTexture) {
+        gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+        let _ = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            GL::TEXTURE_2D,
+            0,
+            GL::LUMINANCE as i32,
+            self.size as i32,
+            self.size as i32,
+            0,
+            GL::LUMINANCE,
+            GL::UNSIGNED_BYTE,
+            Some(&self.generate()),
+        );
+        gl.generate_mipmap(GL::TEXTURE_2D);
+    }
+}
+
+// A tutorial's texNoise, made again whenever its seed changes
+pub struct SeededNoise {
+    seed: u32,
+    texture: Option<WebGlTexture>,
+    // The seed the texture was last made with
+    made_with: Option<u32>,
+}
+
+impl SeededNoise {
+    // The name it goes by in the panel, presets and links, which is what the uniform was called
+    pub const PARAM: &'static str = "noiseSeed";
+
+    pub fn new() -> SeededNoise {
+        SeededNoise { seed: DEFAULT_SEED, texture: None, made_with: None }
+    }
+
+    // The slider for the parameter panel
+    pub fn param() -> Param {
+        Param {
+            name: SeededNoise::PARAM.to_string(),
+            label: "Noise seed".to_string(),
+            kind: ParamKind::Slider { min: 0.0, max: MAX_SEED as f32, step: 1.0 },
+            default: ParamValue::Float(DEFAULT_SEED as f32),
+            previous_names: Vec::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn value(&self) -> ParamValue {
+        ParamValue::Float(self.seed as f32)
+    }
+
+    // From the panel, a preset or a link, so rounded and kept on the slider. Anything that isn't
+    // a number is ignored.
+    pub fn set(&mut self, value: ParamValue) {
+        if let ParamValue::Float(seed) = value {
+            if seed.is_finite() {
+                self.seed = seed.round().clamp(0.0, MAX_SEED as f32) as u32;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.seed = DEFAULT_SEED;
+    }
+
+    // For a new GL context, the texture is made again on the next bind
+    pub fn forget(&mut self) {
+        self.texture = None;
+        self.made_with = None;
+    }
+
+    // Bind the texture on the active unit, making it first if the seed has changed
+    pub fn bind(&mut self, gl: &GL) {
+        if self.texture.is_none() {
+            self.texture = gl.create_texture();
+            self.made_with = None;
+        }
+        let Some(texture) = &self.texture else {
+            return;
+        };
+        if self.made_with == Some(self.seed) {
+            gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+        } else {
+            NoiseTexture::tutorial_default(self.seed).upload(gl, texture);
+            self.made_with = Some(self.seed);
+        }
+    }
+}
+
+impl Default for SeededNoise {
+    fn default() -> Self {
+        SeededNoise::new()
+    }
+}
+
+// Chris Wellons' lowbias32, see https://nullprogram.com/blog/2018/07/31/
+fn mix(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+fn hash(x: u32, y: u32, seed: u32) -> u32 {
+    mix(x ^ mix(y ^ mix(seed)))
+}
+
+// Hash of a lattice point, wrapped round `period` cells so the noise tiles
+fn lattice_hash(x: i64, y: i64, period: u32, seed: u32) -> u32 {
+    let period = period as i64;
+    hash(x.rem_euclid(period) as u32, y.rem_euclid(period) as u32, seed)
+}
+
+fn unit(hash: u32) -> f64 {
+    hash as f64 / u32::MAX as f64
+}
+
+// 0 to 1 with flat ends, so there are no creases at the cell edges
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn value(x: f64, y: f64, period: u32, seed: u32) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let corner = |dx, dy| unit(lattice_hash(ix + dx, iy + dy, period, seed));
+    let (tx, ty) = (fade(x - x0), fade(y - y0));
+    lerp(lerp(corner(0, 0), corner(1, 0), tx), lerp(corner(0, 1), corner(1, 1), tx), ty)
+}
+
+// Eight unit directions, so picking one needs no trig
+const GRADIENTS: [(f64, f64); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (std::f64::consts::FRAC_1_SQRT_2, std::f64::consts::FRAC_1_SQRT_2),
+    (-std::f64::consts::FRAC_1_SQRT_2, std::f64::consts::FRAC_1_SQRT_2),
+    (std::f64::consts::FRAC_1_SQRT_2, -std::f64::consts::FRAC_1_SQRT_2),
+    (-std::f64::consts::FRAC_1_SQRT_2, -std::f64::consts::FRAC_1_SQRT_2),
+];
+
+fn gradient(hash: u32, dx: f64, dy: f64) -> f64 {
+    let (gx, gy) = GRADIENTS[(hash >> 29) as usize];
+    gx * dx + gy * dy
+}
+
+fn perlin(x: f64, y: f64, period: u32, seed: u32) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let (fx, fy) = (x - x0, y - y0);
+    let corner = |dx: i64, dy: i64| gradient(lattice_hash(ix + dx, iy + dy, period, seed), fx - dx as f64, fy - dy as f64);
+    let (tx, ty) = (fade(fx), fade(fy));
+    let noise = lerp(lerp(corner(0, 0), corner(1, 0), tx), lerp(corner(0, 1), corner(1, 1), tx), ty);
+    // Unit gradients keep it within +-1/sqrt(2)
+    0.5 + noise * std::f64::consts::FRAC_1_SQRT_2
+}
+
+// Stefan Gustavson's 2D simplex noise, see "Simplex noise demystified"
+fn simplex(x: f64, y: f64, seed: u32) -> f64 {
+    // Skew to a square grid to find the cell, and back again
+    let skew = 0.5 * (3.0f64.sqrt() - 1.0);
+    let unskew = (3.0 - 3.0f64.sqrt()) / 6.0;
+
+    let s = (x + y) * skew;
+    let (i, j) = ((x + s).floor(), (y + s).floor());
+    let t = (i + j) * unskew;
+    let (x0, y0) = (x - (i - t), y - (j - t));
+
+    // Which of the two triangles in the cell
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+    let corners = [
+        (0, 0, x0, y0),
+        (i1, j1, x0 - i1 as f64 + unskew, y0 - j1 as f64 + unskew),
+        (1, 1, x0 - 1.0 + 2.0 * unskew, y0 - 1.0 + 2.0 * unskew),
+    ];
+
+    let noise: f64 = corners
+        .iter()
+        .map(|&(di, dj, dx, dy)| {
+            let falloff = 0.5 - dx * dx - dy * dy;
+            if falloff <= 0.0 {
+                return 0.0;
+            }
+            let hash = hash((i as i64 + di) as u32, (j as i64 + dj) as u32, seed);
+            falloff.powi(4) * gradient(hash, dx, dy)
+        })
+        .sum();
+    // Scaled to about +-1
+    (0.5 + 35.0 * noise).clamp(0.0, 1.0)
+}
+
+fn worley(x: f64, y: f64, period: u32, seed: u32) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let mut nearest = f64::MAX;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let hash = lattice_hash(ix + dx, iy + dy, period, seed);
+            // The cell's point, from the two halves of the hash
+            let px = (x0 + dx as f64) + (hash & 0xffff) as f64 / 65535.0;
+            let py = (y0 + dy as f64) + (hash >> 16) as f64 / 65535.0;
+            nearest = nearest.min((px - x).hypot(py - y));
+        }
+    }
+    nearest.min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FNV-1a, to compare whole textures against known values
+    fn fnv(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+    }
+
+    const KINDS: [NoiseKind; 4] = [NoiseKind::Value, NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley];
+
+    #[test]
+    fn hash_is_stable() {
+        assert_eq!(mix(0), 0);
+        assert_eq!(mix(1), 0x688990c0);
+        assert_eq!(hash(1, 2, 3), 0xacfd7e59);
+        assert_ne!(hash(1, 2, 3), hash(2, 1, 3));
+        assert_ne!(hash(1, 2, 3), hash(1, 2, 4));
+    }
+
+    #[test]
+    fn textures_match_known_hashes() {
+        let hashes: Vec<u64> = KINDS.iter().map(|kind| fnv(&NoiseTexture::new(*kind, 1).size(64).generate())).collect();
+        assert_eq!(hashes, vec![11642321229938550949, 14096935740624268610, 10672324852583538962, 4982408070456927596]);
+        let fbm = NoiseTexture::new(NoiseKind::Perlin, 1).size(64).cells(4).octaves(5).generate();
+        assert_eq!(fnv(&fbm), 11413434457508613761);
+    }
+
+    #[test]
+    fn same_seed_same_texture() {
+        for kind in KINDS {
+            let a = NoiseTexture::new(kind, 7).size(32).octaves(3).generate();
+            let b = NoiseTexture::new(kind, 7).size(32).octaves(3).generate();
+            let c = NoiseTexture::new(kind, 8).size(32).octaves(3).generate();
+            assert_eq!(a, b, "{:?}", kind);
+            assert_ne!(a, c, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn seeds_stay_on_the_slider() {
+        let mut noise = SeededNoise::new();
+        assert_eq!(noise.seed(), DEFAULT_SEED);
+        noise.set(ParamValue::Float(41.6));
+        assert_eq!(noise.value(), ParamValue::Float(42.0));
+        noise.set(ParamValue::Float(-3.0));
+        assert_eq!(noise.seed(), 0);
+        noise.set(ParamValue::Float(1000.0));
+        assert_eq!(noise.seed(), MAX_SEED);
+        // Not a seed
+        noise.set(ParamValue::Float(f32::NAN));
+        noise.set(ParamValue::Color([5.0, 5.0, 5.0]));
+        assert_eq!(noise.seed(), MAX_SEED);
+        noise.reset();
+        assert_eq!(noise.value(), SeededNoise::param().default);
+    }
+
+    #[test]
+    fn sizes_are_powers_of_two() {
+        assert_eq!(NoiseTexture::new(NoiseKind::Value, 0).size(100).generate().len(), 128 * 128);
+        assert_eq!(NoiseTexture::new(NoiseKind::Value, 0).size(64).generate().len(), 64 * 64);
+    }
+
+    #[test]
+    fn uses_the_whole_range() {
+        for kind in KINDS {
+            let pixels = NoiseTexture::new(kind, 3).size(128).generate();
+            let mean = pixels.iter().map(|p| *p as f64).sum::<f64>() / pixels.len() as f64;
+            let (min, max) = (*pixels.iter().min().unwrap(), *pixels.iter().max().unwrap());
+            assert!((60.0..190.0).contains(&mean), "{:?} mean {}", kind, mean);
+            assert!(max - min > 128, "{:?} {}..{}", kind, min, max);
+        }
+    }
+
+    #[test]
+    fn tiles_except_simplex() {
+        for kind in [NoiseKind::Value, NoiseKind::Perlin, NoiseKind::Worley] {
+            let noise = NoiseTexture::new(kind, 5).cells(6).octaves(4);
+            for (u, v) in [(0.1, 0.2), (0.73, 0.05), (0.5, 0.999)] {
+                assert!((noise.sample(u, v) - noise.sample(u + 1.0, v)).abs() < 1e-9, "{:?}", kind);
+                assert!((noise.sample(u, v) - noise.sample(u, v - 1.0)).abs() < 1e-9, "{:?}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn value_noise_hits_the_lattice_values() {
+        let noise = NoiseTexture::new(NoiseKind::Value, 9).cells(4);
+        let seed = hash(0, 0, 9);
+        assert!((noise.sample(0.25, 0.5) - unit(hash(1, 2, seed))).abs() < 1e-12);
+    }
+
+    #[test]
+    fn perlin_is_zero_at_the_lattice() {
+        let noise = NoiseTexture::new(NoiseKind::Perlin, 2).cells(8);
+        for (u, v) in [(0.0, 0.0), (0.125, 0.5), (0.875, 0.25)] {
+            assert!((noise.sample(u, v) - 0.5).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn worley_is_zero_at_the_points() {
+        let seed = hash(0, 0, 4);
+        let point = hash(3, 1, seed);
+        let (u, v) = ((3.0 + (point & 0xffff) as f64 / 65535.0) / 8.0, (1.0 + (point >> 16) as f64 / 65535.0) / 8.0);
+        let noise = NoiseTexture::new(NoiseKind::Worley, 4).cells(8);
+        assert!(noise.sample(u, v) < 1e-9);
+        assert!(noise.sample(u + 0.02, v) > 0.1);
+    }
+
+    #[test]
+    fn more_octaves_add_detail() {
+        // Neighbouring pixels differ more once finer octaves are added
+        let roughness = |octaves| {
+            let pixels = NoiseTexture::new(NoiseKind::Perlin, 1).size(128).cells(4).octaves(octaves).generate();
+            pixels.windows(2).map(|pair| (pair[0] as i32 - pair[1] as i32).abs()).sum::<i32>()
+        };
+        assert!(roughness(5) > roughness(1));
+    }
+}
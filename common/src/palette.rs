@@ -0,0 +1,243 @@
+// Inigo Quilez's cosine palettes (https://iquilezles.org/articles/palettes/) on the Rust side:
+//
+//   color(t) = a + b * cos(2 pi (c t + d))
+//
+// a is the brightness, b the contrast, c how many times each channel goes round as t goes from
+// 0 to 1, and d where each channel starts. lib/palette.glsl has the same function, and
+// `palette(t)` there reads the coefficients from the uniforms PaletteUniforms sets.
+
+use std::f32::consts::TAU;
+
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub a: [f32; 3],
+    pub b: [f32; 3],
+    pub c: [f32; 3],
+    pub d: [f32; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        PRESETS[0].1
+    }
+}
+
+// The examples from the article, plus a few more
+pub const PRESETS: &[(&str, Palette)] = &[
+    ("Rainbow", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [1.0, 1.0, 1.0], d: [0.0, 0.33, 0.67] }),
+    ("Sunset", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [1.0, 1.0, 1.0], d: [0.0, 0.1, 0.2] }),
+    ("Rose", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [1.0, 1.0, 1.0], d: [0.3, 0.2, 0.2] }),
+    ("Lime", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [1.0, 1.0, 0.5], d: [0.8, 0.9, 0.3] }),
+    ("Ocean", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [1.0, 0.7, 0.4], d: [0.0, 0.15, 0.2] }),
+    ("Candy", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [2.0, 1.0, 0.0], d: [0.5, 0.2, 0.25] }),
+    ("Desert", Palette { a: [0.8, 0.5, 0.4], b: [0.2, 0.4, 0.2], c: [2.0, 1.0, 1.0], d: [0.0, 0.25, 0.25] }),
+    ("Neon", Palette { a: [0.5, 0.5, 0.5], b: [0.6, 0.6, 0.6], c: [1.0, 1.0, 1.0], d: [0.6, 0.9, 0.3] }),
+    ("Ember", Palette { a: [0.6, 0.3, 0.1], b: [0.4, 0.3, 0.1], c: [1.0, 1.0, 1.0], d: [0.0, 0.05, 0.1] }),
+    ("Greyscale", Palette { a: [0.5, 0.5, 0.5], b: [0.5, 0.5, 0.5], c: [1.0, 1.0, 1.0], d: [0.0, 0.0, 0.0] }),
+];
+
+pub fn preset(name: &str) -> Option<Palette> {
+    PRESETS.iter().find(|(preset, _)| *preset == name).map(|(_, palette)| *palette)
+}
+
+impl Palette {
+    // Clamped to 0..1, as it would be on screen
+    pub fn color(&self, t: f32) -> [f32; 3] {
+        let channel = |i: usize| (self.a[i] + self.b[i] * (TAU * (self.c[i] * t + self.d[i])).cos()).clamp(0.0, 1.0);
+        [channel(0), channel(1), channel(2)]
+    }
+
+    // Blends the coefficients rather than the colours, so halfway between two palettes is still
+    // a cosine palette
+    pub fn lerp(&self, other: &Palette, t: f32) -> Palette {
+        let mix = |a: [f32; 3], b: [f32; 3]| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        Palette { a: mix(self.a, other.a), b: mix(self.b, other.b), c: mix(self.c, other.c), d: mix(self.d, other.d) }
+    }
+
+    // The coefficients by index, 0 to 3 for a to d, for editing one at a time
+    pub fn coefficient(&self, index: usize) -> [f32; 3] {
+        [self.a, self.b, self.c, self.d][index]
+    }
+
+    // Anything past 3 isn't a coefficient and is ignored
+    pub fn set_coefficient(&mut self, index: usize, value: [f32; 3]) {
+        match index {
+            0 => self.a = value,
+            1 => self.b = value,
+            2 => self.c = value,
+            3 => self.d = value,
+            _ => {},
+        }
+    }
+
+    // A CSS linear-gradient through `stops` samples, to show the palette on the page
+    pub fn css_gradient(&self, stops: usize) -> String {
+        let stops = stops.max(2);
+        let colors: Vec<String> = (0..stops)
+            .map(|i| {
+                let [r, g, b] = self.color(i as f32 / (stops - 1) as f32);
+                format!("rgb({}, {}, {})", (r * 255.0).round(), (g * 255.0).round(), (b * 255.0).round())
+            })
+            .collect();
+        format!("linear-gradient(to right, {})", colors.join(", "))
+    }
+}
+
+// Moving from one palette to another over a few seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PaletteFade {
+    from: Palette,
+    to: Palette,
+    // Seconds, on whatever clock is passed to at()
+    start: f64,
+    duration: f64,
+}
+
+impl PaletteFade {
+    // Sitting on `palette`
+    pub fn new(palette: Palette) -> Self {
+        PaletteFade { from: palette, to: palette, start: 0.0, duration: 0.0 }
+    }
+
+    // Start from wherever the fade is at `now`, so changing mid-fade doesn't jump
+    pub fn fade_to(&mut self, palette: Palette, now: f64, duration: f64) {
+        self.from = self.at(now);
+        self.to = palette;
+        self.start = now;
+        self.duration = duration;
+    }
+
+    // Straight there, e.g. while dragging a slider
+    pub fn set(&mut self, palette: Palette) {
+        *self = PaletteFade::new(palette);
+    }
+
+    pub fn target(&self) -> Palette {
+        self.to
+    }
+
+    pub fn at(&self, now: f64) -> Palette {
+        if self.duration <= 0.0 {
+            return self.to;
+        }
+        let t = ((now - self.start) / self.duration).clamp(0.0, 1.0) as f32;
+        // Ease in and out
+        self.from.lerp(&self.to, t * t * (3.0 - 2.0 * t))
+    }
+}
+
+#[derive(Default)]
+pub struct PaletteUniforms {
+    locations: [Option<WebGlUniformLocation>; 4],
+}
+
+impl PaletteUniforms {
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.locations = ["u_palette_a", "u_palette_b", "u_palette_c", "u_palette_d"].map(|name| gl.get_uniform_location(program, name));
+    }
+
+    pub fn upload(&self, gl: &GL, palette: &Palette) {
+        for (index, location) in self.locations.iter().enumerate() {
+            let [x, y, z] = palette.coefficient(index);
+            gl.uniform3f(location.as_ref(), x, y, z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    fn same(a: Palette, b: Palette) -> bool {
+        (0..4).all(|i| close(a.coefficient(i), b.coefficient(i)))
+    }
+
+    #[test]
+    fn colors_follow_the_cosine() {
+        let greyscale = preset("Greyscale").unwrap();
+        assert!(close(greyscale.color(0.0), [1.0, 1.0, 1.0]));
+        assert!(close(greyscale.color(0.5), [0.0, 0.0, 0.0]));
+        assert!(close(greyscale.color(0.25), [0.5, 0.5, 0.5]));
+
+        // The three channels a third of a turn apart
+        let rainbow = preset("Rainbow").unwrap();
+        let [r, g, b] = rainbow.color(0.0);
+        assert!((r - 1.0).abs() < 1e-5);
+        assert!(g < 0.5 && b < 0.5);
+    }
+
+    #[test]
+    fn colors_are_clamped() {
+        let bright = Palette { a: [0.9; 3], b: [0.5; 3], c: [1.0; 3], d: [0.0; 3] };
+        assert_eq!(bright.color(0.0), [1.0, 1.0, 1.0]);
+        let dark = Palette { a: [0.1; 3], ..bright };
+        assert_eq!(dark.color(0.5), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn lerp_blends_the_coefficients() {
+        let from = preset("Rainbow").unwrap();
+        let to = preset("Desert").unwrap();
+        assert_eq!(from.lerp(&to, 0.0), from);
+        assert!(same(from.lerp(&to, 1.0), to));
+        let half = from.lerp(&to, 0.5);
+        assert!(close(half.a, [0.65, 0.5, 0.45]));
+        assert!(close(half.c, [1.5, 1.0, 1.0]));
+        assert!(close(half.d, [0.0, 0.29, 0.46]));
+    }
+
+    #[test]
+    fn coefficients_by_index() {
+        let mut palette = Palette::default();
+        palette.set_coefficient(3, [0.1, 0.2, 0.3]);
+        assert_eq!(palette.coefficient(3), [0.1, 0.2, 0.3]);
+        assert_eq!(palette.d, [0.1, 0.2, 0.3]);
+
+        // Out of range changes nothing
+        let before = palette;
+        palette.set_coefficient(4, [9.0; 3]);
+        assert_eq!(palette, before);
+    }
+
+    #[test]
+    fn fades_ease_from_one_to_the_other() {
+        let from = preset("Rainbow").unwrap();
+        let to = preset("Ember").unwrap();
+        let mut fade = PaletteFade::new(from);
+        fade.fade_to(to, 10.0, 2.0);
+        assert_eq!(fade.at(9.0), from);
+        assert_eq!(fade.at(10.0), from);
+        assert_eq!(fade.at(11.0), from.lerp(&to, 0.5));
+        assert!(same(fade.at(12.0), to));
+        assert!(same(fade.at(20.0), to));
+        assert_eq!(fade.target(), to);
+    }
+
+    #[test]
+    fn changing_mid_fade_starts_from_where_it_was() {
+        let from = preset("Rainbow").unwrap();
+        let to = preset("Ember").unwrap();
+        let then = preset("Ocean").unwrap();
+        let mut fade = PaletteFade::new(from);
+        fade.fade_to(to, 0.0, 2.0);
+        let midway = fade.at(1.0);
+        fade.fade_to(then, 1.0, 2.0);
+        assert_eq!(fade.at(1.0), midway);
+        assert!(same(fade.at(3.0), then));
+        assert_eq!(fade.target(), then);
+    }
+
+    #[test]
+    fn css_gradient_samples_the_palette() {
+        let greyscale = preset("Greyscale").unwrap();
+        assert_eq!(greyscale.css_gradient(3), "linear-gradient(to right, rgb(255, 255, 255), rgb(0, 0, 0), rgb(255, 255, 255))");
+        // Never fewer than two stops
+        assert_eq!(greyscale.css_gradient(0), "linear-gradient(to right, rgb(255, 255, 255), rgb(255, 255, 255))");
+    }
+}
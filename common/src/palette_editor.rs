@@ -0,0 +1,84 @@
+// Editing a cosine palette (see palette.rs): a preview of the gradient, a list of presets and a
+// red, green and blue slider for each coefficient. Sits inside the ParamPanel like PresetPanel.
+
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::palette::{self, Palette, PRESETS};
+
+// Label and slider range for a to d
+const COEFFICIENTS: [(&str, f32); 4] = [("Brightness", 1.0), ("Contrast", 1.0), ("Frequency", 3.0), ("Phase", 1.0)];
+const CHANNELS: [&str; 3] = ["red", "green", "blue"];
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct PaletteEditorProps {
+    pub palette: Palette,
+    // A slider moved, the new palette should be used straight away
+    pub on_change: Callback<Palette>,
+    // A preset was picked, worth fading to
+    pub on_preset: Callback<Palette>,
+}
+
+#[function_component(PaletteEditor)]
+pub fn palette_editor(props: &PaletteEditorProps) -> Html {
+    let palette = props.palette;
+
+    let onselect = {
+        let on_preset = props.on_preset.clone();
+        Callback::from(move |evt: Event| {
+            let select: HtmlSelectElement = evt.target_unchecked_into();
+            if let Some(preset) = palette::preset(&select.value()) {
+                on_preset.emit(preset);
+            }
+            // Back to the placeholder so the same preset can be picked again after editing
+            select.set_value("");
+        })
+    };
+
+    let rows = COEFFICIENTS.iter().enumerate().map(|(index, (label, max))| {
+        let value = palette.coefficient(index);
+        let sliders = CHANNELS.iter().enumerate().map(|(channel, name)| {
+            let on_change = props.on_change.clone();
+            let oninput = Callback::from(move |evt: InputEvent| {
+                let input: HtmlInputElement = evt.target_unchecked_into();
+                if let Ok(channel_value) = input.value().parse() {
+                    let mut edited = palette;
+                    let mut coefficient = value;
+                    coefficient[channel] = channel_value;
+                    edited.set_coefficient(index, coefficient);
+                    on_change.emit(edited);
+                }
+            });
+            html! {
+                <input type="range"
+                    class={classes!("palette_channel", *name)}
+                    title={format!("{} {}: {:.2}", label, name, value[channel])}
+                    min="0"
+                    max={max.to_string()}
+                    step="0.01"
+                    value={value[channel].to_string()}
+                    oninput={oninput}
+                />
+            }
+        });
+        html! {
+            <div class="param_row">
+                <label>{ *label }</label>
+                { for sliders }
+            </div>
+        }
+    });
+
+    html! {
+        <div class="palette_editor">
+            <div class="palette_preview" style={format!("background: {};", palette.css_gradient(32))}></div>
+            <select onchange={onselect}>
+                <option value="" selected=true disabled=true>{ "Palette..." }</option>
+                { for PRESETS.iter().map(|(name, _)| html! {
+                    <option value={*name}>{ *name }</option>
+                }) }
+            </select>
+            { for rows }
+        </div>
+    }
+}
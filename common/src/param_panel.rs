@@ -0,0 +1,94 @@
+// A collapsible panel of controls generated from a shader's annotated uniforms (see params.rs).
+
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::params::{color_to_hex, parse_color, Param, ParamKind, ParamValue};
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct ParamPanelProps {
+    pub params: Vec<Param>,
+    // Current value for each param, in the same order
+    pub values: Vec<ParamValue>,
+    pub on_change: Callback<(String, ParamValue)>,
+    #[prop_or_default]
+    pub on_reset: Option<Callback<()>>,
+    // Anything else to show in the panel, e.g. a PresetPanel
+    #[prop_or_default]
+    pub children: Html,
+}
+
+#[function_component(ParamPanel)]
+pub fn param_panel(props: &ParamPanelProps) -> Html {
+    if props.params.is_empty() {
+        return html! {};
+    }
+
+    let rows = props.params.iter().zip(&props.values).map(|(param, value)| {
+        let name = param.name.clone();
+        let on_change = props.on_change.clone();
+
+        let control = match (&param.kind, value) {
+            (ParamKind::Slider { min, max, step }, ParamValue::Float(value)) => {
+                let oninput = Callback::from(move |evt: InputEvent| {
+                    let input: HtmlInputElement = evt.target_unchecked_into();
+                    if let Ok(value) = input.value().parse() {
+                        on_change.emit((name.clone(), ParamValue::Float(value)));
+                    }
+                });
+                html! {
+                    <>
+                        <input type="range"
+                            min={min.to_string()}
+                            max={max.to_string()}
+                            step={step.to_string()}
+                            value={value.to_string()}
+                            oninput={oninput}
+                        />
+                        <span class="param_value">{ format!("{:.3}", value) }</span>
+                    </>
+                }
+            },
+            (ParamKind::Toggle, ParamValue::Float(value)) => {
+                let onchange = Callback::from(move |evt: Event| {
+                    let input: HtmlInputElement = evt.target_unchecked_into();
+                    let value = if input.checked() { 1.0 } else { 0.0 };
+                    on_change.emit((name.clone(), ParamValue::Float(value)));
+                });
+                html! { <input type="checkbox" checked={*value > 0.5} onchange={onchange} /> }
+            },
+            (ParamKind::Color, ParamValue::Color(color)) => {
+                let oninput = Callback::from(move |evt: InputEvent| {
+                    let input: HtmlInputElement = evt.target_unchecked_into();
+                    if let Some(color) = parse_color(&input.value()) {
+                        on_change.emit((name.clone(), ParamValue::Color(color)));
+                    }
+                });
+                html! { <input type="color" value={color_to_hex(*color)} oninput={oninput} /> }
+            },
+            // The value doesn't match the kind of control, shouldn't happen
+            _ => html! {},
+        };
+
+        html! {
+            <div class="param_row">
+                <label>{ &param.label }</label>
+                { control }
+            </div>
+        }
+    });
+
+    let reset = props.on_reset.clone().map(|on_reset| {
+        let onclick = Callback::from(move |_| on_reset.emit(()));
+        html! { <button onclick={onclick}>{ "Reset" }</button> }
+    });
+
+    html! {
+        <details class="param_panel" open=true>
+            <summary>{ "Parameters" }</summary>
+            { for rows }
+            { for reset }
+            { props.children.clone() }
+        </details>
+    }
+}
@@ -0,0 +1,367 @@
+// Tweakable shader parameters, described by annotations on the uniforms themselves.
+//
+//   uniform float blur; // @range 0..1 @default 0.3
+//   uniform float mirror; // @toggle @default 1 @label Mirror image
+//   uniform vec3 tint; // @color @default #ff8000
+//
+// Any uniform with an @ annotation becomes a Param: floats with a range are sliders, @toggle
+// floats are checkboxes (0.0 or 1.0 in the shader) and @color vec3s are colour pickers. When a
+// uniform is renamed, `@was old_name` lets values saved under the old name still find it.
+// ShaderParams keeps the current values and uploads them to whichever program is in use.
+
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamKind {
+    Slider { min: f32, max: f32, step: f32 },
+    Toggle,
+    Color,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamValue {
+    Float(f32),
+    Color([f32; 3]),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub label: String,
+    pub kind: ParamKind,
+    pub default: ParamValue,
+    // Names this uniform used to have
+    pub previous_names: Vec<String>,
+}
+
+impl Param {
+    // `value` made to suit this param: sliders are clamped to their range, toggles are 0 or 1
+    // and colours 0 to 1. None if it's the wrong kind of value, or not a number.
+    pub fn fit(&self, value: ParamValue) -> Option<ParamValue> {
+        match (&self.kind, value) {
+            (_, ParamValue::Float(value)) if value.is_nan() => None,
+            (ParamKind::Slider { min, max, .. }, ParamValue::Float(value)) => Some(ParamValue::Float(value.clamp(*min, *max))),
+            (ParamKind::Toggle, ParamValue::Float(value)) => Some(ParamValue::Float(if value > 0.5 { 1.0 } else { 0.0 })),
+            (ParamKind::Color, ParamValue::Color(color)) if color.iter().all(|c| !c.is_nan()) => {
+                Some(ParamValue::Color(color.map(|c| c.clamp(0.0, 1.0))))
+            },
+            _ => None,
+        }
+    }
+}
+
+// Find every annotated uniform in `source`
+pub fn parse_params(source: &str) -> Vec<Param> {
+    source.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<Param> {
+    let (code, comment) = line.split_once("//")?;
+    if !comment.contains('@') {
+        return None;
+    }
+
+    let mut words = code.trim().strip_suffix(';')?.split_whitespace();
+    if words.next()? != "uniform" {
+        return None;
+    }
+    let mut ty = words.next()?;
+    if matches!(ty, "lowp" | "mediump" | "highp") {
+        ty = words.next()?;
+    }
+    let name = words.next()?.to_string();
+
+    // Split the comment into (tag, argument) pairs, e.g. ("range", "0..1")
+    let tags: Vec<(&str, &str)> = comment
+        .split('@')
+        .skip(1)
+        .map(|tag| {
+            let tag = tag.trim();
+            tag.split_once(char::is_whitespace)
+                .map(|(tag, arg)| (tag, arg.trim()))
+                .unwrap_or((tag, ""))
+        })
+        .collect();
+    let tag = |name: &str| tags.iter().find(|(tag, _)| *tag == name).map(|(_, arg)| *arg);
+
+    let label = tag("label").filter(|label| !label.is_empty()).unwrap_or(&name).to_string();
+
+    let (kind, default) = match ty {
+        "vec3" if tag("color").is_some() => {
+            let default = tag("default").and_then(parse_color).unwrap_or([1.0, 1.0, 1.0]);
+            (ParamKind::Color, ParamValue::Color(default))
+        }
+        "float" if tag("toggle").is_some() => {
+            let on = tag("default").and_then(|arg| arg.parse::<f32>().ok()).unwrap_or(0.0) > 0.5;
+            (ParamKind::Toggle, ParamValue::Float(if on { 1.0 } else { 0.0 }))
+        }
+        "float" => {
+            let (min, max) = tag("range").and_then(parse_range).unwrap_or((0.0, 1.0));
+            let step = tag("step").and_then(|arg| arg.parse().ok()).filter(|step: &f32| *step > 0.0).unwrap_or((max - min) / 100.0);
+            let default = tag("default").and_then(|arg| arg.parse::<f32>().ok()).unwrap_or(min).clamp(min, max);
+            (ParamKind::Slider { min, max, step }, ParamValue::Float(default))
+        }
+        _ => return None,
+    };
+
+    let previous_names = tag("was")
+        .map(|arg| arg.split([',', ' ']).filter(|name| !name.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Some(Param {
+        name,
+        label,
+        kind,
+        default,
+        previous_names,
+    })
+}
+
+// `0..1` or `-2.5..2.5`, None if it's empty or backwards
+fn parse_range(arg: &str) -> Option<(f32, f32)> {
+    let (min, max) = arg.split_once("..")?;
+    let (min, max): (f32, f32) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+    (min < max).then_some((min, max))
+}
+
+// `#ff8000` or `1.0, 0.5, 0.0`
+pub fn parse_color(arg: &str) -> Option<[f32; 3]> {
+    if let Some(hex) = arg.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).ok().map(|c| c as f32 / 255.0);
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+
+    let parts: Vec<f32> = arg.split(',').filter_map(|part| part.trim().parse().ok()).collect();
+    match parts.as_slice() {
+        [r, g, b] => Some([*r, *g, *b]),
+        _ => None,
+    }
+}
+
+pub fn color_to_hex(color: [f32; 3]) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(color[0]), channel(color[1]), channel(color[2]))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ShaderParams {
+    params: Vec<Param>,
+    values: Vec<ParamValue>,
+    locations: Vec<Option<WebGlUniformLocation>>,
+}
+
+impl ShaderParams {
+    pub fn from_source(source: &str) -> Self {
+        let mut params = ShaderParams::default();
+        params.update_source(source);
+        params
+    }
+
+    // Re-read the annotations after the source has changed. Values for parameters that are
+    // still there are kept if they still fit, new ones start at their default.
+    pub fn update_source(&mut self, source: &str) {
+        let params = parse_params(source);
+        self.values = params
+            .iter()
+            .map(|param| self.value(&param.name).and_then(|value| param.fit(value)).unwrap_or(param.default))
+            .collect();
+        self.locations = vec![None; params.len()];
+        self.params = params;
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    pub fn values(&self) -> &[ParamValue] {
+        &self.values
+    }
+
+    pub fn value(&self, name: &str) -> Option<ParamValue> {
+        self.params.iter().position(|param| param.name == name).map(|idx| self.values[idx])
+    }
+
+    // (uniform name, value) pairs, e.g. for saving or sharing
+    pub fn named_values(&self) -> Vec<(String, ParamValue)> {
+        self.params.iter().map(|param| param.name.clone()).zip(self.values.iter().copied()).collect()
+    }
+
+    // Ignored if there's no such param or the value is the wrong kind, see Param::fit
+    pub fn set(&mut self, name: &str, value: ParamValue) {
+        if let Some(idx) = self.params.iter().position(|param| param.name == name) {
+            if let Some(value) = self.params[idx].fit(value) {
+                self.values[idx] = value;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.values = self.params.iter().map(|param| param.default).collect();
+    }
+
+    // Look the uniforms up in a newly compiled program
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.locations = self
+            .params
+            .iter()
+            .map(|param| gl.get_uniform_location(program, &param.name))
+            .collect();
+    }
+
+    // Send the current values to the program in use, call once per frame
+    pub fn upload(&self, gl: &GL) {
+        for (value, location) in self.values.iter().zip(&self.locations) {
+            match value {
+                ParamValue::Float(value) => gl.uniform1f(location.as_ref(), *value),
+                ParamValue::Color([r, g, b]) => gl.uniform3f(location.as_ref(), *r, *g, *b),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(line: &str) -> Option<Param> {
+        parse_params(line).into_iter().next()
+    }
+
+    fn slider(line: &str) -> (f32, f32, f32, ParamValue) {
+        match param(line).unwrap() {
+            Param { kind: ParamKind::Slider { min, max, step }, default, .. } => (min, max, step, default),
+            other => panic!("not a slider: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_sliders() {
+        let param = param("uniform mediump float blur; // @range -2.5..2.5 @default 0.3 @step 0.1 @label Blur amount").unwrap();
+        assert_eq!(
+            param,
+            Param {
+                name: "blur".to_string(),
+                label: "Blur amount".to_string(),
+                kind: ParamKind::Slider { min: -2.5, max: 2.5, step: 0.1 },
+                default: ParamValue::Float(0.3),
+                previous_names: Vec::new(),
+            }
+        );
+        // Without a range, step or default
+        assert_eq!(slider("uniform float blur; // @label Blur"), (0.0, 1.0, 0.01, ParamValue::Float(0.0)));
+        assert_eq!(slider("uniform float blur; // @range 0..10"), (0.0, 10.0, 0.1, ParamValue::Float(0.0)));
+    }
+
+    #[test]
+    fn reads_toggles_and_colours() {
+        let mirror = param("uniform float mirror; // @toggle @default 1 @label Mirror image").unwrap();
+        assert_eq!((mirror.kind, mirror.default, mirror.label.as_str()), (ParamKind::Toggle, ParamValue::Float(1.0), "Mirror image"));
+        assert_eq!(param("uniform float mirror; // @toggle @default 0.2").unwrap().default, ParamValue::Float(0.0));
+
+        let tint = param("uniform vec3 tint; // @color @default #ff8000").unwrap();
+        assert_eq!((tint.kind, tint.default), (ParamKind::Color, ParamValue::Color([1.0, 128.0 / 255.0, 0.0])));
+        assert_eq!(param("uniform vec3 tint; // @color @default 0.1, 0.2, 0.3").unwrap().default, ParamValue::Color([0.1, 0.2, 0.3]));
+        assert_eq!(param("uniform vec3 tint; // @color").unwrap().default, ParamValue::Color([1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn reads_previous_names() {
+        let param = param("uniform float width; // @was thickness, size @range 0..1").unwrap();
+        assert_eq!(param.previous_names, vec!["thickness", "size"]);
+        assert_eq!(param.label, "width");
+    }
+
+    #[test]
+    fn skips_what_isnt_a_param() {
+        let source = "
+uniform float plain;
+uniform float commented; // a comment
+// uniform float blur; // @range 0..1
+float local = 1.0; // @range 0..1
+uniform vec2 size; // @range 0..1
+uniform vec3 tint; // @range 0..1
+uniform float missing_semicolon // @range 0..1
+";
+        assert_eq!(parse_params(source), vec![]);
+    }
+
+    #[test]
+    fn malformed_annotations_fall_back() {
+        // A range that doesn't parse, or runs backwards, is 0..1
+        assert_eq!(slider("uniform float a; // @range lots"), (0.0, 1.0, 0.01, ParamValue::Float(0.0)));
+        assert_eq!(slider("uniform float a; // @range 0..x").1, 1.0);
+        assert_eq!(slider("uniform float a; // @range 5..2").1, 1.0);
+        assert_eq!(slider("uniform float a; // @range 2..2").1, 1.0);
+        // Steps have to go somewhere
+        assert_eq!(slider("uniform float a; // @range 0..2 @step 0").2, 0.02);
+        assert_eq!(slider("uniform float a; // @range 0..2 @step -1").2, 0.02);
+        assert_eq!(slider("uniform float a; // @range 0..2 @step fine").2, 0.02);
+        // Defaults stay in the range, and a bad one is the low end
+        assert_eq!(slider("uniform float a; // @range 0..2 @default 5").3, ParamValue::Float(2.0));
+        assert_eq!(slider("uniform float a; // @range 1..2 @default big").3, ParamValue::Float(1.0));
+        // An empty label is the name, a bad colour is white
+        assert_eq!(param("uniform float a; // @label").unwrap().label, "a");
+        assert_eq!(param("uniform vec3 c; // @color @default #ff80").unwrap().default, ParamValue::Color([1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn keeps_values_through_edits() {
+        let mut params = ShaderParams::from_source("uniform float a; // @range 0..1\nuniform vec3 b; // @color\n");
+        params.set("a", ParamValue::Float(0.5));
+        params.set("b", ParamValue::Color([0.0, 0.0, 0.0]));
+        params.set("nothing", ParamValue::Float(1.0));
+
+        // b turns into a slider, so its colour can't stay
+        params.update_source("uniform float c; // @range 0..1 @default 0.25\nuniform float b; // @range 0..1\nuniform float a; // @range 0..1\n");
+        assert_eq!(
+            params.named_values(),
+            vec![("c".to_string(), ParamValue::Float(0.25)), ("b".to_string(), ParamValue::Float(0.0)), ("a".to_string(), ParamValue::Float(0.5))]
+        );
+        params.reset();
+        assert_eq!(params.value("a"), Some(ParamValue::Float(0.0)));
+    }
+
+    #[test]
+    fn set_keeps_values_in_range() {
+        let mut params = ShaderParams::from_source(
+            "uniform float a; // @range -1..1\nuniform float t; // @toggle\nuniform vec3 c; // @color\n",
+        );
+        params.set("a", ParamValue::Float(5.0));
+        params.set("t", ParamValue::Float(0.7));
+        params.set("c", ParamValue::Color([-1.0, 0.5, 2.0]));
+        assert_eq!(
+            params.values(),
+            &[ParamValue::Float(1.0), ParamValue::Float(1.0), ParamValue::Color([0.0, 0.5, 1.0])]
+        );
+        params.set("a", ParamValue::Float(f32::NEG_INFINITY));
+        assert_eq!(params.value("a"), Some(ParamValue::Float(-1.0)));
+    }
+
+    #[test]
+    fn set_ignores_the_wrong_kind() {
+        let mut params = ShaderParams::from_source("uniform float a; // @range 0..1 @default 0.5\nuniform vec3 c; // @color\n");
+        params.set("a", ParamValue::Color([0.0, 0.0, 0.0]));
+        params.set("c", ParamValue::Float(0.0));
+        params.set("a", ParamValue::Float(f32::NAN));
+        params.set("c", ParamValue::Color([f32::NAN, 0.0, 0.0]));
+        assert_eq!(params.values(), &[ParamValue::Float(0.5), ParamValue::Color([1.0, 1.0, 1.0])]);
+    }
+
+    #[test]
+    fn edits_clamp_kept_values_to_the_new_range() {
+        let mut params = ShaderParams::from_source("uniform float a; // @range 0..10\n");
+        params.set("a", ParamValue::Float(8.0));
+        params.update_source("uniform float a; // @range 0..2\n");
+        assert_eq!(params.value("a"), Some(ParamValue::Float(2.0)));
+    }
+
+    #[test]
+    fn hex_colours() {
+        assert_eq!(parse_color("#00ff80"), Some([0.0, 1.0, 128.0 / 255.0]));
+        assert_eq!(parse_color("#00gg80"), None);
+        assert_eq!(color_to_hex([1.0, 128.0 / 255.0, 2.0]), "#ff80ff");
+    }
+}
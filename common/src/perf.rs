@@ -0,0 +1,241 @@
+// Frame timing for the performance overlay (see perf_hud.rs).
+//
+// FrameStats keeps the last HISTORY frame times, as measured between canvas_update() calls, and
+// works out the frame rate, percentiles and dropped frames from them. The display's refresh
+// interval is taken to be the time of the quicker frames, so a frame that takes half as long
+// again as that has missed at least one refresh. GpuTimer measures how long the GPU spent on the
+// frame with EXT_disjoint_timer_query. Results arrive a few frames late, and most browsers only
+// offer the extension behind a flag, so it's there when it's there.
+
+use std::collections::VecDeque;
+
+use wasm_bindgen::JsCast;
+use web_sys::{ExtDisjointTimerQuery, WebGlQuery, WebGlRenderingContext as GL};
+
+// Frames kept, two seconds at 60 FPS
+pub const HISTORY: usize = 120;
+
+// Longer than this and the page was probably in the background, which isn't a dropped frame
+const MAX_FRAME_MS: f64 = 1000.0;
+// Until there are enough frames to go by
+const DEFAULT_INTERVAL_MS: f64 = 1000.0 / 60.0;
+const MIN_SAMPLES: usize = 10;
+// Nothing refreshes faster than 240Hz, this stops a burst of quick frames skewing the guess
+const MIN_INTERVAL_MS: f64 = 1000.0 / 240.0;
+// The percentile of frame times taken as the refresh interval
+const INTERVAL_PERCENTILE: f64 = 10.0;
+// A frame this many refresh intervals long missed at least one
+const DROPPED_FRAME: f64 = 1.5;
+
+// Queries waiting on the GPU before we stop starting new ones
+const MAX_PENDING_QUERIES: usize = 4;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    // Milliseconds, oldest first
+    frames: VecDeque<f64>,
+    // Refreshes missed since the stats were reset
+    dropped: u64,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats::default()
+    }
+
+    // `delta` is the time since the last frame in milliseconds
+    pub fn record(&mut self, delta: f64) {
+        if !(delta > 0.0 && delta <= MAX_FRAME_MS) {
+            return;
+        }
+        let interval = self.interval();
+        if delta >= interval * DROPPED_FRAME {
+            self.dropped += ((delta / interval).round() as u64).saturating_sub(1).max(1);
+        }
+        if self.frames.len() == HISTORY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(delta);
+    }
+
+    pub fn reset(&mut self) {
+        *self = FrameStats::default();
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = f64> + '_ {
+        self.frames.iter().copied()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // Average over the history, 0 with nothing recorded
+    pub fn fps(&self) -> f64 {
+        let total: f64 = self.frames.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        1000.0 * self.frames.len() as f64 / total
+    }
+
+    // Nearest rank, `percentile` from 0 to 100
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<f64> = self.frames.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.saturating_sub(1)]
+    }
+
+    pub fn max(&self) -> f64 {
+        self.frames.iter().copied().fold(0.0, f64::max)
+    }
+
+    // Our guess at the display's refresh interval in milliseconds
+    pub fn interval(&self) -> f64 {
+        if self.frames.len() < MIN_SAMPLES {
+            return DEFAULT_INTERVAL_MS;
+        }
+        self.percentile(INTERVAL_PERCENTILE).max(MIN_INTERVAL_MS)
+    }
+
+    // Whether a frame of `delta` milliseconds missed a refresh
+    pub fn is_dropped(&self, delta: f64) -> bool {
+        delta >= self.interval() * DROPPED_FRAME
+    }
+}
+
+pub struct GpuTimer {
+    ext: ExtDisjointTimerQuery,
+    // The query for the frame being drawn
+    active: Option<WebGlQuery>,
+    // Waiting on the GPU, oldest first
+    pending: VecDeque<WebGlQuery>,
+    // Finished with, to be used again
+    spare: Vec<WebGlQuery>,
+    // Milliseconds, from the most recent query to come back
+    last: Option<f64>,
+}
+
+impl GpuTimer {
+    // None when the browser doesn't have the extension
+    pub fn new(gl: &GL) -> Option<GpuTimer> {
+        let ext = gl.get_extension("EXT_disjoint_timer_query").ok()??.dyn_into::<ExtDisjointTimerQuery>().ok()?;
+        Some(GpuTimer { ext, active: None, pending: VecDeque::new(), spare: Vec::new(), last: None })
+    }
+
+    // Around the draw calls. A frame goes untimed if too many are still waiting for results.
+    pub fn begin(&mut self) {
+        if self.active.is_some() || self.pending.len() >= MAX_PENDING_QUERIES {
+            return;
+        }
+        let Some(query) = self.spare.pop().or_else(|| self.ext.create_query_ext()) else {
+            return;
+        };
+        self.ext.begin_query_ext(ExtDisjointTimerQuery::TIME_ELAPSED_EXT, &query);
+        self.active = Some(query);
+    }
+
+    pub fn end(&mut self) {
+        if let Some(query) = self.active.take() {
+            self.ext.end_query_ext(ExtDisjointTimerQuery::TIME_ELAPSED_EXT);
+            self.pending.push_back(query);
+        }
+    }
+
+    // Pick up any results that have come back and give the latest, in milliseconds
+    pub fn poll(&mut self, gl: &GL) -> Option<f64> {
+        // Something like a clock change happened on the GPU, the results waiting can't be trusted
+        let disjoint = gl
+            .get_parameter(ExtDisjointTimerQuery::GPU_DISJOINT_EXT)
+            .ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+
+        while let Some(query) = self.pending.front() {
+            let available = self.ext.get_query_object_ext(query, ExtDisjointTimerQuery::QUERY_RESULT_AVAILABLE_EXT);
+            if !disjoint && available.as_bool() != Some(true) {
+                break;
+            }
+            let query = self.pending.pop_front().expect("there was a front");
+            if !disjoint {
+                if let Some(nanoseconds) = self.ext.get_query_object_ext(&query, ExtDisjointTimerQuery::QUERY_RESULT_EXT).as_f64() {
+                    self.last = Some(nanoseconds / 1_000_000.0);
+                }
+            }
+            self.spare.push(query);
+        }
+        self.last
+    }
+}
+
+impl Drop for GpuTimer {
+    // Queries belong to the GL context, which outlives the tutorial's component
+    fn drop(&mut self) {
+        for query in self.active.take().into_iter().chain(self.pending.drain(..)).chain(self.spare.drain(..)) {
+            self.ext.delete_query_ext(Some(&query));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(frames: &[f64]) -> FrameStats {
+        let mut stats = FrameStats::new();
+        for frame in frames {
+            stats.record(*frame);
+        }
+        stats
+    }
+
+    #[test]
+    fn steady_frames() {
+        let stats = stats(&[1000.0 / 60.0; 60]);
+        assert!((stats.fps() - 60.0).abs() < 1e-6);
+        assert_eq!(stats.dropped(), 0);
+        assert!((stats.interval() - 1000.0 / 60.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn counts_missed_refreshes() {
+        let mut frames = vec![16.0; 30];
+        // One refresh missed, then three
+        frames.push(33.0);
+        frames.extend([16.0; 5]);
+        frames.push(64.0);
+        let stats = stats(&frames);
+        assert_eq!(stats.dropped(), 1 + 3);
+        assert!(stats.is_dropped(33.0));
+        assert!(!stats.is_dropped(20.0));
+    }
+
+    #[test]
+    fn ignores_background_gaps() {
+        let stats = stats(&[16.0, 5000.0, 16.0, 0.0, -3.0]);
+        assert_eq!(stats.frames().collect::<Vec<_>>(), vec![16.0, 16.0]);
+        assert_eq!(stats.dropped(), 0);
+    }
+
+    #[test]
+    fn percentiles() {
+        let stats = stats(&(1..=100).map(f64::from).collect::<Vec<_>>());
+        assert_eq!(stats.percentile(50.0), 50.0);
+        assert_eq!(stats.percentile(95.0), 95.0);
+        assert_eq!(stats.percentile(99.0), 99.0);
+        assert_eq!(stats.percentile(100.0), 100.0);
+        assert_eq!(stats.percentile(0.0), 1.0);
+        assert_eq!(stats.max(), 100.0);
+        assert_eq!(FrameStats::new().percentile(50.0), 0.0);
+    }
+
+    #[test]
+    fn keeps_history() {
+        let stats = stats(&vec![10.0; HISTORY + 50]);
+        assert_eq!(stats.frames().count(), HISTORY);
+    }
+}
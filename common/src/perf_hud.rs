@@ -0,0 +1,76 @@
+// An overlay with how the page is keeping up: frame rate, a graph of recent frame times, the
+// slow end of them as percentiles, frames that missed a refresh, GPU time when the browser can
+// measure it and the size of the canvas being drawn (see perf.rs).
+
+use yew::prelude::*;
+
+use crate::perf::{FrameStats, HISTORY};
+
+// Frame times the graph goes up to, in milliseconds, anything longer is cut off at the top
+const GRAPH_MAX_MS: f64 = 50.0;
+const GRAPH_HEIGHT: f64 = 50.0;
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct PerfHudProps {
+    pub stats: FrameStats,
+    // Milliseconds, None while waiting on the first result
+    #[prop_or_default]
+    pub gpu_time: Option<f64>,
+    // Whether the browser can time the GPU at all
+    #[prop_or_default]
+    pub gpu_timer: bool,
+    // In canvas pixels, with how many of those there are to a CSS pixel
+    pub width: i32,
+    pub height: i32,
+    pub pixel_ratio: f64,
+}
+
+#[function_component(PerfHud)]
+pub fn perf_hud(props: &PerfHudProps) -> Html {
+    let stats = &props.stats;
+    let interval = stats.interval();
+    let y = |ms: f64| GRAPH_HEIGHT - (ms / GRAPH_MAX_MS).min(1.0) * GRAPH_HEIGHT;
+
+    // Newest on the right, like a scrolling chart
+    let offset = HISTORY - stats.frames().count();
+    let bars = stats.frames().enumerate().map(|(index, ms)| {
+        let class = if stats.is_dropped(ms) { "perf_bar perf_dropped" } else { "perf_bar" };
+        html! {
+            <rect class={class}
+                x={(offset + index).to_string()}
+                y={y(ms).to_string()}
+                width="1"
+                height={(GRAPH_HEIGHT - y(ms)).to_string()}
+            />
+        }
+    });
+
+    let gpu = match (props.gpu_timer, props.gpu_time) {
+        (false, _) => "not available".to_string(),
+        (true, None) => "waiting".to_string(),
+        (true, Some(ms)) => format!("{:.2} ms", ms),
+    };
+    let row = |label: &str, value: String| html! {
+        <tr><td>{ label.to_string() }</td><td class="perf_value">{ value }</td></tr>
+    };
+
+    html! {
+        <div class="perf_hud">
+            <div class="perf_fps">{ format!("{:.0} FPS", stats.fps()) }</div>
+            <svg class="perf_graph" viewBox={format!("0 0 {} {}", HISTORY, GRAPH_HEIGHT)} preserveAspectRatio="none">
+                { for bars }
+                // The refresh interval, frames above the line were late
+                <line class="perf_interval" x1="0" x2={HISTORY.to_string()} y1={y(interval).to_string()} y2={y(interval).to_string()} />
+            </svg>
+            <table>
+                { row("Median", format!("{:.1} ms", stats.percentile(50.0))) }
+                { row("95th", format!("{:.1} ms", stats.percentile(95.0))) }
+                { row("99th", format!("{:.1} ms", stats.percentile(99.0))) }
+                { row("Worst", format!("{:.1} ms", stats.max())) }
+                { row("Dropped", stats.dropped().to_string()) }
+                { row("GPU", gpu) }
+                { row("Canvas", format!("{} x {} @{}x", props.width, props.height, props.pixel_ratio)) }
+            </table>
+        </div>
+    }
+}
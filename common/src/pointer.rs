@@ -0,0 +1,285 @@
+// Mouse, pen and touch input through pointer events, in the canvas's own coordinates.
+//
+// page_x/page_y are relative to the page, not the canvas, and in CSS pixels rather than the
+// canvas's pixels once the device pixel ratio is taken into account. Everything here is in
+// canvas pixels with y up, the same as gl_FragCoord, so positions can go straight to a shader.
+//
+// PointerState also keeps Shadertoy's iMouse:
+//   xy - where the pointer is while a button is down, where it was let go otherwise
+//   zw - where the button went down. z is negative once it's let go again, w is only positive
+//        on the frame it went down
+
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlCanvasElement, MouseEvent, PointerEvent, WebGlProgram, WebGlRenderingContext as GL, WebGlUniformLocation, WheelEvent};
+
+// A press and release further apart than this (canvas pixels) is a drag, not a click
+pub const CLICK_SLOP: f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerAction {
+    Down,
+    Move,
+    Up,
+    // Left the canvas, or the browser took the pointer away
+    Leave,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerUpdate {
+    pub action: PointerAction,
+    // Canvas pixels, y up
+    pub position: (f32, f32),
+    // Bit mask as in PointerEvent.buttons, 1 is the main button
+    pub buttons: u16,
+}
+
+impl PointerUpdate {
+    // Only the primary pointer is used, so a second finger doesn't make the position jump about
+    pub fn from_event(evt: &PointerEvent, action: PointerAction) -> Option<PointerUpdate> {
+        if !evt.is_primary() {
+            return None;
+        }
+        let canvas: HtmlCanvasElement = evt.current_target()?.dyn_into().ok()?;
+
+        // Keep getting moves while dragging outside the canvas
+        match action {
+            PointerAction::Down => {
+                let _ = canvas.set_pointer_capture(evt.pointer_id());
+            },
+            PointerAction::Up => {
+                let _ = canvas.release_pointer_capture(evt.pointer_id());
+            },
+            _ => (),
+        }
+
+        Some(PointerUpdate {
+            action,
+            position: event_position(evt)?,
+            buttons: evt.buttons(),
+        })
+    }
+}
+
+// Where a mouse (or pointer, or wheel) event on the canvas happened, in canvas pixels y up.
+// The handler has to be on the canvas itself.
+pub fn event_position(evt: &MouseEvent) -> Option<(f32, f32)> {
+    let canvas: HtmlCanvasElement = evt.current_target()?.dyn_into().ok()?;
+    let (x, y) = to_canvas(&canvas, evt.client_x() as f64, evt.client_y() as f64);
+    Some((x as f32, canvas.height() as f32 - y as f32))
+}
+
+// How far the wheel moved in pixels, whatever units the browser reported it in
+pub fn wheel_pixels(evt: &WheelEvent) -> f64 {
+    match evt.delta_mode() {
+        WheelEvent::DOM_DELTA_LINE => evt.delta_y() * 16.0,
+        WheelEvent::DOM_DELTA_PAGE => evt.delta_y() * 800.0,
+        _ => evt.delta_y(),
+    }
+}
+
+// Convert a position in the window (clientX/Y) to canvas pixels, still y down. Allows for the
+// canvas being anywhere on the page, scrolled, and drawn bigger or smaller than its pixel size.
+pub fn to_canvas(canvas: &HtmlCanvasElement, client_x: f64, client_y: f64) -> (f64, f64) {
+    let rect = canvas.get_bounding_client_rect();
+    let scale_x = if rect.width() > 0.0 { canvas.width() as f64 / rect.width() } else { 1.0 };
+    let scale_y = if rect.height() > 0.0 { canvas.height() as f64 / rect.height() } else { 1.0 };
+    ((client_x - rect.left()) * scale_x, (client_y - rect.top()) * scale_y)
+}
+
+// How many canvas pixels to use per CSS pixel, so the canvas is sharp on high DPI screens
+pub fn device_pixel_ratio() -> f64 {
+    web_sys::window().map(|window| window.device_pixel_ratio()).filter(|ratio| *ratio > 0.0).unwrap_or(1.0)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PointerState {
+    position: (f32, f32),
+    buttons: u16,
+    // Where the current drag started, while a button is down
+    drag_start: Option<(f32, f32)>,
+    // Where the last click was, and whether it happened since the last end_frame
+    last_click: Option<(f32, f32)>,
+    clicked: bool,
+    // iMouse as described at the top
+    i_mouse: [f32; 4],
+    i_mouse_location: Option<WebGlUniformLocation>,
+}
+
+impl PointerState {
+    pub fn update(&mut self, update: PointerUpdate) {
+        self.position = update.position;
+        let (x, y) = update.position;
+
+        match update.action {
+            PointerAction::Down => {
+                self.buttons = update.buttons;
+                if self.drag_start.is_none() {
+                    self.drag_start = Some(update.position);
+                    self.i_mouse = [x, y, x, y];
+                }
+            },
+            PointerAction::Move => {
+                self.buttons = update.buttons;
+                if self.drag_start.is_some() {
+                    self.i_mouse[0] = x;
+                    self.i_mouse[1] = y;
+                }
+            },
+            PointerAction::Up | PointerAction::Leave => {
+                // Other buttons may still be held
+                self.buttons = update.buttons;
+                if self.buttons != 0 {
+                    return;
+                }
+                if let Some(start) = self.drag_start.take() {
+                    if update.action == PointerAction::Up && (x - start.0).hypot(y - start.1) <= CLICK_SLOP {
+                        self.last_click = Some(start);
+                        self.clicked = true;
+                    }
+                    self.i_mouse[0] = x;
+                    self.i_mouse[1] = y;
+                }
+                self.i_mouse[2] = -self.i_mouse[2].abs();
+                self.i_mouse[3] = -self.i_mouse[3].abs();
+            },
+        }
+    }
+
+    // Canvas pixels, y up
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    // 0 to 1 across the canvas, y up, as uv is in most of the shaders
+    pub fn uv(&self, canvas_size: (f32, f32)) -> (f32, f32) {
+        (self.position.0 / canvas_size.0.max(1.0), self.position.1 / canvas_size.1.max(1.0))
+    }
+
+    // -1 to 1 across the canvas, the same as gl_Position
+    pub fn clip_space(&self, canvas_size: (f32, f32)) -> (f32, f32) {
+        let (u, v) = self.uv(canvas_size);
+        (u * 2.0 - 1.0, v * 2.0 - 1.0)
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    pub fn buttons(&self) -> u16 {
+        self.buttons
+    }
+
+    pub fn drag_start(&self) -> Option<(f32, f32)> {
+        self.drag_start
+    }
+
+    // How far the pointer has moved since the button went down
+    pub fn drag_delta(&self) -> Option<(f32, f32)> {
+        self.drag_start.map(|start| (self.position.0 - start.0, self.position.1 - start.1))
+    }
+
+    // Where a click (press and release without dragging) happened since the last end_frame
+    pub fn click(&self) -> Option<(f32, f32)> {
+        self.last_click.filter(|_| self.clicked)
+    }
+
+    pub fn i_mouse(&self) -> [f32; 4] {
+        self.i_mouse
+    }
+
+    // Call after drawing, the click and the "just pressed" sign on iMouse.w last one frame
+    pub fn end_frame(&mut self) {
+        self.clicked = false;
+        self.i_mouse[3] = -self.i_mouse[3].abs();
+    }
+
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.i_mouse_location = gl.get_uniform_location(program, "iMouse");
+    }
+
+    pub fn upload(&self, gl: &GL) {
+        let [x, y, z, w] = self.i_mouse;
+        gl.uniform4f(self.i_mouse_location.as_ref(), x, y, z, w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(state: &mut PointerState, action: PointerAction, position: (f32, f32), buttons: u16) {
+        state.update(PointerUpdate { action, position, buttons });
+    }
+
+    #[test]
+    fn starts_up() {
+        let state = PointerState::default();
+        assert_eq!(state.i_mouse(), [0.0; 4]);
+        // Nothing held, so z mustn't read as pressed
+        assert!(state.i_mouse()[2] <= 0.0);
+        assert!(!state.is_down());
+        assert_eq!(state.click(), None);
+    }
+
+    #[test]
+    fn pressed() {
+        let mut state = PointerState::default();
+        update(&mut state, PointerAction::Down, (10.0, 20.0), 1);
+        assert_eq!(state.i_mouse(), [10.0, 20.0, 10.0, 20.0]);
+        assert!(state.is_down());
+
+        // w is only positive on the frame it went down, z stays positive while held
+        state.end_frame();
+        update(&mut state, PointerAction::Move, (30.0, 40.0), 1);
+        assert_eq!(state.i_mouse(), [30.0, 40.0, 10.0, -20.0]);
+        assert_eq!(state.drag_delta(), Some((20.0, 20.0)));
+    }
+
+    #[test]
+    fn released() {
+        let mut state = PointerState::default();
+        update(&mut state, PointerAction::Down, (10.0, 20.0), 1);
+        update(&mut state, PointerAction::Move, (50.0, 60.0), 1);
+        update(&mut state, PointerAction::Up, (50.0, 60.0), 0);
+        assert_eq!(state.i_mouse(), [50.0, 60.0, -10.0, -20.0]);
+        assert!(!state.is_down());
+        // Too far to be a click
+        assert_eq!(state.click(), None);
+
+        // Moving with nothing held leaves iMouse where it was let go
+        update(&mut state, PointerAction::Move, (70.0, 80.0), 0);
+        assert_eq!(state.i_mouse(), [50.0, 60.0, -10.0, -20.0]);
+        assert_eq!(state.position(), (70.0, 80.0));
+    }
+
+    #[test]
+    fn other_buttons_keep_it_down() {
+        let mut state = PointerState::default();
+        update(&mut state, PointerAction::Down, (10.0, 20.0), 3);
+        update(&mut state, PointerAction::Up, (10.0, 20.0), 2);
+        assert!(state.is_down());
+        assert!(state.i_mouse()[2] > 0.0);
+    }
+
+    #[test]
+    fn click_lasts_one_frame() {
+        let mut state = PointerState::default();
+        update(&mut state, PointerAction::Down, (10.0, 20.0), 1);
+        update(&mut state, PointerAction::Up, (12.0, 21.0), 0);
+        assert_eq!(state.click(), Some((10.0, 20.0)));
+
+        state.end_frame();
+        assert_eq!(state.click(), None);
+        assert!(state.i_mouse()[3] < 0.0);
+    }
+
+    #[test]
+    fn leaving_isnt_a_click() {
+        let mut state = PointerState::default();
+        update(&mut state, PointerAction::Down, (10.0, 20.0), 1);
+        update(&mut state, PointerAction::Leave, (10.0, 20.0), 0);
+        assert_eq!(state.click(), None);
+        assert!(!state.is_down());
+        assert!(state.i_mouse()[2] < 0.0);
+    }
+}
@@ -0,0 +1,235 @@
+// A tiny GLSL preprocessor which only knows about `#include "path"`.
+//
+// WebGL has no include support, so shared snippets (see glsl/lib) get pasted in here before the
+// source reaches the driver. Everything else - #define, #ifdef and friends - is left for the real
+// GLSL preprocessor to deal with.
+//
+// We don't emit `#line` directives as the meaning of them changed between GLSL ES 1.00 and 3.00
+// (whether the number applies to the directive's line or the next one). Instead every line of the
+// output is recorded in a SourceMap so compile errors can be pointed back at the right file.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::program::LogLine;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<String>,
+    // (index into files, line number in that file) for every line of the output
+    lines: Vec<(usize, usize)>,
+}
+
+impl SourceMap {
+    // Find where a (1-based) line of the preprocessed output came from
+    pub fn locate(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, line) = self.lines.get(line.checked_sub(1)?)?;
+        Some((self.files[*file].as_str(), *line))
+    }
+
+    // Rewrite the `ERROR: 0:12: ...` style locations in a WebGL info log to `file:line`
+    pub fn remap_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.remap_log_line(line).unwrap_or_else(|| line.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn remap_log_line(&self, line: &str) -> Option<String> {
+        let located = LogLine::parse(line)?;
+        let (file, file_line) = self.locate(located.line)?;
+        Some(located.relocate(file, file_line))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preprocessed {
+    pub source: String,
+    pub source_map: SourceMap,
+    // Every file pulled in, including the one we started with
+    pub files: Vec<String>,
+}
+
+// Expand the includes in `source`, which is called `name` in error messages. `resolve` is handed
+// the path from each include and returns its contents. Each file is only included once, so
+// library snippets can include each other without needing their own include guards.
+pub fn preprocess<F>(name: &str, source: &str, mut resolve: F) -> Result<Preprocessed, PreprocessError>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut state = State {
+        resolve: &mut resolve,
+        output: String::new(),
+        source_map: SourceMap::default(),
+        included: HashSet::new(),
+        stack: Vec::new(),
+    };
+    state.included.insert(name.to_string());
+    state.expand(name, source)?;
+
+    let files = state.source_map.files.clone();
+    Ok(Preprocessed {
+        source: state.output,
+        source_map: state.source_map,
+        files,
+    })
+}
+
+struct State<'a> {
+    resolve: &'a mut dyn FnMut(&str) -> Option<String>,
+    output: String,
+    source_map: SourceMap,
+    included: HashSet<String>,
+    stack: Vec<String>,
+}
+
+impl State<'_> {
+    fn expand(&mut self, name: &str, source: &str) -> Result<(), PreprocessError> {
+        let file_idx = self.source_map.files.len();
+        self.source_map.files.push(name.to_string());
+        self.stack.push(name.to_string());
+
+        for (idx, line) in source.lines().enumerate() {
+            let error = |message: String| PreprocessError {
+                file: name.to_string(),
+                line: idx + 1,
+                message,
+            };
+
+            let path = match parse_include(line).map_err(error)? {
+                Some(path) => path,
+                None => {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                    self.source_map.lines.push((file_idx, idx + 1));
+                    continue;
+                }
+            };
+
+            if self.stack.contains(&path) {
+                return Err(error(format!("`{}` includes itself via {}", path, self.stack.join(" -> "))));
+            }
+            if !self.included.insert(path.clone()) {
+                // Already pulled in further up
+                continue;
+            }
+
+            let contents = (self.resolve)(&path).ok_or_else(|| error(format!("cannot find include `{}`", path)))?;
+            self.expand(&path, &contents)?;
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+// Returns the path for `#include "path"` lines, None for anything else
+fn parse_include(line: &str) -> Result<Option<String>, String> {
+    let directive = match line.trim().strip_prefix('#') {
+        Some(rest) => rest.trim_start(),
+        None => return Ok(None),
+    };
+    let rest = match directive.strip_prefix("include") {
+        Some(rest) => rest.trim(),
+        None => return Ok(None),
+    };
+
+    // Allow a trailing comment after the path
+    let rest = rest.split("//").next().unwrap_or("").trim();
+    match rest.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        Some(path) if !path.is_empty() => Ok(Some(path.to_string())),
+        _ => Err(format!("expected #include \"path\", found `{}`", line.trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files<'a>(files: &'a [(&str, &str)]) -> impl FnMut(&str) -> Option<String> + 'a {
+        |path| files.iter().find(|(name, _)| *name == path).map(|(_, source)| source.to_string())
+    }
+
+    #[test]
+    fn includes_each_file_once() {
+        let library = [
+            ("consts.glsl", "#define PI 3.14159\n"),
+            ("noise.glsl", "#include \"consts.glsl\"\nfloat noise(vec2 p) { return 0.0; }\n"),
+        ];
+        let main = "precision mediump float;\n#include \"consts.glsl\"\n  #  include \"noise.glsl\" // for noise()\nvoid main() {}\n";
+        let processed = preprocess("main.frag", main, files(&library)).unwrap();
+
+        assert_eq!(
+            processed.source,
+            "precision mediump float;\n#define PI 3.14159\nfloat noise(vec2 p) { return 0.0; }\nvoid main() {}\n"
+        );
+        assert_eq!(processed.files, vec!["main.frag", "consts.glsl", "noise.glsl"]);
+        assert_eq!(processed.source_map.locate(1), Some(("main.frag", 1)));
+        assert_eq!(processed.source_map.locate(2), Some(("consts.glsl", 1)));
+        assert_eq!(processed.source_map.locate(3), Some(("noise.glsl", 2)));
+        assert_eq!(processed.source_map.locate(4), Some(("main.frag", 4)));
+        assert_eq!(processed.source_map.locate(0), None);
+        assert_eq!(processed.source_map.locate(5), None);
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let library = [("a.glsl", "#include \"b.glsl\"\n"), ("b.glsl", "float b;\n#include \"a.glsl\"\n")];
+        let err = preprocess("main.frag", "#include \"a.glsl\"\n", files(&library)).unwrap_err();
+        assert_eq!(
+            err,
+            PreprocessError {
+                file: "b.glsl".to_string(),
+                line: 2,
+                message: "`a.glsl` includes itself via main.frag -> a.glsl -> b.glsl".to_string(),
+            }
+        );
+
+        let err = preprocess("main.frag", "\n#include \"main.frag\"\n", files(&[])).unwrap_err();
+        assert_eq!(err.to_string(), "main.frag:2: `main.frag` includes itself via main.frag");
+    }
+
+    #[test]
+    fn reports_missing_includes() {
+        let library = [("a.glsl", "float a;\n\n#include \"missing.glsl\"\n")];
+        let err = preprocess("main.frag", "#include \"a.glsl\"\n", files(&library)).unwrap_err();
+        assert_eq!(err.to_string(), "a.glsl:3: cannot find include `missing.glsl`");
+    }
+
+    #[test]
+    fn reports_bad_includes() {
+        for line in ["#include <noise.glsl>", "#include \"\"", "#include noise.glsl"] {
+            let err = preprocess("main.frag", line, files(&[])).unwrap_err();
+            assert_eq!(err.message, format!("expected #include \"path\", found `{}`", line));
+        }
+        // Only #include is ours
+        let source = "#define INCLUDED 1\n#ifdef INCLUDED\n#endif\n";
+        assert_eq!(preprocess("main.frag", source, files(&[])).unwrap().source, source);
+    }
+
+    #[test]
+    fn remaps_webgl_logs() {
+        let library = [("noise.glsl", "float noise(vec2 p) {\n    return q;\n}\n")];
+        let main = "precision mediump float;\n#include \"noise.glsl\"\nvoid main() { x; }\n";
+        let processed = preprocess("main.frag", main, files(&library)).unwrap();
+
+        let log = "ERROR: 0:3: 'q' : undeclared identifier\nERROR: 0:5: 'x' : undeclared identifier\nERROR: 0:99: 'y' : off the end\nERROR: 2 compilation errors.  No code generated.";
+        assert_eq!(
+            processed.source_map.remap_log(log),
+            "ERROR: noise.glsl:2: 'q' : undeclared identifier\nERROR: main.frag:3: 'x' : undeclared identifier\nERROR: 0:99: 'y' : off the end\nERROR: 2 compilation errors.  No code generated."
+        );
+    }
+}
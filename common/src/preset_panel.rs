@@ -0,0 +1,157 @@
+// Save, load, delete, import and export presets (see presets.rs). Sits inside the ParamPanel
+// and looks after its own storage, the tutorial only has to apply the values it hands back.
+
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{File, HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::download::download_text;
+use crate::params::{Param, ParamValue};
+use crate::presets::{self, Preset, PresetFile};
+
+#[derive(Clone, Debug, PartialEq, Properties)]
+pub struct PresetPanelProps {
+    // Presets are stored separately for each tutorial
+    pub tutorial: AttrValue,
+    pub params: Vec<Param>,
+    pub values: Vec<ParamValue>,
+    pub on_load: Callback<Vec<(String, ParamValue)>>,
+}
+
+pub enum PresetPanelMsg {
+    Name(String),
+    Save,
+    Load(String),
+    Delete,
+    Export,
+    Import(File),
+    Imported(Result<String, String>),
+}
+
+pub struct PresetPanel {
+    file: PresetFile,
+    // What's in the name box, also the preset Delete applies to
+    name: String,
+    error: Option<String>,
+}
+
+impl Component for PresetPanel {
+    type Message = PresetPanelMsg;
+    type Properties = PresetPanelProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        PresetPanel {
+            file: presets::load(&ctx.props().tutorial),
+            name: String::new(),
+            error: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let props = ctx.props();
+        self.error = None;
+
+        match msg {
+            PresetPanelMsg::Name(name) => {
+                self.name = name;
+            },
+            PresetPanelMsg::Save => {
+                let name = self.name.trim();
+                if name.is_empty() {
+                    self.error = Some("Give the preset a name first".to_string());
+                    return true;
+                }
+                let values: Vec<_> = props.params.iter().map(|param| param.name.clone()).zip(props.values.iter().copied()).collect();
+                self.file.insert(Preset::new(name, &values));
+                self.store();
+            },
+            PresetPanelMsg::Load(name) => {
+                if let Some(preset) = self.file.get(&name) {
+                    props.on_load.emit(preset.values_for(&props.params));
+                    self.name = name;
+                }
+            },
+            PresetPanelMsg::Delete => {
+                self.file.remove(self.name.trim());
+                self.name.clear();
+                self.store();
+            },
+            PresetPanelMsg::Export => {
+                if let Err(err) = download_text(&format!("{}-presets.json", props.tutorial), &self.file.to_json(), "application/json") {
+                    self.error = Some(err);
+                }
+            },
+            PresetPanelMsg::Import(file) => {
+                ctx.link().send_future(async move {
+                    let text = JsFuture::from(file.text()).await.map_err(|err| format!("Unable to read {}: {:?}", file.name(), err));
+                    PresetPanelMsg::Imported(text.and_then(|text| text.as_string().ok_or_else(|| "File isn't text".to_string())))
+                });
+                return false;
+            },
+            PresetPanelMsg::Imported(text) => {
+                match text.and_then(|text| presets::parse_import(&text, &props.tutorial)) {
+                    Ok(imported) => {
+                        self.file.merge(imported);
+                        self.store();
+                    },
+                    Err(err) => self.error = Some(err),
+                }
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let onselect = link.callback(|evt: Event| {
+            let select: HtmlSelectElement = evt.target_unchecked_into();
+            PresetPanelMsg::Load(select.value())
+        });
+        let onname = link.callback(|evt: InputEvent| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            PresetPanelMsg::Name(input.value())
+        });
+        let onimport = link.batch_callback(|evt: Event| {
+            let input: HtmlInputElement = evt.target_unchecked_into();
+            let file = input.files().and_then(|files| files.get(0));
+            // Clear it so choosing the same file again still fires a change
+            input.set_value("");
+            file.map(PresetPanelMsg::Import)
+        });
+        let exists = self.file.get(self.name.trim()).is_some();
+
+        html! {
+            <div class="preset_panel">
+                <select onchange={onselect}>
+                    <option value="" selected={!exists} disabled=true>{ "Load preset..." }</option>
+                    { for self.file.names().into_iter().map(|name| html! {
+                        <option selected={name == self.name} value={name.clone()}>{ name }</option>
+                    }) }
+                </select>
+                <div class="preset_row">
+                    <input type="text" placeholder="Preset name" value={self.name.clone()} oninput={onname} />
+                    <button onclick={link.callback(|_| PresetPanelMsg::Save)}>{ if exists { "Update" } else { "Save" } }</button>
+                    <button onclick={link.callback(|_| PresetPanelMsg::Delete)} disabled={!exists}>{ "Delete" }</button>
+                </div>
+                <div class="preset_row">
+                    <button onclick={link.callback(|_| PresetPanelMsg::Export)} disabled={self.file.presets.is_empty()}>{ "Export" }</button>
+                    <label class="preset_import">
+                        { "Import" }
+                        <input type="file" accept=".json,application/json" onchange={onimport} />
+                    </label>
+                </div>
+                if let Some(error) = &self.error {
+                    <div class="preset_error">{ error }</div>
+                }
+            </div>
+        }
+    }
+}
+
+impl PresetPanel {
+    fn store(&mut self) {
+        if let Err(err) = presets::save(&self.file) {
+            self.error = Some(err);
+        }
+    }
+}
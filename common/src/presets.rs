@@ -0,0 +1,279 @@
+// Named sets of parameter values, kept in localStorage per tutorial and exported as JSON.
+//
+//   {
+//     "version": 1,
+//     "tutorial": "neon-swirls",
+//     "presets": [
+//       { "name": "Thin and sharp", "values": { "width": 0.02, "blur": 0.05, "tint": [1, 0.5, 0] } }
+//     ]
+//   }
+//
+// The same layout is used in storage and for export. Shaders change after presets are saved, so
+// values are matched back up to the current uniforms when a preset is loaded: renamed uniforms
+// are found through `@was`, values outside a slider's range are clamped and anything that no
+// longer fits gets its default. A file has to say which version it is, and is only imported
+// into the tutorial it was exported from.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::params::{Param, ParamValue};
+
+// Bump when the layout changes, and convert the older layout in `parse_file`
+pub const SCHEMA_VERSION: u64 = 1;
+
+const STORAGE_PREFIX: &str = "webgl-shader-tutorials.presets.";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StoredValue {
+    Float(f32),
+    Color([f32; 3]),
+}
+
+impl From<StoredValue> for ParamValue {
+    fn from(value: StoredValue) -> Self {
+        match value {
+            StoredValue::Float(value) => ParamValue::Float(value),
+            StoredValue::Color(color) => ParamValue::Color(color),
+        }
+    }
+}
+
+impl From<ParamValue> for StoredValue {
+    fn from(value: ParamValue) -> Self {
+        match value {
+            ParamValue::Float(value) => StoredValue::Float(value),
+            ParamValue::Color(color) => StoredValue::Color(color),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub values: BTreeMap<String, StoredValue>,
+}
+
+impl Preset {
+    pub fn new(name: &str, values: &[(String, ParamValue)]) -> Self {
+        Preset {
+            name: name.to_string(),
+            values: values.iter().map(|(name, value)| (name.clone(), (*value).into())).collect(),
+        }
+    }
+
+    // A value for every one of `params`, taken from the preset where it still fits
+    pub fn values_for(&self, params: &[Param]) -> Vec<(String, ParamValue)> {
+        params
+            .iter()
+            .map(|param| {
+                let stored = std::iter::once(&param.name)
+                    .chain(&param.previous_names)
+                    .find_map(|name| self.values.get(name));
+                let value = stored.and_then(|stored| param.fit((*stored).into())).unwrap_or(param.default);
+                (param.name.clone(), value)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresetFile {
+    pub version: u64,
+    pub tutorial: String,
+    pub presets: Vec<Preset>,
+}
+
+impl PresetFile {
+    pub fn new(tutorial: &str) -> Self {
+        PresetFile {
+            version: SCHEMA_VERSION,
+            tutorial: tutorial.to_string(),
+            presets: Vec::new(),
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.presets.iter().map(|preset| preset.name.clone()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    // Add a preset, replacing any with the same name
+    pub fn insert(&mut self, preset: Preset) {
+        match self.presets.iter_mut().find(|existing| existing.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.presets.retain(|preset| preset.name != name);
+    }
+
+    // Bring in everything from an imported file, the imported version wins on a name clash
+    pub fn merge(&mut self, other: PresetFile) {
+        for preset in other.presets {
+            self.insert(preset);
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Presets always serialize")
+    }
+}
+
+// Read a preset file of any version we know about
+pub fn parse_file(json: &str) -> Result<PresetFile, String> {
+    let value: Value = serde_json::from_str(json).map_err(|err| format!("Not a preset file: {}", err))?;
+    if !value.is_object() {
+        return Err("Not a preset file: expected an object".to_string());
+    }
+    let version = value.get("version").ok_or("Preset file has no version")?;
+    let version = version.as_u64().ok_or("Preset file version isn't a number")?;
+    if version > SCHEMA_VERSION {
+        return Err(format!("Preset file is version {}, this page only understands up to {}", version, SCHEMA_VERSION));
+    }
+
+    serde_json::from_value(value).map_err(|err| format!("Invalid preset file: {}", err))
+}
+
+// Read a file being imported into `tutorial`'s presets. Values are matched up by uniform name,
+// so presets made for another tutorial would mostly load as that tutorial's defaults. A file
+// without a tutorial is taken as it is.
+pub fn parse_import(json: &str, tutorial: &str) -> Result<PresetFile, String> {
+    let file = parse_file(json)?;
+    if !file.tutorial.is_empty() && file.tutorial != tutorial {
+        return Err(format!("These presets are for {}, not {}", file.tutorial, tutorial));
+    }
+    Ok(file)
+}
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// The presets saved for `tutorial`, or an empty set if there aren't any or they can't be read
+pub fn load(tutorial: &str) -> PresetFile {
+    let stored = storage().and_then(|storage| storage.get_item(&format!("{}{}", STORAGE_PREFIX, tutorial)).ok()?);
+    match stored.map(|json| parse_file(&json)) {
+        Some(Ok(file)) => PresetFile {
+            // The storage key already says which tutorial these are for. The one in the file can
+            // only differ after the tutorial was renamed, or be empty from before there was one.
+            tutorial: tutorial.to_string(),
+            ..file
+        },
+        Some(Err(err)) => {
+            web_sys::console::error_1(&format!("Ignoring saved presets: {}", err).into());
+            PresetFile::new(tutorial)
+        },
+        None => PresetFile::new(tutorial),
+    }
+}
+
+pub fn save(file: &PresetFile) -> Result<(), String> {
+    let storage = storage().ok_or("localStorage isn't available")?;
+    storage
+        .set_item(&format!("{}{}", STORAGE_PREFIX, file.tutorial), &file.to_json())
+        .map_err(|err| format!("Unable to save presets: {:?}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::parse_params;
+
+    const SOURCE: &str = "
+uniform float width; // @range 0.01..0.5 @default 0.1 @was thickness
+uniform float mirror; // @toggle @default 1
+uniform vec3 tint; // @color @default #ff8000
+";
+
+    fn preset(json: &str) -> Preset {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut file = PresetFile::new("neon-swirls");
+        file.insert(Preset::new("Thin", &[("width".to_string(), ParamValue::Float(0.02)), ("tint".to_string(), ParamValue::Color([1.0, 0.5, 0.0]))]));
+        assert_eq!(parse_file(&file.to_json()).unwrap(), file);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let err = parse_file(r#"{ "version": 2, "tutorial": "neon-swirls", "presets": [] }"#).unwrap_err();
+        assert!(err.contains("version 2"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_files_without_a_version() {
+        let err = parse_file(r#"{ "tutorial": "neon-swirls", "presets": [ { "name": "Wide", "values": { "width": 0.4 } } ] }"#).unwrap_err();
+        assert_eq!(err, "Preset file has no version");
+        assert!(parse_file(r#"{ "presets": [] }"#).is_err());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(parse_file("").unwrap_err().starts_with("Not a preset file"));
+        assert!(parse_file("{ presets").unwrap_err().starts_with("Not a preset file"));
+        assert!(parse_file("[1, 2]").unwrap_err().starts_with("Not a preset file"));
+        assert!(parse_file(r#"{ "version": "one", "presets": [] }"#).is_err());
+        assert!(parse_file(r#"{ "version": 1, "tutorial": "a", "presets": "none" }"#).unwrap_err().starts_with("Invalid preset file"));
+        assert!(parse_file(r#"{ "version": 1, "tutorial": "a", "presets": [ { "name": "x", "values": { "width": "wide" } } ] }"#).is_err());
+    }
+
+    #[test]
+    fn imports_only_for_the_same_tutorial() {
+        let json = |tutorial: &str| format!(r#"{{ "version": 1, "tutorial": "{}", "presets": [] }}"#, tutorial);
+        assert!(parse_import(&json("neon-swirls"), "neon-swirls").is_ok());
+        assert!(parse_import(&json(""), "neon-swirls").is_ok());
+        let err = parse_import(&json("mandelbrot"), "neon-swirls").unwrap_err();
+        assert_eq!(err, "These presets are for mandelbrot, not neon-swirls");
+    }
+
+    #[test]
+    fn clamps_to_the_range() {
+        let params = parse_params(SOURCE);
+        let values = preset(r#"{ "name": "p", "values": { "width": 3, "mirror": 0.2, "tint": [2, -1, 0.5] } }"#).values_for(&params);
+        assert_eq!(
+            values,
+            vec![
+                ("width".to_string(), ParamValue::Float(0.5)),
+                ("mirror".to_string(), ParamValue::Float(0.0)),
+                ("tint".to_string(), ParamValue::Color([1.0, 0.0, 0.5])),
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_unknown_names_and_wrong_kinds() {
+        let params = parse_params(SOURCE);
+        // A colour where there's now a slider gets the default
+        let values = preset(r#"{ "name": "p", "values": { "blur": 0.3, "width": [1, 1, 1] } }"#).values_for(&params);
+        assert_eq!(
+            values,
+            vec![
+                ("width".to_string(), ParamValue::Float(0.1)),
+                ("mirror".to_string(), ParamValue::Float(1.0)),
+                ("tint".to_string(), ParamValue::Color([1.0, 128.0 / 255.0, 0.0])),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_renamed_params() {
+        let params = parse_params(SOURCE);
+        let values = preset(r#"{ "name": "p", "values": { "thickness": 0.2 } }"#).values_for(&params);
+        assert_eq!(values[0], ("width".to_string(), ParamValue::Float(0.2)));
+
+        // The current name wins over an old one
+        let values = preset(r#"{ "name": "p", "values": { "thickness": 0.2, "width": 0.3 } }"#).values_for(&params);
+        assert_eq!(values[0], ("width".to_string(), ParamValue::Float(0.3)));
+    }
+}
@@ -0,0 +1,296 @@
+// Compiling shader programs with #defines supplied from Rust.
+//
+// Shaders give their tunable constants a default inside `#ifndef NAME ... #endif`, and Rust can
+// override any of them by passing a Defines map when compiling. Every (source, defines)
+// combination is only compiled once - switching back to a quality level or variant that has
+// already been used just hands back the cached program.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use web_sys::{WebGlProgram, WebGlRenderingContext as GL, WebGlShader};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Defines {
+    // Sorted so the same set of defines always produces the same source (and cache key)
+    values: BTreeMap<String, String>,
+}
+
+impl Defines {
+    pub fn new() -> Self {
+        Defines::default()
+    }
+
+    // Set a define to a raw piece of GLSL, e.g. `.set("MODE", "2")`
+    pub fn set(mut self, name: &str, value: impl ToString) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    // Floats always need a decimal point in GLSL ES 1.00, `1` is an int and won't convert. GLSL
+    // has no way to write NaN or infinity, so those aren't set and the shader keeps its default.
+    pub fn set_float(self, name: &str, value: f32) -> Self {
+        if !value.is_finite() {
+            return self;
+        }
+        self.set(name, format!("{:?}", value))
+    }
+
+    pub fn set_int(self, name: &str, value: i32) -> Self {
+        self.set(name, value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // Both sets of defines, with `other`'s value wherever they both set a name
+    pub fn merge(mut self, other: &Defines) -> Self {
+        self.values.extend(other.values.iter().map(|(name, value)| (name.clone(), value.clone())));
+        self
+    }
+
+    // The `#define` lines for these values, one per line
+    pub fn to_glsl(&self) -> String {
+        self.values.iter().map(|(name, value)| format!("#define {} {}\n", name, value)).collect()
+    }
+
+    // Add the defines to the top of `source`, after any #version line
+    pub fn apply(&self, source: &str) -> String {
+        if self.is_empty() {
+            return source.to_string();
+        }
+
+        let (version, body) = split_version(source);
+        format!("{}{}{}", version, self.to_glsl(), body)
+    }
+}
+
+// #version has to be the first thing in a shader, so the defines go just after it
+fn split_version(source: &str) -> (&str, &str) {
+    if source.trim_start().starts_with("#version") {
+        match source.find('\n') {
+            Some(end) => source.split_at(end + 1),
+            None => (source, ""),
+        }
+    } else {
+        ("", source)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ProgramKey {
+    source_hash: u64,
+    defines: Defines,
+}
+
+#[derive(Default)]
+pub struct ProgramCache {
+    programs: HashMap<ProgramKey, WebGlProgram>,
+}
+
+impl ProgramCache {
+    pub fn new() -> Self {
+        ProgramCache::default()
+    }
+
+    // Returns the program for this source and set of defines, compiling it the first time it's
+    // asked for. On failure the error has the driver's info log for whichever stage failed, with
+    // line numbers matching the source passed in (not counting the injected defines).
+    pub fn get_or_compile(
+        &mut self,
+        gl: &GL,
+        vert_code: &str,
+        frag_code: &str,
+        defines: &Defines,
+    ) -> Result<WebGlProgram, String> {
+        let mut hasher = DefaultHasher::new();
+        vert_code.hash(&mut hasher);
+        frag_code.hash(&mut hasher);
+        let key = ProgramKey {
+            source_hash: hasher.finish(),
+            defines: defines.clone(),
+        };
+
+        if let Some(program) = self.programs.get(&key) {
+            return Ok(program.clone());
+        }
+
+        let program = compile_program(gl, vert_code, frag_code, defines)?;
+        self.programs.insert(key, program.clone());
+        Ok(program)
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    // Drop every compiled program, e.g. when the sources themselves have changed
+    pub fn clear(&mut self, gl: &GL) {
+        for (_, program) in self.programs.drain() {
+            gl.delete_program(Some(&program));
+        }
+    }
+}
+
+// Compile and link a program without any caching
+pub fn compile_program(gl: &GL, vert_code: &str, frag_code: &str, defines: &Defines) -> Result<WebGlProgram, String> {
+    let vert_shader = compile_shader(gl, GL::VERTEX_SHADER, vert_code, defines)
+        .map_err(|log| format!("Vertex shader failed to compile:\n{}", log))?;
+    let frag_shader = match compile_shader(gl, GL::FRAGMENT_SHADER, frag_code, defines) {
+        Ok(shader) => shader,
+        Err(log) => {
+            gl.delete_shader(Some(&vert_shader));
+            return Err(format!("Fragment shader failed to compile:\n{}", log));
+        }
+    };
+
+    let program = gl.create_program().ok_or("Unable to create shader program")?;
+    gl.attach_shader(&program, &vert_shader);
+    gl.attach_shader(&program, &frag_shader);
+    gl.link_program(&program);
+
+    // The program keeps what it needs, the shader objects can go
+    gl.delete_shader(Some(&vert_shader));
+    gl.delete_shader(Some(&frag_shader));
+
+    let linked = gl.get_program_parameter(&program, GL::LINK_STATUS).as_bool().unwrap_or(false);
+    if linked {
+        Ok(program)
+    } else {
+        let log = gl.get_program_info_log(&program).unwrap_or_default();
+        gl.delete_program(Some(&program));
+        Err(format!("Shader program failed to link:\n{}", log))
+    }
+}
+
+fn compile_shader(gl: &GL, kind: u32, code: &str, defines: &Defines) -> Result<WebGlShader, String> {
+    let shader = gl.create_shader(kind).ok_or("Unable to create shader")?;
+    gl.shader_source(&shader, &defines.apply(code));
+    gl.compile_shader(&shader);
+
+    let compiled = gl.get_shader_parameter(&shader, GL::COMPILE_STATUS).as_bool().unwrap_or(false);
+    if compiled {
+        Ok(shader)
+    } else {
+        let log = gl.get_shader_info_log(&shader).unwrap_or_default();
+        gl.delete_shader(Some(&shader));
+        Err(shift_log_lines(&log, defines.values.len()))
+    }
+}
+
+// Take the injected #define lines back off the line numbers in `ERROR: 0:12: ...` logs
+fn shift_log_lines(log: &str, offset: usize) -> String {
+    if offset == 0 {
+        return log.to_string();
+    }
+
+    log.lines()
+        .map(|line| match LogLine::parse(line) {
+            Some(located) => located.relocate(&located.source_string.to_string(), located.line.saturating_sub(offset)),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// A line of a WebGL info log that points at a line of the source,
+// `ERROR: 0:12: 'x' : undeclared identifier`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LogLine<'a> {
+    pub severity: &'a str,
+    // Always 0, WebGL only ever passes the driver one string
+    pub source_string: usize,
+    pub line: usize,
+    // Everything after the line number's colon, leading space and all
+    pub message: &'a str,
+}
+
+impl<'a> LogLine<'a> {
+    pub fn parse(line: &'a str) -> Option<LogLine<'a>> {
+        let (severity, rest) = line.split_once(": ")?;
+        let mut parts = rest.splitn(3, ':');
+        let source_string = parts.next()?.trim().parse().ok()?;
+        let line = parts.next()?.trim().parse().ok()?;
+        let message = parts.next()?;
+        Some(LogLine { severity, source_string, line, message })
+    }
+
+    // The same line pointing somewhere else, `file` being a file name or the source string
+    pub fn relocate(&self, file: &str, line: usize) -> String {
+        format!("{}: {}:{}:{}", self.severity, file, line, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_keep_their_decimal_point() {
+        let defines = Defines::new().set_float("A", 1.0).set_float("B", 0.25).set_float("C", -3.5).set_int("D", 4);
+        assert_eq!(defines.to_glsl(), "#define A 1.0\n#define B 0.25\n#define C -3.5\n#define D 4\n");
+    }
+
+    #[test]
+    fn non_finite_floats_are_left_out() {
+        let defines = Defines::new().set_float("A", f32::NAN).set_float("B", f32::INFINITY).set_float("C", f32::NEG_INFINITY);
+        assert!(defines.is_empty());
+        assert_eq!(Defines::new().set_float("A", 2.0).set_float("A", f32::NAN).get("A"), Some("2.0"));
+    }
+
+    #[test]
+    fn merge_prefers_the_other_set() {
+        let base = Defines::new().set_int("DEPTH", 20).set_int("MODE", 1);
+        let lesson = Defines::new().set_int("DEPTH", 0).set_float("SPEED", 0.5);
+        let merged = base.merge(&lesson);
+        assert_eq!(merged.to_glsl(), "#define DEPTH 0\n#define MODE 1\n#define SPEED 0.5\n");
+    }
+
+    #[test]
+    fn defines_go_after_the_version() {
+        let defines = Defines::new().set_int("N", 3);
+        assert_eq!(defines.apply("#version 100\nvoid main() {}\n"), "#version 100\n#define N 3\nvoid main() {}\n");
+        assert_eq!(defines.apply("  #version 100\nx"), "  #version 100\n#define N 3\nx");
+        assert_eq!(defines.apply("precision mediump float;\n"), "#define N 3\nprecision mediump float;\n");
+        // Nothing to add, nothing changes
+        assert_eq!(Defines::new().apply("#version 100\nx"), "#version 100\nx");
+    }
+
+    #[test]
+    fn split_version_without_a_newline() {
+        assert_eq!(split_version("#version 100"), ("#version 100", ""));
+        assert_eq!(split_version("void main() {}"), ("", "void main() {}"));
+        // Only at the very start
+        assert_eq!(split_version("x\n#version 100\n"), ("", "x\n#version 100\n"));
+    }
+
+    #[test]
+    fn log_lines_shift_back_past_the_defines() {
+        let log = "ERROR: 0:12: 'x' : undeclared identifier\nWARNING: 0:1: 'y' : unused\nERROR: 1 compilation errors.  No code generated.";
+        assert_eq!(
+            shift_log_lines(log, 2),
+            "ERROR: 0:10: 'x' : undeclared identifier\nWARNING: 0:0: 'y' : unused\nERROR: 1 compilation errors.  No code generated."
+        );
+        assert_eq!(shift_log_lines(log, 0), log);
+    }
+
+    #[test]
+    fn parses_log_lines() {
+        let line = LogLine::parse("ERROR: 0:7: 'f' : no matching overloaded function found").unwrap();
+        assert_eq!((line.severity, line.source_string, line.line, line.message), ("ERROR", 0, 7, " 'f' : no matching overloaded function found"));
+        assert_eq!(line.relocate("main.frag", 3), "ERROR: main.frag:3: 'f' : no matching overloaded function found");
+        assert_eq!(LogLine::parse("ERROR: 2 compilation errors.  No code generated."), None);
+        assert_eq!(LogLine::parse("ERROR: main.frag:3: already remapped"), None);
+        assert_eq!(LogLine::parse("no severity"), None);
+    }
+}
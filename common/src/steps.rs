@@ -0,0 +1,220 @@
+// Build-up steps. A shader can mark the code each stage of its effect adds with `// @step`
+// comments, and get back a version of itself with only the stages up to a given one, so the
+// final image can be put together a piece at a time.
+//
+//   // @step 1 Reflection
+//   uv.x = abs(uv.x);
+//   // @step end
+//   col += texture2D(texScene, uv).rgb; // @step 3 Texture
+//
+// A marker on a line of its own starts a block that runs until `// @step end` or the next
+// marker, one at the end of a line of code marks just that line. Unmarked code is in every
+// step. Anything after the number names the step. Steps have to come in order through the
+// file, and each can only have one block, though more single lines can follow it. Code that's
+// left out becomes a blank line rather than going, so line numbers in compile errors still match
+// the full shader.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub number: u32,
+    // Empty if no marker for the step gave it a name
+    pub label: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShaderSteps {
+    // Each line of the shader, and the step it belongs to if any
+    lines: Vec<(String, Option<u32>)>,
+    // In order, as they have to be in the file
+    steps: Vec<Step>,
+}
+
+enum Marker {
+    Start(u32, String),
+    End,
+}
+
+impl ShaderSteps {
+    // Errors give the line of the shader they're on, counted from 1
+    pub fn parse(source: &str) -> Result<ShaderSteps, String> {
+        let mut lines = Vec::new();
+        let mut steps: Vec<Step> = Vec::new();
+        let mut block = None;
+        // (step, line) for every block started so far
+        let mut blocks: Vec<(u32, usize)> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let marker = parse_marker(line).map_err(|err| format!("Line {}: {}", index + 1, err))?;
+            let (code, marker) = match marker {
+                Some(marker) => marker,
+                None => {
+                    lines.push((line.to_string(), block));
+                    continue;
+                }
+            };
+
+            let number = match marker {
+                Marker::Start(number, label) => {
+                    if let Some(last) = steps.iter().map(|step| step.number).max().filter(|last| *last > number) {
+                        return Err(format!("Line {}: @step {} comes after @step {}, steps have to be in order", index + 1, number, last));
+                    }
+                    if code.trim().is_empty() {
+                        if let Some((_, start)) = blocks.iter().find(|(step, _)| *step == number) {
+                            return Err(format!("Line {}: @step {} already has a block, on line {}", index + 1, number, start));
+                        }
+                        blocks.push((number, index + 1));
+                    }
+                    match steps.iter_mut().find(|step| step.number == number) {
+                        Some(step) if step.label.is_empty() => step.label = label,
+                        Some(_) => {},
+                        None => steps.push(Step { number, label }),
+                    }
+                    Some(number)
+                },
+                Marker::End if code.trim().is_empty() && block.is_some() => None,
+                Marker::End => return Err(format!("Line {}: @step end without a step to end", index + 1)),
+            };
+
+            if code.trim().is_empty() {
+                // The marker line itself is only a comment, it can stay in every step
+                block = number;
+                lines.push((line.to_string(), None));
+            } else {
+                lines.push((line.to_string(), number));
+            }
+        }
+
+        Ok(ShaderSteps { lines, steps })
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // The shader with only the code for the steps numbered up to and including `number`
+    pub fn up_to(&self, number: u32) -> String {
+        self.lines
+            .iter()
+            .map(|(line, step)| match step {
+                Some(step) if *step > number => "\n".to_string(),
+                _ => format!("{}\n", line),
+            })
+            .collect()
+    }
+}
+
+// The code before the marker and the marker, or None if the line doesn't have one
+fn parse_marker(line: &str) -> Result<Option<(&str, Marker)>, String> {
+    let Some((code, comment)) = line.split_once("//") else {
+        return Ok(None);
+    };
+    let marker = comment.trim().strip_prefix("@step").filter(|args| args.is_empty() || args.starts_with(char::is_whitespace));
+    let Some(args) = marker else {
+        return Ok(None);
+    };
+    let args = args.trim();
+    if args == "end" {
+        return Ok(Some((code, Marker::End)));
+    }
+
+    let (number, label) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match number.parse() {
+        Ok(0) => Err("Steps are numbered from 1".to_string()),
+        Ok(number) => Ok(Some((code, Marker::Start(number, label.trim().to_string())))),
+        Err(_) => Err(format!("@step needs a number, not `{}`", number)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "precision mediump float;
+void main() {
+  vec2 uv = gl_FragCoord.xy;
+  // @step 1 Reflection
+  uv.x = abs(uv.x);
+  // @step end
+  vec3 col = vec3(uv, 0.0);
+  // @step 2
+  col *= 2.0;
+  // @step 3 Texture
+  col += 1.0;
+  // @step end
+  col.b = 0.5; // @step 3
+  gl_FragColor = vec4(col, 1.0);
+}
+";
+
+    fn kept(source: &str) -> Vec<&str> {
+        source.lines().filter(|line| !line.trim().is_empty() && !line.contains("@step")).collect()
+    }
+
+    #[test]
+    fn reads_steps() {
+        let steps = ShaderSteps::parse(SOURCE).unwrap();
+        assert_eq!(
+            steps.steps(),
+            [
+                Step { number: 1, label: "Reflection".to_string() },
+                Step { number: 2, label: String::new() },
+                Step { number: 3, label: "Texture".to_string() },
+            ]
+        );
+        assert!(ShaderSteps::parse("void main() {}\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn unmarked_code_is_always_kept() {
+        let steps = ShaderSteps::parse(SOURCE).unwrap();
+        let first = steps.up_to(1);
+        assert_eq!(
+            kept(&first),
+            vec!["precision mediump float;", "void main() {", "  vec2 uv = gl_FragCoord.xy;", "  uv.x = abs(uv.x);", "  vec3 col = vec3(uv, 0.0);", "  gl_FragColor = vec4(col, 1.0);", "}"]
+        );
+        // Left out lines are blank, so everything else stays on the same line
+        assert_eq!(first.lines().count(), SOURCE.lines().count());
+        assert_eq!(first.lines().nth(13), SOURCE.lines().nth(13));
+
+        assert_eq!(kept(&steps.up_to(2)).len(), 8);
+        // Before any step there's just the code that's in all of them
+        assert_eq!(kept(&steps.up_to(0)).len(), 6);
+    }
+
+    #[test]
+    fn the_last_step_is_the_whole_shader() {
+        let steps = ShaderSteps::parse(SOURCE).unwrap();
+        assert_eq!(steps.up_to(3), SOURCE);
+        assert_eq!(steps.up_to(u32::MAX), SOURCE);
+    }
+
+    #[test]
+    fn steps_have_to_be_in_order() {
+        let err = ShaderSteps::parse("// @step 2\nx;\n// @step 1\ny;\n").unwrap_err();
+        assert_eq!(err, "Line 3: @step 1 comes after @step 2, steps have to be in order");
+        let err = ShaderSteps::parse("// @step 1\nx;\n// @step 2\ny;\nz; // @step 1\n").unwrap_err();
+        assert_eq!(err, "Line 5: @step 1 comes after @step 2, steps have to be in order");
+    }
+
+    #[test]
+    fn steps_only_have_one_block() {
+        let err = ShaderSteps::parse("// @step 1\nx;\n// @step end\n\n// @step 1\ny;\n").unwrap_err();
+        assert_eq!(err, "Line 5: @step 1 already has a block, on line 1");
+        // More single lines after the block are fine
+        assert!(ShaderSteps::parse("// @step 1\nx;\n// @step end\ny; // @step 1\nz; // @step 1\n").is_ok());
+    }
+
+    #[test]
+    fn bad_markers() {
+        assert_eq!(ShaderSteps::parse("x;\n// @step 0\n").unwrap_err(), "Line 2: Steps are numbered from 1");
+        assert_eq!(ShaderSteps::parse("// @step one\n").unwrap_err(), "Line 1: @step needs a number, not `one`");
+        assert_eq!(ShaderSteps::parse("// @step end\n").unwrap_err(), "Line 1: @step end without a step to end");
+        assert_eq!(ShaderSteps::parse("x; // @step end\n").unwrap_err(), "Line 1: @step end without a step to end");
+        // Other annotations that happen to have @step in them aren't markers
+        assert!(ShaderSteps::parse("uniform float a; // @range 0..1 @step 0.1\n").unwrap().is_empty());
+    }
+}
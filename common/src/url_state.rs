@@ -0,0 +1,223 @@
+// What a tutorial is showing, as query string parameters, so a link reproduces it exactly.
+//
+//   /neon-swirls?t=12.5&speed=1&blur=0.4&tint=ff8000&palette=0.5,0.5,0.5,0.5,0.5,0.5,1,1,1,0,0.33,0.67
+//
+// `t` is the shader time in seconds, `speed` how fast it runs and `palette` the twelve cosine
+// palette coefficients, a to d. Everything else is a value keyed by uniform name, usually from
+// ShaderParams, though a tutorial can add Params of its own for state that has no slider. The map
+// goes through yew_router's query helpers, which do the actual URL encoding.
+
+use std::collections::BTreeMap;
+
+use crate::palette::Palette;
+use crate::params::{color_to_hex, parse_color, Param, ParamValue};
+
+pub const TIME_KEY: &str = "t";
+pub const SPEED_KEY: &str = "speed";
+pub const PALETTE_KEY: &str = "palette";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UrlState {
+    pub time: Option<f32>,
+    pub speed: Option<f32>,
+    pub palette: Option<Palette>,
+    pub values: Vec<(String, ParamValue)>,
+}
+
+impl UrlState {
+    pub fn to_query(&self) -> BTreeMap<String, String> {
+        let mut query = BTreeMap::new();
+        if let Some(time) = self.time {
+            query.insert(TIME_KEY.to_string(), time.to_string());
+        }
+        if let Some(speed) = self.speed {
+            query.insert(SPEED_KEY.to_string(), speed.to_string());
+        }
+        if let Some(palette) = &self.palette {
+            query.insert(PALETTE_KEY.to_string(), encode_palette(palette));
+        }
+        for (name, value) in &self.values {
+            query.insert(name.clone(), encode_value(value));
+        }
+        query
+    }
+
+    // Read the state back. Only the keys for `params` are picked up, fitted to them like a
+    // preset's values, and anything that doesn't parse is skipped so a mangled link still shows
+    // the rest of the state.
+    pub fn from_query(query: &BTreeMap<String, String>, params: &[Param]) -> UrlState {
+        let number = |key: &str| query.get(key).and_then(|value| value.parse::<f32>().ok()).filter(|value| value.is_finite());
+
+        let values = params
+            .iter()
+            .filter_map(|param| {
+                let value = decode_value(query.get(&param.name)?, &param.default)?;
+                Some((param.name.clone(), param.fit(value)?))
+            })
+            .collect();
+
+        UrlState {
+            time: number(TIME_KEY),
+            speed: number(SPEED_KEY),
+            palette: query.get(PALETTE_KEY).and_then(|text| decode_palette(text)),
+            values,
+        }
+    }
+}
+
+// Floats use Rust's shortest representation that parses back to the same value, colours are
+// hex without the `#` so they don't need escaping.
+fn encode_value(value: &ParamValue) -> String {
+    match value {
+        ParamValue::Float(value) => value.to_string(),
+        ParamValue::Color(color) => color_to_hex(*color).trim_start_matches('#').to_string(),
+    }
+}
+
+// `like` says which kind of value to expect
+fn decode_value(text: &str, like: &ParamValue) -> Option<ParamValue> {
+    match like {
+        ParamValue::Float(_) => text.parse::<f32>().ok().filter(|value| value.is_finite()).map(ParamValue::Float),
+        ParamValue::Color(_) => parse_color(&format!("#{}", text.trim_start_matches('#'))).map(ParamValue::Color),
+    }
+}
+
+fn encode_palette(palette: &Palette) -> String {
+    (0..4).flat_map(|index| palette.coefficient(index)).map(|value| value.to_string()).collect::<Vec<_>>().join(",")
+}
+
+// All twelve numbers or nothing
+fn decode_palette(text: &str) -> Option<Palette> {
+    let values = text
+        .split(',')
+        .map(|value| value.trim().parse::<f32>().ok().filter(|value| value.is_finite()))
+        .collect::<Option<Vec<f32>>>()?;
+    if values.len() != 12 {
+        return None;
+    }
+    let mut palette = Palette::default();
+    for (index, chunk) in values.chunks(3).enumerate() {
+        palette.set_coefficient(index, [chunk[0], chunk[1], chunk[2]]);
+    }
+    Some(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::parse_params;
+
+    const SOURCE: &str = "
+uniform float blur; // @range 0..1 @default 0.3
+uniform float mirror; // @toggle @default 1
+uniform vec3 tint; // @color @default #ff8000
+uniform float offset; // @range -1e6..1e6
+";
+
+    // Through the same encoding yew_router uses for the query string
+    fn round_trip(state: &UrlState) -> UrlState {
+        let encoded = serde_urlencoded::to_string(state.to_query()).unwrap();
+        let decoded: BTreeMap<String, String> = serde_urlencoded::from_str(&encoded).unwrap();
+        UrlState::from_query(&decoded, &parse_params(SOURCE))
+    }
+
+    #[test]
+    fn round_trips_every_kind_of_value() {
+        let state = UrlState {
+            time: Some(12.5),
+            speed: Some(0.75),
+            palette: Some(crate::palette::preset("Candy").unwrap()),
+            values: vec![
+                ("blur".to_string(), ParamValue::Float(0.4)),
+                ("mirror".to_string(), ParamValue::Float(0.0)),
+                ("tint".to_string(), ParamValue::Color([1.0, 0.0, 0.2])),
+            ],
+        };
+        assert_eq!(round_trip(&state), state);
+    }
+
+    #[test]
+    fn floats_round_trip_exactly() {
+        for value in [0.1, 1.0 / 3.0, -2.5e-7, 123456.79, f32::MIN_POSITIVE] {
+            let state = UrlState {
+                time: Some(value),
+                speed: None,
+                palette: Some(Palette { a: [value; 3], b: [value; 3], c: [value; 3], d: [value; 3] }),
+                values: vec![("offset".to_string(), ParamValue::Float(value))],
+            };
+            assert_eq!(round_trip(&state), state);
+        }
+    }
+
+    #[test]
+    fn colours_round_trip_at_8_bits() {
+        // The hex encoding can only hold 8 bits per channel, so start from values it can
+        let color = [0x12 as f32 / 255.0, 0xab as f32 / 255.0, 1.0];
+        let state = UrlState {
+            values: vec![("tint".to_string(), ParamValue::Color(color))],
+            ..UrlState::default()
+        };
+        assert_eq!(round_trip(&state), state);
+    }
+
+    #[test]
+    fn empty_state_is_an_empty_query() {
+        let state = UrlState::default();
+        assert!(state.to_query().is_empty());
+        assert_eq!(round_trip(&state), state);
+    }
+
+    #[test]
+    fn reads_a_hand_written_link() {
+        let query = serde_urlencoded::from_str("t=12.5&blur=0.4&tint=%23ff0000").unwrap();
+        let state = UrlState::from_query(&query, &parse_params(SOURCE));
+        assert_eq!(state.time, Some(12.5));
+        assert_eq!(state.speed, None);
+        assert_eq!(
+            state.values,
+            vec![
+                ("blur".to_string(), ParamValue::Float(0.4)),
+                ("tint".to_string(), ParamValue::Color([1.0, 0.0, 0.0])),
+            ]
+        );
+    }
+
+    #[test]
+    fn fits_values_to_the_params() {
+        let query = serde_urlencoded::from_str("blur=5&mirror=0.2&offset=-1e9").unwrap();
+        let state = UrlState::from_query(&query, &parse_params(SOURCE));
+        assert_eq!(
+            state.values,
+            vec![
+                ("blur".to_string(), ParamValue::Float(1.0)),
+                ("mirror".to_string(), ParamValue::Float(0.0)),
+                ("offset".to_string(), ParamValue::Float(-1e6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unknown_and_invalid_values() {
+        let query = serde_urlencoded::from_str("t=soon&speed=NaN&blur=lots&tint=fff&other=1&mirror=1&palette=1,2,3").unwrap();
+        let state = UrlState::from_query(&query, &parse_params(SOURCE));
+        assert_eq!(
+            state,
+            UrlState {
+                values: vec![("mirror".to_string(), ParamValue::Float(1.0))],
+                ..UrlState::default()
+            }
+        );
+    }
+
+    #[test]
+    fn reads_a_palette() {
+        let query = serde_urlencoded::from_str("palette=0.8,0.5,0.4,0.2,0.4,0.2,2,1,1,0,0.25,0.25").unwrap();
+        let state = UrlState::from_query(&query, &[]);
+        assert_eq!(state.palette, crate::palette::preset("Desert"));
+
+        for bad in ["0.8,0.5,0.4,0.2,0.4,0.2,2,1,1,0,0.25", "0.8,0.5,0.4,0.2,0.4,0.2,2,1,1,0,0.25,inf", ""] {
+            let query = BTreeMap::from([(PALETTE_KEY.to_string(), bad.to_string())]);
+            assert_eq!(UrlState::from_query(&query, &[]).palette, None, "{:?}", bad);
+        }
+    }
+}
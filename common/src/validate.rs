@@ -0,0 +1,395 @@
+// Build-time checking of the tutorial shaders.
+//
+// The tutorials are written in GLSL ES 1.00 (WebGL 1) which naga can't read directly, its GLSL
+// front-end only understands Vulkan flavoured GLSL 4.50. Rather than writing a parser of our own
+// we rewrite the handful of declarations that differ (attributes, varyings, loose uniforms,
+// samplers and gl_FragColor) line by line and let naga parse and type-check the rest. Every
+// rewritten line stays on the same line number so errors point back at the original file.
+
+use std::fmt;
+use std::path::Path;
+
+use naga::front::glsl::{Frontend, Options};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::ShaderStage;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+impl Stage {
+    // Work out the stage from the file extension - .vert or .frag
+    pub fn from_path(path: &Path) -> Option<Stage> {
+        match path.extension()?.to_str()? {
+            "vert" => Some(Stage::Vertex),
+            "frag" => Some(Stage::Fragment),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+// A declaration we had to rewrite, remembered so we can check it against the Rust side
+struct Declaration {
+    line: usize,
+    ty: String,
+    name: String,
+}
+
+struct Translation {
+    source: String,
+    // Original line number for every line of the translated source
+    line_map: Vec<usize>,
+    attributes: Vec<Declaration>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// Names which are fine in GLSL ES 1.00 but are built-in functions in every later version,
+// using them for a uniform breaks as soon as the shader is ported to WebGL 2.
+const RESERVED_NAMES: &[&str] = &["texture", "sample", "input", "output"];
+
+/// Parses and type-checks `source`, returning every problem found with its line in `source`.
+///
+/// `attributes` lists the vertex attributes the Rust side supplies along with how many floats
+/// each vertex has for it, e.g. `("a_position", 3)` for the `x, y, z` quads used by the tutorials.
+/// Vertex shaders must declare each of them with a matching type.
+pub fn validate(source: &str, stage: Stage, attributes: &[(&str, u32)]) -> Vec<Diagnostic> {
+    let translation = translate(source, stage);
+    let mut diagnostics = translation.diagnostics;
+
+    if stage == Stage::Vertex {
+        for (name, components) in attributes {
+            match translation.attributes.iter().find(|attr| attr.name == *name) {
+                Some(attr) => {
+                    let declared = component_count(&attr.ty);
+                    if declared != Some(*components) {
+                        diagnostics.push(Diagnostic {
+                            line: attr.line,
+                            column: 1,
+                            message: format!(
+                                "attribute `{}` is declared as `{}` but the vertex buffer supplies {} components per vertex",
+                                name, attr.ty, components
+                            ),
+                        });
+                    }
+                }
+                None => diagnostics.push(Diagnostic {
+                    line: 1,
+                    column: 1,
+                    message: format!("attribute `{}` is supplied by the vertex buffer but never declared", name),
+                }),
+            }
+        }
+    }
+
+    let naga_stage = match stage {
+        Stage::Vertex => ShaderStage::Vertex,
+        Stage::Fragment => ShaderStage::Fragment,
+    };
+
+    let locate = |span: naga::Span| {
+        let location = span.location(&translation.source);
+        let line = translation
+            .line_map
+            .get((location.line_number as usize).saturating_sub(1))
+            .copied()
+            .unwrap_or(0);
+        (line, location.line_position as usize)
+    };
+
+    let module = match Frontend::default().parse(&Options::from(naga_stage), &translation.source) {
+        Ok(module) => module,
+        Err(errors) => {
+            for error in errors.errors {
+                let (line, column) = locate(error.meta);
+                diagnostics.push(Diagnostic { line, column, message: error.kind.to_string() });
+            }
+            return sorted(diagnostics);
+        }
+    };
+
+    let mut validator = Validator::new(ValidationFlags::all(), Capabilities::all());
+    if let Err(error) = validator.validate(&module) {
+        // The spans go from the outside in, function first, so the last is nearest the problem
+        let (line, column) = error
+            .spans()
+            .filter(|(span, _)| span.is_defined())
+            .last()
+            .map(|(span, _)| locate(*span))
+            .unwrap_or((0, 0));
+
+        // naga nests the actual problem inside "function X is invalid" style errors
+        let mut message = error.as_inner().to_string();
+        let mut source: Option<&dyn std::error::Error> = std::error::Error::source(error.as_inner());
+        while let Some(inner) = source {
+            message.push_str(": ");
+            message.push_str(&inner.to_string());
+            source = inner.source();
+        }
+
+        diagnostics.push(Diagnostic { line, column, message });
+    }
+
+    sorted(diagnostics)
+}
+
+fn sorted(mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics.sort_by_key(|diag| (diag.line, diag.column));
+    diagnostics
+}
+
+fn component_count(ty: &str) -> Option<u32> {
+    match ty {
+        "float" => Some(1),
+        "vec2" => Some(2),
+        "vec3" => Some(3),
+        "vec4" => Some(4),
+        _ => None,
+    }
+}
+
+// Turn GLSL ES 1.00 into something naga's GLSL 4.50 front-end accepts
+fn translate(source: &str, stage: Stage) -> Translation {
+    let mut header: Vec<(String, usize)> = vec![("#version 450".to_string(), 0)];
+    let mut body: Vec<String> = Vec::new();
+    let mut attributes = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    let mut binding = 0;
+    let mut location = 0;
+    let mut in_comment = false;
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_number = idx + 1;
+
+        // Don't go rewriting declarations that have been commented out
+        let starts_in_comment = in_comment;
+        let code = line.split("//").next().unwrap_or("");
+        if let Some(start) = code.rfind("/*") {
+            in_comment = !code[start..].contains("*/");
+        } else if code.contains("*/") {
+            in_comment = false;
+        }
+
+        let decl = if starts_in_comment { None } else { parse_declaration(code) };
+        let (qualifier, ty, names) = match decl {
+            Some(decl) => decl,
+            None => {
+                body.push(line.to_string());
+                continue;
+            }
+        };
+
+        let mut rewritten = String::new();
+        for name in names {
+            if qualifier == "uniform" && RESERVED_NAMES.contains(&name.as_str()) {
+                diagnostics.push(Diagnostic {
+                    line: line_number,
+                    column: line.find(name.as_str()).map(|col| col + 1).unwrap_or(1),
+                    message: format!("`{}` is a built-in function in later GLSL versions, use a different name", name),
+                });
+            }
+
+            match (qualifier.as_str(), ty.as_str()) {
+                ("uniform", "sampler2D") | ("uniform", "samplerCube") => {
+                    // naga has no combined image samplers, so split it in two and let the
+                    // preprocessor glue them back together wherever the name is used.
+                    let (tex_ty, sampler_ty) =
+                        if ty == "sampler2D" { ("texture2D", "sampler2D") } else { ("textureCube", "samplerCube") };
+                    header.push((
+                        format!("layout(set = 1, binding = {}) uniform {} _{}_tex;", binding, tex_ty, name),
+                        line_number,
+                    ));
+                    header.push((
+                        format!("layout(set = 1, binding = {}) uniform sampler _{}_smp;", binding + 1, name),
+                        line_number,
+                    ));
+                    header.push((
+                        format!("#define {} {}(_{}_tex, _{}_smp)", name, sampler_ty, name, name),
+                        line_number,
+                    ));
+                    binding += 2;
+                }
+                ("uniform", _) => {
+                    rewritten.push_str(&format!(
+                        "layout(set = 0, binding = {}) uniform _{}_block {{ {} {}; }}; ",
+                        binding, name, ty, name
+                    ));
+                    binding += 1;
+                }
+                ("attribute", _) => {
+                    rewritten.push_str(&format!("layout(location = {}) in {} {}; ", location, ty, name));
+                    location += 1;
+                    attributes.push(Declaration { line: line_number, ty: ty.clone(), name: base_name(&name) });
+                }
+                ("varying", _) => {
+                    let direction = if stage == Stage::Vertex { "out" } else { "in" };
+                    rewritten.push_str(&format!("layout(location = {}) {} {} {}; ", location, direction, ty, name));
+                    location += 1;
+                }
+                _ => unreachable!(),
+            }
+        }
+        body.push(rewritten.trim_end().to_string());
+    }
+
+    // These have to come after the sampler declarations above or they'd rewrite those too
+    header.push(("#define texture2D texture".to_string(), 0));
+    header.push(("#define textureCube texture".to_string(), 0));
+    if stage == Stage::Fragment {
+        header.push(("layout(location = 0) out vec4 _gl_FragColor;".to_string(), 0));
+        header.push(("#define gl_FragColor _gl_FragColor".to_string(), 0));
+    }
+
+    let mut translated = String::new();
+    let mut line_map = Vec::new();
+    for (line, origin) in &header {
+        translated.push_str(line);
+        translated.push('\n');
+        line_map.push(*origin);
+    }
+    for (idx, line) in body.iter().enumerate() {
+        translated.push_str(line);
+        translated.push('\n');
+        line_map.push(idx + 1);
+    }
+
+    Translation { source: translated, line_map, attributes, diagnostics }
+}
+
+// Split `uniform mediump vec2 a, b[2];` into ("uniform", "vec2", ["a", "b[2]"])
+fn parse_declaration(code: &str) -> Option<(String, String, Vec<String>)> {
+    let code = code.trim();
+    let mut words = code.split_whitespace();
+    let qualifier = words.next()?;
+    if !matches!(qualifier, "uniform" | "attribute" | "varying") {
+        return None;
+    }
+    let decl = code.strip_suffix(';')?;
+
+    let mut ty = words.next()?;
+    if matches!(ty, "lowp" | "mediump" | "highp") {
+        ty = words.next()?;
+    }
+
+    // Everything after the type is the list of names
+    let type_end = decl.find(ty)? + ty.len();
+    let names: Vec<String> = decl[type_end..]
+        .split(',')
+        .map(|name| name.split_whitespace().collect::<String>())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+
+    Some((qualifier.to_string(), ty.to_string(), names))
+}
+
+fn base_name(name: &str) -> String {
+    name.split('[').next().unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::preprocess;
+
+    const VERT: &str = "attribute vec3 a_position;
+varying vec2 v_uv;
+
+void main() {
+    v_uv = a_position.xy * 0.5 + 0.5;
+    gl_Position = vec4(a_position, 1.0);
+}
+";
+
+    const FRAG: &str = "precision mediump float;
+
+uniform float u_time;
+uniform sampler2D texNoise;
+varying vec2 v_uv;
+
+void main() {
+    vec4 col = texture2D(texNoise, v_uv + u_time);
+    gl_FragColor = col * vec4(0.4, 0.4, 0.9, 1.0);
+}
+";
+
+    #[test]
+    fn accepts_glsl_es_1() {
+        assert_eq!(validate(VERT, Stage::Vertex, &[("a_position", 3)]), vec![]);
+        assert_eq!(validate(FRAG, Stage::Fragment, &[]), vec![]);
+    }
+
+    #[test]
+    fn type_errors_are_on_the_original_line() {
+        // A vec3 times a vec4 on line 9. The sampler adds declarations above the shader, which
+        // mustn't move it.
+        let source = FRAG.replace("vec4 col = texture2D", "vec3 col = texture2D");
+        let diagnostics = validate(&source, Stage::Fragment, &[]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (9, 20), "{:?}", diagnostics);
+        assert!(diagnostics[0].message.contains("Multiply"));
+    }
+
+    #[test]
+    fn type_errors_in_includes_are_in_the_included_file() {
+        let source = FRAG.replace("precision mediump float;", "precision mediump float;\n#include \"helpers.glsl\"");
+        let processed = preprocess("basic.frag", &source, |_| Some("float half(float x) {\n    return vec2(x) * 0.5;\n}\n".to_string())).unwrap();
+        let diagnostics = validate(&processed.source, Stage::Fragment, &[]);
+        assert!(!diagnostics.is_empty());
+        assert_eq!(processed.source_map.locate(diagnostics[0].line), Some(("helpers.glsl", 2)), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn checks_attribute_sizes() {
+        let source = VERT.replace("attribute vec3", "attribute vec2");
+        let diagnostics = validate(&source, Stage::Vertex, &[("a_position", 3)]);
+        assert!(diagnostics.contains(&Diagnostic {
+            line: 1,
+            column: 1,
+            message: "attribute `a_position` is declared as `vec2` but the vertex buffer supplies 3 components per vertex".to_string(),
+        }), "{:?}", diagnostics);
+
+        let diagnostics = validate(VERT, Stage::Vertex, &[("a_position", 3), ("a_normal", 3)]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "attribute `a_normal` is supplied by the vertex buffer but never declared");
+    }
+
+    #[test]
+    fn warns_about_reserved_names() {
+        let source = FRAG.replace("u_time", "sample");
+        let diagnostics = validate(&source, Stage::Fragment, &[]);
+        assert_eq!(diagnostics[0].line, 3);
+        assert_eq!(diagnostics[0].column, 15);
+        assert!(diagnostics[0].message.contains("`sample` is a built-in function"));
+    }
+
+    #[test]
+    fn ignores_commented_out_declarations() {
+        let source = FRAG.replace("uniform float u_time;", "// uniform float texture;\n/*\nuniform float input;\n*/\nuniform float u_time;");
+        assert_eq!(validate(&source, Stage::Fragment, &[]), vec![]);
+    }
+
+    #[test]
+    fn stages_from_paths() {
+        assert_eq!(Stage::from_path(Path::new("src/basic.vert")), Some(Stage::Vertex));
+        assert_eq!(Stage::from_path(Path::new("src/basic.frag")), Some(Stage::Fragment));
+        assert_eq!(Stage::from_path(Path::new("src/lib/noise.glsl")), None);
+    }
+}
@@ -0,0 +1,185 @@
+// A video file as a texture, updated from a hidden <video> element every frame.
+//
+// The video follows the tutorial's clock rather than its own: sync() plays, pauses and seeks it
+// to match u_time, so pausing, changing speed or opening a link to a time all carry over. Until
+// the browser has a frame decoded the texture is left alone and u_video_ready is 0, so the
+// shader can show something else in the meantime. A clip can come in more than one format, each
+// a <source> with its type, and the browser plays the first it can.
+
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlMediaElement, HtmlSourceElement, HtmlVideoElement, WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+
+// How far the video can wander from the clock while playing before it's moved back, in seconds
+const MAX_DRIFT: f64 = 0.1;
+// Paused, it's put exactly where the clock is so stepping through shows the right frame
+const MAX_PAUSED_DRIFT: f64 = 0.001;
+
+pub struct VideoTexture {
+    video: HtmlVideoElement,
+    texture: Option<WebGlTexture>,
+    // Where the frame in the texture came from, so a paused video isn't uploaded every frame
+    uploaded_time: Option<f64>,
+    // Set once any frame has made it into the texture
+    has_frame: bool,
+    texture_location: Option<WebGlUniformLocation>,
+    ready_location: Option<WebGlUniformLocation>,
+    size_location: Option<WebGlUniformLocation>,
+}
+
+impl VideoTexture {
+    // Starts loading straight away. `sources` are (url, MIME type) in order of preference, the
+    // type with a codecs parameter so the browser can tell whether it can decode it without
+    // downloading anything. Muted so the browser lets it play without a click.
+    pub fn new(sources: &[(&str, &str)]) -> Result<VideoTexture, String> {
+        let document = window().and_then(|window| window.document()).ok_or("No document to make a video in")?;
+        let video: HtmlVideoElement = document
+            .create_element("video")
+            .map_err(|err| format!("{:?}", err))?
+            .dyn_into()
+            .map_err(|_| "Not a video element")?;
+
+        video.set_muted(true);
+        video.set_loop(true);
+        video.set_preload("auto");
+        // Or iOS plays it full screen
+        let _ = video.set_attribute("playsinline", "");
+        // Needed to upload it if it's ever served from somewhere else
+        video.set_cross_origin(Some("anonymous"));
+        for (src, mime) in sources {
+            let source: HtmlSourceElement = document
+                .create_element("source")
+                .map_err(|err| format!("{:?}", err))?
+                .dyn_into()
+                .map_err(|_| "Not a source element")?;
+            source.set_src(src);
+            source.set_type(mime);
+            video.append_child(&source).map_err(|err| format!("{:?}", err))?;
+        }
+
+        Ok(VideoTexture {
+            video,
+            texture: None,
+            uploaded_time: None,
+            has_frame: false,
+            texture_location: None,
+            ready_location: None,
+            size_location: None,
+        })
+    }
+
+    // Whether there's a frame to show yet
+    pub fn ready(&self) -> bool {
+        self.video.ready_state() >= HtmlMediaElement::HAVE_CURRENT_DATA && self.video.video_width() > 0
+    }
+
+    pub fn error(&self) -> Option<String> {
+        if let Some(err) = self.video.error() {
+            return Some(format!("Video error {}: {}", err.code(), err.message()));
+        }
+        // A source that can't be played doesn't set error(), the browser just goes on to the
+        // next. Once it's run out it gives up with nothing loaded.
+        let gave_up = self.video.network_state() == HtmlMediaElement::NETWORK_NO_SOURCE
+            && self.video.ready_state() == HtmlMediaElement::HAVE_NOTHING;
+        gave_up.then(|| "None of the video's sources can be played".to_string())
+    }
+
+    // Keep the video at `time` seconds into the tutorial, wrapped round the length of the clip
+    pub fn sync(&self, time: f64, playing: bool, speed: f64) {
+        let video = &self.video;
+        let playing = playing && speed > 0.0;
+
+        if playing {
+            if video.playback_rate() != speed {
+                video.set_playback_rate(speed);
+            }
+            if video.paused() {
+                // Rejected if the browser won't play it, which leaves it paused on the frame
+                let _ = video.play();
+            }
+        } else if !video.paused() {
+            let _ = video.pause();
+        }
+
+        // NaN until the browser has read the header, and no point seeking on top of a seek
+        let duration = video.duration();
+        if !duration.is_finite() || duration <= 0.0 || video.seeking() {
+            return;
+        }
+        let target = time.rem_euclid(duration);
+        let max_drift = if playing { MAX_DRIFT } else { MAX_PAUSED_DRIFT };
+        if loop_distance(video.current_time(), target, duration) > max_drift {
+            video.set_current_time(target);
+        }
+    }
+
+    pub fn locate(&mut self, gl: &GL, program: &WebGlProgram) {
+        self.texture_location = gl.get_uniform_location(program, "u_video");
+        self.ready_location = gl.get_uniform_location(program, "u_video_ready");
+        self.size_location = gl.get_uniform_location(program, "u_video_size");
+    }
+
+    // Leaves TEXTURE0 active again afterwards
+    pub fn upload(&mut self, gl: &GL, unit: u32) {
+        gl.active_texture(GL::TEXTURE0 + unit);
+        if self.texture.is_none() {
+            self.texture = gl.create_texture();
+            gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+            // Videos are rarely a power of two, which WebGL 1 only allows without mipmaps or
+            // wrapping
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            // Black until there's a frame, so it's never sampled incomplete
+            let _ = gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                1,
+                1,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                Some(&[0, 0, 0, 255]),
+            );
+        }
+        gl.bind_texture(GL::TEXTURE_2D, self.texture.as_ref());
+
+        let ready = self.ready();
+        let time = self.video.current_time();
+        if ready && self.uploaded_time != Some(time) {
+            match gl.tex_image_2d_with_u32_and_u32_and_video(GL::TEXTURE_2D, 0, GL::RGBA as i32, GL::RGBA, GL::UNSIGNED_BYTE, &self.video) {
+                Ok(()) => {
+                    self.uploaded_time = Some(time);
+                    self.has_frame = true;
+                },
+                // Seeking can briefly leave nothing to copy, the last frame stays
+                Err(_) => self.uploaded_time = None,
+            }
+        }
+        gl.active_texture(GL::TEXTURE0);
+
+        gl.uniform1i(self.texture_location.as_ref(), unit as i32);
+        gl.uniform1f(self.ready_location.as_ref(), if self.has_frame { 1.0 } else { 0.0 });
+        gl.uniform2f(self.size_location.as_ref(), self.video.video_width() as f32, self.video.video_height() as f32);
+    }
+}
+
+impl Drop for VideoTexture {
+    fn drop(&mut self) {
+        // Otherwise it carries on downloading and decoding with nothing to show it. With the
+        // <source>s gone, load() leaves it with nothing; an empty src would be tried instead of
+        // them, not stop them.
+        let _ = self.video.pause();
+        while let Some(source) = self.video.first_child() {
+            let _ = self.video.remove_child(&source);
+        }
+        self.video.load();
+    }
+}
+
+// How far apart two times are on a loop `duration` long, so the end is next to the start
+fn loop_distance(a: f64, b: f64, duration: f64) -> f64 {
+    let distance = (a - b).rem_euclid(duration);
+    distance.min(duration - distance)
+}
@@ -0,0 +1,229 @@
+#!/usr/bin/env python3
+# Writes the fixtures for common/src/audio.rs, common/src/gradient.rs and common/src/lut.rs. Only
+# needs the Python standard library, so the Ogg Vorbis file is put together by hand: it's valid
+# but silent, since encoding real audio needs a proper Vorbis encoder.
+
+import math
+import random
+import struct
+import wave
+from pathlib import Path
+
+HERE = Path(__file__).parent
+
+
+def write_wav(name, rate, channels, frames, sample_width=2):
+    with wave.open(str(HERE / name), "wb") as out:
+        out.setnchannels(channels)
+        out.setsampwidth(sample_width)
+        out.setframerate(rate)
+        data = bytearray()
+        for frame in frames:
+            for sample in frame:
+                sample = max(-1.0, min(1.0, sample))
+                if sample_width == 1:
+                    data += struct.pack("<B", int(round(sample * 127 + 128)))
+                else:
+                    data += struct.pack("<h", int(round(sample * 32767)))
+        out.writeframes(bytes(data))
+
+
+def write_float_wav(name, rate, frames):
+    # The wave module only does integer PCM, so write a WAVE_FORMAT_IEEE_FLOAT header directly
+    channels = len(frames[0])
+    data = b"".join(struct.pack("<%df" % channels, *frame) for frame in frames)
+    fmt = struct.pack("<HHIIHH", 3, channels, rate, rate * channels * 4, channels * 4, 32)
+    riff = b"WAVE" + b"fmt " + struct.pack("<I", len(fmt)) + fmt + b"data" + struct.pack("<I", len(data)) + data
+    (HERE / name).write_bytes(b"RIFF" + struct.pack("<I", len(riff)) + riff)
+
+
+# Half a second of 440Hz at half volume
+rate = 22050
+write_wav("sine_440.wav", rate, 1, [(0.5 * math.sin(2 * math.pi * 440 * n / rate),) for n in range(rate // 2)])
+
+# A kick drum every half second (120bpm) over quiet hi-hat noise, 8 bit
+rate = 11025
+random.seed(7)
+frames = []
+for n in range(rate * 2):
+    t = (n / rate) % 0.5
+    kick = math.sin(2 * math.pi * (50 + 100 * math.exp(-t * 30)) * t) * math.exp(-t * 12) if t < 0.25 else 0.0
+    hat = (random.random() * 2 - 1) * 0.05
+    frames.append((0.8 * kick + hat,))
+write_wav("kick_120bpm.wav", rate, 1, frames, sample_width=1)
+
+# Stereo float, left and right different so the mix down shows
+rate = 8000
+write_float_wav("stereo_float.wav", rate, [(0.5, -0.25)] * (rate // 4))
+
+
+# --- Ogg Vorbis -------------------------------------------------------------------------------
+
+class Bits:
+    # Vorbis packs bits least significant first
+    def __init__(self):
+        self.bytes = bytearray()
+        self.used = 8
+
+    def write(self, value, count):
+        for bit in range(count):
+            if self.used == 8:
+                self.bytes.append(0)
+                self.used = 0
+            self.bytes[-1] |= ((value >> bit) & 1) << self.used
+            self.used += 1
+
+
+def setup_header():
+    bits = Bits()
+    for byte in b"\x05vorbis":
+        bits.write(byte, 8)
+    # One codebook: one dimension, two entries of one bit, no lookup table
+    bits.write(0, 8)
+    bits.write(0x564342, 24)
+    bits.write(1, 16)
+    bits.write(2, 24)
+    bits.write(0, 1)
+    bits.write(0, 1)
+    bits.write(0, 5)
+    bits.write(0, 5)
+    bits.write(0, 4)
+    # One (unused) time domain transform
+    bits.write(0, 6)
+    bits.write(0, 16)
+    # One floor 1 with no partitions
+    bits.write(0, 6)
+    bits.write(1, 16)
+    bits.write(0, 5)
+    bits.write(0, 2)
+    bits.write(8, 4)
+    # One residue 0 covering nothing
+    bits.write(0, 6)
+    bits.write(0, 16)
+    bits.write(0, 24)
+    bits.write(0, 24)
+    bits.write(31, 24)
+    bits.write(0, 6)
+    bits.write(0, 8)
+    bits.write(0, 3)
+    bits.write(0, 1)
+    # One mapping with a single submap and no coupling
+    bits.write(0, 6)
+    bits.write(0, 16)
+    bits.write(0, 1)
+    bits.write(0, 1)
+    bits.write(0, 2)
+    bits.write(0, 8)
+    bits.write(0, 8)
+    bits.write(0, 8)
+    # One mode using short blocks
+    bits.write(0, 6)
+    bits.write(0, 1)
+    bits.write(0, 16)
+    bits.write(0, 16)
+    bits.write(0, 8)
+    # Framing bit
+    bits.write(1, 1)
+    return bytes(bits.bytes)
+
+
+def crc(data):
+    value = 0
+    for byte in data:
+        value ^= byte << 24
+        for _ in range(8):
+            value = ((value << 1) ^ 0x04C11DB7) if value & 0x80000000 else value << 1
+            value &= 0xFFFFFFFF
+    return value
+
+
+def page(packets, header_type, granule, sequence):
+    lacing = bytearray()
+    for packet in packets:
+        size = len(packet)
+        while size >= 255:
+            lacing.append(255)
+            size -= 255
+        lacing.append(size)
+    header = b"OggS" + struct.pack("<BBqIIIB", 0, header_type, granule, 0x5EED, sequence, 0, len(lacing)) + bytes(lacing)
+    body = header + b"".join(packets)
+    checksum = crc(body)
+    return body[:22] + struct.pack("<I", checksum) + body[26:]
+
+
+rate = 22050
+identification = b"\x01vorbis" + struct.pack("<IBIiiiBB", 0, 1, rate, 0, 0, 0, 0xB8, 1)
+vendor = b"shader tutorials fixture"
+comment = b"\x03vorbis" + struct.pack("<I", len(vendor)) + vendor + struct.pack("<I", 0) + b"\x01"
+# Each audio packet is a short block with the floor marked unused, i.e. silence. After the first
+# every packet adds 128 samples.
+audio_packets = [b"\x00"] * 44
+samples = (len(audio_packets) - 1) * 128
+
+(HERE / "silence.ogg").write_bytes(
+    page([identification], 0x02, 0, 0)
+    + page([comment, setup_header()], 0x00, 0, 1)
+    + page(audio_packets, 0x04, samples, 2)
+)
+
+
+# --- .cube LUTs -------------------------------------------------------------------------------
+
+def cube_rows(size, grade=lambda r, g, b: (r, g, b)):
+    # Red changes fastest, then green, then blue
+    step = lambda i: i / (size - 1)
+    return [
+        "%.6f %.6f %.6f" % grade(step(r), step(g), step(b))
+        for b in range(size)
+        for g in range(size)
+        for r in range(size)
+    ]
+
+
+def write_cube(name, lines, newline="\n"):
+    (HERE / name).write_bytes((newline.join(lines) + newline).encode())
+
+
+write_cube("identity_2.cube", ['TITLE "Identity"', "LUT_3D_SIZE 2", ""] + cube_rows(2))
+write_cube("swap_red_blue.cube", ["LUT_3D_SIZE 3"] + cube_rows(3, lambda r, g, b: (b, g, r)))
+write_cube("domain.cube", ["LUT_3D_SIZE 2", "DOMAIN_MIN -0.5 0 0", "DOMAIN_MAX 1.5 2 1"] + cube_rows(2))
+write_cube("resolve_input_range.cube", ["LUT_3D_SIZE 2", "LUT_3D_INPUT_RANGE 0.0 4.0", "LUT_IN_VIDEO_RANGE"] + cube_rows(2))
+write_cube(
+    "comments_crlf.cube",
+    ["# Written by make_fixtures.py", 'TITLE "With comments"', "", "LUT_3D_SIZE 2", "  # Now the data"]
+    + [row + "  # row %d" % n for n, row in enumerate(cube_rows(2))],
+    newline="\r\n",
+)
+
+# Broken ones, each should fail with a message pointing at the problem
+write_cube("missing_size.cube", ['TITLE "No size"'])
+write_cube("too_few_rows.cube", ["LUT_3D_SIZE 2"] + cube_rows(2)[:7])
+write_cube("too_many_rows.cube", ['TITLE "Extra"', "LUT_3D_SIZE 2", ""] + cube_rows(2) + ["0 0 0"])
+write_cube("bad_number.cube", ["LUT_3D_SIZE 2", "0 0 0", "1 0 0", "0 1 0", "1 1 0.x"])
+write_cube("two_columns.cube", ["LUT_3D_SIZE 2", "0 0 0", "1 0 0", "0 1"])
+write_cube("lut_1d.cube", ["LUT_1D_SIZE 4", "0 0 0", "0.33 0.33 0.33", "0.67 0.67 0.67", "1 1 1"])
+write_cube("size_too_small.cube", ["LUT_3D_SIZE 1", "0 0 0"])
+write_cube("data_before_size.cube", ['TITLE "Backwards"', "0 0 0", "LUT_3D_SIZE 2"])
+write_cube("bad_domain.cube", ["LUT_3D_SIZE 2", "DOMAIN_MIN 0 0 1", "DOMAIN_MAX 1 1 1"] + cube_rows(2))
+write_cube("keyword_after_data.cube", ["LUT_3D_SIZE 2"] + cube_rows(2)[:4] + ["DOMAIN_MAX 1 1 1"] + cube_rows(2)[4:])
+write_cube("not_a_cube.cube", ["<html><body>Not found</body></html>"])
+
+# --- GIMP gradients ---------------------------------------------------------------------------
+# Laid out the way GIMP 2.10 saves them: left, middle and right positions, the two RGBA colours,
+# blending (0 linear, 1 curved, 5 step), colouring (0 RGB, 1 HSV anticlockwise) and the two end
+# colour types.
+
+def write_ggr(name, title, segments):
+    lines = ["GIMP Gradient", "Name: " + title, str(len(segments))]
+    for segment in segments:
+        lines.append(" ".join("%.6f" % value for value in segment[:11]) + " " + " ".join(str(value) for value in segment[11:]))
+    (HERE / name).write_text("\n".join(lines) + "\n")
+
+
+write_ggr("gimp_sunset.ggr", "Fixture Sunset", [
+    # Off centre middle
+    (0.0, 0.2, 0.3, 0.0, 0.0, 0.2, 1.0, 0.8, 0.1, 0.3, 1.0, 0, 0, 0, 0),
+    (0.3, 0.45, 0.6, 0.8, 0.1, 0.3, 1.0, 1.0, 0.6, 0.0, 1.0, 1, 0, 0, 0),
+    (0.6, 0.7, 0.8, 1.0, 0.6, 0.0, 1.0, 0.0, 0.4, 1.0, 1.0, 0, 1, 0, 0),
+    (0.8, 0.9, 1.0, 0.0, 0.4, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 5, 0, 0, 0),
+])
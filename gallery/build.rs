@@ -0,0 +1,68 @@
+use std::{env, fs, path::Path};
+
+use shader_common::build::ShaderBuild;
+use shader_common::exercise::Manifest;
+
+const EXERCISE_DIR: &str = "exercises";
+
+// Every tutorial with a shader in TUTORIALS in src/previews.rs, a test there checks they match
+const TUTORIALS: &[&str] = &[
+    "01-simple-shader",
+    "02-texture",
+    "03-texture-overlay",
+    "04-simple-tunnel",
+    "04.1-light-rays",
+    "04.2-stretch-effect",
+    "05-neon-swirls",
+    "06-fractal-pattern",
+    "06.1-kaleidoscope",
+];
+
+fn main() {
+    // The previews draw each tutorial's own shaders, built the same way its build.rs does. Every
+    // quad vertex is x, y, z.
+    for dir in TUTORIALS {
+        ShaderBuild::new(format!("../{}/src", dir))
+            .attribute("a_position", 3)
+            .out_subdir(dir)
+            .run();
+    }
+    println!("cargo:rerun-if-changed=../README.md");
+
+    ShaderBuild::new(EXERCISE_DIR)
+        .attribute("a_position", 3)
+        .out_subdir(EXERCISE_DIR)
+        .run();
+    exercise_files();
+}
+
+// Check the manifest and write out a table of the shaders it names, as the learner will see
+// them, for src/exercises.rs to include
+fn exercise_files() {
+    let dir = Path::new(EXERCISE_DIR).canonicalize().expect("The exercises directory is missing");
+    let manifest_path = dir.join("exercises.json");
+    println!("cargo:rerun-if-changed={}", manifest_path.display());
+
+    let text = fs::read_to_string(&manifest_path).unwrap_or_else(|err| panic!("Failed to read {}: {}", manifest_path.display(), err));
+    let manifest = Manifest::parse(&text).unwrap_or_else(|err| {
+        eprintln!("{}: {}", manifest_path.display(), err);
+        std::process::exit(1);
+    });
+
+    let mut table = String::from("// Generated by build.rs from exercises/exercises.json\npub const FILES: &[(&str, &str)] = &[\n");
+    let mut names: Vec<&str> = manifest.exercises.iter().flat_map(|exercise| [exercise.start.as_str(), exercise.target.as_str()]).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let path = dir.join(name);
+        if !path.is_file() {
+            eprintln!("{}: {} isn't in {}", manifest_path.display(), name, dir.display());
+            std::process::exit(1);
+        }
+        table.push_str(&format!("    ({:?}, include_str!({:?})),\n", name, path.display().to_string()));
+    }
+    table.push_str("];\n");
+
+    let out_path = Path::new(&env::var("OUT_DIR").expect("OUT_DIR is only set when run from build.rs")).join("exercise_files.rs");
+    fs::write(&out_path, table).unwrap_or_else(|err| panic!("Failed to write {}: {}", out_path.display(), err));
+}
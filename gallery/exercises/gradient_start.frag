@@ -0,0 +1,11 @@
+precision mediump float;
+
+uniform float u_time;
+uniform vec2 canvasSize;
+
+void main()
+{
+    // From 0 to 1 across the canvas
+    vec2 uv = gl_FragCoord.xy / canvasSize;
+    gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,10 @@
+precision mediump float;
+
+uniform float u_time;
+uniform vec2 canvasSize;
+
+void main()
+{
+    vec2 uv = gl_FragCoord.xy / canvasSize;
+    gl_FragColor = vec4(uv.x, uv.y, 0.5 + 0.5 * sin(u_time), 1.0);
+}
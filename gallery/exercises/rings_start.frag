@@ -0,0 +1,14 @@
+precision mediump float;
+
+uniform float u_time;
+uniform vec2 canvasSize;
+
+void main()
+{
+    // Centred, with y from -1 to 1 and x stretched to keep circles round
+    vec2 p = (2.0 * gl_FragCoord.xy - canvasSize) / canvasSize.y;
+    float d = length(p);
+
+    float brightness = step(d, 0.5);
+    gl_FragColor = vec4(vec3(brightness), 1.0);
+}
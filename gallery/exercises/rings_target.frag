@@ -0,0 +1,13 @@
+precision mediump float;
+
+uniform float u_time;
+uniform vec2 canvasSize;
+
+void main()
+{
+    vec2 p = (2.0 * gl_FragCoord.xy - canvasSize) / canvasSize.y;
+    float d = length(p);
+
+    float brightness = 0.5 + 0.5 * sin(d * 20.0 - u_time * 4.0);
+    gl_FragColor = vec4(vec3(brightness), 1.0);
+}
@@ -0,0 +1,13 @@
+precision mediump float;
+
+#include "lib/tunnel.glsl"
+
+uniform float u_time;
+uniform vec2 canvasSize;
+uniform sampler2D texNoise;
+
+void main()
+{
+    vec2 uv = gl_FragCoord.xy / canvasSize;
+    gl_FragColor = texture2D(texNoise, uv);
+}
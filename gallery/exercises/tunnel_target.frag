@@ -0,0 +1,14 @@
+precision mediump float;
+
+#include "lib/tunnel.glsl"
+
+uniform float u_time;
+uniform vec2 canvasSize;
+uniform sampler2D texNoise;
+
+void main()
+{
+    vec2 uv = gl_FragCoord.xy / canvasSize;
+    uv = tunnel(uv, 0.25, u_time * 0.5);
+    gl_FragColor = texture2D(texNoise, uv);
+}
@@ -0,0 +1,308 @@
+// Two tutorials next to each other, both running, with what changed in their files from one to
+// the other underneath. The pair is kept in the query string so a comparison can be linked to:
+//
+//   /compare?from=04-simple-tunnel&to=04.1-light-rays
+
+use std::collections::BTreeMap;
+
+use web_sys::{window, HtmlCanvasElement, HtmlSelectElement};
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use wasm_bindgen::{prelude::*, JsCast};
+use gloo_console::log;
+
+use shader_common::diff::{line_counts, side_by_side};
+use shader_common::diff_view::DiffView;
+
+use crate::previews::{PreviewRenderer, SourceFile, Tutorial, TUTORIALS};
+use crate::readme::{self, ReadmeEntry};
+use crate::Route;
+
+const CANVAS_WIDTH: u32 = 480;
+const CANVAS_HEIGHT: u32 = 270;
+
+// Shown when nothing's been picked, the first of the tutorials that build on the one before
+const DEFAULT_PAIR: (&str, &str) = ("04-simple-tunnel", "04.1-light-rays");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    Fragment,
+    Vertex,
+    Rust,
+}
+
+impl SourceKind {
+    const ALL: [SourceKind; 3] = [SourceKind::Fragment, SourceKind::Vertex, SourceKind::Rust];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SourceKind::Fragment => "Fragment shader",
+            SourceKind::Vertex => "Vertex shader",
+            SourceKind::Rust => "Rust setup",
+        }
+    }
+
+    // Name and text of this file in `tutorial`, empty if it doesn't have one
+    fn file(&self, tutorial: &Tutorial) -> (&'static str, &'static str) {
+        let file = match self {
+            SourceKind::Fragment => tutorial.sources.frag.as_ref(),
+            SourceKind::Vertex => tutorial.sources.vert.as_ref(),
+            SourceKind::Rust => Some(&tutorial.sources.rust),
+        };
+        file.map(|SourceFile { name, text }| (*name, *text)).unwrap_or(("(none)", ""))
+    }
+}
+
+pub struct Compare {
+    callback: Closure<dyn FnMut()>,
+    renderer: Option<PreviewRenderer>,
+    canvases: [NodeRef; 2],
+    entries: Vec<ReadmeEntry>,
+    // Indexes into TUTORIALS
+    from: usize,
+    to: usize,
+    kind: SourceKind,
+    start: f64,
+    frame: Option<i32>,
+}
+
+pub enum CompareMsg {
+    SetFrom(usize),
+    SetTo(usize),
+    Swap,
+    ShowFile(SourceKind),
+    Render,
+}
+
+impl Component for Compare {
+    type Message = CompareMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let comp_ctx = ctx.link().clone();
+        let callback =
+            Closure::wrap(Box::new(move || comp_ctx.send_message(CompareMsg::Render)) as Box<dyn FnMut()>);
+
+        let query = ctx.link().location().and_then(|location| location.query::<BTreeMap<String, String>>().ok()).unwrap_or_default();
+        let pick = |key: &str, default: &str| {
+            let dir = query.get(key).map(String::as_str).unwrap_or(default);
+            tutorial_index(dir).or_else(|| tutorial_index(default)).unwrap_or(0)
+        };
+
+        Compare {
+            callback,
+            renderer: None,
+            canvases: Default::default(),
+            entries: readme::tutorials(include_str!("../../README.md")),
+            from: pick("from", DEFAULT_PAIR.0),
+            to: pick("to", DEFAULT_PAIR.1),
+            kind: SourceKind::Fragment,
+            start: instant::now(),
+            frame: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            CompareMsg::SetFrom(index) => {
+                self.from = index;
+                self.write_query(ctx);
+                true
+            },
+            CompareMsg::SetTo(index) => {
+                self.to = index;
+                self.write_query(ctx);
+                true
+            },
+            CompareMsg::Swap => {
+                (self.from, self.to) = (self.to, self.from);
+                self.write_query(ctx);
+                true
+            },
+            CompareMsg::ShowFile(kind) => {
+                self.kind = kind;
+                true
+            },
+            CompareMsg::Render => {
+                self.frame = None;
+                // Both on the same clock, so they move in step
+                let time = ((instant::now() - self.start) / 1000.0) as f32;
+                for (index, canvas) in [self.from, self.to].into_iter().zip(&self.canvases) {
+                    if let (Some(renderer), Some(canvas)) = (&mut self.renderer, canvas.cast::<HtmlCanvasElement>()) {
+                        renderer.draw(index, time, &canvas);
+                    }
+                }
+                self.request_frame();
+                false
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let select = |selected: usize, msg: fn(usize) -> CompareMsg| {
+            let onchange = ctx.link().batch_callback(move |evt: Event| {
+                let select: HtmlSelectElement = evt.target_unchecked_into();
+                select.value().parse().ok().map(msg)
+            });
+            html! {
+                <select onchange={onchange}>
+                    { for TUTORIALS.iter().enumerate().map(|(index, _)| html! {
+                        <option value={index.to_string()} selected={index == selected}>{ self.title(index) }</option>
+                    }) }
+                </select>
+            }
+        };
+        let onback = ctx.link().navigator().map(|navigator| Callback::from(move |_: MouseEvent| navigator.push(&Route::Home)));
+        let onswap = ctx.link().callback(|_: MouseEvent| CompareMsg::Swap);
+
+        let canvases = [self.from, self.to].into_iter().zip(&self.canvases).map(|(index, node_ref)| html! {
+            <figure class="compare_preview">
+                <canvas width={CANVAS_WIDTH.to_string()} height={CANVAS_HEIGHT.to_string()} ref={node_ref.clone()}></canvas>
+                <figcaption>{ self.title(index) }</figcaption>
+            </figure>
+        });
+
+        let tabs = SourceKind::ALL.iter().map(|kind| {
+            let (_, old) = kind.file(&TUTORIALS[self.from]);
+            let (_, new) = kind.file(&TUTORIALS[self.to]);
+            let (removed, added) = line_counts(&side_by_side(old, new, 0));
+            let onclick = ctx.link().callback({
+                let kind = *kind;
+                move |_: MouseEvent| CompareMsg::ShowFile(kind)
+            });
+            let class = if *kind == self.kind { "selected" } else { "" };
+            html! {
+                <button class={class} onclick={onclick}>
+                    { kind.name() }
+                    <span class="compare_counts">{ format!(" -{} +{}", removed, added) }</span>
+                </button>
+            }
+        });
+
+        let (old_name, old) = self.kind.file(&TUTORIALS[self.from]);
+        let (new_name, new) = self.kind.file(&TUTORIALS[self.to]);
+
+        html! {
+            <div class="compare">
+                <div class="compare_header">
+                    <button onclick={onback}>{ "Back to the gallery" }</button>
+                    { select(self.from, CompareMsg::SetFrom) }
+                    <button onclick={onswap}>{ "Swap" }</button>
+                    { select(self.to, CompareMsg::SetTo) }
+                </div>
+                <div class="compare_previews">
+                    { for canvases }
+                </div>
+                <div class="compare_tabs">
+                    { for tabs }
+                </div>
+                <DiffView
+                    old_name={format!("{}/{}", TUTORIALS[self.from].dir, old_name)}
+                    old={old}
+                    new_name={format!("{}/{}", TUTORIALS[self.to].dir, new_name)}
+                    new={new}
+                />
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if !first_render {
+            return;
+        }
+
+        // Nothing to redraw when an image arrives, the next frame picks it up
+        match PreviewRenderer::new(Callback::noop()) {
+            Ok(renderer) => self.renderer = Some(renderer),
+            Err(err) => {
+                log!("ERROR No previews:", err);
+                return;
+            }
+        }
+        ctx.link().send_message(CompareMsg::Render);
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        if let Some(frame) = self.frame.take() {
+            let _ = window().unwrap().cancel_animation_frame(frame);
+        }
+    }
+}
+
+impl Compare {
+
+    fn title(&self, index: usize) -> String {
+        readme::title(&self.entries, TUTORIALS[index].dir)
+    }
+
+    // Replace rather than push, so flicking through pairs doesn't fill up the history
+    fn write_query(&self, ctx: &Context<Self>) {
+        let query = compare_query(self.from, self.to);
+        let Some(navigator) = ctx.link().navigator() else {
+            return;
+        };
+        if let Err(err) = navigator.replace_with_query(&Route::Compare, &query) {
+            log!("ERROR updating the link:", err.to_string());
+        }
+    }
+
+    fn request_frame(&mut self) {
+        if self.frame.is_some() {
+            return;
+        }
+        self.frame = window()
+            .unwrap()
+            .request_animation_frame(self.callback.as_ref().unchecked_ref())
+            .ok();
+    }
+}
+
+// The query string for comparing TUTORIALS[from] with TUTORIALS[to]
+pub fn compare_query(from: usize, to: usize) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("from".to_string(), TUTORIALS[from].dir.to_string()),
+        ("to".to_string(), TUTORIALS[to].dir.to_string()),
+    ])
+}
+
+fn tutorial_index(dir: &str) -> Option<usize> {
+    TUTORIALS.iter().position(|tutorial| tutorial.dir == dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shader_common::diff::DiffRow;
+
+    // The line numbers on one side of the diff, with skipped runs counted in
+    fn numbers(rows: &[DiffRow], old: bool) -> Vec<usize> {
+        let mut numbers = Vec::new();
+        for row in rows {
+            match row {
+                DiffRow::Lines { old: line, .. } if old => numbers.extend(line.as_ref().map(|(number, _)| *number)),
+                DiffRow::Lines { new: line, .. } => numbers.extend(line.as_ref().map(|(number, _)| *number)),
+                DiffRow::Skipped(count) => {
+                    let next = numbers.last().copied().unwrap_or(0) + 1;
+                    numbers.extend(next..next + count);
+                },
+            }
+        }
+        numbers
+    }
+
+    // Every file of every tutorial against the next one's, as the compare page starts out, shows
+    // each line of both exactly once and in order
+    #[test]
+    fn neighbouring_tutorials_diff_cleanly() {
+        for pair in TUTORIALS.windows(2) {
+            for kind in SourceKind::ALL {
+                let (_, old) = kind.file(&pair[0]);
+                let (_, new) = kind.file(&pair[1]);
+                let rows = side_by_side(old, new, 0);
+                assert_eq!(numbers(&rows, true), (1..=old.lines().count()).collect::<Vec<_>>(), "{} to {}: {}", pair[0].dir, pair[1].dir, kind.name());
+                assert_eq!(numbers(&rows, false), (1..=new.lines().count()).collect::<Vec<_>>(), "{} to {}: {}", pair[0].dir, pair[1].dir, kind.name());
+            }
+        }
+    }
+}
@@ -0,0 +1,447 @@
+// Shader exercises, listed in exercises/exercises.json (see shader_common::exercise). The
+// learner's shader and the target are drawn next to each other on the same clock and compared
+// every frame, with the score shown under the learner's canvas and, if asked for, the parts that
+// don't match marked on it. The open exercise is kept in the query string:
+//
+//   /exercise?id=rings
+
+use std::collections::BTreeMap;
+
+use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement, HtmlSelectElement, WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use wasm_bindgen::{prelude::*, JsCast};
+use gloo_console::log;
+
+use shader_common::editor::ShaderEditor;
+use shader_common::exercise::{score_frame, Exercise, ExerciseTexture, FrameScore, Manifest, BLOCK_SIZE};
+use shader_common::library;
+use shader_common::preprocess::preprocess;
+use shader_common::program::{compile_program, Defines};
+
+use crate::previews::{copy_canvas, noise_texture, offscreen_gl};
+use crate::Route;
+
+// The shaders the manifest names, checked by build.rs
+include!(concat!(env!("OUT_DIR"), "/exercise_files.rs"));
+
+const VERT_SOURCE: &str = include_str!(concat!(env!("OUT_DIR"), "/exercises/quad.vert"));
+
+const CANVAS_WIDTH: u32 = 400;
+const CANVAS_HEIGHT: u32 = 225;
+
+fn file(name: &str) -> &'static str {
+    FILES.iter().find(|(file, _)| *file == name).map(|(_, text)| *text).unwrap_or("")
+}
+
+struct ExerciseProgram {
+    program: WebGlProgram,
+    time_location: Option<WebGlUniformLocation>,
+    canvas_size_location: Option<WebGlUniformLocation>,
+}
+
+impl ExerciseProgram {
+    // Includes are expanded here rather than by build.rs, since the learner's shader changes as
+    // they type. Errors come back with line numbers in `name`.
+    fn compile(gl: &GL, name: &str, source: &str) -> Result<ExerciseProgram, String> {
+        let processed = preprocess(name, source, |path| library::resolve(path).map(str::to_string))
+            .map_err(|err| format!("ERROR: {}", err))?;
+        let program = compile_program(gl, VERT_SOURCE, &processed.source, &Defines::new())
+            .map_err(|log| processed.source_map.remap_log(&log))?;
+        Ok(ExerciseProgram {
+            time_location: gl.get_uniform_location(&program, "u_time"),
+            canvas_size_location: gl.get_uniform_location(&program, "canvasSize"),
+            program,
+        })
+    }
+
+    fn draw(&self, gl: &GL, texture: Option<&WebGlTexture>, tri_count: i32, time: f32) {
+        gl.use_program(Some(&self.program));
+        let position = gl.get_attrib_location(&self.program, "a_position") as u32;
+        gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(position);
+
+        gl.active_texture(GL::TEXTURE0);
+        gl.bind_texture(GL::TEXTURE_2D, texture);
+        gl.uniform1f(self.time_location.as_ref(), time);
+        gl.uniform2f(self.canvas_size_location.as_ref(), CANVAS_WIDTH as f32, CANVAS_HEIGHT as f32);
+
+        gl.draw_arrays(GL::TRIANGLES, 0, tri_count);
+    }
+}
+
+// Like the gallery's previews both shaders share one offscreen context. Each is drawn and read
+// back in turn, then copied onto its canvas on the page.
+struct ExerciseRenderer {
+    canvas: HtmlCanvasElement,
+    gl: GL,
+    tri_count: i32,
+    noise: Option<WebGlTexture>,
+    texture: ExerciseTexture,
+    // The learner's and the target's
+    programs: [Option<ExerciseProgram>; 2],
+    pixels: [Vec<u8>; 2],
+}
+
+impl ExerciseRenderer {
+    fn new() -> Result<ExerciseRenderer, String> {
+        let (canvas, gl, tri_count) = offscreen_gl()?;
+        canvas.set_width(CANVAS_WIDTH);
+        canvas.set_height(CANVAS_HEIGHT);
+        gl.viewport(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32);
+        let noise = noise_texture(&gl);
+        let pixels = (CANVAS_WIDTH * CANVAS_HEIGHT * 4) as usize;
+        Ok(ExerciseRenderer {
+            canvas,
+            gl,
+            tri_count,
+            noise,
+            texture: ExerciseTexture::None,
+            programs: [None, None],
+            pixels: [vec![0; pixels], vec![0; pixels]],
+        })
+    }
+
+    // Compile the target for `exercise`, and its starting shader for the learner
+    fn load(&mut self, exercise: &Exercise) -> Result<(), String> {
+        self.texture = exercise.texture;
+        match ExerciseProgram::compile(&self.gl, &exercise.target, file(&exercise.target)) {
+            Ok(program) => self.replace(1, Some(program)),
+            Err(err) => {
+                log!("ERROR compiling the target for", &exercise.id, err);
+                self.replace(1, None);
+            }
+        }
+        self.replace(0, None);
+        self.set_learner(&exercise.start, file(&exercise.start))
+    }
+
+    // Keeps drawing the last shader that compiled if this one doesn't
+    fn set_learner(&mut self, name: &str, source: &str) -> Result<(), String> {
+        let program = ExerciseProgram::compile(&self.gl, name, source)?;
+        self.replace(0, Some(program));
+        Ok(())
+    }
+
+    fn replace(&mut self, index: usize, program: Option<ExerciseProgram>) {
+        if let Some(old) = std::mem::replace(&mut self.programs[index], program) {
+            self.gl.delete_program(Some(&old.program));
+        }
+    }
+
+    // Draw both shaders at `time` and compare them, copying them onto `canvases` if given
+    fn frame(&mut self, time: f32, canvases: Option<[&HtmlCanvasElement; 2]>) -> FrameScore {
+        let gl = &self.gl;
+        let texture = match self.texture {
+            ExerciseTexture::None => None,
+            ExerciseTexture::Noise => self.noise.as_ref(),
+        };
+
+        for index in 0..2 {
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            gl.clear(GL::COLOR_BUFFER_BIT);
+            if let Some(program) = &self.programs[index] {
+                program.draw(gl, texture, self.tri_count, time);
+            }
+
+            // Both before the browser is free to clear the drawing buffer
+            let read = gl.read_pixels_with_opt_u8_array(
+                0,
+                0,
+                CANVAS_WIDTH as i32,
+                CANVAS_HEIGHT as i32,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                Some(&mut self.pixels[index]),
+            );
+            if let Err(err) = read {
+                log!("ERROR reading the frame back:", err);
+            }
+            if let Some(canvases) = canvases {
+                copy_canvas(&self.canvas, canvases[index]);
+            }
+        }
+
+        score_frame(&self.pixels[0], &self.pixels[1], CANVAS_WIDTH as usize, CANVAS_HEIGHT as usize)
+    }
+}
+
+// Cover the blocks that don't match, the worse the match the stronger the colour
+fn mark_mismatches(canvas: &HtmlCanvasElement, score: &FrameScore) {
+    let context: Option<CanvasRenderingContext2d> = canvas.get_context("2d").ok().flatten().and_then(|context| context.dyn_into().ok());
+    let Some(context) = context else {
+        return;
+    };
+    let size = BLOCK_SIZE as f64;
+    for (column, row, strength) in score.mismatches() {
+        context.set_fill_style_str(&format!("rgba(255, 40, 80, {:.2})", 0.2 + 0.5 * strength));
+        // The rows were read from the bottom up
+        let y = CANVAS_HEIGHT as f64 - (row + 1) as f64 * size;
+        context.fill_rect(column as f64 * size, y, size, size);
+    }
+}
+
+pub struct ExercisePage {
+    callback: Closure<dyn FnMut()>,
+    manifest: Manifest,
+    index: usize,
+    renderer: Option<ExerciseRenderer>,
+    // The learner's and the target's
+    canvases: [NodeRef; 2],
+    on_edit: Callback<String>,
+    editor_error: Option<String>,
+    // To the nearest percent, so the page is only drawn again when that changes
+    score: Option<u32>,
+    // Scores at each checkpoint from the last time the answer was checked
+    checked: Option<Vec<f32>>,
+    show_mismatches: bool,
+    start: f64,
+    // The time the clock stopped at
+    paused: Option<f32>,
+    frame: Option<i32>,
+}
+
+pub enum ExerciseMsg {
+    Select(usize),
+    Edit(String),
+    Check,
+    ToggleMismatches,
+    TogglePause,
+    Restart,
+    Render,
+}
+
+impl Component for ExercisePage {
+    type Message = ExerciseMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let comp_ctx = ctx.link().clone();
+        let callback =
+            Closure::wrap(Box::new(move || comp_ctx.send_message(ExerciseMsg::Render)) as Box<dyn FnMut()>);
+
+        let manifest = Manifest::parse(include_str!("../exercises/exercises.json")).expect("build.rs checks the manifest");
+        let query = ctx.link().location().and_then(|location| location.query::<BTreeMap<String, String>>().ok()).unwrap_or_default();
+        let index = query
+            .get("id")
+            .and_then(|id| manifest.exercises.iter().position(|exercise| &exercise.id == id))
+            .unwrap_or(0);
+
+        ExercisePage {
+            callback,
+            manifest,
+            index,
+            renderer: None,
+            canvases: Default::default(),
+            on_edit: ctx.link().callback(ExerciseMsg::Edit),
+            editor_error: None,
+            score: None,
+            checked: None,
+            show_mismatches: true,
+            start: instant::now(),
+            paused: None,
+            frame: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ExerciseMsg::Select(index) => {
+                self.index = index;
+                self.write_query(ctx);
+                self.load();
+                true
+            },
+            ExerciseMsg::Edit(source) => {
+                let exercise = &self.manifest.exercises[self.index];
+                if let Some(renderer) = &mut self.renderer {
+                    self.editor_error = renderer.set_learner(&exercise.start, &source).err();
+                }
+                // A check was of the shader before this one
+                self.checked = None;
+                true
+            },
+            ExerciseMsg::Check => {
+                let exercise = &self.manifest.exercises[self.index];
+                if let Some(renderer) = &mut self.renderer {
+                    self.checked = Some(exercise.checkpoints.iter().map(|time| renderer.frame(*time, None).score).collect());
+                }
+                true
+            },
+            ExerciseMsg::ToggleMismatches => {
+                self.show_mismatches = !self.show_mismatches;
+                true
+            },
+            ExerciseMsg::TogglePause => {
+                self.paused = match self.paused {
+                    Some(time) => {
+                        self.start = instant::now() - time as f64 * 1000.0;
+                        None
+                    },
+                    None => Some(self.time()),
+                };
+                true
+            },
+            ExerciseMsg::Restart => {
+                self.start = instant::now();
+                self.paused = self.paused.map(|_| 0.0);
+                false
+            },
+            ExerciseMsg::Render => {
+                self.frame = None;
+                let time = self.time();
+                let canvases = [&self.canvases[0], &self.canvases[1]].map(|canvas| canvas.cast::<HtmlCanvasElement>());
+                let (Some(renderer), [Some(learner), Some(target)]) = (&mut self.renderer, &canvases) else {
+                    return false;
+                };
+                let score = renderer.frame(time, Some([learner, target]));
+                if self.show_mismatches {
+                    mark_mismatches(learner, &score);
+                }
+                self.request_frame();
+
+                let rounded = Some(score.score.floor() as u32);
+                let changed = rounded != self.score;
+                self.score = rounded;
+                changed
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let exercise = &self.manifest.exercises[self.index];
+        let onback = ctx.link().navigator().map(|navigator| Callback::from(move |_: MouseEvent| navigator.push(&Route::Home)));
+        let onselect = ctx.link().batch_callback(|evt: Event| {
+            let select: HtmlSelectElement = evt.target_unchecked_into();
+            select.value().parse().ok().map(ExerciseMsg::Select)
+        });
+
+        let score = match self.score {
+            Some(score) => format!("Your shader, {}% matching", score),
+            None => "Your shader".to_string(),
+        };
+        let result = self.checked.as_ref().map(|scores| {
+            let passed = exercise.passed(scores);
+            let checkpoints = exercise.checkpoints.iter().zip(scores).map(|(time, score)| html! {
+                <li class={if *score >= exercise.pass_score { "checkpoint_passed" } else { "checkpoint_failed" }}>
+                    { format!("At {}s: {:.1}%", time, score) }
+                </li>
+            });
+            html! {
+                <div class={classes!("exercise_result", if passed { "passed" } else { "failed" })}>
+                    { if passed { "Passed, well done!".to_string() } else { format!("Not yet, every checkpoint needs {}%", exercise.pass_score) } }
+                    <ul>{ for checkpoints }</ul>
+                </div>
+            }
+        });
+
+        html! {
+            <div class="exercise">
+                <div class="compare_header">
+                    <button onclick={onback}>{ "Back to the gallery" }</button>
+                    <select onchange={onselect}>
+                        { for self.manifest.exercises.iter().enumerate().map(|(index, exercise)| html! {
+                            <option value={index.to_string()} selected={index == self.index}>{ &exercise.title }</option>
+                        }) }
+                    </select>
+                </div>
+                <h1>{ &exercise.title }</h1>
+                <p class="exercise_description">{ &exercise.description }</p>
+                <div class="exercise_body">
+                    <div class="exercise_previews">
+                        <figure class="compare_preview">
+                            <canvas width={CANVAS_WIDTH.to_string()} height={CANVAS_HEIGHT.to_string()} ref={self.canvases[0].clone()}></canvas>
+                            <figcaption>{ score }</figcaption>
+                        </figure>
+                        <figure class="compare_preview">
+                            <canvas width={CANVAS_WIDTH.to_string()} height={CANVAS_HEIGHT.to_string()} ref={self.canvases[1].clone()}></canvas>
+                            <figcaption>{ "Target" }</figcaption>
+                        </figure>
+                        <div class="exercise_controls">
+                            <button onclick={ctx.link().callback(|_| ExerciseMsg::TogglePause)}>
+                                { if self.paused.is_some() { "Play" } else { "Pause" } }
+                            </button>
+                            <button onclick={ctx.link().callback(|_| ExerciseMsg::Restart)}>{ "Restart" }</button>
+                            <button onclick={ctx.link().callback(|_| ExerciseMsg::ToggleMismatches)}>
+                                { if self.show_mismatches { "Hide differences" } else { "Show differences" } }
+                            </button>
+                            <button onclick={ctx.link().callback(|_| ExerciseMsg::Check)}>{ "Check my answer" }</button>
+                        </div>
+                        { for result }
+                    </div>
+                    // A new editor for each exercise, so it starts from that exercise's shader
+                    <ShaderEditor
+                        key={exercise.id.clone()}
+                        name={exercise.start.clone()}
+                        original={file(&exercise.start)}
+                        error={self.editor_error.clone().map(AttrValue::from)}
+                        on_change={self.on_edit.clone()}
+                    />
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if !first_render {
+            return;
+        }
+
+        match ExerciseRenderer::new() {
+            Ok(renderer) => self.renderer = Some(renderer),
+            Err(err) => {
+                log!("ERROR No exercises:", err);
+                return;
+            }
+        }
+        self.load();
+        ctx.link().send_message(ExerciseMsg::Render);
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        if let Some(frame) = self.frame.take() {
+            let _ = window().unwrap().cancel_animation_frame(frame);
+        }
+    }
+}
+
+impl ExercisePage {
+
+    fn time(&self) -> f32 {
+        self.paused.unwrap_or(((instant::now() - self.start) / 1000.0) as f32)
+    }
+
+    // Back to the start of the exercise, and of its animation
+    fn load(&mut self) {
+        let exercise = &self.manifest.exercises[self.index];
+        if let Some(renderer) = &mut self.renderer {
+            self.editor_error = renderer.load(exercise).err();
+        }
+        self.checked = None;
+        self.score = None;
+        self.start = instant::now();
+        self.paused = self.paused.map(|_| 0.0);
+    }
+
+    // Replace rather than push, like the comparisons
+    fn write_query(&self, ctx: &Context<Self>) {
+        let query = BTreeMap::from([("id".to_string(), self.manifest.exercises[self.index].id.clone())]);
+        let Some(navigator) = ctx.link().navigator() else {
+            return;
+        };
+        if let Err(err) = navigator.replace_with_query(&Route::Exercise, &query) {
+            log!("ERROR updating the link:", err.to_string());
+        }
+    }
+
+    fn request_frame(&mut self) {
+        if self.frame.is_some() {
+            return;
+        }
+        self.frame = window()
+            .unwrap()
+            .request_animation_frame(self.callback.as_ref().unchecked_ref())
+            .ok();
+    }
+}
@@ -0,0 +1,177 @@
+use web_sys::{window, HtmlCanvasElement};
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use wasm_bindgen::{prelude::*, JsCast};
+use gloo_console::log;
+
+use crate::compare::compare_query;
+use crate::previews::{PreviewRenderer, PREVIEW_HEIGHT, PREVIEW_WIDTH, TUTORIALS};
+use crate::readme::{self, ReadmeEntry};
+use crate::Route;
+
+// Seconds into each tutorial the still snapshots are taken
+const SNAPSHOT_TIME: f32 = 2.0;
+
+pub struct Gallery {
+    callback: Closure<dyn FnMut()>,
+    renderer: Option<PreviewRenderer>,
+    canvases: Vec<NodeRef>,
+    entries: Vec<ReadmeEntry>,
+    // The card under the pointer animates, the others keep their last frame
+    hovered: Option<usize>,
+    hover_start: f64,
+    // The animation frame asked for, if there's one coming
+    frame: Option<i32>,
+}
+
+pub enum GalleryMsg {
+    Hover(Option<usize>),
+    // An image texture arrived, the snapshots need redoing
+    Snapshot,
+    Render,
+}
+
+impl Component for Gallery {
+    type Message = GalleryMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let comp_ctx = ctx.link().clone();
+        let callback =
+            Closure::wrap(Box::new(move || comp_ctx.send_message(GalleryMsg::Render)) as Box<dyn FnMut()>);
+
+        Gallery {
+            callback,
+            renderer: None,
+            canvases: TUTORIALS.iter().map(|_| NodeRef::default()).collect(),
+            entries: readme::tutorials(include_str!("../../README.md")),
+            hovered: None,
+            hover_start: 0.0,
+            frame: None,
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            GalleryMsg::Hover(hovered) => {
+                self.hovered = hovered;
+                self.hover_start = instant::now();
+                if hovered.is_some() {
+                    self.request_frame();
+                }
+            },
+            GalleryMsg::Snapshot => {
+                self.snapshot();
+            },
+            GalleryMsg::Render => {
+                self.frame = None;
+                if let Some(index) = self.hovered {
+                    let time = SNAPSHOT_TIME + ((instant::now() - self.hover_start) / 1000.0) as f32;
+                    self.draw(index, time);
+                    self.request_frame();
+                }
+            },
+        }
+        // Nothing on the page itself changes, only the canvases
+        false
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let cards = TUTORIALS.iter().enumerate().map(|(index, tutorial)| {
+            let entry = readme::find(&self.entries, tutorial.dir);
+            let title = readme::title(&self.entries, tutorial.dir);
+            // Each tutorial against the one it follows on from
+            let compare = (index > 0).then(|| ctx.link().navigator()).flatten().map(|navigator| {
+                Callback::from(move |_: MouseEvent| {
+                    if let Err(err) = navigator.push_with_query(&Route::Compare, &compare_query(index - 1, index)) {
+                        log!("ERROR opening the comparison:", err.to_string());
+                    }
+                })
+            });
+            let onmouseenter = ctx.link().callback(move |_: MouseEvent| GalleryMsg::Hover(Some(index)));
+            let onmouseleave = ctx.link().callback(|_: MouseEvent| GalleryMsg::Hover(None));
+
+            html! {
+                <div class="card" onmouseenter={onmouseenter} onmouseleave={onmouseleave}>
+                    <canvas class="card_preview"
+                        width={PREVIEW_WIDTH.to_string()}
+                        height={PREVIEW_HEIGHT.to_string()}
+                        ref={self.canvases[index].clone()}
+                    ></canvas>
+                    <h2>{ title }</h2>
+                    if let Some(entry) = entry {
+                        <p>{ &entry.description }</p>
+                    }
+                    <code>{ format!("cd {} && trunk serve", tutorial.dir) }</code>
+                    if let Some(onclick) = compare {
+                        <button class="card_compare" onclick={onclick}>{ "Compare with the one before" }</button>
+                    }
+                </div>
+            }
+        });
+
+        html! {
+            <div class="gallery">
+                <h1>{ "Shader Tutorials" }</h1>
+                <p class="gallery_hint">
+                    { "Hover over a tutorial to see it move, or " }
+                    <Link<Route> to={Route::Exercise}>{ "try the exercises" }</Link<Route>>
+                </p>
+                <div class="cards">
+                    { for cards }
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if !first_render {
+            return;
+        }
+
+        match PreviewRenderer::new(ctx.link().callback(|_| GalleryMsg::Snapshot)) {
+            Ok(renderer) => self.renderer = Some(renderer),
+            Err(err) => {
+                log!("ERROR No previews:", err);
+                return;
+            }
+        }
+        self.snapshot();
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        if let Some(frame) = self.frame.take() {
+            let _ = window().unwrap().cancel_animation_frame(frame);
+        }
+    }
+}
+
+impl Gallery {
+
+    // Every card at SNAPSHOT_TIME, apart from one that's animating
+    fn snapshot(&mut self) {
+        for index in 0..TUTORIALS.len() {
+            if self.hovered != Some(index) {
+                self.draw(index, SNAPSHOT_TIME);
+            }
+        }
+    }
+
+    fn draw(&mut self, index: usize, time: f32) {
+        let (Some(renderer), Some(canvas)) = (&mut self.renderer, self.canvases[index].cast::<HtmlCanvasElement>()) else {
+            return;
+        };
+        renderer.draw(index, time, &canvas);
+    }
+
+    fn request_frame(&mut self) {
+        if self.frame.is_some() {
+            return;
+        }
+        self.frame = window()
+            .unwrap()
+            .request_animation_frame(self.callback.as_ref().unchecked_ref())
+            .ok();
+    }
+}
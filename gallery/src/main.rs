@@ -0,0 +1,45 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+mod compare;
+mod exercises;
+mod gallery;
+mod previews;
+mod readme;
+
+#[derive(Clone, Routable, PartialEq)]
+enum Route {
+    #[at("/")]
+    Home,
+    #[at("/compare")]
+    Compare,
+    #[at("/exercise")]
+    Exercise,
+}
+
+fn switch(routes: Route) -> Html {    
+    match routes {
+        Route::Home => html!{
+            <gallery::Gallery />
+        },
+        Route::Compare => html!{
+            <compare::Compare />
+        },
+        Route::Exercise => html!{
+            <exercises::ExercisePage />
+        }
+    }
+}
+
+#[function_component(App)]
+fn app_body() -> Html {
+    html! {
+        <BrowserRouter>
+            <Switch<Route> render={switch} />
+        </BrowserRouter>
+    }
+}
+
+fn main() {
+    yew::Renderer::<App>::new().render();
+}
@@ -0,0 +1,393 @@
+// Everything the gallery draws goes through one WebGL context on a canvas that's never shown.
+// Browsers only allow a handful of live contexts on a page, so rather than one per card each
+// tutorial's shader is compiled into this one, drawn at the size of the canvas it's wanted on
+// and copied onto that 2D canvas.
+
+use std::rc::Rc;
+
+use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, WebGlProgram, WebGlRenderingContext as GL, WebGlTexture, WebGlUniformLocation};
+use yew::Callback;
+
+use wasm_bindgen::{prelude::*, JsCast};
+use gloo_console::log;
+
+use shader_common::camera::Camera2d;
+use shader_common::noise::{NoiseTexture, DEFAULT_SEED};
+use shader_common::palette::{Palette, PaletteUniforms};
+use shader_common::params::ShaderParams;
+use shader_common::program::{compile_program, Defines};
+
+pub const PREVIEW_WIDTH: u32 = 320;
+pub const PREVIEW_HEIGHT: u32 = 180;
+
+// Never has a texture bound, so samplers pointed at it read black
+const EMPTY_UNIT: i32 = 7;
+
+// Uniforms the tutorial would set from its page, given the value it starts with
+pub enum Uniform {
+    Float(&'static str, f32),
+    Vec4(&'static str, [f32; 4]),
+    // Textures the preview doesn't have, like the keyboard or a video
+    Empty(&'static str),
+}
+
+pub enum Texture {
+    None,
+    // The same seeded noise the tutorials generate
+    Noise,
+    Image(&'static str),
+}
+
+pub struct Tutorial {
+    // Also where its README section's number comes from
+    pub dir: &'static str,
+    // Vertex and fragment shader from build.rs, None for 00 which only clears the screen
+    pub shaders: Option<(&'static str, &'static str)>,
+    pub texture: Texture,
+    pub uniforms: &'static [Uniform],
+    // The files as written, to compare with another tutorial's
+    pub sources: Sources,
+}
+
+pub struct SourceFile {
+    pub name: &'static str,
+    pub text: &'static str,
+}
+
+pub struct Sources {
+    pub vert: Option<SourceFile>,
+    pub frag: Option<SourceFile>,
+    // canvas_control.rs, where the tutorial sets WebGL up
+    pub rust: SourceFile,
+}
+
+macro_rules! shaders {
+    ($dir:literal, $name:literal) => {
+        Some((
+            include_str!(concat!(env!("OUT_DIR"), "/", $dir, "/", $name, ".vert")),
+            include_str!(concat!(env!("OUT_DIR"), "/", $dir, "/", $name, ".frag")),
+        ))
+    };
+}
+
+macro_rules! source_file {
+    ($dir:literal, $name:expr) => {
+        SourceFile { name: $name, text: include_str!(concat!("../../", $dir, "/src/", $name)) }
+    };
+}
+
+macro_rules! sources {
+    ($dir:literal) => {
+        Sources { vert: None, frag: None, rust: source_file!($dir, "canvas_control.rs") }
+    };
+    ($dir:literal, $name:literal) => {
+        Sources {
+            vert: Some(source_file!($dir, concat!($name, ".vert"))),
+            frag: Some(source_file!($dir, concat!($name, ".frag"))),
+            rust: source_file!($dir, "canvas_control.rs"),
+        }
+    };
+}
+
+pub const TUTORIALS: &[Tutorial] = &[
+    Tutorial {
+        dir: "00-boilerplate",
+        shaders: None,
+        texture: Texture::None,
+        uniforms: &[],
+        sources: sources!("00-boilerplate"),
+    },
+    Tutorial {
+        dir: "01-simple-shader",
+        shaders: shaders!("01-simple-shader", "simple"),
+        texture: Texture::None,
+        uniforms: &[],
+        sources: sources!("01-simple-shader", "simple"),
+    },
+    Tutorial {
+        dir: "02-texture",
+        shaders: shaders!("02-texture", "texture"),
+        texture: Texture::Noise,
+        uniforms: &[],
+        sources: sources!("02-texture", "texture"),
+    },
+    Tutorial {
+        dir: "03-texture-overlay",
+        shaders: shaders!("03-texture-overlay", "basic"),
+        texture: Texture::Noise,
+        uniforms: &[],
+        sources: sources!("03-texture-overlay", "basic"),
+    },
+    Tutorial {
+        dir: "04-simple-tunnel",
+        shaders: shaders!("04-simple-tunnel", "basic"),
+        texture: Texture::Noise,
+        uniforms: &[],
+        sources: sources!("04-simple-tunnel", "basic"),
+    },
+    Tutorial {
+        dir: "04.1-light-rays",
+        shaders: shaders!("04.1-light-rays", "basic"),
+        texture: Texture::Noise,
+        uniforms: &[],
+        sources: sources!("04.1-light-rays", "basic"),
+    },
+    Tutorial {
+        dir: "04.2-stretch-effect",
+        shaders: shaders!("04.2-stretch-effect", "basic"),
+        texture: Texture::Noise,
+        uniforms: &[],
+        sources: sources!("04.2-stretch-effect", "basic"),
+    },
+    Tutorial {
+        dir: "05-neon-swirls",
+        shaders: shaders!("05-neon-swirls", "swirl"),
+        texture: Texture::Noise,
+        uniforms: &[
+            Uniform::Float("red", 0.0),
+            Uniform::Float("blue", 0.0),
+            Uniform::Float("u_zoom", 1.0),
+            // No button held
+            Uniform::Vec4("iMouse", [0.0, 0.0, -1.0, -1.0]),
+            Uniform::Empty("iKeyboard"),
+            Uniform::Empty("u_audio"),
+            Uniform::Empty("u_video"),
+        ],
+        sources: sources!("05-neon-swirls", "swirl"),
+    },
+    Tutorial {
+        dir: "06-fractal-pattern",
+        shaders: shaders!("06-fractal-pattern", "fractal"),
+        texture: Texture::Noise,
+        uniforms: &[],
+        sources: sources!("06-fractal-pattern", "fractal"),
+    },
+    Tutorial {
+        dir: "06.1-kaleidoscope",
+        shaders: shaders!("06.1-kaleidoscope", "fractal"),
+        texture: Texture::Image("/assets/forest_scene.png"),
+        uniforms: &[Uniform::Float("mouse_x", 0.85), Uniform::Float("mouse_y", 0.85)],
+        sources: sources!("06.1-kaleidoscope", "fractal"),
+    },
+];
+
+struct Preview {
+    program: Option<WebGlProgram>,
+    time_location: Option<WebGlUniformLocation>,
+    canvas_size_location: Option<WebGlUniformLocation>,
+    uniform_locations: Vec<Option<WebGlUniformLocation>>,
+    camera: Camera2d,
+    params: ShaderParams,
+    palette: PaletteUniforms,
+    // Its own texture, otherwise the shared noise
+    texture: Option<WebGlTexture>,
+}
+
+pub struct PreviewRenderer {
+    canvas: HtmlCanvasElement,
+    gl: GL,
+    previews: Vec<Preview>,
+    noise: Option<WebGlTexture>,
+    tri_count: i32,
+}
+
+impl PreviewRenderer {
+    // `on_image` is called as each image texture arrives, when it's worth drawing again
+    pub fn new(on_image: Callback<()>) -> Result<PreviewRenderer, String> {
+        let (canvas, gl, tri_count) = offscreen_gl()?;
+        let noise = noise_texture(&gl);
+        let previews = TUTORIALS.iter().map(|tutorial| Preview::new(&gl, tutorial, &on_image)).collect();
+        Ok(PreviewRenderer { canvas, gl, previews, noise, tri_count })
+    }
+
+    // Draw tutorial `index` as it looks `time` seconds in, onto `target` at its size
+    pub fn draw(&mut self, index: usize, time: f32, target: &HtmlCanvasElement) {
+        let gl = &self.gl;
+        let preview = &mut self.previews[index];
+
+        let (width, height) = (target.width(), target.height());
+        if (self.canvas.width(), self.canvas.height()) != (width, height) {
+            self.canvas.set_width(width);
+            self.canvas.set_height(height);
+        }
+        gl.viewport(0, 0, width as i32, height as i32);
+        preview.camera.set_viewport(width as f64, height as f64);
+        gl.clear_color(0., 0.7, 0., 1.0);
+        gl.clear(GL::COLOR_BUFFER_BIT);
+
+        if let Some(program) = &preview.program {
+            gl.use_program(Some(program));
+            let position = gl.get_attrib_location(program, "a_position") as u32;
+            gl.vertex_attrib_pointer_with_i32(position, 3, GL::FLOAT, false, 0, 0);
+            gl.enable_vertex_attrib_array(position);
+
+            gl.active_texture(GL::TEXTURE0);
+            gl.bind_texture(GL::TEXTURE_2D, preview.texture.as_ref().or(self.noise.as_ref()));
+
+            gl.uniform1f(preview.time_location.as_ref(), time);
+            gl.uniform2f(preview.canvas_size_location.as_ref(), width as f32, height as f32);
+            for (location, uniform) in preview.uniform_locations.iter().zip(TUTORIALS[index].uniforms) {
+                match uniform {
+                    Uniform::Float(_, value) => gl.uniform1f(location.as_ref(), *value),
+                    Uniform::Vec4(_, [x, y, z, w]) => gl.uniform4f(location.as_ref(), *x, *y, *z, *w),
+                    Uniform::Empty(_) => gl.uniform1i(location.as_ref(), EMPTY_UNIT),
+                }
+            }
+            preview.params.upload(gl);
+            preview.camera.upload(gl);
+            preview.palette.upload(gl, &Palette::default());
+
+            gl.draw_arrays(GL::TRIANGLES, 0, self.tri_count);
+        }
+
+        // Straight after drawing, before the browser is free to clear the drawing buffer
+        copy_canvas(&self.canvas, target);
+    }
+}
+
+// A canvas that's never put on the page, with a WebGL context and a quad covering the screen in
+// its vertex buffer. Also gives the number of triangles in the quad.
+pub fn offscreen_gl() -> Result<(HtmlCanvasElement, GL, i32), String> {
+    let canvas: HtmlCanvasElement = window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.create_element("canvas").ok())
+        .and_then(|element| element.dyn_into().ok())
+        .ok_or("Unable to create the preview canvas")?;
+    canvas.set_width(PREVIEW_WIDTH);
+    canvas.set_height(PREVIEW_HEIGHT);
+    let gl: GL = canvas
+        .get_context("webgl")
+        .ok()
+        .flatten()
+        .and_then(|context| context.dyn_into().ok())
+        .ok_or("WebGL isn't available")?;
+
+    let vertices: Vec<f32> = vec![
+        -1.0, -1.0, 0.,
+        1.0, -1.0, 0.,
+        1.0, 1.0, 0.,
+        -1.0, -1.0, 0.,
+        -1.0, 1.0, 0.,
+        1.0, 1.0, 0.
+    ];
+    let vertex_buffer = gl.create_buffer().ok_or("Unable to create the vertex buffer")?;
+    let verts = js_sys::Float32Array::from(vertices.as_slice());
+    gl.bind_buffer(GL::ARRAY_BUFFER, Some(&vertex_buffer));
+    gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &verts, GL::STATIC_DRAW);
+
+    Ok((canvas, gl, vertices.len() as i32 / 3))
+}
+
+// The same seeded noise the tutorials generate
+pub fn noise_texture(gl: &GL) -> Option<WebGlTexture> {
+    let texture = gl.create_texture()?;
+    NoiseTexture::tutorial_default(DEFAULT_SEED).upload(gl, &texture);
+    Some(texture)
+}
+
+// Copy what's been drawn onto a canvas that's on the page
+pub fn copy_canvas(from: &HtmlCanvasElement, to: &HtmlCanvasElement) {
+    let context: Option<CanvasRenderingContext2d> = to.get_context("2d").ok().flatten().and_then(|context| context.dyn_into().ok());
+    if let Some(context) = context {
+        let _ = context.draw_image_with_html_canvas_element(from, 0.0, 0.0);
+    }
+}
+
+impl Preview {
+    fn new(gl: &GL, tutorial: &Tutorial, on_image: &Callback<()>) -> Preview {
+        let mut camera = Camera2d::new();
+        camera.set_viewport(PREVIEW_WIDTH as f64, PREVIEW_HEIGHT as f64);
+        let mut preview = Preview {
+            program: None,
+            time_location: None,
+            canvas_size_location: None,
+            uniform_locations: Vec::new(),
+            camera,
+            params: ShaderParams::from_source(tutorial.shaders.map(|(_, frag)| frag).unwrap_or("")),
+            palette: PaletteUniforms::default(),
+            texture: None,
+        };
+
+        if let Texture::Image(src) = tutorial.texture {
+            preview.texture = load_image(gl, src, on_image.clone());
+        }
+
+        let (vert_code, frag_code) = match tutorial.shaders {
+            Some(shaders) => shaders,
+            None => return preview,
+        };
+        let program = match compile_program(gl, vert_code, frag_code, &Defines::new()) {
+            Ok(program) => program,
+            Err(err) => {
+                log!("ERROR compiling the preview for", tutorial.dir, err);
+                return preview;
+            }
+        };
+
+        preview.time_location = gl.get_uniform_location(&program, "u_time");
+        preview.canvas_size_location = gl.get_uniform_location(&program, "canvasSize");
+        preview.uniform_locations = tutorial
+            .uniforms
+            .iter()
+            .map(|uniform| match uniform {
+                Uniform::Float(name, _) | Uniform::Vec4(name, _) | Uniform::Empty(name) => gl.get_uniform_location(&program, name),
+            })
+            .collect();
+        preview.params.locate(gl, &program);
+        preview.camera.locate(gl, &program);
+        preview.palette.locate(gl, &program);
+        preview.program = Some(program);
+        preview
+    }
+}
+
+// Black until the image arrives
+fn load_image(gl: &GL, src: &str, on_load: Callback<()>) -> Option<WebGlTexture> {
+    let texture = gl.create_texture()?;
+    let image = HtmlImageElement::new().ok()?;
+    let imgrc = Rc::new(image.clone());
+
+    {
+        let image = imgrc.clone();
+        let texture = texture.clone();
+        let gl = gl.clone();
+
+        let a = Closure::wrap(Box::new(move || {
+            gl.active_texture(GL::TEXTURE0);
+            gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+            let _ = gl.tex_image_2d_with_u32_and_u32_and_image(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                &image,
+            );
+            gl.generate_mipmap(GL::TEXTURE_2D);
+            on_load.emit(());
+        }) as Box<dyn FnMut()>);
+
+        imgrc.set_onload(Some(a.as_ref().unchecked_ref()));
+        // Only ever one of these per image, it can live as long as the page
+        a.forget();
+    }
+    image.set_src(src);
+    Some(texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // build.rs keeps its own list of the tutorials to build shaders for, as it can't use this one
+    #[test]
+    fn build_script_builds_every_tutorial_with_shaders() {
+        let build = include_str!("../build.rs");
+        let start = build.find("const TUTORIALS").unwrap();
+        let end = start + build[start..].find("];").unwrap();
+        let built: Vec<&str> = build[start..end].lines().skip(1).map(|line| line.trim().trim_end_matches(',').trim_matches('"')).collect();
+
+        let with_shaders: Vec<&str> = TUTORIALS.iter().filter(|tutorial| tutorial.shaders.is_some()).map(|tutorial| tutorial.dir).collect();
+        assert_eq!(built, with_shaders);
+    }
+}
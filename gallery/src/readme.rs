@@ -0,0 +1,96 @@
+// The tutorial descriptions come straight from the README, so they're only written in one place
+
+pub struct ReadmeEntry {
+    // "04.1", the start of the tutorial's directory name
+    pub number: String,
+    pub title: String,
+    pub description: String,
+}
+
+// The `#### 00 - Boilerplate` sections under `### Tutorials`, in order
+pub fn tutorials(readme: &str) -> Vec<ReadmeEntry> {
+    let mut entries: Vec<ReadmeEntry> = Vec::new();
+    let mut in_tutorials = false;
+
+    for line in readme.lines().map(str::trim) {
+        if let Some(heading) = line.strip_prefix("### ") {
+            in_tutorials = heading.trim() == "Tutorials";
+        } else if !in_tutorials {
+            continue;
+        } else if let Some(heading) = line.strip_prefix("#### ") {
+            let (number, title) = heading.split_once(" - ").unwrap_or(("", heading));
+            entries.push(ReadmeEntry { number: number.trim().to_string(), title: title.trim().to_string(), description: String::new() });
+        } else if let Some(entry) = entries.last_mut().filter(|_| !line.is_empty()) {
+            if !entry.description.is_empty() {
+                entry.description.push(' ');
+            }
+            // Code spans read fine as plain text on a card
+            entry.description.push_str(&line.replace('`', ""));
+        }
+    }
+    entries
+}
+
+// The entry for a tutorial's directory, e.g. "04.1-light-rays"
+pub fn find<'a>(entries: &'a [ReadmeEntry], dir: &str) -> Option<&'a ReadmeEntry> {
+    let number = dir.split('-').next().unwrap_or(dir);
+    entries.iter().find(|entry| entry.number == number)
+}
+
+// "04.1 - Light Rays", or the directory name if the README doesn't have it
+pub fn title(entries: &[ReadmeEntry], dir: &str) -> String {
+    find(entries, dir).map(|entry| format!("{} - {}", entry.number, entry.title)).unwrap_or_else(|| dir.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const README: &str = "# Shader tutorials
+
+#### 99 - Not a tutorial
+Above the tutorials, so it's left out.
+
+### Tutorials
+
+#### 00 - Boilerplate
+Just clears the screen.
+
+#### 04.1 - Light Rays
+Light rays from the `tunnel`,
+over two lines.
+
+A second paragraph.
+  #### 05 - Neon Swirls  
+#### Extras
+Without a number.
+
+### Running them
+#### 06 - After the tutorials
+Also left out.
+";
+
+    #[test]
+    fn reads_the_tutorials_section() {
+        let entries = tutorials(README);
+        let read: Vec<(&str, &str, &str)> = entries.iter().map(|entry| (entry.number.as_str(), entry.title.as_str(), entry.description.as_str())).collect();
+        assert_eq!(
+            read,
+            vec![
+                ("00", "Boilerplate", "Just clears the screen."),
+                ("04.1", "Light Rays", "Light rays from the tunnel, over two lines. A second paragraph."),
+                ("05", "Neon Swirls", ""),
+                ("", "Extras", "Without a number."),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_entries_by_directory() {
+        let entries = tutorials(README);
+        assert_eq!(find(&entries, "04.1-light-rays").map(|entry| entry.title.as_str()), Some("Light Rays"));
+        assert!(find(&entries, "04-simple-tunnel").is_none());
+        assert_eq!(title(&entries, "05-neon-swirls"), "05 - Neon Swirls");
+        assert_eq!(title(&entries, "06-fractal-pattern"), "06-fractal-pattern");
+    }
+}